@@ -11,6 +11,45 @@ use serde::{Deserialize, Serialize};
 fn default_searxng_url() -> String {
     "http://localhost:8888".to_string()
 }
+fn default_scan_model_stores() -> bool {
+    true
+}
+fn default_enrichment_providers() -> Vec<String> {
+    vec!["model_card".into(), "huggingface".into(), "searxng_llm".into()]
+}
+fn default_post_download_step() -> bool {
+    true
+}
+fn default_rag_top_k() -> u32 {
+    4
+}
+fn default_memory_max_lessons() -> u32 {
+    200
+}
+fn default_memory_context_tokens() -> u32 {
+    1500
+}
+fn default_agent_max_steps() -> u32 {
+    50
+}
+fn default_agent_max_depth() -> u8 {
+    4
+}
+fn default_agent_max_wall_clock_secs() -> u64 {
+    3600
+}
+fn default_agent_max_timeout_secs() -> u64 {
+    900
+}
+fn default_agent_headless_policy() -> PolicyAction {
+    PolicyAction::Deny
+}
+fn default_agent_sandbox_cpu_secs() -> u64 {
+    120
+}
+fn default_agent_sandbox_memory_mb() -> u64 {
+    4096
+}
 fn default_ui_transparency() -> f32 {
     0.1
 }
@@ -164,6 +203,36 @@ impl Default for ModelOverride {
     }
 }
 
+/// A rule of the agent approval policy. The first rule that matches a tool
+/// call decides it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRule {
+    /// Tool name, or `*` for every tool.
+    pub tool: String,
+    #[serde(default)]
+    pub matcher: ArgMatcher,
+    /// The argument the matcher looks at, e.g. `command` or `cwd`.
+    #[serde(default)]
+    pub arg: String,
+    /// A regex, or a path prefix.
+    #[serde(default)]
+    pub pattern: String,
+    pub action: PolicyAction,
+}
+
+impl ApprovalRule {
+    /// e.g. "allow run_command when command matches `^git status`".
+    pub fn describe(&self) -> String {
+        let tool = if self.tool == "*" { "any tool" } else { self.tool.as_str() };
+        let when = match self.matcher {
+            ArgMatcher::Any => String::new(),
+            ArgMatcher::Regex => format!(" when {} matches `{}`", self.arg, self.pattern),
+            ArgMatcher::PathPrefix => format!(" when {} is under `{}`", self.arg, self.pattern),
+        };
+        format!("{} {tool}{when}", self.action.as_str())
+    }
+}
+
 
 // ── Enums ───────────────────────────────────────────────────────────────────
 
@@ -213,6 +282,97 @@ impl CacheType {
     }
 }
 
+/// What to do with a model once a download of it has been verified and indexed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PostDownloadAction {
+    #[default]
+    None,
+    /// Save the current settings with the new model as a profile named after it.
+    CreateProfile,
+    /// Switch to the new model and start the server, unless one is running.
+    StartServer,
+}
+impl PostDownloadAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::None => "none",
+            Self::CreateProfile => "create_profile",
+            Self::StartServer => "start_server",
+        }
+    }
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "create_profile" => Self::CreateProfile,
+            "start_server" => Self::StartServer,
+            _ => Self::None,
+        }
+    }
+}
+
+/// What the agent approval policy does with a tool call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    /// Ask the user; in a run nobody is watching, the headless policy decides.
+    #[default]
+    Ask,
+}
+impl PolicyAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Ask => "ask",
+        }
+    }
+}
+impl std::str::FromStr for PolicyAction {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "allow" => Self::Allow,
+            "deny" => Self::Deny,
+            _ => Self::Ask,
+        })
+    }
+}
+
+/// How an [`ApprovalRule`] looks at an argument of the call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgMatcher {
+    /// Every call of the tool.
+    #[default]
+    Any,
+    /// The pattern is found in the argument; for an allow rule it has to
+    /// match the whole argument.
+    Regex,
+    /// The argument is a path inside the pattern's directory, which must be
+    /// absolute.
+    PathPrefix,
+}
+impl ArgMatcher {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Any => "any",
+            Self::Regex => "regex",
+            Self::PathPrefix => "path_prefix",
+        }
+    }
+}
+impl std::str::FromStr for ArgMatcher {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "regex" => Self::Regex,
+            "path_prefix" => Self::PathPrefix,
+            _ => Self::Any,
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum RopeScaling {
     None,
@@ -391,6 +551,103 @@ pub struct ServerConfig {
     pub model_scan_dirs: Vec<String>,
     #[serde(default = "default_searxng_url")]
     pub searxng_url: String,
+    /// Also index the Hugging Face hub cache, Ollama and LM Studio model stores.
+    #[serde(default = "default_scan_model_stores")]
+    pub scan_model_stores: bool,
+    /// Library enrichment providers, tried in order until the metadata is
+    /// complete: `model_card` (local README), `huggingface`, `searxng_llm`.
+    #[serde(default = "default_enrichment_providers")]
+    pub enrichment_providers: Vec<String>,
+    /// Memory budget for the download picker's fit estimate, in GiB. 0 means
+    /// detect it (`nvidia-smi` for VRAM, `/proc/meminfo` for RAM).
+    #[serde(default)]
+    pub vram_budget_gb: f32,
+    #[serde(default)]
+    pub ram_budget_gb: f32,
+    /// Post-download hooks, run in order on every finished model file: check
+    /// the GGUF structure, add it to the library index, enrich it, then
+    /// `post_download_action`.
+    #[serde(default = "default_post_download_step")]
+    pub post_download_verify: bool,
+    #[serde(default = "default_post_download_step")]
+    pub post_download_index: bool,
+    #[serde(default = "default_post_download_step")]
+    pub post_download_enrich: bool,
+    #[serde(default)]
+    pub post_download_action: PostDownloadAction,
+
+    // ─ Document Chat
+    /// OpenAI-compatible server that embeds attached documents, e.g.
+    /// `http://127.0.0.1:8081`; empty uses the chat's own server, which then
+    /// needs `embedding` on.
+    #[serde(default)]
+    pub rag_embedding_url: String,
+    /// Sent as `model` in embedding requests; llama-server ignores it.
+    #[serde(default)]
+    pub rag_embedding_model: String,
+    /// Passages added to each question; 0 turns retrieval off.
+    #[serde(default = "default_rag_top_k")]
+    pub rag_top_k: u32,
+
+    // ─ Agent Memory
+    /// Hold lessons the agent and chat propose until they are approved in
+    /// the Memory view; off means they are used right away.
+    #[serde(default)]
+    pub memory_review_queue: bool,
+    /// Lessons kept after consolidation; the lowest-scoring ones go first.
+    #[serde(default = "default_memory_max_lessons")]
+    pub memory_max_lessons: u32,
+    /// Embedding server used to pick the lessons, notes, todos and other
+    /// records relevant to an agent's task; empty uses the Document Chat one,
+    /// and with both empty they are ranked by keywords (BM25) alone.
+    #[serde(default)]
+    pub memory_embedding_url: String,
+    /// Tokens of such records put in an agent's system prompt; 0 = all.
+    #[serde(default = "default_memory_context_tokens")]
+    pub memory_context_tokens: u32,
+
+    // ─ Agent Budgets
+    // Ceilings for the budget an agent run asks for; a request over one is
+    // refused. Unset budget fields use the built-in defaults, capped here.
+    #[serde(default = "default_agent_max_steps")]
+    pub agent_max_steps: u32,
+    #[serde(default = "default_agent_max_depth")]
+    pub agent_max_depth: u8,
+    /// Longest a run may take, in seconds; 0 = no limit.
+    #[serde(default = "default_agent_max_wall_clock_secs")]
+    pub agent_max_wall_clock_secs: u64,
+    /// Most tokens a run may use; 0 = no limit.
+    #[serde(default)]
+    pub agent_max_tokens: u64,
+    /// Longest tool, model or approval timeout a run may set, in seconds.
+    #[serde(default = "default_agent_max_timeout_secs")]
+    pub agent_max_timeout_secs: u64,
+
+    // ─ Agent Approvals
+    // Consulted before every tool call; the first matching rule decides.
+    // Calls no rule matches are asked about when the tool has side effects.
+    #[serde(default)]
+    pub agent_approval_rules: Vec<ApprovalRule>,
+    /// What scheduled runs, with nobody there to ask, do instead of asking.
+    #[serde(default = "default_agent_headless_policy")]
+    pub agent_headless_policy: PolicyAction,
+
+    // ─ Command Sandbox
+    /// Confine `run_command` (Linux only): a read-only root, one writable
+    /// workspace, no network unless allowed, and CPU and memory limits.
+    #[serde(default)]
+    pub agent_sandbox: bool,
+    /// The one writable directory; empty = `agent-workspace` in the config dir.
+    #[serde(default)]
+    pub agent_sandbox_workspace: String,
+    #[serde(default)]
+    pub agent_sandbox_network: bool,
+    /// CPU time per command, in seconds; 0 = no limit.
+    #[serde(default = "default_agent_sandbox_cpu_secs")]
+    pub agent_sandbox_cpu_secs: u64,
+    /// Address space per command, in MiB; 0 = no limit.
+    #[serde(default = "default_agent_sandbox_memory_mb")]
+    pub agent_sandbox_memory_mb: u64,
 
     // ─ UI Settings
     #[serde(default = "default_ui_transparency")]
@@ -578,6 +835,43 @@ impl Default for ServerConfig {
             // Model Indexing / Scan Settings
             model_scan_dirs: Vec::new(),
             searxng_url: "http://localhost:8888".into(),
+            scan_model_stores: true,
+            enrichment_providers: default_enrichment_providers(),
+            vram_budget_gb: 0.0,
+            ram_budget_gb: 0.0,
+            post_download_verify: true,
+            post_download_index: true,
+            post_download_enrich: true,
+            post_download_action: PostDownloadAction::None,
+
+            // Document Chat
+            rag_embedding_url: String::new(),
+            rag_embedding_model: String::new(),
+            rag_top_k: default_rag_top_k(),
+
+            // Agent Memory
+            memory_review_queue: false,
+            memory_max_lessons: default_memory_max_lessons(),
+            memory_embedding_url: String::new(),
+            memory_context_tokens: default_memory_context_tokens(),
+
+            // Agent Budgets
+            agent_max_steps: default_agent_max_steps(),
+            agent_max_depth: default_agent_max_depth(),
+            agent_max_wall_clock_secs: default_agent_max_wall_clock_secs(),
+            agent_max_tokens: 0,
+            agent_max_timeout_secs: default_agent_max_timeout_secs(),
+
+            // Agent Approvals
+            agent_approval_rules: Vec::new(),
+            agent_headless_policy: default_agent_headless_policy(),
+
+            // Command Sandbox
+            agent_sandbox: false,
+            agent_sandbox_workspace: String::new(),
+            agent_sandbox_network: false,
+            agent_sandbox_cpu_secs: default_agent_sandbox_cpu_secs(),
+            agent_sandbox_memory_mb: default_agent_sandbox_memory_mb(),
 
             // UI Settings
             ui_transparency: 0.1,
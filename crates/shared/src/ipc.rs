@@ -17,13 +17,219 @@ pub const AGENT_EVENT: &str = "agent://event";
 pub const CONFIG_CHANGED_EVENT: &str = "config://changed";
 /// Each llama-server stdout/stderr line is emitted as a plain `String` here.
 pub const SERVER_LOG_EVENT: &str = "server://log";
+pub const DOWNLOAD_EVENT: &str = "download://event";
 
 // ── Chat ──────────────────────────────────────────────────────────────────
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
+    /// Stable id in the conversation's message tree; empty until first saved.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub role: String, // "system" | "user" | "assistant"
     pub content: String,
+    /// A thinking model's reasoning, kept apart from `content` and never sent
+    /// back to the model.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reasoning: String,
+    /// How the reply was generated; only set on streamed assistant replies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<GenerationStats>,
+    /// Ids of this message and its alternatives (edits, regenerations) in the
+    /// order they were made; empty when there are none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub siblings: Vec<String>,
+    /// Attached-document passages the reply was given, numbered as cited.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<ChatSource>,
+}
+
+impl ChatMessage {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            id: String::new(),
+            role: role.into(),
+            content: content.into(),
+            reasoning: String::new(),
+            stats: None,
+            siblings: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// A passage retrieved from a conversation's attached documents. `index` is
+/// the number the model cites it by (`[1]`); lines are 1-based and inclusive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChatSource {
+    pub index: u32,
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Enclosing Markdown heading(s), `›`-joined; empty for code and text.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub heading: String,
+    /// Cosine similarity to the question.
+    pub score: f32,
+}
+
+/// A file attached to a conversation for retrieval.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatAttachment {
+    pub id: String,
+    pub path: String,
+    pub chunks: u32,
+    pub added_at: i64,
+}
+
+/// Timing and token counts for one completion, from llama-server's `timings`
+/// and `usage`. Fields the server didn't report stay `None`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    #[serde(default)]
+    pub model: String,
+    /// From sending the request to the first streamed token, measured by us.
+    #[serde(default)]
+    pub ttft_ms: Option<f64>,
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default)]
+    pub prompt_per_second: Option<f64>,
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    #[serde(default)]
+    pub predicted_per_second: Option<f64>,
+}
+
+/// Sampling settings for a chat request, saved with each conversation. Unset
+/// fields are left out of the request, so the server's launch flags apply.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatSampling {
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    #[serde(default)]
+    pub repeat_penalty: Option<f32>,
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    /// Maximum tokens to generate.
+    #[serde(default)]
+    pub n_predict: Option<i32>,
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// GBNF grammar; ignored when `response_format` asks for JSON.
+    #[serde(default)]
+    pub grammar: Option<String>,
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+    /// Token id (or token text) → bias added to its logit.
+    #[serde(default)]
+    pub logit_bias: Vec<(String, f32)>,
+}
+
+impl ChatSampling {
+    /// The launch-time sampling flags of `cfg`, as a starting preset.
+    pub fn from_config(cfg: &crate::ServerConfig) -> Self {
+        Self {
+            temperature: Some(cfg.temp),
+            top_k: Some(cfg.top_k),
+            top_p: Some(cfg.top_p),
+            min_p: Some(cfg.min_p),
+            repeat_penalty: Some(cfg.repeat_penalty),
+            presence_penalty: Some(cfg.presence_penalty),
+            frequency_penalty: Some(cfg.frequency_penalty),
+            seed: (cfg.seed != -1).then_some(cfg.seed),
+            n_predict: (cfg.predict >= 0).then_some(cfg.predict),
+            grammar: Some(cfg.grammar.clone()).filter(|g| !g.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Constrain the reply to JSON, optionally matching a schema.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    JsonObject,
+    JsonSchema { schema: serde_json::Value },
+}
+
+/// A conversation row in the chat list; timestamps are Unix seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub model: String,
+    pub archived: bool,
+    /// Messages on every branch, not just the active one.
+    pub message_count: u32,
+}
+
+/// Return type of `chat_get_conversation` and the branch commands.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Conversation {
+    pub summary: ConversationSummary,
+    pub sampling: ChatSampling,
+    /// The active branch, root first.
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Fields to change with `chat_update_conversation`; `None` leaves a field as is.
+/// Setting a title stops it being regenerated from the first message.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConversationPatch {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub sampling: Option<ChatSampling>,
+    #[serde(default)]
+    pub archived: Option<bool>,
+}
+
+/// One `chat_search` match; `snippet` marks the hit with `[` `]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatSearchHit {
+    pub conversation_id: String,
+    pub title: String,
+    pub role: String,
+    pub snippet: String,
+    pub updated_at: i64,
+}
+
+/// File formats for `chat_export`. ShareGPT and OpenAI JSONL can also be
+/// read back by `chat_import`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatExportFormat {
+    Markdown,
+    /// `[{"conversations": [{"from": "human", "value": …}]}]`
+    Sharegpt,
+    /// One `{"messages": [{"role": …, "content": …}]}` per line, as used for
+    /// OpenAI chat fine-tuning.
+    OpenaiJsonl,
+}
+
+impl ChatExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Sharegpt => "json",
+            Self::OpenaiJsonl => "jsonl",
+        }
+    }
 }
 
 // ── Productivity (todos / notes) ─────────────────────────────────────────────
@@ -56,6 +262,63 @@ pub struct NotesStore {
     pub notes: Vec<Note>,
 }
 
+/// A saved prompt in the library (`prompts.json`). Editing the text adds a
+/// version rather than overwriting; the last version is the current one.
+/// Placeholders are written `{{name}}` (see [`crate::prompts`]).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Oldest first; never empty.
+    pub versions: Vec<PromptVersion>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl PromptTemplate {
+    /// The current text.
+    pub fn body(&self) -> &str {
+        self.versions.last().map_or("", |v| v.body.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PromptVersion {
+    /// 1, 2, 3…
+    pub version: u32,
+    pub body: String,
+    pub saved_at: i64,
+}
+
+/// Arguments for `prompt_save`: a new prompt when `id` is `None`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PromptDraft {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub body: String,
+}
+
+/// A library prompt to render: its current version unless `version` is set,
+/// with values for its user variables. Built-ins like `{{date}}` are filled in
+/// at render time.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PromptRef {
+    pub id: String,
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub vars: std::collections::BTreeMap<String, String>,
+}
+
 /// Arguments for the `chat_send` command. `host`/`port` target either the local
 /// server or a routed instance; `stream_id` correlates the streamed events back
 /// to the in-flight assistant message in the UI.
@@ -66,7 +329,12 @@ pub struct ChatRequest {
     pub port: u16,
     pub model: String,
     pub messages: Vec<ChatMessage>,
-    pub temperature: f32,
+    /// `None` sends no sampling fields at all.
+    #[serde(default)]
+    pub sampling: Option<ChatSampling>,
+    /// The saved conversation, whose attached documents ground the reply.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
 }
 
 /// Return type of `chat_list_models`.
@@ -82,8 +350,19 @@ pub struct ModelList {
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum ChatEvent {
     Token { stream_id: String, delta: String },
-    Done { stream_id: String },
+    /// Reasoning text from a thinking model, streamed apart from the answer.
+    Reasoning { stream_id: String, delta: String },
+    /// Passages from attached documents added to the prompt; sent before the
+    /// first token.
+    Sources { stream_id: String, sources: Vec<ChatSource> },
+    Done {
+        stream_id: String,
+        #[serde(default)]
+        stats: Option<GenerationStats>,
+    },
     Error { stream_id: String, message: String },
+    /// Stopped by `chat_cancel`; `partial` is the text streamed so far.
+    Cancelled { stream_id: String, partial: String },
 }
 
 // ── Agent ───────────────────────────────────────────────────────────────────
@@ -139,6 +418,84 @@ pub struct AgentRequest {
     pub port: u16,
     pub model: String,
     pub task: String,
+    /// A library prompt to use as the task; `task`, if not empty, is added
+    /// after it.
+    #[serde(default)]
+    pub prompt: Option<PromptRef>,
+    /// Limits for this run; unset fields use the defaults, and everything is
+    /// checked against the ceilings in settings.
+    #[serde(default)]
+    pub budget: Option<AgentBudget>,
+}
+
+/// What a caller asks of an agent run. Every field is optional.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentBudget {
+    /// Model calls per agent (each one answers or calls tools).
+    #[serde(default)]
+    pub max_steps: Option<u32>,
+    /// How deep sub-agents may nest.
+    #[serde(default)]
+    pub max_depth: Option<u8>,
+    /// Seconds the whole run, sub-agents included, may take.
+    #[serde(default)]
+    pub wall_clock_secs: Option<u64>,
+    /// Prompt plus completion tokens across the run, sub-agents included.
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    /// Seconds one tool call may take.
+    #[serde(default)]
+    pub tool_timeout_secs: Option<u64>,
+    /// Tool name → seconds, overriding `tool_timeout_secs` for that tool.
+    #[serde(default)]
+    pub tool_timeouts: std::collections::BTreeMap<String, u64>,
+    /// Seconds to wait on the model server before retrying.
+    #[serde(default)]
+    pub llm_timeout_secs: Option<u64>,
+    /// Seconds to wait for an approval before denying.
+    #[serde(default)]
+    pub approval_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// The limits a run actually has: its [`AgentBudget`] with the defaults filled
+/// in. A 0 for the wall clock or tokens means no limit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentLimits {
+    pub max_steps: u32,
+    pub max_depth: u8,
+    pub wall_clock_secs: u64,
+    pub max_tokens: u64,
+    pub tool_timeout_secs: u64,
+    #[serde(default)]
+    pub tool_timeouts: std::collections::BTreeMap<String, u64>,
+    pub llm_timeout_secs: u64,
+    pub approval_timeout_secs: u64,
+    pub temperature: f32,
+}
+
+impl Default for AgentLimits {
+    fn default() -> Self {
+        Self {
+            max_steps: 8,
+            max_depth: 2,
+            wall_clock_secs: 0,
+            max_tokens: 0,
+            tool_timeout_secs: 30,
+            tool_timeouts: Default::default(),
+            llm_timeout_secs: 90,
+            approval_timeout_secs: 120,
+            temperature: 0.2,
+        }
+    }
+}
+
+impl AgentLimits {
+    /// Seconds a call to `tool` may take.
+    pub fn tool_timeout(&self, tool: &str) -> u64 {
+        self.tool_timeouts.get(tool).copied().unwrap_or(self.tool_timeout_secs)
+    }
 }
 
 /// Arguments for `agent_approve`.
@@ -147,6 +504,22 @@ pub struct ApprovalDecision {
     pub agent_id: String,
     pub call_id: String,
     pub approved: bool,
+    /// Also approve later calls of the same tool without asking.
+    #[serde(default)]
+    pub remember: ApprovalScope,
+}
+
+/// How long an approval holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalScope {
+    /// This call only.
+    #[default]
+    Once,
+    /// The rest of the run, sub-agents included.
+    Run,
+    /// Every run until the app is restarted.
+    Session,
 }
 
 /// Streamed on [`AGENT_EVENT`]. `agent_id` identifies the originating agent or
@@ -159,6 +532,9 @@ pub enum AgentEvent {
         parent: Option<String>,
         role: String,
         task: String,
+        /// The limits the run was started with; sub-agents share them.
+        #[serde(default)]
+        limits: AgentLimits,
     },
     Plan {
         agent_id: String,
@@ -174,10 +550,18 @@ pub enum AgentEvent {
         index: u32,
         thought: String,
     },
+    /// Streamed answer text.
     Token {
         agent_id: String,
         delta: String,
     },
+    /// The `Token`s streamed since the last step, `text`, weren't the answer
+    /// after all: the reply went on to call tools (the text comes again as a
+    /// `Step` thought) or the call failed and will be retried.
+    Retract {
+        agent_id: String,
+        text: String,
+    },
     ToolCall {
         agent_id: String,
         call: ToolCall,
@@ -190,6 +574,17 @@ pub enum AgentEvent {
         agent_id: String,
         request: ApprovalRequest,
     },
+    /// How a tool call's approval was decided: by the user, a policy rule,
+    /// an earlier "for this run/session" approval, the headless policy, a
+    /// timeout or cancellation. Policy decisions come without a request.
+    ApprovalResolved {
+        agent_id: String,
+        call_id: String,
+        approved: bool,
+        /// What decided it, e.g. "rule 2: allow run_command when …".
+        #[serde(default)]
+        decided_by: String,
+    },
     SubAgentSpawned {
         agent_id: String,
         child_id: String,
@@ -210,6 +605,90 @@ pub enum AgentEvent {
     },
 }
 
+/// Where a lesson stands. Only approved lessons reach the agent's context;
+/// rejected ones are kept so the same lesson isn't proposed again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LessonStatus {
+    #[default]
+    Approved,
+    Pending,
+    Rejected,
+}
+
+/// Something the agent learned from a run or a chat (`agent_memory.json`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lesson {
+    pub id: String,
+    pub text: String,
+    /// `agent`, `chat` or `task` (the fallback summary of a run).
+    pub source: String,
+    #[serde(default)]
+    pub status: LessonStatus,
+    /// 0–1; how likely the lesson is to matter on later tasks.
+    pub importance: f32,
+    /// Times it was proposed, counting near-duplicates merged into it.
+    pub hits: u32,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+}
+
+/// Where a recorded agent run stands. `Interrupted` runs were still going
+/// when the app closed; they, failed and cancelled runs can be resumed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentRunStatus {
+    #[default]
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+    Interrupted,
+}
+
+impl AgentRunStatus {
+    pub fn resumable(self) -> bool {
+        matches!(self, Self::Failed | Self::Cancelled | Self::Interrupted)
+    }
+}
+
+/// One recorded agent run, as listed by `agent_runs_list`. The id is the
+/// root agent's.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentRunSummary {
+    pub id: String,
+    pub task: String,
+    pub model: String,
+    pub status: AgentRunStatus,
+    /// Unix milliseconds.
+    pub started_at: i64,
+    #[serde(default)]
+    pub finished_at: Option<i64>,
+    /// Steps of the root agent completed so far.
+    pub steps: u32,
+    pub tokens: u64,
+    #[serde(default)]
+    pub final_text: Option<String>,
+}
+
+/// An agent event as recorded, with when it happened (Unix milliseconds).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedAgentEvent {
+    pub at: i64,
+    pub event: AgentEvent,
+}
+
+/// A recorded run with everything that happened in it, sub-agents included,
+/// for `agent_run_get`. Streamed tokens aren't kept; the steps and the final
+/// answer carry the text.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentRun {
+    pub summary: AgentRunSummary,
+    pub limits: AgentLimits,
+    pub plan: Vec<PlanStep>,
+    pub events: Vec<RecordedAgentEvent>,
+}
+
 // ── Shared structs for remaining tabs ────────────────────────────────────────
 
 // ── Model Library ──
@@ -223,13 +702,129 @@ pub struct ScannedModel {
     pub hf_link: String,
     pub github_link: String,
     pub size_info: String,
-    pub status: String, // "pending_enrichment", "enriching", "enriched", "failed"
+    pub status: String, // "pending_enrichment", "enriching", "enriched", "not_found", "failed"
     #[serde(default)]
     pub family: String,
     #[serde(default)]
     pub version: String,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Where the entry came from: `""` for a plain directory scan, otherwise
+    /// `"huggingface"`, `"ollama"` or `"lmstudio"` (see `library::stores`).
+    #[serde(default)]
+    pub source: String,
+    /// Upstream identifier resolved by a store scanner: the Hugging Face repo id
+    /// (`org/name`) or the Ollama reference (`llama3:8b`). Empty for plain files.
+    #[serde(default)]
+    pub repo_id: String,
+}
+
+/// Sort order for [`LibraryQuery`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    #[default]
+    Name,
+    SizeAsc,
+    SizeDesc,
+    Family,
+}
+
+/// Filters for `library_query`. Every field is optional; unset fields don't
+/// filter. `text` matches filename, clean name, family, use-case and tags.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibraryQuery {
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub quant: Option<String>,
+    #[serde(default)]
+    pub min_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub sort: LibrarySort,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Per-dimension counts for the Library sidebar. Each dimension is counted
+/// with every filter applied except its own, so picking a family still shows
+/// the other families to switch to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibraryFacets {
+    pub families: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+    pub quants: Vec<FacetCount>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LibraryQueryResult {
+    pub models: Vec<ScannedModel>,
+    pub facets: LibraryFacets,
+}
+
+/// Space used by indexed models under one root (a scan dir or a model store)
+/// and the free space left on its filesystem.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirUsage {
+    pub dir: String,
+    pub model_count: u64,
+    pub model_bytes: u64,
+    /// Zero when the filesystem couldn't be queried.
+    pub fs_total_bytes: u64,
+    pub fs_free_bytes: u64,
+}
+
+/// Distinct files (not hard/sym links to one file) with identical content.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub size_bytes: u64,
+    pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnusedModel {
+    pub path: String,
+    pub size_bytes: u64,
+    /// Unix seconds of the last `server_start` with this model; `None` = never.
+    pub last_launched: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StorageReport {
+    pub dirs: Vec<DirUsage>,
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Never launched, or not launched within the requested window; largest first.
+    pub unused: Vec<UnusedModel>,
+    /// Bytes freed by removing every extra duplicate copy and every unused model.
+    pub reclaimable_bytes: u64,
+}
+
+/// First half of a guarded delete: `library_delete_prepare` returns this and
+/// nothing is removed until `library_delete_confirm` is called with `token`
+/// before `expires_at` (unix seconds).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteTicket {
+    pub token: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub expires_at: i64,
+}
+
+/// A named snapshot of [`crate::ServerConfig`] stored under `profiles/`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub config: crate::ServerConfig,
 }
 
 // ── Planner / Kanban ──
@@ -323,6 +918,10 @@ pub struct CalendarEvent {
     pub tags: Vec<String>,
     #[serde(default)]
     pub recurrence: Option<String>, // "daily", "weekly", "monthly"
+    /// Fire this library prompt instead of `prompt`, which then keeps a
+    /// preview of it for display.
+    #[serde(default)]
+    pub prompt_ref: Option<PromptRef>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -343,7 +942,108 @@ pub struct LlamaInstance {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DownloadStatus {
     pub is_downloading: bool,
-    pub log: String,
+    pub items: Vec<DownloadItem>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// One file (or git clone) in the download queue.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadItem {
+    pub id: String,
+    /// Source URL, or `repo/path` for Hugging Face files.
+    pub source: String,
+    /// Destination file (or directory, for git clones).
+    pub dest: String,
+    pub state: DownloadState,
+    pub downloaded_bytes: u64,
+    /// Unknown until the server reports a length.
+    pub total_bytes: Option<u64>,
+    pub error: Option<String>,
+    /// What the post-download hooks did, one line per step.
+    #[serde(default)]
+    pub post_log: Vec<String>,
+}
+
+/// Streamed on [`DOWNLOAD_EVENT`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    Added {
+        item: DownloadItem,
+    },
+    Progress {
+        id: String,
+        downloaded_bytes: u64,
+        total_bytes: Option<u64>,
+        bytes_per_sec: u64,
+    },
+    State {
+        id: String,
+        state: DownloadState,
+        error: Option<String>,
+    },
+    /// A post-download hook finished a step (verified, indexed, ...).
+    PostStep {
+        id: String,
+        message: String,
+    },
+}
+
+/// How a download would fit the local [`MemoryBudget`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryFit {
+    /// Fully offloaded to the GPU.
+    Gpu,
+    /// Needs system RAM as well (partial `--n-gpu-layers`).
+    Offload,
+    TooLarge,
+    /// No budget configured or detected.
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MemoryBudget {
+    pub vram_bytes: u64,
+    pub ram_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HfRepoFile {
+    /// Path inside the repo.
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: Option<String>,
+}
+
+/// One downloadable model in a repo: a single GGUF, or every shard of a split one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HfQuantOption {
+    /// `Q4_K_M`, `IQ3_XS`, … or empty when the name carries no quant.
+    pub quant: String,
+    pub files: Vec<HfRepoFile>,
+    pub size_bytes: u64,
+    pub fit: MemoryFit,
+}
+
+/// Returned by `hf_list_repo`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HfRepoListing {
+    pub repo: String,
+    /// Sorted by size, smallest first.
+    pub options: Vec<HfQuantOption>,
+    /// Everything else: `mmproj` projectors, safetensors, configs, READMEs.
+    pub extras: Vec<HfRepoFile>,
+    pub budget: MemoryBudget,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
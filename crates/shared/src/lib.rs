@@ -9,6 +9,8 @@
 
 pub mod config;
 pub mod ipc;
+pub mod prompts;
+pub mod targets;
 
 pub use config::ServerConfig;
 pub use config::ModelOverride;
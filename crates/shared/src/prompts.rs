@@ -0,0 +1,82 @@
+//! `{{variable}}` templates for the prompt library. Parsing and substitution
+//! are pure so the UI can list a template's variables and the backend can
+//! render it; the values of the built-in variables are the backend's job.
+
+/// Filled in when a prompt is rendered rather than typed by the user.
+pub const BUILTIN_VARIABLES: &[&str] = &["date", "time", "datetime", "weekday", "clipboard"];
+
+/// `(start, end, name)` of every well-formed placeholder, in order.
+fn placeholders(body: &str) -> Vec<(usize, usize, &str)> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(open) = body[from..].find("{{").map(|i| from + i) {
+        let Some(close) = body[open + 2..].find("}}").map(|i| open + 2 + i) else {
+            break;
+        };
+        let name = body[open + 2..close].trim();
+        if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+            found.push((open, close + 2, name));
+            from = close + 2;
+        } else {
+            // Not a placeholder (e.g. `{{ a b }}` or a JSON example); look
+            // again from the next brace.
+            from = open + 1;
+        }
+    }
+    found
+}
+
+/// The distinct variable names in `body`, in order of first use.
+pub fn variables(body: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (_, _, name) in placeholders(body) {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// The variables the user has to supply: everything but the built-ins.
+pub fn user_variables(body: &str) -> Vec<String> {
+    variables(body)
+        .into_iter()
+        .filter(|v| !BUILTIN_VARIABLES.contains(&v.as_str()))
+        .collect()
+}
+
+/// Replace each placeholder with `value(name)`; placeholders without a value
+/// stay as written.
+pub fn render(body: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut last = 0;
+    for (start, end, name) in placeholders(body) {
+        out.push_str(&body[last..start]);
+        match value(name) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(&body[start..end]),
+        }
+        last = end;
+    }
+    out.push_str(&body[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_fills_placeholders() {
+        let body = "Summarise {{ topic }} for {{audience}} as of {{date}}. Again: {{topic}}. JSON: {{\"a\": 1}} {{missing}}";
+        assert_eq!(variables(body), vec!["topic", "audience", "date", "missing"]);
+        assert_eq!(user_variables(body), vec!["topic", "audience", "missing"]);
+        let out = render(body, |name| match name {
+            "topic" => Some("GGUF".into()),
+            "audience" => Some("newcomers".into()),
+            "date" => Some("2026-10-19".into()),
+            _ => None,
+        });
+        assert_eq!(out, "Summarise GGUF for newcomers as of 2026-10-19. Again: GGUF. JSON: {{\"a\": 1}} {{missing}}");
+    }
+}
@@ -0,0 +1,274 @@
+//! The download targets file (`t` in the config dir):
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! text:                      <- category header, a folder under the model dir
+//! - stable                   <- optional subfolder, until the next header
+//! Qwen/Qwen3-8B-GGUF         <- Hugging Face repo id
+//! https://host/file.gguf     <- direct file URL
+//! https://github.com/u/r.git <- git repository
+//! ```
+//!
+//! [`parse_targets`] and [`print_targets`] round-trip the format, so the UI can
+//! validate as the user types and the backend can rewrite the file.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Category used for targets that appear before any header.
+pub const DEFAULT_CATEGORY: &str = "mtp";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TargetSource {
+    Url { url: String },
+    Git { url: String },
+    HfRepo { repo: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadTarget {
+    /// 1-based line in the targets file.
+    pub line: usize,
+    pub category: String,
+    pub subfolder: Option<String>,
+    pub source: TargetSource,
+}
+
+impl DownloadTarget {
+    /// Folder relative to the model dir: `category` or `category/subfolder`.
+    pub fn rel_dir(&self) -> String {
+        match &self.subfolder {
+            Some(sub) => format!("{}/{}", self.category, sub),
+            None => self.category.clone(),
+        }
+    }
+
+    /// Short label for lists: file name, `git: repo` or `HF: repo`.
+    pub fn display_name(&self) -> String {
+        match &self.source {
+            TargetSource::Url { url } => url.rsplit('/').next().unwrap_or(url).to_string(),
+            TargetSource::Git { url } => {
+                let name = url.rsplit('/').next().unwrap_or(url);
+                format!("git: {}", name.trim_end_matches(".git"))
+            }
+            TargetSource::HfRepo { repo } => format!("HF: {}", repo),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetError {
+    /// 1-based line in the targets file.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// A folder name: no separators, no `..`, nothing hidden.
+fn check_folder(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("folder name is empty".into());
+    }
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("`{name}` must be a single folder name (no `/`, `..` or leading `.`)"));
+    }
+    Ok(())
+}
+
+fn is_repo_part(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(['.', '-'])
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn parse_source(line: &str) -> Result<TargetSource, String> {
+    if line.contains(char::is_whitespace) {
+        return Err("a target must not contain spaces".into());
+    }
+    if line.ends_with(".git") || is_github_repo_root(line) {
+        return Ok(TargetSource::Git { url: line.to_string() });
+    }
+    if line.starts_with("http://") || line.starts_with("https://") {
+        let rest = line.split_once("://").map_or("", |(_, r)| r);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let file = path.split('?').next().unwrap_or("").rsplit('/').next().unwrap_or("");
+        if host.is_empty() || file.is_empty() {
+            return Err(format!("`{line}` does not point at a file"));
+        }
+        return Ok(TargetSource::Url { url: line.to_string() });
+    }
+    match line.split_once('/') {
+        Some((owner, name)) if is_repo_part(owner) && is_repo_part(name) => {
+            Ok(TargetSource::HfRepo { repo: line.to_string() })
+        }
+        _ => Err(format!(
+            "`{line}` is not a URL, git repository or Hugging Face repo id (owner/name)"
+        )),
+    }
+}
+
+/// `https://github.com/<owner>/<repo>`, with an optional trailing `/`. Deeper
+/// GitHub links, such as release assets, are plain file URLs.
+fn is_github_repo_root(url: &str) -> bool {
+    let Some(path) = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("http://github.com/"))
+    else {
+        return false;
+    };
+    match path.trim_end_matches('/').split_once('/') {
+        Some((owner, repo)) => is_repo_part(owner) && is_repo_part(repo),
+        None => false,
+    }
+}
+
+/// Parse the whole file, collecting every error rather than stopping at the first.
+pub fn parse_targets(text: &str) -> Result<Vec<DownloadTarget>, Vec<TargetError>> {
+    let mut targets = Vec::new();
+    let mut errors = Vec::new();
+    let mut category = DEFAULT_CATEGORY.to_string();
+    let mut subfolder: Option<String> = None;
+
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut fail = |message: String| errors.push(TargetError { line, message });
+
+        if let Some(header) = trimmed.strip_suffix(':') {
+            let header = header.trim();
+            match check_folder(header) {
+                Ok(()) => category = header.to_string(),
+                Err(e) => fail(format!("bad category: {e}")),
+            }
+            subfolder = None;
+            continue;
+        }
+        if let Some(sub) = trimmed.strip_prefix('-') {
+            let sub = sub.trim();
+            match check_folder(sub) {
+                Ok(()) => subfolder = Some(sub.to_string()),
+                Err(e) => fail(format!("bad subfolder: {e}")),
+            }
+            continue;
+        }
+        match parse_source(trimmed) {
+            Ok(source) => targets.push(DownloadTarget {
+                line,
+                category: category.clone(),
+                subfolder: subfolder.clone(),
+                source,
+            }),
+            Err(e) => fail(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(targets)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Render targets back into the file format, emitting a header only when the
+/// category or subfolder changes.
+pub fn print_targets(targets: &[DownloadTarget]) -> String {
+    let mut out = String::new();
+    let mut current: Option<(&str, Option<&str>)> = None;
+    for t in targets {
+        let (cat, sub) = (t.category.as_str(), t.subfolder.as_deref());
+        if current.map(|(c, _)| c) != Some(cat) {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{cat}:\n"));
+            if let Some(sub) = sub {
+                out.push_str(&format!("- {sub}\n"));
+            }
+        } else if current.and_then(|(_, s)| s) != sub {
+            match sub {
+                Some(sub) => out.push_str(&format!("- {sub}\n")),
+                // Dropping back to the bare category needs the header again.
+                None => out.push_str(&format!("{cat}:\n")),
+            }
+        }
+        current = Some((cat, sub));
+        match &t.source {
+            TargetSource::Url { url } | TargetSource::Git { url } => out.push_str(url),
+            TargetSource::HfRepo { repo } => out.push_str(repo),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+# models
+Qwen/Qwen3-8B-GGUF
+text:
+- stable
+https://huggingface.co/org/Repo-GGUF/resolve/main/model-Q4_K_M.gguf
+https://github.com/user/tool.git
+
+vision:
+org/Vision-GGUF
+";
+
+    #[test]
+    fn parses_and_round_trips() {
+        let targets = parse_targets(FILE).unwrap();
+        assert_eq!(targets.len(), 4);
+        assert_eq!(targets[0].category, DEFAULT_CATEGORY);
+        assert_eq!(targets[1].line, 5);
+        assert_eq!(targets[1].rel_dir(), "text/stable");
+        assert!(matches!(targets[2].source, TargetSource::Git { .. }));
+        assert_eq!(targets[3].rel_dir(), "vision");
+        assert_eq!(targets[3].display_name(), "HF: org/Vision-GGUF");
+
+        let printed = print_targets(&targets);
+        let reparsed: Vec<_> = parse_targets(&printed)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.rel_dir(), t.source))
+            .collect();
+        let original: Vec<_> = targets.into_iter().map(|t| (t.rel_dir(), t.source)).collect();
+        assert_eq!(reparsed, original);
+    }
+
+    #[test]
+    fn github_links_are_git_only_for_a_repository() {
+        let source = |line: &str| parse_source(line).unwrap();
+        assert!(matches!(source("https://github.com/user/tool"), TargetSource::Git { .. }));
+        assert!(matches!(source("https://github.com/user/tool/"), TargetSource::Git { .. }));
+        assert!(matches!(source("https://github.com/user/tool.git"), TargetSource::Git { .. }));
+        assert!(matches!(
+            source("https://github.com/ggml-org/llama.cpp/releases/download/b6000/llama-b6000-bin-ubuntu-x64.zip"),
+            TargetSource::Url { .. }
+        ));
+        assert!(matches!(
+            source("https://github.com/user/tool/raw/main/weights/model-Q4_K_M.gguf"),
+            TargetSource::Url { .. }
+        ));
+    }
+
+    #[test]
+    fn reports_every_bad_line() {
+        let errors = parse_targets("../up:\nok/repo\n- \nnot a target\njust-a-word\nhttps://host/\n").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5, 6]);
+        assert!(errors[0].to_string().starts_with("line 1: bad category"));
+    }
+}
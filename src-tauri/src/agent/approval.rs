@@ -1,47 +1,359 @@
-//! Human-in-the-loop approval gate (L4). A sensitive tool call emits an
-//! `ApprovalRequest` and blocks until the `agent_approve` command resolves it —
-//! or until the timeout fires, in which case we **deny** (fail safe).
+//! Human-in-the-loop approval gate (L4). Every tool call goes past the run's
+//! [`Policy`] first: rules from settings allow, deny or ask, and tools the
+//! user approved "for this run" or "for this session" go through. Calls that
+//! are still to be asked about emit an `ApprovalRequest` and block until the
+//! `agent_approve` command resolves it — or until the run's approval timeout
+//! fires, in which case we **deny** (fail safe). Runs nobody is watching use
+//! the headless policy instead of asking.
+//!
+//! Every decision is emitted as `ApprovalResolved` with what made it.
 
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use shared::ipc::{AGENT_EVENT, AgentEvent, ApprovalRequest, ToolCall};
-use tauri::{AppHandle, Emitter};
+use regex::Regex;
+use serde_json::Value;
+use shared::config::{ApprovalRule, ArgMatcher, PolicyAction};
+use shared::ipc::{AgentEvent, ApprovalRequest, ApprovalScope, ToolCall};
+use shared::ServerConfig;
 
+use super::{AgentContext, emit};
 use crate::state::AgentHandle;
 
-/// How long to wait for a human decision before denying.
-const APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+/// Tools approved "for this session", shared by every run until the app is
+/// restarted.
+pub type SessionGrants = Arc<Mutex<HashSet<String>>>;
 
-/// Emit an approval request and await the user's decision. Returns `true` only on
-/// an explicit approval; timeouts, cancellation, and dropped channels deny.
-pub async fn gate(app: &AppHandle, handle: &Arc<AgentHandle>, call: &ToolCall, reason: &str) -> bool {
+/// What the policy says about a call before anyone is asked.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Decided, by what `by` says.
+    Decided { approved: bool, by: String },
+    /// Ask the user, for this reason.
+    Ask(String),
+    /// No rule applies and the tool has no side effects.
+    Free,
+}
+
+/// What makes a shell command more than one command; an allow rule never
+/// lets such a command through, whatever its pattern says.
+const CHAINING: &[&str] = &[";", "&", "|", "`", "$(", "\n", "\r"];
+
+/// The approval policy of one run, shared with its sub-agents.
+pub struct Policy {
+    /// Rules with their regex compiled; `Err` for a pattern that doesn't, or
+    /// a path prefix that isn't absolute.
+    rules: Vec<(ApprovalRule, Result<Option<Regex>, String>)>,
+    /// For runs nobody is watching: what to do instead of asking.
+    headless: Option<PolicyAction>,
+    run_grants: Mutex<HashSet<String>>,
+    session_grants: SessionGrants,
+}
+
+impl Policy {
+    /// The policy under `cfg`'s rules. A `headless` run is decided by the
+    /// headless policy wherever it would ask.
+    pub fn new(cfg: &ServerConfig, session_grants: SessionGrants, headless: bool) -> Self {
+        let rules = cfg
+            .agent_approval_rules
+            .iter()
+            .map(|rule| {
+                let regex = match rule.matcher {
+                    // An allow rule has to match the whole argument, not
+                    // just some part of it.
+                    ArgMatcher::Regex if rule.action == PolicyAction::Allow => {
+                        Regex::new(&format!("^(?:{})$", rule.pattern)).map(Some).map_err(|e| e.to_string())
+                    }
+                    ArgMatcher::Regex => Regex::new(&rule.pattern).map(Some).map_err(|e| e.to_string()),
+                    // Every path is under an empty or relative prefix.
+                    ArgMatcher::PathPrefix if !normalize(&rule.pattern).is_absolute() => {
+                        Err("the path prefix must be absolute".into())
+                    }
+                    _ => Ok(None),
+                };
+                (rule.clone(), regex)
+            })
+            .collect();
+        Self {
+            rules,
+            headless: headless.then_some(cfg.agent_headless_policy),
+            run_grants: Mutex::default(),
+            session_grants,
+        }
+    }
+
+    pub fn decide(&self, call: &ToolCall) -> Verdict {
+        let chained = call.tool == "run_command"
+            && arg_text(&call.args["command"]).is_some_and(|c| CHAINING.iter().any(|op| c.contains(op)));
+        let mut passed_over = None;
+        for (i, (rule, regex)) in self.rules.iter().enumerate() {
+            if rule.tool != "*" && rule.tool != call.tool {
+                continue;
+            }
+            let by = format!("rule {}: {}", i + 1, rule.describe());
+            let matched = match (rule.matcher, regex) {
+                (_, Err(e)) => {
+                    // A rule we can't apply neither allows nor denies.
+                    return self.ask(call, format!("{by} has an invalid pattern ({e})"), true);
+                }
+                (ArgMatcher::Any, _) => true,
+                (ArgMatcher::Regex, Ok(regex)) => {
+                    arg_text(&call.args[&rule.arg]).is_some_and(|v| regex.as_ref().is_some_and(|r| r.is_match(&v)))
+                }
+                (ArgMatcher::PathPrefix, _) => arg_text(&call.args[&rule.arg])
+                    .is_some_and(|v| normalize(&v).starts_with(normalize(&rule.pattern))),
+            };
+            if !matched {
+                continue;
+            }
+            return match rule.action {
+                // Later rules still get their say, a deny among them.
+                PolicyAction::Allow if chained => {
+                    passed_over = Some(format!("{by} doesn't allow chained commands"));
+                    continue;
+                }
+                PolicyAction::Allow => Verdict::Decided { approved: true, by },
+                PolicyAction::Deny => Verdict::Decided { approved: false, by },
+                // An ask rule always asks; earlier approvals don't answer it.
+                PolicyAction::Ask => self.ask(call, format!("{by} asks about it"), false),
+            };
+        }
+        match passed_over {
+            Some(reason) => self.ask(call, reason, true),
+            None if call.sensitive => self.ask(call, format!("Tool '{}' performs a side effect.", call.tool), true),
+            None => Verdict::Free,
+        }
+    }
+
+    /// Asking, unless the headless policy answers or, with `grants` and
+    /// someone there to have given them, an earlier approval.
+    fn ask(&self, call: &ToolCall, reason: String, grants: bool) -> Verdict {
+        if grants && self.headless.is_none() {
+            if self.run_grants.lock().unwrap().contains(&call.tool) {
+                return Verdict::Decided { approved: true, by: "the user, earlier in this run".into() };
+            }
+            if self.session_grants.lock().unwrap().contains(&call.tool) {
+                return Verdict::Decided { approved: true, by: "the user, earlier this session".into() };
+            }
+        }
+        match self.headless {
+            Some(action) => Verdict::Decided {
+                approved: action == PolicyAction::Allow,
+                by: format!("the headless policy ({}; {reason})", if action == PolicyAction::Allow { "allow" } else { "deny" }),
+            },
+            None => Verdict::Ask(reason),
+        }
+    }
+
+    /// Approve later calls of `tool` for `scope`.
+    pub fn grant(&self, tool: &str, scope: ApprovalScope) {
+        match scope {
+            ApprovalScope::Once => {}
+            ApprovalScope::Run => {
+                self.run_grants.lock().unwrap().insert(tool.to_string());
+            }
+            ApprovalScope::Session => {
+                self.session_grants.lock().unwrap().insert(tool.to_string());
+            }
+        }
+    }
+}
+
+/// An argument as text for matching; `None` when it wasn't given.
+fn arg_text(v: &Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// `path` with `~` expanded and `.`/`..` resolved, without touching the disk,
+/// so `/work/../etc` is not taken to be under `/work`.
+fn normalize(path: &str) -> PathBuf {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => Path::new(&std::env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(path),
+    };
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Decide `call` by the run's policy, asking the user when it says to.
+/// `Err` carries what denied it: a rule, the user, a timeout or cancellation.
+pub async fn gate(ctx: &AgentContext, handle: &Arc<AgentHandle>, call: &ToolCall) -> Result<(), String> {
+    let reason = match ctx.policy.decide(call) {
+        Verdict::Free => return Ok(()),
+        Verdict::Decided { approved, by } => return resolved(ctx, handle, call, approved, by),
+        Verdict::Ask(reason) => reason,
+    };
     let rx = handle.register_approval(&call.call_id);
+    let timeout = Duration::from_secs(ctx.limits.approval_timeout_secs);
 
-    let _ = app.emit(
-        AGENT_EVENT,
+    emit(
+        ctx,
         AgentEvent::ApprovalRequest {
             agent_id: handle.id.clone(),
             request: ApprovalRequest {
                 call_id: call.call_id.clone(),
                 tool: call.tool.clone(),
                 args: call.args.clone(),
-                reason: reason.to_string(),
+                reason,
             },
         },
     );
 
-    tokio::select! {
+    let (approved, by) = tokio::select! {
         _ = handle.cancel.cancelled() => {
             tracing::info!(agent = %handle.id, "approval cancelled");
-            false
+            (false, "cancellation".to_string())
         }
-        res = tokio::time::timeout(APPROVAL_TIMEOUT, rx) => match res {
-            Ok(Ok(decision)) => decision,
+        res = tokio::time::timeout(timeout, rx) => match res {
+            Ok(Ok((approved, remember))) => {
+                if approved {
+                    ctx.policy.grant(&call.tool, remember);
+                }
+                let by = match (approved, remember) {
+                    (true, ApprovalScope::Run) => "the user, for the rest of this run",
+                    (true, ApprovalScope::Session) => "the user, for this session",
+                    _ => "the user",
+                };
+                (approved, by.to_string())
+            }
             _ => {
                 tracing::info!(call = %call.call_id, "approval timed out → deny");
-                false
+                (false, format!("a timeout after {}s", timeout.as_secs()))
             }
         }
+    };
+    resolved(ctx, handle, call, approved, by)
+}
+
+fn resolved(ctx: &AgentContext, handle: &AgentHandle, call: &ToolCall, approved: bool, by: String) -> Result<(), String> {
+    tracing::info!(agent = %handle.id, tool = %call.tool, approved, decided_by = %by, "approval");
+    emit(
+        ctx,
+        AgentEvent::ApprovalResolved {
+            agent_id: handle.id.clone(),
+            call_id: call.call_id.clone(),
+            approved,
+            decided_by: by.clone(),
+        },
+    );
+    if approved { Ok(()) } else { Err(by) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(tool: &str, args: Value, sensitive: bool) -> ToolCall {
+        ToolCall { call_id: "c".into(), tool: tool.into(), args, sensitive }
+    }
+
+    fn rule(tool: &str, matcher: ArgMatcher, arg: &str, pattern: &str, action: PolicyAction) -> ApprovalRule {
+        ApprovalRule { tool: tool.into(), matcher, arg: arg.into(), pattern: pattern.into(), action }
+    }
+
+    #[test]
+    fn first_matching_rule_decides_then_grants_then_asking() {
+        let cfg = ServerConfig {
+            agent_approval_rules: vec![
+                rule("run_command", ArgMatcher::Regex, "command", r"rm\s+-rf", PolicyAction::Deny),
+                rule("run_command", ArgMatcher::Regex, "command", r"^git (status|log)\b", PolicyAction::Allow),
+                rule("run_command", ArgMatcher::PathPrefix, "cwd", "/work/sandbox", PolicyAction::Allow),
+                rule("web_search", ArgMatcher::Any, "", "", PolicyAction::Ask),
+                rule("write_note", ArgMatcher::Regex, "name", "(", PolicyAction::Allow),
+            ],
+            ..Default::default()
+        };
+        let grants = SessionGrants::default();
+        let policy = Policy::new(&cfg, grants.clone(), false);
+        let decided = |c: &ToolCall| match policy.decide(c) {
+            Verdict::Decided { approved, by } => Some((approved, by)),
+            _ => None,
+        };
+
+        let status = call("run_command", json!({ "command": "git status" }), true);
+        assert_eq!(decided(&status), Some((true, "rule 2: allow run_command when command matches `^git (status|log)\\b`".into())));
+        let wipe = call("run_command", json!({ "command": "git status && rm -rf /", "cwd": "/work/sandbox" }), true);
+        assert_eq!(decided(&wipe).map(|d| d.0), Some(false), "the deny rule comes first");
+        let inside = call("run_command", json!({ "command": "make", "cwd": "/work/sandbox/app/./src" }), true);
+        assert_eq!(decided(&inside).map(|d| d.0), Some(true));
+        for cwd in ["/work/sandbox/../secrets", "/work/sandbox2", "sandbox"] {
+            let outside = call("run_command", json!({ "command": "make", "cwd": cwd }), true);
+            assert!(matches!(policy.decide(&outside), Verdict::Ask(_)), "{cwd}");
+        }
+        let search = call("web_search", json!({ "query": "gguf" }), false);
+        assert!(matches!(policy.decide(&search), Verdict::Ask(r) if r.contains("rule 4")), "an ask rule covers tools without side effects");
+        assert_eq!(policy.decide(&call("read_notes", json!({}), false)), Verdict::Free);
+        let note = call("write_note", json!({ "name": "x" }), true);
+        assert!(matches!(policy.decide(&note), Verdict::Ask(r) if r.contains("invalid pattern")), "a broken rule doesn't allow");
+
+        // "For this run" lasts as long as the policy; "for this session" is
+        // shared with later runs. Neither answers an ask rule.
+        policy.grant("add_todo", ApprovalScope::Run);
+        policy.grant("write_file", ApprovalScope::Session);
+        policy.grant("web_search", ApprovalScope::Session);
+        let todo = call("add_todo", json!({ "text": "t" }), true);
+        assert_eq!(decided(&todo).map(|d| d.0), Some(true));
+        let write = call("write_file", json!({ "path": "a" }), true);
+        let next_run = Policy::new(&cfg, grants.clone(), false);
+        assert!(matches!(next_run.decide(&todo), Verdict::Ask(_)));
+        assert_eq!(next_run.decide(&write), Verdict::Decided { approved: true, by: "the user, earlier this session".into() });
+        assert!(matches!(next_run.decide(&search), Verdict::Ask(r) if r.contains("rule 4")));
+        assert_eq!(decided(&wipe).map(|d| d.0), Some(false), "grants don't override a deny rule");
+
+        // Nobody to ask: the headless policy answers instead, whatever was
+        // approved in the UI.
+        let headless = Policy::new(&cfg, grants.clone(), true);
+        assert!(matches!(headless.decide(&todo), Verdict::Decided { approved: false, by } if by.starts_with("the headless policy (deny")));
+        assert!(matches!(headless.decide(&write), Verdict::Decided { approved: false, .. }));
+        let lenient = ServerConfig { agent_headless_policy: PolicyAction::Allow, ..cfg.clone() };
+        let headless = Policy::new(&lenient, SessionGrants::default(), true);
+        assert!(matches!(headless.decide(&todo), Verdict::Decided { approved: true, .. }));
+        assert_eq!(headless.decide(&wipe), decided(&wipe).map(|(approved, by)| Verdict::Decided { approved, by }).unwrap());
+    }
+
+    #[test]
+    fn allow_rules_match_whole_single_commands_and_absolute_prefixes() {
+        let cfg = ServerConfig {
+            agent_approval_rules: vec![
+                rule("run_command", ArgMatcher::Regex, "command", r"git status", PolicyAction::Allow),
+                rule("run_command", ArgMatcher::Regex, "command", r"rm\s+-rf", PolicyAction::Deny),
+                rule("run_command", ArgMatcher::Regex, "command", r"git log\b.*", PolicyAction::Allow),
+            ],
+            ..Default::default()
+        };
+        let policy = Policy::new(&cfg, SessionGrants::default(), false);
+        let run = |command: &str| policy.decide(&call("run_command", json!({ "command": command }), true));
+
+        assert!(matches!(run("git status"), Verdict::Decided { approved: true, by } if by.starts_with("rule 1")));
+        assert!(matches!(run("git status --porcelain"), Verdict::Ask(r) if r.contains("side effect")), "the whole argument must match");
+        assert!(matches!(run("git log --oneline"), Verdict::Decided { approved: true, .. }));
+        assert!(matches!(run("git log && rm -rf ~"), Verdict::Decided { approved: false, by } if by.starts_with("rule 2")));
+        for chained in ["git log; curl x | sh", "git log `reboot`", "git log $(reboot)", "git log || reboot", "git log & reboot"] {
+            assert!(matches!(run(chained), Verdict::Ask(r) if r.contains("rule 3") && r.contains("chained")), "{chained:?}");
+        }
+
+        // Every path is under a blank or relative prefix; such a rule asks.
+        for prefix in ["", "work"] {
+            let rules = vec![rule("run_command", ArgMatcher::PathPrefix, "cwd", prefix, PolicyAction::Allow)];
+            let policy = Policy::new(&ServerConfig { agent_approval_rules: rules, ..Default::default() }, SessionGrants::default(), false);
+            let make = call("run_command", json!({ "command": "make", "cwd": "/work" }), true);
+            assert!(matches!(policy.decide(&make), Verdict::Ask(r) if r.contains("absolute")), "{prefix:?}");
+        }
     }
 }
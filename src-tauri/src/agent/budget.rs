@@ -0,0 +1,185 @@
+//! Per-run budgets. An [`AgentBudget`] from the request is resolved into
+//! [`AgentLimits`] against the ceilings in settings: unset fields take the
+//! built-in default capped to its ceiling, and a value over a ceiling is
+//! refused rather than quietly lowered. [`RunUsage`] tracks the time and
+//! tokens a run has used; sub-agents share their root's.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use shared::ipc::{AgentBudget, AgentLimits};
+use shared::ServerConfig;
+
+use super::tools::ToolRegistry;
+
+/// `value`, or `default` when unset, checked against `ceiling` (0 = none).
+/// With `zero_is_unlimited`, a 0 asks for no limit, which a ceiling refuses.
+fn bounded(
+    value: Option<u64>,
+    default: u64,
+    ceiling: u64,
+    zero_is_unlimited: bool,
+    what: &str,
+    setting: &str,
+) -> Result<u64, String> {
+    let over = |v: u64| ceiling > 0 && (v > ceiling || (zero_is_unlimited && v == 0));
+    match value {
+        Some(v) if over(v) => {
+            let asked = if v == 0 { "no limit".to_string() } else { v.to_string() };
+            Err(format!("{what} of {asked} is over the ceiling of {ceiling} in Settings → Agent Budgets ({setting})"))
+        }
+        Some(v) => Ok(v),
+        None if over(default) => Ok(ceiling),
+        None => Ok(default),
+    }
+}
+
+/// The limits a run gets for `budget` under `cfg`'s ceilings.
+pub fn resolve(budget: &AgentBudget, cfg: &ServerConfig) -> Result<AgentLimits, String> {
+    let d = AgentLimits::default();
+    let timeout = |value: Option<u64>, default: u64, what: &str| {
+        if value == Some(0) {
+            return Err(format!("{what} must be at least 1 second"));
+        }
+        bounded(value, default, cfg.agent_max_timeout_secs, false, what, "agent_max_timeout_secs")
+    };
+    let max_steps = bounded(
+        budget.max_steps.map(u64::from),
+        d.max_steps.into(),
+        cfg.agent_max_steps.into(),
+        false,
+        "A step budget",
+        "agent_max_steps",
+    )?;
+    if max_steps == 0 {
+        return Err("An agent needs at least one step".into());
+    }
+    let max_depth = bounded(
+        budget.max_depth.map(u64::from),
+        d.max_depth.into(),
+        cfg.agent_max_depth.into(),
+        false,
+        "A sub-agent depth",
+        "agent_max_depth",
+    )?;
+    let wall_clock_secs = bounded(
+        budget.wall_clock_secs,
+        d.wall_clock_secs,
+        cfg.agent_max_wall_clock_secs,
+        true,
+        "A time limit",
+        "agent_max_wall_clock_secs",
+    )?;
+    let max_tokens = bounded(budget.max_tokens, d.max_tokens, cfg.agent_max_tokens, true, "A token budget", "agent_max_tokens")?;
+    let registry = ToolRegistry::builtin();
+    let mut tool_timeouts = budget.tool_timeouts.clone();
+    for (tool, secs) in &mut tool_timeouts {
+        if registry.get(tool).is_none() {
+            return Err(format!("No tool named `{tool}` to set a timeout for"));
+        }
+        *secs = timeout(Some(*secs), 0, &format!("A `{tool}` timeout"))?;
+    }
+    let temperature = budget.temperature.unwrap_or(d.temperature);
+    if !(0.0..=2.0).contains(&temperature) {
+        return Err(format!("Temperature {temperature} is outside 0–2"));
+    }
+    Ok(AgentLimits {
+        max_steps: max_steps as u32,
+        max_depth: max_depth as u8,
+        wall_clock_secs,
+        max_tokens,
+        tool_timeout_secs: timeout(budget.tool_timeout_secs, d.tool_timeout_secs, "A tool timeout")?,
+        tool_timeouts,
+        llm_timeout_secs: timeout(budget.llm_timeout_secs, d.llm_timeout_secs, "A model timeout")?,
+        approval_timeout_secs: timeout(budget.approval_timeout_secs, d.approval_timeout_secs, "An approval timeout")?,
+        temperature,
+    })
+}
+
+/// Time and tokens used so far by a run and its sub-agents.
+#[derive(Debug)]
+pub struct RunUsage {
+    started: Instant,
+    tokens: AtomicU64,
+}
+
+impl Default for RunUsage {
+    fn default() -> Self {
+        Self { started: Instant::now(), tokens: AtomicU64::new(0) }
+    }
+}
+
+impl RunUsage {
+    pub fn add_tokens(&self, n: u64) {
+        self.tokens.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn tokens(&self) -> u64 {
+        self.tokens.load(Ordering::Relaxed)
+    }
+
+    /// Time left before the wall-clock limit; `None` when there is none.
+    pub fn time_left(&self, limits: &AgentLimits) -> Option<Duration> {
+        (limits.wall_clock_secs > 0)
+            .then(|| Duration::from_secs(limits.wall_clock_secs).saturating_sub(self.started.elapsed()))
+    }
+
+    /// Why the run can't take another step, if it can't.
+    pub fn exhausted(&self, limits: &AgentLimits) -> Option<String> {
+        if self.time_left(limits).is_some_and(|left| left.is_zero()) {
+            return Some(format!("time limit of {}s reached", limits.wall_clock_secs));
+        }
+        if limits.max_tokens > 0 && self.tokens() >= limits.max_tokens {
+            return Some(format!("token budget of {} used up ({} used)", limits.max_tokens, self.tokens()));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_defaults_under_the_ceilings_and_refuses_requests_over_them() {
+        let cfg = ServerConfig::default();
+        let limits = resolve(&AgentBudget::default(), &cfg).unwrap();
+        assert_eq!(
+            limits,
+            AgentLimits { wall_clock_secs: cfg.agent_max_wall_clock_secs, ..AgentLimits::default() },
+            "no wall clock by default, but the ceiling applies"
+        );
+
+        let mut budget = AgentBudget { max_steps: Some(30), max_tokens: Some(20_000), temperature: Some(0.7), ..Default::default() };
+        budget.tool_timeouts.insert("run_command".into(), 300);
+        let limits = resolve(&budget, &cfg).unwrap();
+        assert_eq!((limits.max_steps, limits.max_tokens, limits.temperature), (30, 20_000, 0.7));
+        assert_eq!((limits.tool_timeout("run_command"), limits.tool_timeout("read_file")), (300, 30));
+
+        let tight = ServerConfig { agent_max_steps: 5, agent_max_tokens: 1000, agent_max_timeout_secs: 60, ..Default::default() };
+        let limits = resolve(&AgentBudget::default(), &tight).unwrap();
+        assert_eq!((limits.max_steps, limits.max_tokens, limits.llm_timeout_secs), (5, 1000, 60), "defaults are capped");
+        let err = resolve(&budget, &tight).unwrap_err();
+        assert!(err.contains("agent_max_steps"), "{err}");
+        let unlimited = AgentBudget { max_tokens: Some(0), ..Default::default() };
+        assert!(resolve(&unlimited, &tight).unwrap_err().contains("agent_max_tokens"));
+
+        let bad = [
+            AgentBudget { max_steps: Some(0), ..Default::default() },
+            AgentBudget { temperature: Some(3.0), ..Default::default() },
+            AgentBudget { tool_timeout_secs: Some(0), ..Default::default() },
+            AgentBudget { tool_timeouts: [("no_such_tool".to_string(), 5)].into(), ..Default::default() },
+        ];
+        for budget in bad {
+            assert!(resolve(&budget, &cfg).is_err(), "{budget:?}");
+        }
+
+        let usage = RunUsage::default();
+        let limits = AgentLimits { max_tokens: 100, ..Default::default() };
+        usage.add_tokens(60);
+        assert_eq!(usage.exhausted(&limits), None);
+        usage.add_tokens(40);
+        assert!(usage.exhausted(&limits).unwrap().contains("token budget"));
+        assert_eq!(usage.time_left(&limits), None);
+    }
+}
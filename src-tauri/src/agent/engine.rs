@@ -1,27 +1,26 @@
 //! The ReAct execution loop (L1), integrating planning (L2), sub-agents (L3), and
 //! approval/timeout/retry/cancellation (L4).
 //!
-//! Flow per run: emit `Started` → build a plan → loop up to [`MAX_STEPS`]:
-//! call the model with the tool schemas; if it returns tool calls, execute each
-//! (gating sensitive ones behind approval, bounding every call by a timeout +
-//! retries) and feed results back; otherwise treat the content as the final
-//! answer, persist a lesson, and finish.
-
+//! Flow per run: emit `Started` → build a plan → loop up to the run's
+//! `max_steps`: call the model with the tool schemas; if it returns tool calls,
+//! execute each (gating sensitive ones behind approval, bounding every call by
+//! a timeout + retries) and feed results back; otherwise treat the content as
+//! the final answer, persist a lesson, and finish. A run that runs out of time
+//! or tokens (see [`super::budget`]) stops with an error.
+
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
 use serde_json::{Value, json};
-use shared::ipc::{AGENT_EVENT, AgentEvent, PlanStatus, ToolCall, ToolResult};
-use tauri::{AppHandle, Emitter};
+use shared::ipc::{AgentEvent, PlanStatus, ToolCall, ToolResult};
 
+use super::runs::{CANCELLED, Checkpoint};
 use super::tools::{SPAWN_SUBAGENT, ToolContext, ToolRegistry};
-use super::{AgentContext, MAX_STEPS, approval, llm, memory::MemoryManager, planner, supervisor};
+use super::{AgentContext, approval, emit, llm, memory::MemoryManager, planner, supervisor};
 use crate::state::AgentHandle;
 use crate::util::new_id;
-use crate::commands::remaining::log_monitor_event_internal;
 
-/// Per-tool execution budget (the sensitive `run_command` also self-limits).
-const TOOL_TIMEOUT: Duration = Duration::from_secs(30);
 const TOOL_RETRIES: u32 = 2;
 
 /// Circuit breaker: if we see this many consecutive non-retryable LLM errors
@@ -142,19 +141,24 @@ pub async fn run(
     role: String,
     task: String,
     depth: u8,
+    resume: Option<Checkpoint>,
 ) -> String {
-    let app = ctx.app.clone();
+    let root = parent.is_none();
+    if root {
+        ctx.run.start(&ctx, &role, &task);
+    }
     emit(
-        &app,
+        &ctx,
         AgentEvent::Started {
             agent_id: handle.id.clone(),
             parent,
             role: role.clone(),
             task: task.clone(),
+            limits: ctx.limits.clone(),
         },
     );
 
-    log_monitor_event_internal(
+    ctx.sink.activity(
         &handle.id,
         "Active",
         &format!("Starting task: {}", truncate(&task, 50)),
@@ -165,93 +169,44 @@ pub async fn run(
     let tool_ctx = ToolContext {
         searxng_url: ctx.searxng_url.clone(),
         config_dir: ctx.config_dir.clone(),
+        sandbox: ctx.sandbox.clone(),
     };
     let memory = MemoryManager::new(&ctx.config_dir);
 
-    // ── L2: plan ──────────────────────────────────────────────────────────
-    let plan = planner::make_plan(&ctx, &task, &handle.cancel).await;
+    // ── L2: plan, or the one a resumed run already had ────────────────────
+    let (plan, mut messages, first_step) = match resume {
+        Some(cp) => (cp.plan, cp.messages, cp.steps),
+        None => {
+            let plan = planner::make_plan(&ctx, &task, &handle.cancel).await;
+            (plan, seed(&ctx, &role, &task, &memory).await, 0)
+        }
+    };
     emit(
-        &app,
+        &ctx,
         AgentEvent::Plan {
             agent_id: handle.id.clone(),
             steps: plan.clone(),
         },
     );
 
-    // ── Load MCP context ─────────────────────────────────────────────────
-    let mcp_context = match std::fs::read_to_string(ctx.config_dir.join("mcp_registry.json")) {
-        Ok(content) => {
-            if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&content) {
-                let servers = map.get("mcpServers").or_else(|| map.get("mcp_servers")).and_then(|v| v.as_object());
-                if let Some(servers) = servers {
-                    if !servers.is_empty() {
-                        let mut prompt_part = "\n\nAvailable Model Context Protocol (MCP) servers and tools (you can run them using the `call_mcp_tool` tool):\n".to_string();
-                        for (name, server_val) in servers {
-                            prompt_part.push_str(&format!("- Server '{}':\n", name));
-                            if let Some(tools_arr) = server_val.get("tools").and_then(|v| v.as_array()) {
-                                for t in tools_arr {
-                                    if let Some(t_str) = t.as_str() {
-                                        prompt_part.push_str(&format!("  - Tool '{}'\n", t_str));
-                                    }
-                                }
-                            } else {
-                                prompt_part.push_str("  - (no pre-listed tools, inspect server or invoke its tools)\n");
-                            }
-                        }
-                        prompt_part
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            }
-        }
-        Err(_) => String::new(),
-    };
-
-    // Load all agent rules natively if present (AGENTS.md, CLAUDE.md, .cursorrules, etc.)
-    let agents_md_context = load_all_agent_rules(&ctx.config_dir);
-
-    // ── Conversation seed ─────────────────────────────────────────────────
-    let system = format!(
-        "You are a {role} agent. You have autonomous capabilities to complete the user's task using the provided tools. \
-         {}\
-         \n\n=== AUTONOMOUS DATA WRITE GUIDELINES ===\n\
-         If you determine that writing or updating user data is necessary to complete the task or improve future runs, you have full authority to execute write operations autonomously:\n\
-         - Use `write_note` to record documentation, analysis, or persistent knowledge.\n\
-         - Use `add_todo` / `complete_todo` to log and track checklist tasks.\n\
-         - Use `add_calendar_event` / `delete_calendar_event` to schedule, reschedule, or clear calendar events.\n\
-         - Use `add_planner_task` / `update_planner_task` / `delete_planner_task` to create, transition, or resolve tasks on the Kanban board.\n\
-         \n=== SELF-IMPROVEMENT & MEMORY LOOP ===\n\
-         Read the 'Lessons learned from previous tasks' and other database records carefully. You are a self-learning agent: use this context to refine your plans, adopt user preferences, correct past errors, and avoid duplicate actions. Ensure your decisions build on past learnings.\n\n\
-         Think step-by-step, call tools when useful, and stop when done by replying with the final answer (no tool call).{}{}",
-        agents_md_context,
-        memory.context(),
-        mcp_context
-    );
-    let mut messages = vec![
-        json!({ "role": "system", "content": system }),
-        json!({ "role": "user", "content": task }),
-    ];
-
     let specs = registry.specs();
     let mut final_text = String::from("No answer produced.");
 
     let mut consecutive_llm_failures: u32 = 0;
 
-    for step in 0..MAX_STEPS {
+    for step in first_step..ctx.limits.max_steps {
         if handle.cancel.is_cancelled() {
-            emit(&app, err(&handle.id, "cancelled by user"));
+            emit(&ctx, err(&handle.id, CANCELLED));
             return "Cancelled.".to_string();
         }
+        if let Some(why) = ctx.usage.exhausted(&ctx.limits) {
+            return out_of_budget(&ctx, &handle.id, &why);
+        }
 
         // Surface plan progress loosely against step index.
         if let Some(s) = plan.get(step as usize) {
             emit(
-                &app,
+                &ctx,
                 AgentEvent::PlanUpdate {
                     agent_id: handle.id.clone(),
                     step_id: s.id.clone(),
@@ -260,16 +215,40 @@ pub async fn run(
             );
         }
 
-        let reply = match llm::call(&ctx, &messages, Some(&specs), 0.2, &handle.cancel).await {
+        // Stream the reply so the UI sees the answer as it is written. Until
+        // the reply ends we can't tell an answer from a preface to tool calls.
+        let mut streamed = String::new();
+        let call = llm::call_streaming(&ctx, &messages, Some(&specs), ctx.limits.temperature, &handle.cancel, |delta| {
+            streamed.push_str(delta);
+            emit(
+                &ctx,
+                AgentEvent::Token {
+                    agent_id: handle.id.clone(),
+                    delta: delta.to_string(),
+                },
+            );
+        });
+        let result = within(&ctx, call).await;
+        let retract = |streamed: String| {
+            if !streamed.is_empty() {
+                emit(&ctx, AgentEvent::Retract { agent_id: handle.id.clone(), text: streamed });
+            }
+        };
+        let Some(result) = result else {
+            retract(streamed);
+            return out_of_budget(&ctx, &handle.id, &format!("time limit of {}s reached", ctx.limits.wall_clock_secs));
+        };
+        let reply = match result {
             Ok(r) => {
                 consecutive_llm_failures = 0;
                 r
             }
             Err(llm::LlmError::Cancelled) => {
-                emit(&app, err(&handle.id, "cancelled by user"));
+                emit(&ctx, err(&handle.id, CANCELLED));
                 return "Cancelled.".to_string();
             }
             Err(e) => {
+                retract(streamed);
                 consecutive_llm_failures += 1;
                 let msg = e.to_string();
                 tracing::warn!(agent = %handle.id, attempt = consecutive_llm_failures, error = %msg, "llm call failed");
@@ -279,7 +258,7 @@ pub async fn run(
                     } else {
                         msg
                     };
-                    emit(&app, err(&handle.id, &why));
+                    emit(&ctx, err(&handle.id, &why));
                     return format!("Error: {}", why);
                 }
                 // Retryable, under threshold: tell the model about it and let the
@@ -289,24 +268,19 @@ pub async fn run(
             }
         };
 
-        // No tool calls → the model is answering. We're done.
+        // No tool calls → the model is answering, and the answer has been
+        // streamed. We're done.
         if reply.tool_calls.is_empty() {
             final_text = reply.content.clone();
-            emit(
-                &app,
-                AgentEvent::Token {
-                    agent_id: handle.id.clone(),
-                    delta: final_text.clone(),
-                },
-            );
-            mark_done(&app, &handle.id, &plan);
+            mark_done(&ctx, &handle.id, &plan);
             break;
         }
+        retract(streamed);
 
         // Emit the model's reasoning preface (if any) as a step thought.
         if !reply.content.trim().is_empty() {
             emit(
-                &app,
+                &ctx,
                 AgentEvent::Step {
                     agent_id: handle.id.clone(),
                     index: step,
@@ -347,27 +321,29 @@ pub async fn run(
         // Execute each call, appending a tool response message for each.
         for call in &calls {
             if handle.cancel.is_cancelled() {
-                emit(&app, err(&handle.id, "cancelled by user"));
+                emit(&ctx, err(&handle.id, CANCELLED));
                 return "Cancelled.".to_string();
             }
             emit(
-                &app,
+                &ctx,
                 AgentEvent::ToolCall {
                     agent_id: handle.id.clone(),
                     call: call.clone(),
                 },
             );
 
-            log_monitor_event_internal(
+            ctx.sink.activity(
                 &handle.id,
                 "Active",
                 &format!("Running tool '{}'", call.tool),
                 &format!("Invoked tool '{}' with arguments: {}", call.tool, call.args),
             );
 
-            let result = execute_call(&ctx, &handle, &registry, &tool_ctx, call, depth).await;
+            let Some(result) = within(&ctx, execute_call(&ctx, &handle, &registry, &tool_ctx, call, depth)).await else {
+                return out_of_budget(&ctx, &handle.id, &format!("time limit of {}s reached", ctx.limits.wall_clock_secs));
+            };
 
-            log_monitor_event_internal(
+            ctx.sink.activity(
                 &handle.id,
                 "Active",
                 &format!("Finished tool '{}'", call.tool),
@@ -375,7 +351,7 @@ pub async fn run(
             );
 
             emit(
-                &app,
+                &ctx,
                 AgentEvent::ToolResult {
                     agent_id: handle.id.clone(),
                     result: result.clone(),
@@ -387,6 +363,10 @@ pub async fn run(
                 "content": result.output,
             }));
         }
+        // The step is complete: a resumed run picks up after it.
+        if root {
+            ctx.run.checkpoint(step + 1, &messages, ctx.usage.tokens());
+        }
     }
 
     // ── Reflection: keep a one-line lesson for future runs (best effort) ────
@@ -405,7 +385,7 @@ pub async fn run(
             Ok(Ok(reply)) => {
                 let lesson = reply.content.trim().trim_matches('"').trim().to_string();
                 if !lesson.is_empty() {
-                    memory.add_lesson(&lesson);
+                    memory.add_lesson(&lesson, "agent", chrono::Utc::now().timestamp());
                     lesson_added = true;
                 }
             }
@@ -417,19 +397,20 @@ pub async fn run(
             }
         }
         if !lesson_added {
-            memory.add_lesson(&format!("Task '{}' → {}", truncate(&task, 80), truncate(&final_text, 160)));
+            let summary = format!("Task '{}' → {}", truncate(&task, 80), truncate(&final_text, 160));
+            memory.add_lesson(&summary, "task", chrono::Utc::now().timestamp());
         }
     }
 
     emit(
-        &app,
+        &ctx,
         AgentEvent::Done {
             agent_id: handle.id.clone(),
             final_text: final_text.clone(),
         },
     );
 
-    log_monitor_event_internal(
+    ctx.sink.activity(
         &handle.id,
         "Idle",
         "Idle",
@@ -439,8 +420,72 @@ pub async fn run(
     final_text
 }
 
-/// Run one tool call: route `spawn_subagent` to the supervisor, gate sensitive
-/// tools behind approval, and bound everything by a timeout + retries.
+/// The system prompt and task a new run starts from: role, agent rules, the
+/// records relevant to the task and the MCP servers on offer.
+async fn seed(ctx: &AgentContext, role: &str, task: &str, memory: &MemoryManager) -> Vec<Value> {
+    // ── Load MCP context ─────────────────────────────────────────────────
+    let mcp_context = match std::fs::read_to_string(ctx.config_dir.join("mcp_registry.json")) {
+        Ok(content) => {
+            if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&content) {
+                let servers = map.get("mcpServers").or_else(|| map.get("mcp_servers")).and_then(|v| v.as_object());
+                if let Some(servers) = servers {
+                    if !servers.is_empty() {
+                        let mut prompt_part = "\n\nAvailable Model Context Protocol (MCP) servers and tools (you can run them using the `call_mcp_tool` tool):\n".to_string();
+                        for (name, server_val) in servers {
+                            prompt_part.push_str(&format!("- Server '{}':\n", name));
+                            if let Some(tools_arr) = server_val.get("tools").and_then(|v| v.as_array()) {
+                                for t in tools_arr {
+                                    if let Some(t_str) = t.as_str() {
+                                        prompt_part.push_str(&format!("  - Tool '{}'\n", t_str));
+                                    }
+                                }
+                            } else {
+                                prompt_part.push_str("  - (no pre-listed tools, inspect server or invoke its tools)\n");
+                            }
+                        }
+                        prompt_part
+                    } else {
+                        String::new()
+                    }
+                } else {
+                    String::new()
+                }
+            } else {
+                String::new()
+            }
+        }
+        Err(_) => String::new(),
+    };
+
+    // Load all agent rules natively if present (AGENTS.md, CLAUDE.md, .cursorrules, etc.)
+    let agents_md_context = load_all_agent_rules(&ctx.config_dir);
+
+    // ── Conversation seed ─────────────────────────────────────────────────
+    let memory_context = memory.context(task).await;
+    let system = format!(
+        "You are a {role} agent. You have autonomous capabilities to complete the user's task using the provided tools. \
+         {}\
+         \n\n=== AUTONOMOUS DATA WRITE GUIDELINES ===\n\
+         If you determine that writing or updating user data is necessary to complete the task or improve future runs, you have full authority to execute write operations autonomously:\n\
+         - Use `write_note` to record documentation, analysis, or persistent knowledge.\n\
+         - Use `add_todo` / `complete_todo` to log and track checklist tasks.\n\
+         - Use `add_calendar_event` / `delete_calendar_event` to schedule, reschedule, or clear calendar events.\n\
+         - Use `add_planner_task` / `update_planner_task` / `delete_planner_task` to create, transition, or resolve tasks on the Kanban board.\n\
+         \n=== SELF-IMPROVEMENT & MEMORY LOOP ===\n\
+         Read the 'Lessons learned from previous tasks' and other database records carefully. You are a self-learning agent: use this context to refine your plans, adopt user preferences, correct past errors, and avoid duplicate actions. Ensure your decisions build on past learnings.\n\n\
+         Think step-by-step, call tools when useful, and stop when done by replying with the final answer (no tool call).{}{}",
+        agents_md_context,
+        memory_context,
+        mcp_context
+    );
+    vec![
+        json!({ "role": "system", "content": system }),
+        json!({ "role": "user", "content": task }),
+    ]
+}
+
+/// Run one tool call: route `spawn_subagent` to the supervisor, put the rest
+/// past the approval policy, and bound everything by a timeout + retries.
 async fn execute_call(
     ctx: &AgentContext,
     handle: &Arc<AgentHandle>,
@@ -459,22 +504,13 @@ async fn execute_call(
         };
     }
 
-    // L4: human approval for side-effecting tools.
-    if call.sensitive {
-        let approved = approval::gate(
-            &ctx.app,
-            handle,
-            call,
-            &format!("Tool '{}' performs a side effect.", call.tool),
-        )
-        .await;
-        if !approved {
-            return ToolResult {
-                call_id: call.call_id.clone(),
-                ok: false,
-                output: "Denied by user (or timed out).".to_string(),
-            };
-        }
+    // L4: the approval policy, and the user where it asks for them.
+    if let Err(by) = approval::gate(ctx, handle, call).await {
+        return ToolResult {
+            call_id: call.call_id.clone(),
+            ok: false,
+            output: format!("Denied by {by}."),
+        };
     }
 
     let Some(tool) = registry.get(&call.tool) else {
@@ -486,13 +522,14 @@ async fn execute_call(
     };
 
     // Bounded retries with linear backoff; timeout each attempt (L4 sandboxing).
+    let timeout = Duration::from_secs(ctx.limits.tool_timeout(&call.tool));
     let mut last_err = String::new();
     for attempt in 0..=TOOL_RETRIES {
         if handle.cancel.is_cancelled() {
             last_err = "cancelled".into();
             break;
         }
-        match tokio::time::timeout(TOOL_TIMEOUT, tool.run(&call.args, tool_ctx)).await {
+        match tokio::time::timeout(timeout, tool.run(&call.args, tool_ctx)).await {
             Ok(Ok(output)) => {
                 return ToolResult {
                     call_id: call.call_id.clone(),
@@ -501,7 +538,7 @@ async fn execute_call(
                 };
             }
             Ok(Err(e)) => last_err = e,
-            Err(_) => last_err = format!("timed out after {}s", TOOL_TIMEOUT.as_secs()),
+            Err(_) => last_err = format!("timed out after {}s", timeout.as_secs()),
         }
         if attempt < TOOL_RETRIES {
             tokio::time::sleep(Duration::from_millis(300 * (attempt as u64 + 1))).await;
@@ -515,10 +552,25 @@ async fn execute_call(
     }
 }
 
-fn mark_done(app: &AppHandle, agent_id: &str, plan: &[shared::ipc::PlanStep]) {
+/// Run `fut` within the time the run has left; `None` if that runs out.
+async fn within<T>(ctx: &AgentContext, fut: impl Future<Output = T>) -> Option<T> {
+    match ctx.usage.time_left(&ctx.limits) {
+        Some(left) => tokio::time::timeout(left, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+fn out_of_budget(ctx: &AgentContext, agent_id: &str, why: &str) -> String {
+    let why = format!("budget exhausted: {why}");
+    tracing::info!(agent = %agent_id, "{why}");
+    emit(ctx, err(agent_id, &why));
+    format!("Error: {why}")
+}
+
+fn mark_done(ctx: &AgentContext, agent_id: &str, plan: &[shared::ipc::PlanStep]) {
     for s in plan {
         emit(
-            app,
+            ctx,
             AgentEvent::PlanUpdate {
                 agent_id: agent_id.to_string(),
                 step_id: s.id.clone(),
@@ -528,12 +580,6 @@ fn mark_done(app: &AppHandle, agent_id: &str, plan: &[shared::ipc::PlanStep]) {
     }
 }
 
-fn emit(app: &AppHandle, event: AgentEvent) {
-    if let Err(e) = app.emit(AGENT_EVENT, event) {
-        tracing::warn!(%e, "failed to emit agent event");
-    }
-}
-
 fn err(agent_id: &str, message: &str) -> AgentEvent {
     AgentEvent::Error {
         agent_id: agent_id.to_string(),
@@ -548,3 +594,156 @@ fn truncate(s: &str, max: usize) -> String {
         format!("{}…", &s[..max])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::approval::Policy;
+    use crate::agent::runs::{RunRecorder, RunStore};
+    use crate::agent::sink::EventSink;
+    use crate::test_support::{MockLlm, RecordingSink, Reply, TempDir};
+    use shared::ipc::{AgentLimits, AgentRunStatus};
+
+    fn context(sink: &Arc<RecordingSink>, llm: &MockLlm, dir: &TempDir, limits: AgentLimits) -> AgentContext {
+        AgentContext {
+            sink: sink.clone(),
+            model: "mock".into(),
+            host: "127.0.0.1".into(),
+            port: llm.port,
+            searxng_url: None,
+            config_dir: dir.0.clone(),
+            limits,
+            usage: Default::default(),
+            run: Arc::new(RunRecorder::new(&dir.0, "root")),
+            policy: Arc::new(Policy::new(&Default::default(), Default::default(), false)),
+            sandbox: None,
+        }
+    }
+
+    /// Run `task` as the root agent `root`, whose id is also the run's, as
+    /// `agent_start` would.
+    async fn run_root(ctx: AgentContext, sink: &Arc<RecordingSink>, task: &str) -> String {
+        let handle = AgentHandle::new("root".into());
+        sink.register_agent(handle.clone());
+        run(ctx, handle, None, "general".into(), task.into(), 0, None).await
+    }
+
+    #[tokio::test]
+    async fn runs_tools_behind_approval_to_an_answer() {
+        let llm = MockLlm::start(vec![
+            Reply::text(r#"["Add the todo", "Check the list"]"#),
+            Reply::Tools(
+                "Adding it.".into(),
+                vec![("add_todo".into(), json!({ "text": "Buy a GPU" })), ("add_todo".into(), json!({ "text": "Buy two" }))],
+            ),
+            Reply::tool("get_todos", json!({})),
+            Reply::text("Added one todo."),
+            Reply::text("The user is shopping for GPUs"),
+        ])
+        .await;
+        let dir = TempDir::new("engine");
+        // The first add is approved; nobody answers the second, which times out.
+        let sink = Arc::new(RecordingSink::approving(&[true]));
+        let limits = AgentLimits { approval_timeout_secs: 1, ..Default::default() };
+        let ctx = context(&sink, &llm, &dir, limits);
+        let usage = ctx.usage.clone();
+
+        assert_eq!(run_root(ctx, &sink, "Add a todo to buy a GPU").await, "Added one todo.");
+
+        let events = sink.events();
+        let resolved: Vec<bool> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::ApprovalResolved { approved, .. } => Some(*approved),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(resolved, vec![true, false], "get_todos needs no approval");
+        let results: Vec<(bool, String)> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::ToolResult { result, .. } => Some((result.ok, result.output.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!((results[0].0, results[1].0), (true, false));
+        assert!(results[2].1.contains("Buy a GPU") && !results[2].1.contains("Buy two"), "{}", results[2].1);
+        assert!(matches!(events.last(), Some(AgentEvent::Done { final_text, .. }) if final_text == "Added one todo."));
+
+        // Each step saw the results of the one before.
+        let requests = llm.requests();
+        assert_eq!(requests.len(), 5, "plan, three steps, reflection");
+        let messages = requests[3]["messages"].as_array().unwrap();
+        let last = messages.last().unwrap();
+        assert_eq!((last["role"].as_str(), last["tool_call_id"].as_str()), (Some("tool"), Some("call_2_0")));
+        assert_eq!(requests[1]["stream"], true);
+        assert_eq!(usage.tokens(), 75, "usage reported by every call");
+
+        let run = RunStore::open(&dir.0).unwrap().get("root").unwrap().unwrap();
+        assert_eq!((run.summary.status, run.summary.steps), (AgentRunStatus::Done, 2));
+    }
+
+    #[tokio::test]
+    async fn sub_agents_run_to_completion_under_their_parent() {
+        let llm = MockLlm::start(vec![
+            Reply::text(r#"["Delegate the research"]"#),
+            Reply::tool("spawn_subagent", json!({ "role": "researcher", "prompt": "Find the answer" })),
+            Reply::text(r#"["Look it up"]"#),
+            Reply::text("42"),
+            Reply::text("Answers are numbers"),
+            Reply::text("The answer is 42."),
+            Reply::text("Delegate research"),
+        ])
+        .await;
+        let dir = TempDir::new("engine");
+        let sink = Arc::new(RecordingSink::default());
+        let ctx = context(&sink, &llm, &dir, AgentLimits::default());
+
+        assert_eq!(run_root(ctx, &sink, "What is the answer?").await, "The answer is 42.");
+
+        let events = sink.events();
+        let child = events
+            .iter()
+            .find_map(|e| match e {
+                AgentEvent::SubAgentSpawned { agent_id, child_id, role, .. } if agent_id == "root" && role == "researcher" => {
+                    Some(child_id.clone())
+                }
+                _ => None,
+            })
+            .expect("a researcher was spawned");
+        assert!(events.iter().any(|e| matches!(e,
+            AgentEvent::Started { agent_id, parent: Some(p), .. } if *agent_id == child && p == "root")));
+        assert!(events.iter().any(|e| matches!(e,
+            AgentEvent::Done { agent_id, final_text } if *agent_id == child && final_text == "42")));
+        assert!(events.iter().any(|e| matches!(e,
+            AgentEvent::ToolResult { agent_id, result } if agent_id == "root" && result.ok && result.output == "42")));
+        assert_eq!(sink.live_agents(), vec!["root".to_string()], "the child is unregistered when it finishes");
+        assert_eq!(llm.requests().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker_stops_a_run_the_model_keeps_failing() {
+        // A plan, then only 500s: each step's call fails after its retries.
+        let llm = MockLlm::start(vec![Reply::text(r#"["Try"]"#)]).await;
+        let dir = TempDir::new("engine");
+        let sink = Arc::new(RecordingSink::default());
+        let ctx = context(&sink, &llm, &dir, AgentLimits::default());
+
+        let out = run_root(ctx, &sink, "Anything").await;
+        assert!(out.starts_with("Error: circuit breaker tripped after 3 consecutive LLM failures"), "{out}");
+        assert_eq!(llm.requests().len(), 1 + 3 * 3, "three steps of three attempts, not the whole step budget");
+        let events = sink.events();
+        assert!(matches!(events.last(), Some(AgentEvent::Error { message, .. }) if message.contains("circuit breaker")));
+        assert!(!events.iter().any(|e| matches!(e, AgentEvent::Done { .. })));
+        let run = RunStore::open(&dir.0).unwrap().get("root").unwrap().unwrap();
+        assert_eq!(run.summary.status, AgentRunStatus::Failed);
+
+        // A request the server rejects is not retried at all.
+        let llm = MockLlm::start(vec![Reply::text(r#"["Try"]"#), Reply::Status(400)]).await;
+        let ctx = context(&sink, &llm, &dir, AgentLimits::default());
+        let out = run_root(ctx, &sink, "Anything").await;
+        assert!(out.starts_with("Error: ") && !out.contains("circuit breaker"), "{out}");
+        assert_eq!(llm.requests().len(), 2);
+    }
+}
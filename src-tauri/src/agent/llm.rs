@@ -1,9 +1,11 @@
 //! Thin wrapper around the OpenAI-compatible `/v1/chat/completions` endpoint used
-//! by the planner and engine. Non-streaming: agent reasoning needs the *complete*
-//! message (and any tool calls) before it can act.
+//! by the planner and engine. [`call`] waits for the *complete* message (and any
+//! tool calls); [`call_streaming`] hands the answer over token by token while it
+//! assembles the tool calls, so the UI sees a step as it is generated.
 //!
 //! Hardened for "bulletproof":
-//!  - Per-call timeout (the model server can stall for minutes under load).
+//!  - Per-call timeout, from the run's budget (the model server can stall
+//!    for minutes under load).
 //!  - Cancellation via the agent's [`CancellationToken`] (the in-flight HTTP
 //!    request aborts immediately instead of waiting for the timeout).
 //!  - Bounded exponential backoff with jitter on transient failures (network
@@ -15,6 +17,7 @@
 
 use std::time::Duration;
 
+use futures_util::StreamExt;
 use serde_json::{Value, json};
 use tokio_util::sync::CancellationToken;
 
@@ -24,6 +27,9 @@ use super::AgentContext;
 pub struct LlmReply {
     pub content: String,
     pub tool_calls: Vec<ParsedToolCall>,
+    /// Prompt plus completion tokens, as the server reports them or, when it
+    /// doesn't, estimated from the text.
+    pub tokens: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -34,9 +40,6 @@ pub struct ParsedToolCall {
     pub arguments: String,
 }
 
-/// Hard call budget. Most model servers respond in <2s; even a 70B on CPU tops
-/// out around 30-60s. Anything past 90s is almost certainly wedged.
-const LLM_TIMEOUT: Duration = Duration::from_secs(90);
 const LLM_RETRIES: u32 = 2;
 const LLM_BACKOFF_BASE_MS: u64 = 300;
 const LLM_BACKOFF_CAP_MS: u64 = 3_000;
@@ -79,18 +82,15 @@ impl LlmError {
     }
 }
 
-/// Call the model. When `tools` is provided the request advertises them with
+fn chat_url(ctx: &AgentContext) -> String {
+    format!("http://{}:{}/v1/chat/completions", ctx.host, ctx.port)
+}
+
+/// When `tools` is provided the request advertises them with
 /// `tool_choice: "auto"`, enabling structured tool calls (L1).
-pub async fn call(
-    ctx: &AgentContext,
-    messages: &[Value],
-    tools: Option<&[Value]>,
-    temperature: f32,
-    cancel: &CancellationToken,
-) -> Result<LlmReply, LlmError> {
-    let url = format!("http://{}:{}/v1/chat/completions", ctx.host, ctx.port);
+fn payload(model: &str, messages: &[Value], tools: Option<&[Value]>, temperature: f32) -> Value {
     let mut payload = json!({
-        "model": ctx.model,
+        "model": model,
         "messages": messages,
         "temperature": temperature,
     });
@@ -98,12 +98,85 @@ pub async fn call(
         payload["tools"] = json!(t);
         payload["tool_choice"] = json!("auto");
     }
+    payload
+}
 
+fn client() -> Result<reqwest::Client, LlmError> {
     // Shared client across retries (keeps the connection pool warm).
-    let client = reqwest::Client::builder()
+    reqwest::Client::builder()
         .pool_idle_timeout(Duration::from_secs(60))
         .build()
-        .map_err(|e| LlmError::Transport(e.to_string()))?;
+        .map_err(|e| LlmError::Transport(e.to_string()))
+}
+
+/// How one request attempt went, up to the response headers.
+enum Sent {
+    Ok(reqwest::Response),
+    /// Worth another attempt after a backoff.
+    Retry(LlmError),
+    Fail(LlmError),
+}
+
+/// Send one attempt. A streaming request has no overall timeout (a long
+/// answer may take minutes); only the wait for the headers is bounded here.
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    payload: &Value,
+    stream: bool,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> Sent {
+    let req = client.post(url).json(payload);
+    let send_result = if stream {
+        tokio::select! {
+            _ = cancel.cancelled() => return Sent::Fail(LlmError::Cancelled),
+            r = tokio::time::timeout(timeout, req.send()) => match r {
+                Ok(r) => r,
+                Err(_) => return Sent::Retry(LlmError::Timeout(timeout)),
+            },
+        }
+    } else {
+        tokio::select! {
+            _ = cancel.cancelled() => return Sent::Fail(LlmError::Cancelled),
+            r = req.timeout(timeout).send() => r,
+        }
+    };
+
+    let res = match send_result {
+        Ok(r) => r,
+        Err(e) if e.is_timeout() => return Sent::Retry(LlmError::Timeout(timeout)),
+        Err(e) if e.is_connect() || e.is_request() => return Sent::Retry(LlmError::Transport(e.to_string())),
+        Err(e) => return Sent::Fail(LlmError::Transport(e.to_string())),
+    };
+
+    let status = res.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        return Sent::Retry(LlmError::Server(status.as_u16()));
+    }
+    if status.is_client_error() {
+        let body = res.text().await.unwrap_or_default();
+        return Sent::Fail(LlmError::Client(status.as_u16(), truncate(&body, 300)));
+    }
+    if !status.is_success() {
+        return Sent::Fail(LlmError::Server(status.as_u16()));
+    }
+    Sent::Ok(res)
+}
+
+/// Call the model and wait for the whole reply. Its tokens count against the
+/// run's budget.
+pub async fn call(
+    ctx: &AgentContext,
+    messages: &[Value],
+    tools: Option<&[Value]>,
+    temperature: f32,
+    cancel: &CancellationToken,
+) -> Result<LlmReply, LlmError> {
+    let url = chat_url(ctx);
+    let payload = payload(&ctx.model, messages, tools, temperature);
+    let timeout = Duration::from_secs(ctx.limits.llm_timeout_secs);
+    let client = client()?;
 
     let mut last_err: Option<LlmError> = None;
     for attempt in 0..=LLM_RETRIES {
@@ -112,58 +185,19 @@ pub async fn call(
             return Err(LlmError::Cancelled);
         }
 
-        let req = client
-            .post(&url)
-            .json(&payload)
-            .timeout(LLM_TIMEOUT);
-
-        let send_result = tokio::select! {
-            _ = cancel.cancelled() => {
-                return Err(LlmError::Cancelled);
-            }
-            r = req.send() => r,
-        };
-
-        let res = match send_result {
-            Ok(r) => r,
-            Err(e) if e.is_timeout() => {
-                last_err = Some(LlmError::Timeout(LLM_TIMEOUT));
-                if attempt < LLM_RETRIES {
-                    backoff(attempt).await;
-                    continue;
-                }
-                return Err(last_err.unwrap());
-            }
-            Err(e) if e.is_connect() || e.is_request() => {
-                last_err = Some(LlmError::Transport(e.to_string()));
+        let res = match send(&client, &url, &payload, false, timeout, cancel).await {
+            Sent::Ok(r) => r,
+            Sent::Retry(e) => {
+                last_err = Some(e);
                 if attempt < LLM_RETRIES {
                     backoff(attempt).await;
                     continue;
                 }
                 return Err(last_err.unwrap());
             }
-            Err(e) => {
-                return Err(LlmError::Transport(e.to_string()));
-            }
+            Sent::Fail(e) => return Err(e),
         };
 
-        let status = res.status();
-        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
-            last_err = Some(LlmError::Server(status.as_u16()));
-            if attempt < LLM_RETRIES {
-                backoff(attempt).await;
-                continue;
-            }
-            return Err(last_err.unwrap());
-        }
-        if status.is_client_error() {
-            let body = res.text().await.unwrap_or_default();
-            return Err(LlmError::Client(status.as_u16(), truncate(&body, 300)));
-        }
-        if !status.is_success() {
-            return Err(LlmError::Server(status.as_u16()));
-        }
-
         // Body read is also cancel-aware.
         let body_result = tokio::select! {
             _ = cancel.cancelled() => return Err(LlmError::Cancelled),
@@ -202,12 +236,213 @@ pub async fn call(
             }
         }
 
-        return Ok(LlmReply { content, tool_calls });
+        let tokens = reported_tokens(&v).unwrap_or_else(|| estimate_tokens(&payload, &content));
+        ctx.usage.add_tokens(tokens);
+        return Ok(LlmReply { content, tool_calls, tokens });
+    }
+
+    Err(last_err.unwrap_or(LlmError::Transport("exhausted retries".into())))
+}
+
+/// Like [`call`], but streamed: each content delta goes to `on_token` as it
+/// arrives, and the `tool_calls` fragments are put back together into
+/// [`ParsedToolCall`]s. The timeout applies to the wait for each piece
+/// rather than the whole reply.
+///
+/// Retries follow [`call`] until the first token is forwarded; after that a
+/// failure is returned as is, so the caller never sees a token twice.
+pub async fn call_streaming(
+    ctx: &AgentContext,
+    messages: &[Value],
+    tools: Option<&[Value]>,
+    temperature: f32,
+    cancel: &CancellationToken,
+    on_token: impl FnMut(&str),
+) -> Result<LlmReply, LlmError> {
+    let payload = payload(&ctx.model, messages, tools, temperature);
+    let timeout = Duration::from_secs(ctx.limits.llm_timeout_secs);
+    let reply = stream_chat(&chat_url(ctx), payload, timeout, cancel, on_token).await?;
+    ctx.usage.add_tokens(reply.tokens);
+    Ok(reply)
+}
+
+async fn stream_chat(
+    url: &str,
+    mut payload: Value,
+    timeout: Duration,
+    cancel: &CancellationToken,
+    mut on_token: impl FnMut(&str),
+) -> Result<LlmReply, LlmError> {
+    payload["stream"] = json!(true);
+    // Ask for a final chunk with the token counts.
+    payload["stream_options"] = json!({ "include_usage": true });
+    let client = client()?;
+    let mut forwarded = false;
+
+    let mut last_err: Option<LlmError> = None;
+    for attempt in 0..=LLM_RETRIES {
+        if cancel.is_cancelled() {
+            return Err(LlmError::Cancelled);
+        }
+        let res = match send(&client, url, &payload, true, timeout, cancel).await {
+            Sent::Ok(r) => r,
+            Sent::Retry(e) => {
+                last_err = Some(e);
+                if attempt < LLM_RETRIES {
+                    backoff(attempt).await;
+                    continue;
+                }
+                return Err(last_err.unwrap());
+            }
+            Sent::Fail(e) => return Err(e),
+        };
+        match read_stream(res, timeout, cancel, &mut on_token, &mut forwarded).await {
+            Ok(mut reply) => {
+                if reply.tokens == 0 {
+                    reply.tokens = estimate_tokens(&payload, &reply.content);
+                }
+                return Ok(reply);
+            }
+            Err(e) if e.is_retryable() && !forwarded && attempt < LLM_RETRIES => {
+                last_err = Some(e);
+                backoff(attempt).await;
+            }
+            Err(e) => return Err(e),
+        }
     }
 
     Err(last_err.unwrap_or(LlmError::Transport("exhausted retries".into())))
 }
 
+/// Read an SSE reply to the end: `data: [DONE]`, or the stream closing after
+/// a `finish_reason`. A stream that closes before either was cut off, and is
+/// reported as [`LlmError::Transport`] so it can be retried.
+async fn read_stream(
+    res: reqwest::Response,
+    timeout: Duration,
+    cancel: &CancellationToken,
+    on_token: &mut impl FnMut(&str),
+    forwarded: &mut bool,
+) -> Result<LlmReply, LlmError> {
+    let mut stream = res.bytes_stream();
+    // Bytes, not text: a chunk can end inside a multi-byte character.
+    let mut buf: Vec<u8> = Vec::new();
+    let mut content = String::new();
+    let mut calls = ToolCallAssembler::default();
+    let mut tokens = 0;
+    let mut finished = false;
+    loop {
+        let next = tokio::select! {
+            _ = cancel.cancelled() => return Err(LlmError::Cancelled),
+            next = tokio::time::timeout(timeout, stream.next()) => {
+                next.map_err(|_| LlmError::Timeout(timeout))?
+            }
+        };
+        let Some(chunk) = next else { break };
+        buf.extend_from_slice(&chunk.map_err(|e| LlmError::Transport(e.to_string()))?);
+        while let Some(nl) = buf.iter().position(|&b| b == b'\n') {
+            // One chunk can carry many tokens; stop between them too.
+            if cancel.is_cancelled() {
+                return Err(LlmError::Cancelled);
+            }
+            let line: Vec<u8> = buf.drain(..=nl).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                return Ok(LlmReply { content, tool_calls: calls.finish(), tokens });
+            }
+            let v: Value = serde_json::from_str(data).map_err(|e| LlmError::Invalid(e.to_string()))?;
+            if let Some(message) = v["error"]["message"].as_str() {
+                return Err(LlmError::Invalid(truncate(message, 300)));
+            }
+            if let Some(n) = reported_tokens(&v) {
+                tokens = n;
+            }
+            if v["choices"][0]["finish_reason"].is_string() {
+                finished = true;
+            }
+            let delta = &v["choices"][0]["delta"];
+            if let Some(text) = delta["content"].as_str().filter(|t| !t.is_empty()) {
+                content.push_str(text);
+                *forwarded = true;
+                on_token(text);
+            }
+            for fragment in delta["tool_calls"].as_array().into_iter().flatten() {
+                calls.push(fragment);
+            }
+        }
+    }
+    if !finished {
+        return Err(LlmError::Transport("the stream closed before the reply was complete".into()));
+    }
+    Ok(LlmReply { content, tool_calls: calls.finish(), tokens })
+}
+
+/// Tokens a reply says it used: OpenAI-style `usage`, or llama-server's
+/// `timings`.
+fn reported_tokens(v: &Value) -> Option<u64> {
+    v["usage"]["total_tokens"].as_u64().or_else(|| {
+        let t = &v["timings"];
+        Some(t["prompt_n"].as_u64()? + t["predicted_n"].as_u64()?)
+    })
+}
+
+/// Four characters a token, over the request and the reply.
+fn estimate_tokens(payload: &Value, content: &str) -> u64 {
+    let chars = payload["messages"].to_string().chars().count() + content.chars().count();
+    chars.div_ceil(4) as u64
+}
+
+/// Builds tool calls from streamed fragments: the first fragment of a call
+/// carries its `index`, `id` and name, later ones more of its arguments.
+#[derive(Default)]
+struct ToolCallAssembler {
+    calls: Vec<(u64, ParsedToolCall)>,
+}
+
+impl ToolCallAssembler {
+    fn push(&mut self, fragment: &Value) {
+        // Servers that send each call whole may leave `index` out.
+        let index = fragment["index"].as_u64().unwrap_or(self.calls.len() as u64);
+        let pos = match self.calls.iter().position(|(i, _)| *i == index) {
+            Some(pos) => pos,
+            None => {
+                let call = ParsedToolCall { id: String::new(), name: String::new(), arguments: String::new() };
+                self.calls.push((index, call));
+                self.calls.len() - 1
+            }
+        };
+        let call = &mut self.calls[pos].1;
+        if let Some(id) = fragment["id"].as_str().filter(|id| !id.is_empty()) {
+            call.id = id.to_string();
+        }
+        let function = &fragment["function"];
+        if let Some(name) = function["name"].as_str() {
+            call.name.push_str(name);
+        }
+        if let Some(args) = function["arguments"].as_str() {
+            call.arguments.push_str(args);
+        }
+    }
+
+    /// The calls in index order; nameless ones are dropped, as in [`call`].
+    fn finish(mut self) -> Vec<ParsedToolCall> {
+        self.calls.sort_by_key(|(i, _)| *i);
+        self.calls
+            .into_iter()
+            .map(|(_, mut c)| {
+                if c.arguments.trim().is_empty() {
+                    c.arguments = "{}".into();
+                }
+                c
+            })
+            .filter(|c| !c.name.is_empty())
+            .collect()
+    }
+}
+
 /// Exponential backoff with full jitter, capped at LLM_BACKOFF_CAP_MS.
 async fn backoff(attempt: u32) {
     let exp = LLM_BACKOFF_BASE_MS.saturating_mul(1u64 << attempt.min(4));
@@ -278,6 +513,98 @@ mod tests {
         assert!(LlmError::Timeout(Duration::from_secs(1)).is_retryable());
     }
 
+    fn sse(chunks: &[Value]) -> String {
+        let mut body: String = chunks.iter().map(|c| format!("data: {c}\n\n")).collect();
+        body.push_str("data: [DONE]\n\n");
+        body
+    }
+
+    #[tokio::test]
+    async fn streams_tokens_and_assembles_tool_calls_after_a_retry() {
+        use crate::test_support::{StubResponse, StubServer};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let delta = |d: Value| json!({ "choices": [{ "delta": d }] });
+        let body = sse(&[
+            delta(json!({ "role": "assistant" })),
+            delta(json!({ "content": "Let me " })),
+            delta(json!({ "content": "look." })),
+            delta(json!({ "tool_calls": [{ "index": 0, "id": "call_a", "type": "function", "function": { "name": "read_file", "arguments": "" } }] })),
+            delta(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": "{\"path\":" } }] })),
+            delta(json!({ "tool_calls": [{ "index": 1, "id": "call_b", "function": { "name": "web_search", "arguments": "{\"q\":\"gguf\"}" } }] })),
+            delta(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": " \"a.md\"}" } }] })),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 30, "completion_tokens": 12, "total_tokens": 42 } }),
+        ]);
+        // Busy once, then the scripted stream.
+        let hits = AtomicU32::new(0);
+        let server = StubServer::start(move |_| {
+            if hits.fetch_add(1, Ordering::SeqCst) == 0 {
+                StubResponse::text(503, "loading model")
+            } else {
+                StubResponse::text(200, &body)
+            }
+        })
+        .await;
+
+        let url = format!("{}/v1/chat/completions", server.base_url);
+        let timeout = Duration::from_secs(5);
+        let mut tokens = Vec::new();
+        let reply = stream_chat(&url, payload("m", &[], None, 0.2), timeout, &CancellationToken::new(), |t| tokens.push(t.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(tokens, vec!["Let me ", "look."]);
+        assert_eq!(reply.content, "Let me look.");
+        let calls: Vec<_> = reply.tool_calls.iter().map(|c| (c.id.as_str(), c.name.as_str(), c.arguments.as_str())).collect();
+        assert_eq!(calls, vec![("call_a", "read_file", "{\"path\": \"a.md\"}"), ("call_b", "web_search", "{\"q\":\"gguf\"}")]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2, "retried after the 503");
+        let sent: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(sent["stream"], true);
+        assert_eq!(reply.tokens, 42, "usage from the last chunk");
+
+        // Cancelling from the first token stops the call without a retry.
+        let cancel = CancellationToken::new();
+        let err = stream_chat(&url, payload("m", &[], None, 0.2), timeout, &cancel, |_| cancel.cancel()).await.unwrap_err();
+        assert!(matches!(err, LlmError::Cancelled), "{err}");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn a_stream_that_closes_early_is_a_transport_error() {
+        use crate::test_support::{StubResponse, StubServer};
+
+        let delta = |d: Value| format!("data: {}\n\n", json!({ "choices": [{ "delta": d }] }));
+        let url = |server: &StubServer| format!("{}/v1/chat/completions", server.base_url);
+        let timeout = Duration::from_secs(5);
+
+        // Cut off before any token: retried, then reported.
+        let server = StubServer::start(move |_| StubResponse::text(200, &delta(json!({ "role": "assistant" })))).await;
+        let err = stream_chat(&url(&server), payload("m", &[], None, 0.2), timeout, &CancellationToken::new(), |_| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Transport(_)), "{err}");
+        assert_eq!(server.requests().len(), 1 + LLM_RETRIES as usize);
+
+        // Cut off after a token: reported without a retry.
+        let server = StubServer::start(move |_| StubResponse::text(200, &delta(json!({ "content": "Half an ans" })))).await;
+        let mut tokens = Vec::new();
+        let err = stream_chat(&url(&server), payload("m", &[], None, 0.2), timeout, &CancellationToken::new(), |t| tokens.push(t.to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Transport(_)), "{err}");
+        assert_eq!(tokens, vec!["Half an ans"]);
+        assert_eq!(server.requests().len(), 1);
+
+        // A finish_reason without [DONE] is a complete reply.
+        let body = delta(json!({ "content": "Done." })) + &format!("data: {}\n\n", json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }));
+        let server = StubServer::start(move |_| StubResponse::text(200, &body)).await;
+        let reply = stream_chat(&url(&server), payload("m", &[], None, 0.2), timeout, &CancellationToken::new(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(reply.content, "Done.");
+    }
+
     #[test]
     fn jitter_helper_returns_in_range() {
         for _ in 0..100 {
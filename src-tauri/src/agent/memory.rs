@@ -1,97 +1,189 @@
 //! Agent memory (L1 context). File-backed "lessons" the agent accumulates and
 //! re-reads on later tasks. Stored as a JSON array under the config dir.
+//!
+//! Every chat reply and agent run proposes a lesson, so the list is kept in
+//! shape as it grows: a proposal that says the same as an existing lesson is
+//! merged into it, lessons are ranked by importance and how recently they
+//! came up, and only the best `memory_max_lessons` are kept. With the review
+//! queue on, new lessons wait as pending until approved in the Memory view.
+//!
+//! An agent's context holds only the records relevant to its task, within a
+//! token budget; see [`recall`].
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use shared::ipc::{Lesson, LessonStatus};
+use shared::ServerConfig;
+
+use super::recall::{self, Embeddings, Item};
+use crate::rag::embed::Embedder;
+
+/// Word overlap (Jaccard) at which two lessons count as the same one.
+const DUPLICATE_SIMILARITY: f32 = 0.6;
+/// Days after which a lesson that hasn't come up again counts half as much.
+const HALF_LIFE_DAYS: f32 = 30.0;
+/// Rejected lessons remembered so they aren't proposed again.
+const MAX_REJECTED: usize = 100;
+/// Words that mark a rule or preference rather than a one-off fact.
+const RULE_WORDS: &[&str] = &[
+    "always", "never", "prefer", "prefers", "must", "should", "avoid", "don't", "instead", "remember",
+];
+const STOP_WORDS: &[&str] = &["the", "and", "for", "with", "that", "this", "when", "from", "are", "was", "use"];
 
 pub struct MemoryManager {
+    dir: PathBuf,
     path: PathBuf,
+    cfg: ServerConfig,
 }
 
 impl MemoryManager {
+    /// Takes its settings (review queue, size cap, retrieval) from
+    /// `config.json` in `config_dir`.
     pub fn new(config_dir: &PathBuf) -> Self {
+        let cfg: ServerConfig = std::fs::read_to_string(config_dir.join("config.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self::with_config(config_dir, cfg)
+    }
+
+    pub fn with_config(config_dir: &Path, cfg: ServerConfig) -> Self {
         Self {
+            dir: config_dir.to_path_buf(),
             path: config_dir.join("agent_memory.json"),
+            cfg,
         }
     }
 
-    fn load(&self) -> Vec<String> {
-        std::fs::read_to_string(&self.path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
+    /// Lessons as stored. Files from before lessons were structured (a list
+    /// of strings, oldest first) load as approved lessons, newest ranked first.
+    fn load(&self, now: i64) -> Vec<Lesson> {
+        let Ok(json) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        if let Ok(lessons) = serde_json::from_str::<Vec<Lesson>>(&json) {
+            return lessons;
+        }
+        let legacy: Vec<String> = serde_json::from_str(&json).unwrap_or_default();
+        let n = legacy.len() as i64;
+        legacy
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| new_lesson(&text, "agent", LessonStatus::Approved, now - (n - i as i64)))
+            .collect()
+    }
+
+    fn write(&self, lessons: &[Lesson]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(lessons).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, json).map_err(|e| format!("agent_memory.json: {e}"))
+    }
+
+    /// Every lesson for the Memory view: the review queue, then approved
+    /// lessons best first, then rejected ones.
+    pub fn list(&self, now: i64) -> Vec<Lesson> {
+        let mut lessons = self.load(now);
+        let rank = |l: &Lesson| match l.status {
+            LessonStatus::Pending => 0,
+            LessonStatus::Approved => 1,
+            LessonStatus::Rejected => 2,
+        };
+        lessons.sort_by(|a, b| rank(a).cmp(&rank(b)).then(score(b, now).total_cmp(&score(a, now))));
+        lessons
+    }
+
+    /// The approved lessons, best first.
+    fn context_lessons(&self, now: i64) -> Vec<Lesson> {
+        let mut lessons: Vec<Lesson> = self
+            .load(now)
+            .into_iter()
+            .filter(|l| l.status == LessonStatus::Approved)
+            .collect();
+        lessons.sort_by(|a, b| score(b, now).total_cmp(&score(a, now)));
+        lessons
+    }
+
+    /// The lessons, Obsidian memories, notes, todos, calendar events and
+    /// planner tasks most relevant to `task`, within `memory_context_tokens`,
+    /// as a system-prompt fragment.
+    pub async fn context(&self, task: &str) -> String {
+        let items = self.items(chrono::Utc::now().timestamp());
+        if items.is_empty() {
+            return String::new();
+        }
+        let embeddings = self.embeddings();
+        let order = recall::rank(&items, task, embeddings.as_ref()).await;
+        let chosen = recall::select(&items, &order, self.cfg.memory_context_tokens as usize);
+        tracing::debug!(candidates = items.len(), chosen = chosen.len(), "agent context");
+        recall::render(&items, &chosen)
     }
 
-    /// Render accumulated lessons, Obsidian memories, Notes, and Todos as a system-prompt fragment.
-    pub fn context(&self) -> String {
-        let mut s = String::new();
+    /// The memory embedding server, else the Document Chat one; none means
+    /// keyword ranking only.
+    fn embeddings(&self) -> Option<Embeddings<'_>> {
+        let url = [&self.cfg.memory_embedding_url, &self.cfg.rag_embedding_url]
+            .into_iter()
+            .map(|u| u.trim())
+            .find(|u| !u.is_empty())?;
+        let model = &self.cfg.rag_embedding_model;
+        Some(Embeddings {
+            embedder: Embedder::new(url, model),
+            key: format!("{url} {model}"),
+            cache_dir: &self.dir,
+        })
+    }
+
+    /// Every record that may go into the context, each rendered as it would
+    /// appear, in the order used when relevance doesn't decide.
+    fn items(&self, now: i64) -> Vec<Item> {
+        let mut items = Vec::new();
+        let mut push = |section: &'static str, text: String| items.push(Item { section, text });
 
         // 1. Lessons
-        let lessons = self.load();
-        if !lessons.is_empty() {
-            s.push_str("\nLessons learned from previous tasks:\n");
-            for l in lessons.iter().take(20) {
-                s.push_str(&format!("- {l}\n"));
-            }
+        for l in self.context_lessons(now) {
+            push("Lessons learned from previous tasks", format!("- {}\n", l.text));
         }
 
         // 2. Obsidian Memories
         if let Ok(mems) = crate::commands::remaining::obsidian_memories_get() {
-            if !mems.is_empty() {
-                s.push_str("\nObsidian Knowledge Graph Memories:\n");
-                for m in mems.iter().take(15) {
-                    s.push_str(&format!("- Memory ID: {} (Title: \"{}\", Scope: {})\n", m.id, m.title, m.scope));
-                    if !m.tags.is_empty() {
-                        s.push_str(&format!("  Tags: {}\n", m.tags.join(", ")));
-                    }
-                    if !m.links.is_empty() {
-                        s.push_str(&format!("  Links: {}\n", m.links.join(", ")));
-                    }
-                    let indented = m.content.replace('\n', "\n  ");
-                    s.push_str(&format!("  Content:\n  \"\"\"\n  {}\n  \"\"\"\n", indented));
+            for m in mems {
+                let mut s = format!("- Memory ID: {} (Title: \"{}\", Scope: {})\n", m.id, m.title, m.scope);
+                if !m.tags.is_empty() {
+                    s.push_str(&format!("  Tags: {}\n", m.tags.join(", ")));
                 }
+                if !m.links.is_empty() {
+                    s.push_str(&format!("  Links: {}\n", m.links.join(", ")));
+                }
+                let indented = m.content.replace('\n', "\n  ");
+                s.push_str(&format!("  Content:\n  \"\"\"\n  {}\n  \"\"\"\n", indented));
+                push("Obsidian Knowledge Graph Memories", s);
             }
         }
 
         // 3. User Notes
-        let global_notes = crate::commands::store::notes_store_get("global".to_string());
-        let project_notes = crate::commands::store::notes_store_get("project".to_string());
-        let mut has_notes = false;
-        
-        let mut notes_str = String::from("\nUser Notes (Global & Project):\n");
-        for note in &global_notes.notes {
-            if !note.content.trim().is_empty() {
-                has_notes = true;
-                let indented = note.content.replace('\n', "\n  ");
-                notes_str.push_str(&format!("- Note Name: \"{}\" (Scope: global)\n  Content:\n  \"\"\"\n  {}\n  \"\"\"\n", note.name, indented));
-            }
-        }
-        for note in &project_notes.notes {
-            if !note.content.trim().is_empty() {
-                has_notes = true;
-                let indented = note.content.replace('\n', "\n  ");
-                notes_str.push_str(&format!("- Note Name: \"{}\" (Scope: project)\n  Content:\n  \"\"\"\n  {}\n  \"\"\"\n", note.name, indented));
+        for scope in ["global", "project"] {
+            for note in crate::commands::store::notes_store_get(scope.to_string()).notes {
+                if !note.content.trim().is_empty() {
+                    let indented = note.content.replace('\n', "\n  ");
+                    push(
+                        "User Notes (Global & Project)",
+                        format!("- Note Name: \"{}\" (Scope: {scope})\n  Content:\n  \"\"\"\n  {}\n  \"\"\"\n", note.name, indented),
+                    );
+                }
             }
         }
-        if has_notes {
-            s.push_str(&notes_str);
-        }
 
         // 4. Todos
-        let todos = crate::commands::store::todos_get();
-        if !todos.is_empty() {
-            s.push_str("\nUser Todos Checklist:\n");
-            for t in todos {
-                let check = if t.done { "[x]" } else { "[ ]" };
-                s.push_str(&format!("- {} {} (id: {})\n", check, t.text, t.id));
-            }
+        for t in crate::commands::store::todos_get() {
+            let check = if t.done { "[x]" } else { "[ ]" };
+            push("User Todos Checklist", format!("- {} {} (id: {})\n", check, t.text, t.id));
         }
 
         // 5. Scheduled Calendar Events
-        let calendar = crate::commands::remaining::calendar_load();
-        if !calendar.events.is_empty() {
-            s.push_str("\nScheduled Calendar Events:\n");
-            for ev in calendar.events {
-                s.push_str(&format!(
+        for ev in crate::commands::remaining::calendar_load().events {
+            push(
+                "Scheduled Calendar Events",
+                format!(
                     "- [{:?}] Title: '{}', Time: {}, Trigger Prompt: '{}', Note: '{}', Result: '{}' (id: {})\n",
                     ev.status,
                     ev.title,
@@ -100,16 +192,15 @@ impl MemoryManager {
                     ev.note.as_deref().unwrap_or(""),
                     ev.result.as_deref().unwrap_or(""),
                     ev.id
-                ));
-            }
+                ),
+            );
         }
 
         // 6. Task Planner Kanban Board
-        let planner = crate::commands::remaining::planner_load();
-        if !planner.tasks.is_empty() {
-            s.push_str("\nTask Planner Kanban Board:\n");
-            for t in planner.tasks {
-                s.push_str(&format!(
+        for t in crate::commands::remaining::planner_load().tasks {
+            push(
+                "Task Planner Kanban Board",
+                format!(
                     "- [{:?}] Title: '{}', Summary: '{}', Assignee: {}, Priority: {}, Created At: {} (id: {})\n",
                     t.status,
                     t.title,
@@ -118,26 +209,206 @@ impl MemoryManager {
                     t.priority,
                     t.created_at,
                     t.id
-                ));
-            }
+                ),
+            );
         }
 
-        s
+        items
     }
 
-    /// Append a lesson (best-effort; logs on failure rather than erroring the run).
-    pub fn add_lesson(&self, text: &str) {
+    /// Propose a lesson from `source` (`agent`, `chat` or `task`). It merges
+    /// into a near-duplicate if there is one, else joins as pending (review
+    /// queue on) or approved; then the list is consolidated. Best-effort: logs
+    /// on failure rather than erroring the run.
+    pub fn add_lesson(&self, text: &str, source: &str, now: i64) {
         let text = text.trim();
         if text.is_empty() {
             return;
         }
-        let mut lessons = self.load();
-        lessons.push(text.to_string());
-        if let Err(e) = std::fs::write(
-            &self.path,
-            serde_json::to_string_pretty(&lessons).unwrap_or_default(),
-        ) {
+        let status = if self.cfg.memory_review_queue { LessonStatus::Pending } else { LessonStatus::Approved };
+        let mut lessons = self.load(now);
+        lessons.push(new_lesson(text, source, status, now));
+        if let Err(e) = self.write(&consolidate(lessons, self.cfg.memory_max_lessons as usize, now)) {
             tracing::warn!(%e, "failed to persist agent lesson");
         }
     }
+
+    /// Approve (optionally reworded) or reject a lesson.
+    pub fn review(&self, id: &str, approve: bool, text: Option<String>, now: i64) -> Result<(), String> {
+        let mut lessons = self.load(now);
+        let lesson = lessons
+            .iter_mut()
+            .find(|l| l.id == id)
+            .ok_or_else(|| format!("Lesson {id} not found"))?;
+        lesson.status = if approve { LessonStatus::Approved } else { LessonStatus::Rejected };
+        if let Some(text) = text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) {
+            lesson.text = text;
+        }
+        self.write(&lessons)
+    }
+
+    pub fn delete(&self, id: &str, now: i64) -> Result<(), String> {
+        let mut lessons = self.load(now);
+        lessons.retain(|l| l.id != id);
+        self.write(&lessons)
+    }
+
+    /// Merge near-duplicates and trim to the cap now, e.g. after the cap was
+    /// lowered. Returns what is left, as [`Self::list`] orders it.
+    pub fn consolidate(&self, now: i64) -> Result<Vec<Lesson>, String> {
+        self.write(&consolidate(self.load(now), self.cfg.memory_max_lessons as usize, now))?;
+        Ok(self.list(now))
+    }
+}
+
+fn new_lesson(text: &str, source: &str, status: LessonStatus, now: i64) -> Lesson {
+    Lesson {
+        id: crate::util::new_id("lesson"),
+        text: text.trim().to_string(),
+        source: source.to_string(),
+        status,
+        importance: importance(text, source),
+        hits: 1,
+        created_at: now,
+        last_seen_at: now,
+    }
+}
+
+/// A first guess at how much a lesson matters: rules and preferences more
+/// than one-off facts, and the fallback summary of a run least.
+fn importance(text: &str, source: &str) -> f32 {
+    let base = if source == "task" { 0.2 } else { 0.5 };
+    let lower = text.to_lowercase();
+    let rule = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .any(|w| RULE_WORDS.contains(&w));
+    if rule { base + 0.3 } else { base }
+}
+
+/// Importance, lifted a little each time the lesson came up again, halved
+/// for every [`HALF_LIFE_DAYS`] since it last did.
+fn score(l: &Lesson, now: i64) -> f32 {
+    let age_days = (now - l.last_seen_at).max(0) as f32 / 86_400.0;
+    (l.importance + 0.1 * (l.hits.max(1) as f32).ln()) * 0.5f32.powf(age_days / HALF_LIFE_DAYS)
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() >= 3 && !STOP_WORDS.contains(w))
+        .map(str::to_string)
+        .collect()
+}
+
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / a.union(b).count() as f32
+}
+
+/// Fold `other` into `into`. A rejection outlasts anything, so a rejected
+/// lesson stays rejected however it is proposed again (approved, with the
+/// review queue off); otherwise approved outlasts pending. The wording
+/// follows the status.
+fn merge(into: &mut Lesson, other: Lesson) {
+    let rank = |s: LessonStatus| match s {
+        LessonStatus::Pending => 0,
+        LessonStatus::Approved => 1,
+        LessonStatus::Rejected => 2,
+    };
+    if rank(other.status) > rank(into.status) {
+        into.status = other.status;
+        into.text = other.text;
+    }
+    into.hits += other.hits;
+    into.importance = into.importance.max(other.importance);
+    into.created_at = into.created_at.min(other.created_at);
+    into.last_seen_at = into.last_seen_at.max(other.last_seen_at);
+}
+
+/// Merge near-duplicates into the earliest lesson of each group, so its id
+/// and wording stay put, then keep the best `max` live lessons and the latest
+/// [`MAX_REJECTED`] rejected ones.
+fn consolidate(mut lessons: Vec<Lesson>, max: usize, now: i64) -> Vec<Lesson> {
+    lessons.sort_by_key(|l| l.created_at);
+    let mut groups: Vec<(Lesson, HashSet<String>)> = Vec::new();
+    for lesson in lessons {
+        let w = words(&lesson.text);
+        match groups.iter_mut().find(|(_, g)| similarity(g, &w) >= DUPLICATE_SIMILARITY) {
+            Some((into, _)) => merge(into, lesson),
+            None => groups.push((lesson, w)),
+        }
+    }
+    let (mut rejected, mut live): (Vec<Lesson>, Vec<Lesson>) = groups
+        .into_iter()
+        .map(|(l, _)| l)
+        .partition(|l| l.status == LessonStatus::Rejected);
+    live.sort_by(|a, b| score(b, now).total_cmp(&score(a, now)));
+    if max > 0 {
+        live.truncate(max);
+    }
+    rejected.sort_by_key(|l| std::cmp::Reverse(l.last_seen_at));
+    rejected.truncate(MAX_REJECTED);
+    live.extend(rejected);
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    const DAY: i64 = 86_400;
+
+    #[test]
+    fn merges_duplicates_ranks_recent_rules_and_queues_for_review() {
+        let dir = TempDir::new("memory");
+        // An old-style file: plain strings, oldest first.
+        std::fs::write(dir.0.join("agent_memory.json"), r#"["Old fact one", "Old fact two"]"#).unwrap();
+        let memory = MemoryManager::with_config(&dir.0, ServerConfig { memory_max_lessons: 2, ..Default::default() });
+        let now = 100 * DAY;
+        let texts = |v: Vec<Lesson>| v.into_iter().map(|l| l.text).collect::<Vec<_>>();
+        assert_eq!(texts(memory.context_lessons(now)), vec!["Old fact two", "Old fact one"]);
+
+        memory.add_lesson("The user prefers answers in metric units", "chat", now);
+        memory.add_lesson("User prefers answers in metric units.", "agent", now + 1);
+        let lessons = memory.list(now + 1);
+        assert_eq!(lessons.len(), 2, "duplicate merged and capped at 2: {lessons:?}");
+        assert_eq!((lessons[0].text.as_str(), lessons[0].hits), ("The user prefers answers in metric units", 2));
+        assert_eq!(lessons[1].text, "Old fact two", "the oldest lesson went first");
+
+        // A month without coming up halves a lesson's weight.
+        let later = now + 30 * DAY;
+        memory.add_lesson("Task 'x' → done", "task", later);
+        assert_eq!(texts(memory.context_lessons(later))[0], "The user prefers answers in metric units");
+
+        let cfg = ServerConfig { memory_review_queue: true, memory_max_lessons: 0, ..Default::default() };
+        let queued = MemoryManager::with_config(&dir.0, cfg);
+        queued.add_lesson("Never run rm -rf in the home directory", "agent", later);
+        let pending = queued.list(later).remove(0);
+        assert_eq!(pending.status, LessonStatus::Pending);
+        assert!(!texts(queued.context_lessons(later)).contains(&pending.text));
+        queued.review(&pending.id, false, None, later).unwrap();
+        queued.add_lesson("Never run rm -rf in the home directory!", "agent", later + 1);
+        let rejected: Vec<_> = queued.list(later + 1).into_iter().filter(|l| l.status == LessonStatus::Rejected).collect();
+        assert_eq!((rejected.len(), rejected[0].hits), (1, 2), "re-proposal folded into the rejection");
+        assert!(queued.list(later + 1).iter().all(|l| l.status != LessonStatus::Pending));
+    }
+
+    #[test]
+    fn a_rejection_outlasts_proposals_without_the_review_queue() {
+        let dir = TempDir::new("memory");
+        let memory = MemoryManager::with_config(&dir.0, ServerConfig::default());
+        memory.add_lesson("Always push straight to the main branch", "agent", DAY);
+        let id = memory.list(DAY)[0].id.clone();
+        memory.review(&id, false, None, DAY).unwrap();
+
+        memory.add_lesson("Always push straight to the main branch!", "chat", 2 * DAY);
+        let lessons = memory.list(2 * DAY);
+        assert_eq!(lessons.len(), 1, "{lessons:?}");
+        assert_eq!(lessons[0].status, LessonStatus::Rejected);
+        assert_eq!((lessons[0].text.as_str(), lessons[0].hits), ("Always push straight to the main branch", 2));
+        assert!(memory.context_lessons(2 * DAY).is_empty());
+    }
 }
@@ -7,44 +7,108 @@
 //!
 //! All streaming flows over the single `agent://event` channel as tagged
 //! [`shared::ipc::AgentEvent`]s, keyed by `agent_id` so the UI can draw a
-//! sub-agent tree.
+//! sub-agent tree. The engine emits through an [`EventSink`] (the
+//! `AppHandle` in the app), so it runs the same without a GUI. Every event
+//! also goes to the run store ([`runs`]), so runs can be looked at later and
+//! resumed after a crash.
 
 use std::future::Future;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 
-use tauri::AppHandle;
+use shared::ipc::{AgentEvent, AgentLimits};
+use shared::ServerConfig;
 
+use crate::config_io;
 use crate::state::AgentHandle;
+use approval::Policy;
+use budget::RunUsage;
+use runs::{Checkpoint, RunRecorder};
+use sink::EventSink;
+use tools::Sandbox;
 
 pub mod approval;
+pub mod budget;
 pub mod engine;
 pub mod llm;
 pub mod memory;
 pub mod planner;
+pub mod recall;
+pub mod runs;
+pub mod sink;
 pub mod supervisor;
 pub mod tools;
 
-/// Maximum sub-agent nesting depth (L3 guardrail against runaway spawning).
-pub const MAX_DEPTH: u8 = 2;
-/// Maximum reasoning/tool steps per agent (L1/L4 budget).
-pub const MAX_STEPS: u32 = 8;
-
 /// Everything an agent task needs to run. Cheaply cloneable so sub-agents inherit
-/// it. `AppHandle` is the gateway both to event emission and to managed state
-/// (`app.state::<AppState>()`) from inside the spawned task.
+/// it. The sink is the way out of the spawned task: events, monitor activity
+/// and the registry of live sub-agents.
 #[derive(Clone)]
 pub struct AgentContext {
-    pub app: AppHandle,
+    pub sink: Arc<dyn EventSink>,
     pub model: String,
     pub host: String,
     pub port: u16,
     pub searxng_url: Option<String>,
     pub config_dir: PathBuf,
+    /// Steps, depth, timeouts and temperature for this run (L1/L3/L4
+    /// guardrails), resolved by [`budget::resolve`].
+    pub limits: AgentLimits,
+    /// Time and tokens used so far; shared with sub-agents, which spend the
+    /// same budget.
+    pub usage: Arc<RunUsage>,
+    /// Where the run is recorded; shared with sub-agents, whose events are
+    /// part of the same run.
+    pub run: Arc<RunRecorder>,
+    /// Which tool calls go ahead without asking; shared with sub-agents, so
+    /// "approve for this run" covers them too.
+    pub policy: Arc<Policy>,
+    /// Set when `run_command` must run its commands confined.
+    pub sandbox: Option<Sandbox>,
+}
+
+impl AgentContext {
+    /// The context of a new root run `run_id` against a model server, with
+    /// the approval rules in `cfg` and no earlier approvals; see
+    /// [`Self::with_policy`].
+    pub fn new(
+        sink: Arc<dyn EventSink>,
+        cfg: &ServerConfig,
+        host: String,
+        port: u16,
+        model: String,
+        limits: AgentLimits,
+        run_id: &str,
+    ) -> Self {
+        let config_dir = config_io::config_dir();
+        Self {
+            sink,
+            model,
+            host,
+            port,
+            searxng_url: (!cfg.searxng_url.is_empty()).then(|| cfg.searxng_url.clone()),
+            sandbox: Sandbox::from_config(cfg, &config_dir),
+            run: Arc::new(RunRecorder::new(&config_dir, run_id)),
+            config_dir,
+            limits,
+            usage: Default::default(),
+            policy: Arc::new(Policy::new(cfg, Default::default(), false)),
+        }
+    }
+
+    pub fn with_policy(self, policy: Policy) -> Self {
+        Self { policy: Arc::new(policy), ..self }
+    }
+}
+
+/// Send an event to the sink and record it with the run.
+pub fn emit(ctx: &AgentContext, event: AgentEvent) {
+    ctx.run.record(&event);
+    ctx.sink.emit(&event);
 }
 
-/// Run an agent to completion, returning its final answer text.
+/// Run an agent to completion, returning its final answer text. A root agent
+/// given a `resume` checkpoint carries on from it instead of starting over.
 ///
 /// Boxed because the engine recurses here for sub-agents (L3); a plain `async fn`
 /// calling itself would have infinite size.
@@ -55,6 +119,7 @@ pub fn run_agent(
     role: String,
     task: String,
     depth: u8,
+    resume: Option<Checkpoint>,
 ) -> Pin<Box<dyn Future<Output = String> + Send>> {
-    Box::pin(async move { engine::run(ctx, handle, parent, role, task, depth).await })
+    Box::pin(async move { engine::run(ctx, handle, parent, role, task, depth, resume).await })
 }
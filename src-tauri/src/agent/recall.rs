@@ -0,0 +1,279 @@
+//! Picks the records that go into an agent's context. Lessons, memories,
+//! notes, todos, calendar events and planner tasks are ranked against the
+//! task — by embedding similarity fused with BM25 keyword ranking when an
+//! embedding server is configured, by BM25 alone otherwise — and taken best
+//! first until the token budget is spent.
+//!
+//! Embeddings are cached by text in `memory_vectors.db`, so a run only embeds
+//! what changed since the last one.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::rag::embed::Embedder;
+
+const DB_FILE: &str = "memory_vectors.db";
+/// Cached vectors not used for this long are dropped.
+const CACHE_TTL_SECS: i64 = 30 * 86_400;
+/// BM25 term-frequency saturation and length normalisation.
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+/// Reciprocal rank fusion constant; damps the weight of the very top ranks.
+const RRF_K: f32 = 60.0;
+
+/// One record that may go into the context, rendered as it would appear.
+#[derive(Clone, Debug)]
+pub struct Item {
+    /// Heading of the block the record is listed under.
+    pub section: &'static str,
+    pub text: String,
+}
+
+/// Where to get embeddings from; `key` names the server and model so that
+/// vectors from different ones never mix in the cache.
+pub struct Embeddings<'a> {
+    pub embedder: Embedder,
+    pub key: String,
+    pub cache_dir: &'a Path,
+}
+
+/// Rough token count: four characters a token.
+pub fn tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn terms(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 2)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Okapi BM25 score of each document for `query`.
+pub fn bm25(docs: &[&str], query: &str) -> Vec<f32> {
+    let docs: Vec<Vec<String>> = docs.iter().map(|d| terms(d)).collect();
+    let n = docs.len() as f32;
+    let avg_len = (docs.iter().map(Vec::len).sum::<usize>() as f32 / n.max(1.0)).max(1.0);
+    let mut query = terms(query);
+    query.sort();
+    query.dedup();
+    let idf: Vec<f32> = query
+        .iter()
+        .map(|q| {
+            let df = docs.iter().filter(|d| d.contains(q)).count() as f32;
+            ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+        })
+        .collect();
+    docs.iter()
+        .map(|d| {
+            let len = d.len() as f32;
+            query
+                .iter()
+                .zip(&idf)
+                .map(|(q, idf)| {
+                    let tf = d.iter().filter(|t| *t == q).count() as f32;
+                    idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / avg_len))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Item indices, most relevant to `query` first. Items no ranking finds
+/// relevant keep their given order at the end.
+pub async fn rank(items: &[Item], query: &str, embeddings: Option<&Embeddings<'_>>) -> Vec<usize> {
+    let texts: Vec<&str> = items.iter().map(|i| i.text.as_str()).collect();
+    let mut fused = vec![0f32; items.len()];
+    add_ranks(&mut fused, &bm25(&texts, query));
+    if let Some(e) = embeddings {
+        match similarities(e, &texts, query).await {
+            Ok(sims) => add_ranks(&mut fused, &sims),
+            Err(err) => tracing::debug!(%err, "agent context falls back to keyword ranking"),
+        }
+    }
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| fused[b].total_cmp(&fused[a]));
+    order
+}
+
+/// Reciprocal rank fusion: each item gains `1 / (RRF_K + rank)` for its
+/// place in `scores`. Items scoring 0 or less gain nothing.
+fn add_ranks(fused: &mut [f32], scores: &[f32]) {
+    let mut order: Vec<usize> = (0..scores.len()).filter(|&i| scores[i] > 0.0).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    for (rank, i) in order.into_iter().enumerate() {
+        fused[i] += 1.0 / (RRF_K + rank as f32 + 1.0);
+    }
+}
+
+/// Cosine similarity of each text to the query.
+async fn similarities(e: &Embeddings<'_>, texts: &[&str], query: &str) -> Result<Vec<f32>, String> {
+    let mut inputs: Vec<String> = texts.iter().map(|t| t.to_string()).collect();
+    inputs.push(query.to_string());
+    let mut vectors = cached_embed(e, &inputs).await?;
+    let query = vectors.pop().unwrap_or_default();
+    Ok(vectors
+        .iter()
+        .map(|v| if v.len() == query.len() { v.iter().zip(&query).map(|(a, b)| a * b).sum() } else { 0.0 })
+        .collect())
+}
+
+/// Embed `inputs`, reusing cached vectors and caching the new ones.
+async fn cached_embed(e: &Embeddings<'_>, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let keys: Vec<String> = inputs
+        .iter()
+        .map(|t| format!("{:x}", Sha256::digest(format!("{}\n{t}", e.key))))
+        .collect();
+    let now = chrono::Utc::now().timestamp();
+    let mut found: HashMap<usize, Vec<f32>> = HashMap::new();
+    {
+        let conn = open_cache(e.cache_dir)?;
+        for (i, key) in keys.iter().enumerate() {
+            let blob: Option<Vec<u8>> = conn
+                .query_row("SELECT vector FROM vectors WHERE key = ?1", [key], |r| r.get(0))
+                .optional()
+                .map_err(|e| e.to_string())?;
+            if let Some(blob) = blob {
+                found.insert(i, blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect());
+            }
+        }
+    }
+    let missing: Vec<usize> = (0..inputs.len()).filter(|i| !found.contains_key(i)).collect();
+    if !missing.is_empty() {
+        let texts: Vec<String> = missing.iter().map(|&i| inputs[i].clone()).collect();
+        let vectors = e.embedder.embed(&texts).await?;
+        found.extend(missing.into_iter().zip(vectors));
+    }
+    let conn = open_cache(e.cache_dir)?;
+    for (i, key) in keys.iter().enumerate() {
+        let blob: Vec<u8> = found[&i].iter().flat_map(|x| x.to_le_bytes()).collect();
+        conn.execute(
+            "INSERT INTO vectors (key, vector, used_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET used_at = excluded.used_at",
+            params![key, blob, now],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute("DELETE FROM vectors WHERE used_at < ?1", [now - CACHE_TTL_SECS])
+        .map_err(|e| e.to_string())?;
+    Ok((0..inputs.len()).map(|i| found.remove(&i).unwrap_or_default()).collect())
+}
+
+fn open_cache(dir: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(dir.join(DB_FILE)).map_err(|e| e.to_string())?;
+    conn.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS vectors (
+            key     TEXT PRIMARY KEY,
+            vector  BLOB NOT NULL,
+            used_at INTEGER NOT NULL
+        );",
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Take items in `order` while they fit in `budget` tokens (0 = no limit);
+/// one that doesn't fit is skipped so smaller ones after it still can.
+pub fn select(items: &[Item], order: &[usize], budget: usize) -> Vec<usize> {
+    let mut used = 0;
+    order
+        .iter()
+        .copied()
+        .filter(|&i| {
+            let cost = tokens(&items[i].text);
+            let fits = budget == 0 || used + cost <= budget;
+            if fits {
+                used += cost;
+            }
+            fits
+        })
+        .collect()
+}
+
+/// The chosen items under their section headings, sections in the order they
+/// first appear in `items`, items in `chosen` order.
+pub fn render(items: &[Item], chosen: &[usize]) -> String {
+    let mut sections: Vec<&str> = Vec::new();
+    for item in items {
+        if !sections.contains(&item.section) {
+            sections.push(item.section);
+        }
+    }
+    let mut out = String::new();
+    for section in sections {
+        let mut entries = chosen.iter().map(|&i| &items[i]).filter(|i| i.section == section).peekable();
+        if entries.peek().is_none() {
+            continue;
+        }
+        out.push_str(&format!("\n{section}:\n"));
+        for entry in entries {
+            out.push_str(&entry.text);
+            if !entry.text.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer, TempDir};
+    use serde_json::json;
+
+    fn item(section: &'static str, text: &str) -> Item {
+        Item { section, text: text.into() }
+    }
+
+    #[tokio::test]
+    async fn ranks_by_keywords_and_meaning_within_a_budget() {
+        let items = vec![
+            item("Notes", "- Grocery list: eggs, milk, bread"),
+            item("Todos", "- [ ] Benchmark the llama-server build with CUDA"),
+            item("Lessons", "- Quantised GGUF models need less VRAM"),
+            item("Todos", "- [ ] Call a plumber"),
+        ];
+        let task = "Benchmark llama-server on the GPU";
+        let keyword_only = rank(&items, task, None).await;
+        assert_eq!(keyword_only, vec![1, 0, 2, 3], "unmatched items keep their order");
+
+        // A toy embedding: is the text about GPUs (CUDA, VRAM, GPU) or not.
+        let server = StubServer::start(|req| {
+            let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+            let data: Vec<_> = body["input"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let t = text.as_str().unwrap().to_lowercase();
+                    let gpu = ["cuda", "vram", "gpu"].iter().any(|w| t.contains(w));
+                    json!({ "index": i, "embedding": if gpu { [1.0, 0.0] } else { [0.0, 1.0] } })
+                })
+                .collect();
+            StubResponse::json(json!({ "data": data }))
+        })
+        .await;
+        let dir = TempDir::new("recall");
+        let e = Embeddings { embedder: Embedder::new(&server.base_url, ""), key: server.base_url.clone(), cache_dir: &dir.0 };
+        let order = rank(&items, task, Some(&e)).await;
+        assert_eq!(&order[..2], &[1, 2], "the VRAM lesson shares no words with the task but is close in meaning");
+        rank(&items, task, Some(&e)).await;
+        assert_eq!(server.requests().len(), 1, "second ranking came from the cache");
+
+        let budget = tokens(&items[1].text) + tokens(&items[3].text);
+        let chosen = select(&items, &order, budget);
+        assert_eq!(chosen, vec![1, 3], "items that don't fit are skipped");
+        assert_eq!(
+            render(&items, &chosen),
+            "\nTodos:\n- [ ] Benchmark the llama-server build with CUDA\n- [ ] Call a plumber\n"
+        );
+    }
+}
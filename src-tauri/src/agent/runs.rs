@@ -0,0 +1,409 @@
+//! SQLite-backed store of agent runs (`agent_runs.db` in the config dir).
+//!
+//! `runs` has one row per root agent run: what it was asked, where, its
+//! limits, plan and status, and a checkpoint of the root agent's messages
+//! taken after every completed step. `events` holds every [`AgentEvent`] of
+//! the run and its sub-agents as JSON, in order, except streamed tokens.
+//! Rows are written as the run goes, so a crash loses at most the step in
+//! progress; [`RunStore::mark_interrupted`] flags such runs at startup and
+//! [`RunStore::load`] hands back the checkpoint to resume from.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::Value;
+use shared::ipc::{AgentEvent, AgentLimits, AgentRun, AgentRunStatus, AgentRunSummary, PlanStep, RecordedAgentEvent};
+
+use super::AgentContext;
+
+const DB_FILE: &str = "agent_runs.db";
+const SCHEMA_VERSION: i64 = 1;
+/// The message a cancelled agent fails with.
+pub const CANCELLED: &str = "cancelled by user";
+
+const SUMMARY_COLUMNS: &str = "id, task, model, status, started_at, finished_at, steps, tokens, final_text";
+
+/// Where to pick a run up again: its plan, the root agent's messages and
+/// the number of steps they cover.
+#[derive(Clone, Debug, Default)]
+pub struct Checkpoint {
+    pub plan: Vec<PlanStep>,
+    pub messages: Vec<Value>,
+    pub steps: u32,
+}
+
+/// A run about to start, for [`RunStore::start`].
+pub struct NewRun<'a> {
+    pub id: &'a str,
+    pub task: &'a str,
+    pub role: &'a str,
+    pub model: &'a str,
+    pub host: &'a str,
+    pub port: u16,
+    pub limits: &'a AgentLimits,
+}
+
+/// What [`RunStore::load`] returns: enough to start the run again.
+pub struct StoredRun {
+    pub summary: AgentRunSummary,
+    pub role: String,
+    pub host: String,
+    pub port: u16,
+    pub limits: AgentLimits,
+    pub checkpoint: Checkpoint,
+}
+
+pub struct RunStore {
+    conn: Connection,
+}
+
+impl RunStore {
+    /// Open (creating and migrating as needed) the store in `config_dir`.
+    pub fn open(config_dir: &Path) -> Result<Self, String> {
+        let conn = Connection::open(config_dir.join(DB_FILE)).map_err(|e| e.to_string())?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        let store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+        self.conn
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS runs (
+                    id          TEXT PRIMARY KEY,
+                    task        TEXT NOT NULL,
+                    role        TEXT NOT NULL,
+                    model       TEXT NOT NULL,
+                    host        TEXT NOT NULL,
+                    port        INTEGER NOT NULL,
+                    limits      TEXT NOT NULL,
+                    status      TEXT NOT NULL,
+                    started_at  INTEGER NOT NULL,
+                    finished_at INTEGER,
+                    steps       INTEGER NOT NULL DEFAULT 0,
+                    tokens      INTEGER NOT NULL DEFAULT 0,
+                    final_text  TEXT,
+                    plan        TEXT NOT NULL DEFAULT '[]',
+                    messages    TEXT NOT NULL DEFAULT '[]'
+                );
+                CREATE TABLE IF NOT EXISTS events (
+                    id     INTEGER PRIMARY KEY,
+                    run_id TEXT NOT NULL,
+                    at     INTEGER NOT NULL,
+                    event  TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_events_run ON events(run_id, id);",
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Record that a run started, or started again on resume.
+    pub fn start(&self, run: &NewRun, now: i64) -> Result<(), String> {
+        let limits = serde_json::to_string(run.limits).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO runs (id, task, role, model, host, port, limits, status, started_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'running', ?8)
+                 ON CONFLICT(id) DO UPDATE SET status = 'running', finished_at = NULL, final_text = NULL",
+                params![run.id, run.task, run.role, run.model, run.host, run.port, limits, now],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Append an event of run `run_id`; the root agent's plan, answer or
+    /// failure also update the run.
+    pub fn record(&self, run_id: &str, event: &AgentEvent, now: i64) -> Result<(), String> {
+        if matches!(event, AgentEvent::Token { .. } | AgentEvent::Retract { .. }) {
+            return Ok(());
+        }
+        let json = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        self.conn
+            .execute("INSERT INTO events (run_id, at, event) VALUES (?1, ?2, ?3)", params![run_id, now, json])
+            .map_err(|e| e.to_string())?;
+        let finish = |status: AgentRunStatus, text: &str| {
+            self.conn
+                .execute(
+                    "UPDATE runs SET status = ?2, final_text = ?3, finished_at = ?4 WHERE id = ?1",
+                    params![run_id, status_name(status), text, now],
+                )
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        match event {
+            AgentEvent::Plan { agent_id, steps } if agent_id == run_id => self.set_plan(run_id, steps),
+            AgentEvent::PlanUpdate { agent_id, step_id, status } if agent_id == run_id => {
+                let mut plan = self.plan(run_id)?;
+                for step in plan.iter_mut().filter(|s| s.id == *step_id) {
+                    step.status = *status;
+                }
+                self.set_plan(run_id, &plan)
+            }
+            AgentEvent::Done { agent_id, final_text } if agent_id == run_id => finish(AgentRunStatus::Done, final_text),
+            AgentEvent::Error { agent_id, message } if agent_id == run_id => {
+                let status = if message == CANCELLED { AgentRunStatus::Cancelled } else { AgentRunStatus::Failed };
+                finish(status, message)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn plan(&self, run_id: &str) -> Result<Vec<PlanStep>, String> {
+        let json: Option<String> = self
+            .conn
+            .query_row("SELECT plan FROM runs WHERE id = ?1", [run_id], |r| r.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        Ok(json.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default())
+    }
+
+    fn set_plan(&self, run_id: &str, plan: &[PlanStep]) -> Result<(), String> {
+        let json = serde_json::to_string(plan).map_err(|e| e.to_string())?;
+        self.conn
+            .execute("UPDATE runs SET plan = ?2 WHERE id = ?1", params![run_id, json])
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Save the root agent's messages after `steps` completed steps.
+    pub fn checkpoint(&self, run_id: &str, steps: u32, messages: &[Value], tokens: u64) -> Result<(), String> {
+        let json = serde_json::to_string(messages).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "UPDATE runs SET steps = ?2, messages = ?3, tokens = ?4 WHERE id = ?1",
+                params![run_id, steps, json, tokens as i64],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Flag runs left `running` by a previous session; returns how many.
+    pub fn mark_interrupted(&self) -> Result<usize, String> {
+        self.conn
+            .execute("UPDATE runs SET status = 'interrupted' WHERE status = 'running'", [])
+            .map_err(|e| e.to_string())
+    }
+
+    /// Every run, newest first.
+    pub fn list(&self) -> Result<Vec<AgentRunSummary>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {SUMMARY_COLUMNS} FROM runs ORDER BY started_at DESC, rowid DESC"))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], summary_from_row).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// A run with its events, in order.
+    pub fn get(&self, id: &str) -> Result<Option<AgentRun>, String> {
+        let Some(run) = self.load(id)? else {
+            return Ok(None);
+        };
+        let mut stmt = self
+            .conn
+            .prepare("SELECT at, event FROM events WHERE run_id = ?1 ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([id], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut events = Vec::new();
+        for row in rows {
+            let (at, json) = row.map_err(|e| e.to_string())?;
+            // Skip events this build no longer knows rather than failing the run.
+            if let Ok(event) = serde_json::from_str(&json) {
+                events.push(RecordedAgentEvent { at, event });
+            }
+        }
+        Ok(Some(AgentRun {
+            summary: run.summary,
+            limits: run.limits,
+            plan: run.checkpoint.plan,
+            events,
+        }))
+    }
+
+    /// A run as stored, with the checkpoint to resume it from.
+    pub fn load(&self, id: &str) -> Result<Option<StoredRun>, String> {
+        self.conn
+            .query_row(
+                &format!("SELECT {SUMMARY_COLUMNS}, role, host, port, limits, plan, messages FROM runs WHERE id = ?1"),
+                [id],
+                |r| {
+                    let json = |i: usize| r.get::<_, String>(i);
+                    Ok(StoredRun {
+                        summary: summary_from_row(r)?,
+                        role: r.get(9)?,
+                        host: r.get(10)?,
+                        port: r.get(11)?,
+                        limits: serde_json::from_str(&json(12)?).unwrap_or_default(),
+                        checkpoint: Checkpoint {
+                            plan: serde_json::from_str(&json(13)?).unwrap_or_default(),
+                            messages: serde_json::from_str(&json(14)?).unwrap_or_default(),
+                            steps: r.get(6)?,
+                        },
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        for (table, column) in [("events", "run_id"), ("runs", "id")] {
+            self.conn
+                .execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [id])
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn status_name(status: AgentRunStatus) -> &'static str {
+    match status {
+        AgentRunStatus::Running => "running",
+        AgentRunStatus::Done => "done",
+        AgentRunStatus::Failed => "failed",
+        AgentRunStatus::Cancelled => "cancelled",
+        AgentRunStatus::Interrupted => "interrupted",
+    }
+}
+
+fn summary_from_row(r: &Row) -> rusqlite::Result<AgentRunSummary> {
+    let status: String = r.get(3)?;
+    Ok(AgentRunSummary {
+        id: r.get(0)?,
+        task: r.get(1)?,
+        model: r.get(2)?,
+        status: serde_json::from_value(Value::String(status)).unwrap_or(AgentRunStatus::Interrupted),
+        started_at: r.get(4)?,
+        finished_at: r.get(5)?,
+        steps: r.get(6)?,
+        tokens: r.get::<_, i64>(7)? as u64,
+        final_text: r.get(8)?,
+    })
+}
+
+/// A run's handle on the store, shared by its agents through the
+/// [`AgentContext`]. Recording is best effort: a store that can't be opened
+/// or written is logged and the run goes on without it.
+pub struct RunRecorder {
+    /// The run's id, which is its root agent's.
+    pub id: String,
+    store: Mutex<Option<RunStore>>,
+}
+
+impl RunRecorder {
+    pub fn new(config_dir: &Path, id: &str) -> Self {
+        let store = RunStore::open(config_dir)
+            .map_err(|e| tracing::warn!(run = %id, "agent runs won't be recorded: {e}"))
+            .ok();
+        Self { id: id.to_string(), store: Mutex::new(store) }
+    }
+
+    fn with(&self, what: &str, f: impl FnOnce(&RunStore) -> Result<(), String>) {
+        if let Some(store) = self.store.lock().unwrap().as_ref() {
+            if let Err(e) = f(store) {
+                tracing::warn!(run = %self.id, "failed to record {what}: {e}");
+            }
+        }
+    }
+
+    pub fn start(&self, ctx: &AgentContext, role: &str, task: &str) {
+        let run = NewRun {
+            id: &self.id,
+            task,
+            role,
+            model: &ctx.model,
+            host: &ctx.host,
+            port: ctx.port,
+            limits: &ctx.limits,
+        };
+        self.with("run start", |s| s.start(&run, now_ms()));
+    }
+
+    pub fn record(&self, event: &AgentEvent) {
+        self.with("agent event", |s| s.record(&self.id, event, now_ms()));
+    }
+
+    pub fn checkpoint(&self, steps: u32, messages: &[Value], tokens: u64) {
+        self.with("checkpoint", |s| s.checkpoint(&self.id, steps, messages, tokens));
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+    use shared::ipc::PlanStatus;
+
+    #[test]
+    fn records_runs_and_keeps_a_checkpoint_to_resume_from() {
+        let dir = TempDir::new("runs");
+        let store = RunStore::open(&dir.0).unwrap();
+        let limits = AgentLimits { max_steps: 20, ..Default::default() };
+        let run = NewRun { id: "agent-1", task: "Sum the todos", role: "task", model: "m", host: "127.0.0.1", port: 8080, limits: &limits };
+        store.start(&run, 1_000).unwrap();
+
+        let step = PlanStep { id: "step-1".into(), description: "Read todos".into(), status: PlanStatus::Pending };
+        let events = [
+            AgentEvent::Plan { agent_id: "agent-1".into(), steps: vec![step] },
+            AgentEvent::Token { agent_id: "agent-1".into(), delta: "Let me".into() },
+            AgentEvent::PlanUpdate { agent_id: "agent-1".into(), step_id: "step-1".into(), status: PlanStatus::Running },
+            // A sub-agent finishing doesn't finish the run.
+            AgentEvent::Done { agent_id: "subagent-1".into(), final_text: "3 todos".into() },
+        ];
+        for (i, event) in events.iter().enumerate() {
+            store.record("agent-1", event, 1_001 + i as i64).unwrap();
+        }
+        let messages = vec![json!({ "role": "system", "content": "…" }), json!({ "role": "user", "content": "Sum the todos" })];
+        store.checkpoint("agent-1", 1, &messages, 120).unwrap();
+
+        // The app closed mid-run.
+        assert_eq!(store.mark_interrupted().unwrap(), 1);
+        let stored = store.load("agent-1").unwrap().unwrap();
+        assert_eq!((stored.summary.status, stored.summary.steps, stored.summary.tokens), (AgentRunStatus::Interrupted, 1, 120));
+        assert!(stored.summary.status.resumable());
+        assert_eq!((stored.role.as_str(), stored.port, stored.limits.max_steps), ("task", 8080, 20));
+        assert_eq!(stored.checkpoint.messages, messages);
+        assert_eq!(stored.checkpoint.plan[0].status, PlanStatus::Running);
+
+        let got = store.get("agent-1").unwrap().unwrap();
+        assert_eq!(got.events.len(), 3, "tokens aren't recorded");
+        assert_eq!(got.events[0].at, 1_001);
+
+        // Resumed and finished.
+        store.start(&run, 2_000).unwrap();
+        store.record("agent-1", &AgentEvent::Done { agent_id: "agent-1".into(), final_text: "3 todos".into() }, 2_500).unwrap();
+        let list = store.list().unwrap();
+        assert_eq!(list.len(), 1);
+        let s = &list[0];
+        assert_eq!((s.status, s.started_at, s.finished_at, s.final_text.as_deref()), (AgentRunStatus::Done, 1_000, Some(2_500), Some("3 todos")));
+        assert!(!s.status.resumable());
+
+        store.start(&NewRun { id: "agent-2", ..run }, 3_000).unwrap();
+        store.record("agent-2", &AgentEvent::Error { agent_id: "agent-2".into(), message: CANCELLED.into() }, 3_100).unwrap();
+        assert_eq!(store.list().unwrap()[0].status, AgentRunStatus::Cancelled, "newest first");
+
+        store.delete("agent-1").unwrap();
+        assert!(store.get("agent-1").unwrap().is_none());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,43 @@
+//! Where an agent run reports to. In the app that is the UI (events on
+//! `agent://event`), the agent monitor's activity log and the registry of
+//! live agents in [`AppState`], through which `agent_approve` and
+//! `agent_cancel` reach sub-agents. The engine only sees an [`EventSink`], so
+//! tests can run it end to end and collect what it reports instead.
+
+use std::sync::Arc;
+
+use shared::ipc::{AGENT_EVENT, AgentEvent};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::remaining::log_monitor_event_internal;
+use crate::state::{AgentHandle, AppState};
+
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &AgentEvent);
+    /// A line in the agent monitor: the agent's status, what it is doing, and
+    /// a longer message for the activity feed.
+    fn activity(&self, agent_id: &str, status: &str, action: &str, message: &str);
+    /// A sub-agent started; it must be reachable by id until it is removed.
+    fn register_agent(&self, handle: Arc<AgentHandle>);
+    fn remove_agent(&self, id: &str);
+}
+
+impl EventSink for AppHandle {
+    fn emit(&self, event: &AgentEvent) {
+        if let Err(e) = Emitter::emit(self, AGENT_EVENT, event) {
+            tracing::warn!(%e, "failed to emit agent event");
+        }
+    }
+
+    fn activity(&self, agent_id: &str, status: &str, action: &str, message: &str) {
+        log_monitor_event_internal(agent_id, status, action, message);
+    }
+
+    fn register_agent(&self, handle: Arc<AgentHandle>) {
+        self.state::<AppState>().register_agent(handle);
+    }
+
+    fn remove_agent(&self, id: &str) {
+        self.state::<AppState>().remove_agent(id);
+    }
+}
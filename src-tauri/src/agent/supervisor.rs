@@ -1,15 +1,14 @@
 //! Sub-agent supervision (L3). `spawn_subagent` is intercepted by the engine and
 //! routed here. A child agent is a full agent run with its own id, handle, and
-//! event stream; we cap nesting via [`MAX_DEPTH`] and run children to completion,
+//! event stream; we cap nesting at the run's `max_depth` and run children to completion,
 //! returning their final text back to the parent as the tool result.
 
 use std::sync::Arc;
 
-use shared::ipc::{AGENT_EVENT, AgentEvent, ToolCall};
-use tauri::{AppHandle, Emitter, Manager};
+use shared::ipc::{AgentEvent, ToolCall};
 
-use super::{AgentContext, MAX_DEPTH, run_agent};
-use crate::state::{AgentHandle, AppState};
+use super::{AgentContext, emit, run_agent};
+use crate::state::AgentHandle;
 use crate::util::new_id;
 
 /// Run a sub-agent for the given `spawn_subagent` call and return its result text.
@@ -19,7 +18,7 @@ pub async fn spawn(
     call: &ToolCall,
     depth: u8,
 ) -> String {
-    if depth >= MAX_DEPTH {
+    if depth >= ctx.limits.max_depth {
         return "Sub-agent depth limit reached; handle this step directly.".to_string();
     }
 
@@ -31,7 +30,7 @@ pub async fn spawn(
 
     let child_id = new_id("subagent");
     let child_handle = AgentHandle::new(child_id.clone());
-    register(&ctx.app, child_handle.clone());
+    ctx.sink.register_agent(child_handle.clone());
 
     // Tie the child's cancellation to the parent's, so cancelling the root stops
     // the whole tree.
@@ -42,8 +41,8 @@ pub async fn spawn(
         child_cancel.cancel();
     });
 
-    let _ = ctx.app.emit(
-        AGENT_EVENT,
+    emit(
+        ctx,
         AgentEvent::SubAgentSpawned {
             agent_id: parent.id.clone(),
             child_id: child_id.clone(),
@@ -59,17 +58,10 @@ pub async fn spawn(
         role,
         prompt,
         depth + 1,
+        None,
     )
     .await;
 
-    unregister(&ctx.app, &child_id);
+    ctx.sink.remove_agent(&child_id);
     result
 }
-
-fn register(app: &AppHandle, handle: Arc<AgentHandle>) {
-    app.state::<AppState>().register_agent(handle);
-}
-
-fn unregister(app: &AppHandle, id: &str) {
-    app.state::<AppState>().remove_agent(id);
-}
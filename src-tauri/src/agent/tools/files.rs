@@ -3,8 +3,8 @@
 //! Twelve tools that read and write the same JSON files used by the UI panels,
 //! so changes made by the agent are immediately visible in the sidebar.
 //!
-//! All write-path tools have `is_sensitive() → true` and require L4 human
-//! approval before executing.
+//! All write-path tools have `is_sensitive() → true` and require L4 approval
+//! before executing: from the user, unless an approval rule decides.
 //!
 //! ## Tool summary
 //!
@@ -46,24 +46,23 @@
 
 use async_trait::async_trait;
 use serde_json::{Value, json};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use shared::ipc::{TodoItem, Note, NotesStore};
-use crate::config_io::config_dir;
 
 use super::{Tool, ToolContext};
 
-fn get_notes_store_path(scope: &str) -> PathBuf {
+fn get_notes_store_path(dir: &Path, scope: &str) -> PathBuf {
     if scope == "global" {
         let home = std::env::var("HOME").unwrap_or_else(|_| "/home/notroot".to_string());
         PathBuf::from(home)
             .join(".local/share/llama-manager/global_notes.json")
     } else {
-        config_dir().join(".llama-manager-notes.json")
+        dir.join(".llama-manager-notes.json")
     }
 }
 
-fn load_notes_store(scope: &str) -> NotesStore {
-    let path = get_notes_store_path(scope);
+fn load_notes_store(dir: &Path, scope: &str) -> NotesStore {
+    let path = get_notes_store_path(dir, scope);
     if path.exists() {
         if let Ok(content) = std::fs::read_to_string(&path) {
             if let Ok(store) = serde_json::from_str::<NotesStore>(&content) {
@@ -90,8 +89,8 @@ fn load_notes_store(scope: &str) -> NotesStore {
     }
 }
 
-fn save_notes_store(scope: &str, store: &NotesStore) -> Result<(), String> {
-    let path = get_notes_store_path(scope);
+fn save_notes_store(dir: &Path, scope: &str, store: &NotesStore) -> Result<(), String> {
+    let path = get_notes_store_path(dir, scope);
     if let Some(parent) = path.parent() {
         let _ = std::fs::create_dir_all(parent);
     }
@@ -100,16 +99,16 @@ fn save_notes_store(scope: &str, store: &NotesStore) -> Result<(), String> {
     Ok(())
 }
 
-fn load_todos() -> Vec<TodoItem> {
-    std::fs::read_to_string(config_dir().join("agent_todos_v2.json"))
+fn load_todos(dir: &Path) -> Vec<TodoItem> {
+    std::fs::read_to_string(dir.join("agent_todos_v2.json"))
         .ok()
         .and_then(|s| serde_json::from_str(&s).ok())
         .unwrap_or_default()
 }
 
-fn save_todos(items: &[TodoItem]) -> Result<(), String> {
+fn save_todos(dir: &Path, items: &[TodoItem]) -> Result<(), String> {
     let json = serde_json::to_string_pretty(items).map_err(|e| e.to_string())?;
-    std::fs::write(config_dir().join("agent_todos_v2.json"), json).map_err(|e| e.to_string())
+    std::fs::write(dir.join("agent_todos_v2.json"), json).map_err(|e| e.to_string())
 }
 
 // ── GET TODOS TOOL ───────────────────────────────────────────────────────────
@@ -130,8 +129,8 @@ impl Tool for GetTodos {
             "properties": {}
         })
     }
-    async fn run(&self, _args: &Value, _ctx: &ToolContext) -> Result<String, String> {
-        let items = load_todos();
+    async fn run(&self, _args: &Value, ctx: &ToolContext) -> Result<String, String> {
+        let items = load_todos(&ctx.config_dir);
         if items.is_empty() {
             Ok("No todo items in checklist.".to_string())
         } else {
@@ -168,15 +167,15 @@ impl Tool for AddTodo {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let text = args["text"].as_str().unwrap_or("").trim().to_string();
         if text.is_empty() {
             return Err("`text` is required".into());
         }
-        let mut items = load_todos();
+        let mut items = load_todos(&ctx.config_dir);
         let id = format!("t{}", chrono::Utc::now().timestamp_millis());
         items.push(TodoItem { id: id.clone(), text: text.clone(), done: false, priority: String::new(), due_date: None, tags: vec![] });
-        save_todos(&items)?;
+        save_todos(&ctx.config_dir, &items)?;
         Ok(format!("Added todo: {} (id: {})", text, id))
     }
 }
@@ -207,7 +206,7 @@ impl Tool for CompleteTodo {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let id = args["id"].as_str().unwrap_or("").trim().to_string();
         if id.is_empty() {
             return Err("`id` is required".into());
@@ -215,7 +214,7 @@ impl Tool for CompleteTodo {
         let done = args["done"].as_bool().unwrap_or(true);
         let delete = args["delete"].as_bool().unwrap_or(false);
 
-        let mut items = load_todos();
+        let mut items = load_todos(&ctx.config_dir);
         let exists = items.iter().any(|item| item.id == id);
         if !exists {
             return Err(format!("No todo item found with id '{}'. Use get_todos to check IDs.", id));
@@ -223,7 +222,7 @@ impl Tool for CompleteTodo {
 
         if delete {
             items.retain(|item| item.id != id);
-            save_todos(&items)?;
+            save_todos(&ctx.config_dir, &items)?;
             Ok(format!("Deleted todo item '{}'.", id))
         } else {
             for item in &mut items {
@@ -231,7 +230,7 @@ impl Tool for CompleteTodo {
                     item.done = done;
                 }
             }
-            save_todos(&items)?;
+            save_todos(&ctx.config_dir, &items)?;
             Ok(format!("Marked todo item '{}' as {}.", id, if done { "completed" } else { "pending" }))
         }
     }
@@ -262,9 +261,9 @@ impl Tool for ReadNotes {
             }
         })
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let scope = args["scope"].as_str().unwrap_or("project");
-        let store = load_notes_store(scope);
+        let store = load_notes_store(&ctx.config_dir, scope);
         if store.notes.is_empty() {
             Ok(format!("No notes found in scope '{}'.", scope))
         } else {
@@ -318,7 +317,7 @@ impl Tool for WriteNote {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let scope = args["scope"].as_str().unwrap_or("project");
         let name = args["name"].as_str().unwrap_or("").trim().to_string();
         let content = args["content"].as_str().unwrap_or("").to_string();
@@ -328,7 +327,7 @@ impl Tool for WriteNote {
             return Err("`name` is required".into());
         }
 
-        let mut store = load_notes_store(scope);
+        let mut store = load_notes_store(&ctx.config_dir, scope);
         let mut found = false;
 
         for note in &mut store.notes {
@@ -353,7 +352,7 @@ impl Tool for WriteNote {
             });
         }
 
-        save_notes_store(scope, &store)?;
+        save_notes_store(&ctx.config_dir, scope, &store)?;
         Ok(format!("Successfully wrote to note '{}' in scope '{}'.", name, scope))
     }
 }
@@ -362,8 +361,8 @@ impl Tool for WriteNote {
 
 use shared::ipc::{CalendarEvent, CalendarState, KanbanTask, PlannerState, TaskStatus, EventStatus};
 
-fn load_calendar() -> CalendarState {
-    let path = config_dir().join("calendar.json");
+fn load_calendar(dir: &Path) -> CalendarState {
+    let path = dir.join("calendar.json");
     if path.exists() {
         if let Ok(content) = std::fs::read_to_string(&path) {
             if let Ok(state) = serde_json::from_str::<CalendarState>(&content) {
@@ -374,14 +373,14 @@ fn load_calendar() -> CalendarState {
     CalendarState::default()
 }
 
-fn save_calendar(state: &CalendarState) -> Result<(), String> {
-    let path = config_dir().join("calendar.json");
+fn save_calendar(dir: &Path, state: &CalendarState) -> Result<(), String> {
+    let path = dir.join("calendar.json");
     let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
     std::fs::write(path, content).map_err(|e| e.to_string())
 }
 
-fn load_planner() -> PlannerState {
-    let path = config_dir().join("planner_tasks.json");
+fn load_planner(dir: &Path) -> PlannerState {
+    let path = dir.join("planner_tasks.json");
     if path.exists() {
         if let Ok(content) = std::fs::read_to_string(&path) {
             if let Ok(state) = serde_json::from_str::<PlannerState>(&content) {
@@ -392,8 +391,8 @@ fn load_planner() -> PlannerState {
     PlannerState::default()
 }
 
-fn save_planner(state: &PlannerState) -> Result<(), String> {
-    let path = config_dir().join("planner_tasks.json");
+fn save_planner(dir: &Path, state: &PlannerState) -> Result<(), String> {
+    let path = dir.join("planner_tasks.json");
     let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
     std::fs::write(path, content).map_err(|e| e.to_string())
 }
@@ -416,8 +415,8 @@ impl Tool for GetCalendarEvents {
             "properties": {}
         })
     }
-    async fn run(&self, _args: &Value, _ctx: &ToolContext) -> Result<String, String> {
-        let state = load_calendar();
+    async fn run(&self, _args: &Value, ctx: &ToolContext) -> Result<String, String> {
+        let state = load_calendar(&ctx.config_dir);
         if state.events.is_empty() {
             Ok("No scheduled events in calendar.".to_string())
         } else {
@@ -466,7 +465,7 @@ impl Tool for AddCalendarEvent {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let title = args["title"].as_str().unwrap_or("").trim().to_string();
         let time = args["time"].as_str().unwrap_or("").trim().to_string();
         let prompt = args["prompt"].as_str().unwrap_or("").trim().to_string();
@@ -477,7 +476,7 @@ impl Tool for AddCalendarEvent {
             return Err("`title`, `time`, and `prompt` are required fields".into());
         }
 
-        let mut state = load_calendar();
+        let mut state = load_calendar(&ctx.config_dir);
         let id = format!("e{}", chrono::Utc::now().timestamp_millis());
         let new_event = CalendarEvent {
             id: id.clone(),
@@ -490,9 +489,10 @@ impl Tool for AddCalendarEvent {
             result: None,
             tags: vec![],
             recurrence: None,
+            prompt_ref: None,
         };
         state.events.push(new_event);
-        save_calendar(&state)?;
+        save_calendar(&ctx.config_dir, &state)?;
         Ok(format!("Successfully scheduled event: '{}' (id: {})", title, id))
     }
 }
@@ -521,13 +521,13 @@ impl Tool for DeleteCalendarEvent {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let id = args["id"].as_str().unwrap_or("").trim().to_string();
         if id.is_empty() {
             return Err("`id` is required".into());
         }
 
-        let mut state = load_calendar();
+        let mut state = load_calendar(&ctx.config_dir);
         let len_before = state.events.len();
         state.events.retain(|e| e.id != id);
 
@@ -535,7 +535,7 @@ impl Tool for DeleteCalendarEvent {
             return Err(format!("No scheduled event found with ID '{}'.", id));
         }
 
-        save_calendar(&state)?;
+        save_calendar(&ctx.config_dir, &state)?;
         Ok(format!("Successfully deleted scheduled event '{}'.", id))
     }
 }
@@ -558,8 +558,8 @@ impl Tool for GetPlannerTasks {
             "properties": {}
         })
     }
-    async fn run(&self, _args: &Value, _ctx: &ToolContext) -> Result<String, String> {
-        let state = load_planner();
+    async fn run(&self, _args: &Value, ctx: &ToolContext) -> Result<String, String> {
+        let state = load_planner(&ctx.config_dir);
         if state.tasks.is_empty() {
             Ok("No tasks found in planner.".to_string())
         } else {
@@ -609,7 +609,7 @@ impl Tool for AddPlannerTask {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let title = args["title"].as_str().unwrap_or("").trim().to_string();
         let summary = args["summary"].as_str().unwrap_or("").trim().to_string();
         let assigned_agent = args["assigned_agent"].as_str().unwrap_or("ConfigOptimizer").trim().to_string();
@@ -627,7 +627,7 @@ impl Tool for AddPlannerTask {
             _ => TaskStatus::Todo,
         };
 
-        let mut state = load_planner();
+        let mut state = load_planner(&ctx.config_dir);
         let id = format!("task-{}", chrono::Utc::now().timestamp_millis());
         let created_at = chrono::Local::now().format("%Y-%m-%d").to_string();
 
@@ -641,7 +641,7 @@ impl Tool for AddPlannerTask {
             created_at,
         };
         state.tasks.push(new_task);
-        save_planner(&state)?;
+        save_planner(&ctx.config_dir, &state)?;
         Ok(format!("Successfully created planner task: '{}' (id: {})", title, id))
     }
 }
@@ -675,13 +675,13 @@ impl Tool for UpdatePlannerTask {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let id = args["id"].as_str().unwrap_or("").trim().to_string();
         if id.is_empty() {
             return Err("`id` is required".into());
         }
 
-        let mut state = load_planner();
+        let mut state = load_planner(&ctx.config_dir);
         let mut found = false;
 
         for t in &mut state.tasks {
@@ -715,7 +715,7 @@ impl Tool for UpdatePlannerTask {
             return Err(format!("No planner task found with ID '{}'.", id));
         }
 
-        save_planner(&state)?;
+        save_planner(&ctx.config_dir, &state)?;
         Ok(format!("Successfully updated task '{}'.", id))
     }
 }
@@ -744,13 +744,13 @@ impl Tool for DeletePlannerTask {
     fn is_sensitive(&self) -> bool {
         true
     }
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let id = args["id"].as_str().unwrap_or("").trim().to_string();
         if id.is_empty() {
             return Err("`id` is required".into());
         }
 
-        let mut state = load_planner();
+        let mut state = load_planner(&ctx.config_dir);
         let len_before = state.tasks.len();
         state.tasks.retain(|t| t.id != id);
 
@@ -758,7 +758,7 @@ impl Tool for DeletePlannerTask {
             return Err(format!("No task found with ID '{}'.", id));
         }
 
-        save_planner(&state)?;
+        save_planner(&ctx.config_dir, &state)?;
         Ok(format!("Successfully deleted planner task '{}'.", id))
     }
 }
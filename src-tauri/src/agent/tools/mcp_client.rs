@@ -54,7 +54,6 @@ use std::collections::HashMap;
 use std::process::Stdio;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
-use crate::config_io::config_dir;
 
 use super::{Tool, ToolContext};
 
@@ -101,7 +100,7 @@ impl Tool for CallMcpTool {
         })
     }
 
-    async fn run(&self, args: &Value, _ctx: &ToolContext) -> Result<String, String> {
+    async fn run(&self, args: &Value, ctx: &ToolContext) -> Result<String, String> {
         let server_name = args["server_name"].as_str().unwrap_or("").trim();
         let tool_name = args["tool_name"].as_str().unwrap_or("").trim();
         let tool_args = &args["arguments"];
@@ -111,7 +110,7 @@ impl Tool for CallMcpTool {
         }
 
         // 1. Find command & args from mcp_registry.json
-        let registry_path = config_dir().join("mcp_registry.json");
+        let registry_path = ctx.config_dir.join("mcp_registry.json");
         if !registry_path.exists() {
             return Err("No MCP servers are configured. mcp_registry.json does not exist.".into());
         }
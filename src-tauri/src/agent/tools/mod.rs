@@ -13,14 +13,19 @@ use serde_json::{Value, json};
 
 mod files;
 mod mcp_client;
+mod sandbox;
 mod shell;
 mod web;
 
+pub use sandbox::Sandbox;
+
 /// Ambient context handed to every tool. Intentionally small: tools do I/O, they
 /// don't emit events or touch agent state.
 pub struct ToolContext {
     pub searxng_url: Option<String>,
     pub config_dir: PathBuf,
+    /// Set when `run_command` must run its commands confined.
+    pub sandbox: Option<Sandbox>,
 }
 
 #[async_trait]
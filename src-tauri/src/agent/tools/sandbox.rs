@@ -0,0 +1,621 @@
+//! Confinement for `run_command`, on when `agent_sandbox` is set. A confined
+//! command sees the filesystem read-only except for one workspace directory,
+//! has no network unless `agent_sandbox_network` allows it, and runs under
+//! CPU time and address space rlimits.
+//!
+//! On Linux that is done one of two ways:
+//! - **bubblewrap**, when `bwrap` is on `PATH` and works here: `/` bound
+//!   read-only, the workspace bound writable over it, a private `/tmp`, and
+//!   new user, PID, IPC and (unless allowed) network namespaces.
+//! - **natively** otherwise. Landlock makes everything outside the workspace
+//!   read-only. A new user and network namespace takes the network away or,
+//!   where unprivileged namespaces are off, the seccomp filter refuses inet
+//!   sockets instead. That filter also refuses syscalls a command has no
+//!   business making: ptrace, mount, bpf, keyrings, module loading and so on.
+//!
+//! Either way the rlimits are set in the child before it execs. A command is
+//! refused, not run with less than was asked for, when the read-only root or
+//! the network cut can't be enforced; on other platforms it always is. The
+//! report says which restrictions were applied and goes in the tool output.
+
+use std::path::{Path, PathBuf};
+
+use shared::ServerConfig;
+use tokio::process::Command;
+
+/// The sandbox settings of a run; see [`Sandbox::from_config`].
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub workspace: PathBuf,
+    pub network: bool,
+    /// 0 = no limit.
+    pub cpu_secs: u64,
+    /// 0 = no limit.
+    pub memory_mb: u64,
+}
+
+impl Sandbox {
+    /// The sandbox `cfg` asks for, or `None` with it off.
+    pub fn from_config(cfg: &ServerConfig, config_dir: &Path) -> Option<Self> {
+        let workspace = cfg.agent_sandbox_workspace.trim();
+        cfg.agent_sandbox.then(|| Self {
+            workspace: if workspace.is_empty() {
+                config_dir.join("agent-workspace")
+            } else {
+                PathBuf::from(workspace)
+            },
+            network: cfg.agent_sandbox_network,
+            cpu_secs: cfg.agent_sandbox_cpu_secs,
+            memory_mb: cfg.agent_sandbox_memory_mb,
+        })
+    }
+
+    /// The part of a report every mechanism shares; `net` is how the
+    /// network was cut, if it was.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn limits(&self, net: &str) -> String {
+        let cap = |v: u64, unit: &str| {
+            if v == 0 {
+                "unlimited".to_string()
+            } else {
+                format!("{v}{unit}")
+            }
+        };
+        format!(
+            "rw={} net={} cpu={} mem={}",
+            self.workspace.display(),
+            if self.network { "host" } else { net },
+            cap(self.cpu_secs, "s"),
+            cap(self.memory_mb, "MiB"),
+        )
+    }
+}
+
+/// A command set up to run confined, and a line saying how, e.g.
+/// `sandbox=landlock+netns+seccomp ro-root rw=/ws net=none cpu=120s mem=4096MiB`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Confined {
+    pub command: Command,
+    pub report: String,
+}
+
+/// Set up `shell prefix… command` to run in `cwd` inside the sandbox.
+#[cfg(target_os = "linux")]
+pub fn confine(
+    sandbox: &Sandbox,
+    shell: &str,
+    prefix: &[String],
+    command: &str,
+    cwd: &Path,
+) -> Result<Confined, String> {
+    linux::confine(sandbox, shell, prefix, command, cwd)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn confine(
+    _sandbox: &Sandbox,
+    _shell: &str,
+    _prefix: &[String],
+    _command: &str,
+    _cwd: &Path,
+) -> Result<Confined, String> {
+    Err("The command sandbox is only available on Linux. Turn it off under \
+         Settings → Command Sandbox to run commands unconfined."
+        .into())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Stdio;
+    use std::sync::OnceLock;
+
+    use tokio::process::Command;
+
+    use super::{Confined, Sandbox};
+
+    pub fn confine(
+        sandbox: &Sandbox,
+        shell: &str,
+        prefix: &[String],
+        command: &str,
+        cwd: &Path,
+    ) -> Result<Confined, String> {
+        std::fs::create_dir_all(&sandbox.workspace).map_err(|e| {
+            format!(
+                "Can't create the sandbox workspace `{}`: {e}",
+                sandbox.workspace.display()
+            )
+        })?;
+        let limits = Rlimits {
+            cpu_secs: sandbox.cpu_secs,
+            memory_bytes: sandbox.memory_mb.saturating_mul(1 << 20),
+        };
+
+        if let Some(bwrap) = bwrap() {
+            let mut cmd = Command::new(bwrap);
+            cmd.args(["--die-with-parent", "--new-session", "--unshare-all"]);
+            if sandbox.network {
+                cmd.arg("--share-net");
+            }
+            cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+                .args(["--tmpfs", "/tmp"])
+                .arg("--bind")
+                .arg(&sandbox.workspace)
+                .arg(&sandbox.workspace)
+                .arg("--chdir")
+                .arg(cwd)
+                .arg("--")
+                .arg(shell)
+                .args(prefix)
+                .arg(command)
+                .env("TMPDIR", "/tmp");
+            // SAFETY: only setrlimit, which is async-signal-safe, runs in the child.
+            unsafe {
+                cmd.pre_exec(move || limits.apply());
+            }
+            return Ok(Confined {
+                command: cmd,
+                report: format!(
+                    "sandbox=bubblewrap ro-root {} tmp=private",
+                    sandbox.limits("none")
+                ),
+            });
+        }
+
+        let abi = landlock_abi();
+        if abi < 1 {
+            return Err("Sandbox unavailable: neither bubblewrap nor Landlock (Linux 5.13+) \
+                        is there to make the filesystem read-only. Install bubblewrap, or \
+                        turn the command sandbox off in Settings to run commands unconfined."
+                .into());
+        }
+        let ids = IdMaps::current();
+        let netns = !sandbox.network && userns_available(&ids);
+        let filter = seccomp_filter(!sandbox.network && !netns);
+        if !sandbox.network && !netns && filter.is_none() {
+            return Err("Sandbox unavailable: user namespaces are disabled and there is no \
+                        seccomp support for this architecture, so the network can't be cut. \
+                        Allow the network in the sandbox settings or install bubblewrap."
+                .into());
+        }
+        let landlock = Landlock::new(abi, &sandbox.workspace)?;
+
+        // The workspace is the only place a command can write to, so its
+        // temporary files go there too.
+        let tmp = sandbox.workspace.join(".tmp");
+        std::fs::create_dir_all(&tmp)
+            .map_err(|e| format!("Can't create `{}`: {e}", tmp.display()))?;
+
+        let mut cmd = Command::new(shell);
+        cmd.args(prefix).arg(command).current_dir(cwd).env("TMPDIR", &tmp);
+        let seccomp = filter.is_some();
+        // SAFETY: everything the closure does is a raw syscall on data
+        // prepared beforehand; nothing allocates or takes a lock.
+        unsafe {
+            cmd.pre_exec(move || {
+                limits.apply()?;
+                if netns {
+                    ids.enter_netns()?;
+                }
+                cvt(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+                landlock.restrict_self()?;
+                if let Some(filter) = &filter {
+                    filter.install()?;
+                }
+                Ok(())
+            });
+        }
+
+        let mut mechanisms = vec!["landlock"];
+        if netns {
+            mechanisms.push("netns");
+        }
+        if seccomp {
+            mechanisms.push("seccomp");
+        }
+        let net = if netns { "none" } else { "no-inet" };
+        Ok(Confined {
+            command: cmd,
+            report: format!(
+                "sandbox={} ro-root {}",
+                mechanisms.join("+"),
+                sandbox.limits(net)
+            ),
+        })
+    }
+
+    fn cvt(ret: libc::c_int) -> io::Result<()> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// `bwrap`, if it is on `PATH` and can set up namespaces here (it can't
+    /// inside some containers, or where unprivileged namespaces are off).
+    fn bwrap() -> Option<&'static Path> {
+        static BWRAP: OnceLock<Option<PathBuf>> = OnceLock::new();
+        BWRAP
+            .get_or_init(|| {
+                let path = std::env::var_os("PATH")?;
+                let bin = std::env::split_paths(&path)
+                    .map(|dir| dir.join("bwrap"))
+                    .find(|p| p.is_file())?;
+                std::process::Command::new(&bin)
+                    .args(["--unshare-all", "--ro-bind", "/", "/", "--dev", "/dev", "true"])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .is_ok_and(|s| s.success())
+                    .then_some(bin)
+            })
+            .as_deref()
+    }
+
+    // ── rlimits ──────────────────────────────────────────────────────────────
+
+    #[derive(Clone, Copy)]
+    struct Rlimits {
+        cpu_secs: u64,
+        memory_bytes: u64,
+    }
+
+    impl Rlimits {
+        fn apply(self) -> io::Result<()> {
+            // Lowered, never raised: a hard limit already below ours stays.
+            macro_rules! lower {
+                ($resource:expr, $value:expr) => {
+                    if $value > 0 {
+                        let mut lim = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                        cvt(unsafe { libc::getrlimit($resource, &mut lim) })?;
+                        let v = ($value as libc::rlim_t).min(lim.rlim_max);
+                        lim = libc::rlimit { rlim_cur: v, rlim_max: v };
+                        cvt(unsafe { libc::setrlimit($resource, &lim) })?;
+                    }
+                };
+            }
+            lower!(libc::RLIMIT_CPU, self.cpu_secs);
+            lower!(libc::RLIMIT_AS, self.memory_bytes);
+            Ok(())
+        }
+    }
+
+    // ── User and network namespace ───────────────────────────────────────────
+
+    /// The uid and gid maps of a user namespace in which the command keeps
+    /// its own ids, so files it creates are still the user's.
+    struct IdMaps {
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    impl IdMaps {
+        fn current() -> Self {
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Self {
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+            }
+        }
+
+        /// Move into a new user namespace and a network namespace owned by
+        /// it, whose only interface is a loopback that is down.
+        fn enter_netns(&self) -> io::Result<()> {
+            cvt(unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) })?;
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)
+        }
+    }
+
+    fn write_file(path: &CStr, data: &[u8]) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        cvt(fd)?;
+        let written = unsafe { libc::write(fd, data.as_ptr().cast(), data.len()) };
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        if written == data.len() as isize { Ok(()) } else { Err(err) }
+    }
+
+    /// Whether [`IdMaps::enter_netns`] works here, found out once in a
+    /// throwaway child: it is off in some kernels and containers.
+    fn userns_available(ids: &IdMaps) -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| unsafe {
+            match libc::fork() {
+                -1 => false,
+                0 => libc::_exit(if ids.enter_netns().is_ok() { 0 } else { 1 }),
+                pid => {
+                    let mut status = 0;
+                    libc::waitpid(pid, &mut status, 0) == pid
+                        && libc::WIFEXITED(status)
+                        && libc::WEXITSTATUS(status) == 0
+                }
+            }
+        })
+    }
+
+    // ── Landlock ─────────────────────────────────────────────────────────────
+
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    /// ABI 2.
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    /// ABI 3.
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: libc::c_int,
+    }
+
+    /// The Landlock ABI version the kernel speaks; below 1 when it has none
+    /// or it is disabled.
+    fn landlock_abi() -> i64 {
+        unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        }
+    }
+
+    /// A ruleset that handles every kind of write and allows them only
+    /// beneath the workspace, plus writing to `/dev/null`. Reads and
+    /// execution aren't handled, so they stay allowed everywhere.
+    struct Landlock {
+        handled: u64,
+        file_access: u64,
+        workspace: CString,
+    }
+
+    impl Landlock {
+        fn new(abi: i64, workspace: &Path) -> Result<Self, String> {
+            let mut handled = ACCESS_FS_WRITE_FILE
+                | ACCESS_FS_REMOVE_DIR
+                | ACCESS_FS_REMOVE_FILE
+                | ACCESS_FS_MAKE_CHAR
+                | ACCESS_FS_MAKE_DIR
+                | ACCESS_FS_MAKE_REG
+                | ACCESS_FS_MAKE_SOCK
+                | ACCESS_FS_MAKE_FIFO
+                | ACCESS_FS_MAKE_BLOCK
+                | ACCESS_FS_MAKE_SYM;
+            let mut file_access = ACCESS_FS_WRITE_FILE;
+            if abi >= 2 {
+                handled |= ACCESS_FS_REFER;
+            }
+            if abi >= 3 {
+                handled |= ACCESS_FS_TRUNCATE;
+                file_access |= ACCESS_FS_TRUNCATE;
+            }
+            let workspace = CString::new(workspace.as_os_str().as_bytes())
+                .map_err(|_| "The sandbox workspace path contains a NUL byte.".to_string())?;
+            Ok(Self { handled, file_access, workspace })
+        }
+
+        fn restrict_self(&self) -> io::Result<()> {
+            let attr = RulesetAttr { handled_access_fs: self.handled };
+            let ruleset = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr as *const RulesetAttr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0u32,
+                )
+            } as libc::c_int;
+            cvt(ruleset)?;
+            let result = allow(ruleset, &self.workspace, self.handled)
+                .and_then(|()| allow(ruleset, c"/dev/null", self.file_access))
+                .and_then(|()| {
+                    cvt(unsafe {
+                        libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32)
+                    } as libc::c_int)
+                });
+            unsafe { libc::close(ruleset) };
+            result
+        }
+    }
+
+    fn allow(ruleset: libc::c_int, path: &CStr, access: u64) -> io::Result<()> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        cvt(fd)?;
+        let rule = PathBeneathAttr { allowed_access: access, parent_fd: fd };
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset,
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0u32,
+            )
+        } as libc::c_int;
+        let result = cvt(ret);
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    // ── seccomp ──────────────────────────────────────────────────────────────
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_003E);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_00B7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    /// Refused with EPERM: ways out of the sandbox or deeper into the kernel.
+    const DENIED: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_userfaultfd,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_io_uring_setup,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+    ];
+
+    // Offsets into `struct seccomp_data`; the low half of args[0] on the
+    // little-endian architectures above.
+    const DATA_NR: u32 = 0;
+    const DATA_ARCH: u32 = 4;
+    const DATA_ARG0: u32 = 16;
+
+    const LD_W_ABS: u16 = 0x20;
+    const JEQ_K: u16 = 0x15;
+    const JGE_K: u16 = 0x35;
+    const RET_K: u16 = 0x06;
+
+    struct Filter(Vec<libc::sock_filter>);
+
+    impl Filter {
+        fn install(&self) -> io::Result<()> {
+            let prog = libc::sock_fprog {
+                len: self.0.len() as libc::c_ushort,
+                filter: self.0.as_ptr().cast_mut(),
+            };
+            cvt(unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                    &prog as *const libc::sock_fprog as libc::c_ulong,
+                )
+            })
+        }
+    }
+
+    fn op(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code, jt: 0, jf: 0, k }
+    }
+
+    /// The filter for this architecture, if there is one; `block_inet` also
+    /// refuses IPv4, IPv6 and packet sockets.
+    fn seccomp_filter(block_inet: bool) -> Option<Filter> {
+        let arch = AUDIT_ARCH?;
+        let mut prog = vec![
+            op(LD_W_ABS, DATA_ARCH),
+            libc::sock_filter { code: JEQ_K, jt: 1, jf: 0, k: arch },
+            op(RET_K, libc::SECCOMP_RET_KILL_PROCESS),
+            op(LD_W_ABS, DATA_NR),
+        ];
+        // Jumps to the final EPERM, patched in once its place is known.
+        let mut to_deny = Vec::new();
+        if cfg!(target_arch = "x86_64") {
+            // x32 syscalls share the arch but carry this bit.
+            to_deny.push(prog.len());
+            prog.push(op(JGE_K, 0x4000_0000));
+        }
+        for &nr in DENIED {
+            to_deny.push(prog.len());
+            prog.push(op(JEQ_K, nr as u32));
+        }
+        if block_inet {
+            let domains = [libc::AF_INET, libc::AF_INET6, libc::AF_PACKET];
+            prog.push(libc::sock_filter {
+                code: JEQ_K,
+                jt: 0,
+                jf: domains.len() as u8 + 1,
+                k: libc::SYS_socket as u32,
+            });
+            prog.push(op(LD_W_ABS, DATA_ARG0));
+            for domain in domains {
+                to_deny.push(prog.len());
+                prog.push(op(JEQ_K, domain as u32));
+            }
+        }
+        prog.push(op(RET_K, libc::SECCOMP_RET_ALLOW));
+        let deny = prog.len();
+        prog.push(op(RET_K, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+        for i in to_deny {
+            prog[i].jt = (deny - i - 1) as u8;
+        }
+        Some(Filter(prog))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::process::Stdio;
+
+    #[tokio::test]
+    async fn writes_only_to_the_workspace_and_has_no_network() {
+        let (workspace, outside) = (TempDir::new("sandbox"), TempDir::new("sandbox"));
+        let sandbox = Sandbox {
+            workspace: workspace.0.clone(),
+            network: false,
+            cpu_secs: 10,
+            memory_mb: 1024,
+        };
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let script = format!(
+            "echo in > {ws}/in; echo out > {out}/out; echo quiet > /dev/null && echo devnull; \
+             python3 -c 'import socket; socket.create_connection((\"127.0.0.1\", {port}), 2)' \
+             2>/dev/null && echo connected",
+            ws = workspace.0.display(),
+            out = outside.0.display(),
+        );
+        let mut confined =
+            match confine(&sandbox, "/bin/sh", &["-c".into()], &script, &workspace.0) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("skipping, no sandbox here: {e}");
+                    return;
+                }
+            };
+        assert!(confined.report.contains("ro-root"), "{}", confined.report);
+        assert!(confined.report.contains("net=none") || confined.report.contains("net=no-inet"));
+
+        let output = confined
+            .command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(workspace.0.join("in").exists());
+        assert!(!outside.0.join("out").exists());
+        assert!(stdout.contains("devnull"), "{stdout}");
+        assert!(!stdout.contains("connected"), "{stdout}");
+    }
+}
@@ -20,13 +20,17 @@
 //! - Execution ceiling: `timeout_secs` (1–120 s, default 30). Exceeded → SIGKILL.
 //! - `kill_on_drop` is set — no orphan processes survive the agent task.
 //! - `cwd` is validated to exist before spawning.
+//! - With the command sandbox on (Settings → Command Sandbox, Linux only) the
+//!   command gets a read-only root, one writable workspace, no network unless
+//!   allowed, and CPU/memory rlimits; see [`super::sandbox`]. It is refused
+//!   when that can't be enforced, never run unconfined.
 //!
 //! ## Parameters (JSON Schema)
 //!
 //! | Name           | Type    | Required | Description                                        |
 //! |----------------|---------|----------|----------------------------------------------------|
 //! | `command`      | string  | yes      | Command string, e.g. `"ls -la"` or `"Get-Process"` |
-//! | `cwd`          | string  | no       | Working directory (absolute, or relative to config dir) |
+//! | `cwd`          | string  | no       | Working directory (absolute, or relative to config dir / sandbox workspace) |
 //! | `timeout_secs` | integer | no       | Max run time in seconds 1–120 (default 30)         |
 //! | `env`          | object  | no       | Extra `{KEY: VALUE}` environment variable overrides |
 //! | `stdin`        | string  | no       | Text fed to the command's standard input            |
@@ -44,6 +48,7 @@
 //! ## Output format
 //! ```
 //! exit=<code> time=<ms>ms shell=<path>
+//! sandbox=<mechanisms> ro-root rw=<dir> net=<none|no-inet|host> cpu=<s> mem=<MiB>  (when sandboxed)
 //! <stdout>
 //! [stderr]
 //! <stderr if non-empty>
@@ -56,7 +61,7 @@ use tokio::io::AsyncWriteExt;
 use async_trait::async_trait;
 use serde_json::{Value, json};
 
-use super::{Tool, ToolContext};
+use super::{Tool, ToolContext, sandbox};
 
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 const MAX_TIMEOUT_SECS: u64 = 120;
@@ -142,6 +147,8 @@ impl Tool for RunCommand {
          `env` (extra {KEY:VALUE} environment variables), \
          `stdin` (text piped to the command). \
          Output is capped at 16 KiB. Always requires user approval. \
+         When sandboxed, only the workspace is writable and the output says \
+         which restrictions applied. \
          Examples: {\"command\":\"git status\"}, \
          {\"command\":\"python3 script.py\",\"cwd\":\"/path\",\"timeout_secs\":60}, \
          {\"command\":\"npm run build\",\"env\":{\"NODE_ENV\":\"production\"}}"
@@ -162,7 +169,8 @@ impl Tool for RunCommand {
                     "type": "string",
                     "description": "Working directory for the command. \
                         Absolute paths are used as-is. Relative paths are resolved \
-                        from the application config directory. Omit to use config dir."
+                        from the application config directory, or from the sandbox \
+                        workspace when commands are sandboxed. Omit to use that directory."
                 },
                 "timeout_secs": {
                     "type": "integer",
@@ -201,16 +209,25 @@ impl Tool for RunCommand {
         }
 
         // ── Resolve working directory ────────────────────────────────────────
+        let base = match &ctx.sandbox {
+            Some(sb) => {
+                std::fs::create_dir_all(&sb.workspace).map_err(|e| {
+                    format!("Can't create the sandbox workspace `{}`: {e}", sb.workspace.display())
+                })?;
+                &sb.workspace
+            }
+            None => &ctx.config_dir,
+        };
         let cwd = match args["cwd"].as_str().filter(|s| !s.is_empty()) {
             Some(raw) => {
                 let p = std::path::Path::new(raw);
                 if p.is_absolute() {
                     p.to_path_buf()
                 } else {
-                    ctx.config_dir.join(raw)
+                    base.join(raw)
                 }
             }
-            None => ctx.config_dir.clone(),
+            None => base.clone(),
         };
         if !cwd.exists() {
             return Err(format!(
@@ -243,11 +260,19 @@ impl Tool for RunCommand {
         let (shell_bin, shell_prefix) = resolve_shell();
 
         // ── Build and spawn the process ───────────────────────────────────────
-        let mut cmd = Command::new(&shell_bin);
-        cmd.args(&shell_prefix)
-            .arg(command_str)
-            .current_dir(&cwd)
-            .envs(extra_env)
+        let (mut cmd, sandbox_report) = match &ctx.sandbox {
+            Some(sb) => {
+                let confined =
+                    sandbox::confine(sb, &shell_bin, &shell_prefix, command_str, &cwd)?;
+                (confined.command, Some(confined.report))
+            }
+            None => {
+                let mut cmd = Command::new(&shell_bin);
+                cmd.args(&shell_prefix).arg(command_str).current_dir(&cwd);
+                (cmd, None)
+            }
+        };
+        cmd.envs(extra_env)
             .stdin(if stdin_data.is_some() {
                 Stdio::piped()
             } else {
@@ -299,6 +324,10 @@ impl Tool for RunCommand {
         let mut result = format!(
             "exit={exit_code} time={elapsed_ms}ms shell={shell_bin}\n"
         );
+        if let Some(report) = sandbox_report {
+            result.push_str(&report);
+            result.push('\n');
+        }
 
         if !stdout.trim().is_empty() {
             result.push_str(stdout.trim_end());
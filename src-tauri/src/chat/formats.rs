@@ -0,0 +1,291 @@
+//! Conversation export (Markdown, ShareGPT, OpenAI JSONL) and import
+//! (ShareGPT, OpenAI JSONL). Pure text in, text out; the commands handle files.
+//!
+//! Exports carry the active branch only and leave out reasoning, except in
+//! Markdown, and empty replies. Messages map to the formats' roles as
+//! system/user/assistant/tool. Our messages have no structured tool calls, so
+//! an imported assistant `tool_calls` list is kept as JSON in the content.
+
+use serde_json::{json, Value};
+use shared::ipc::{ChatMessage, Conversation};
+
+/// One conversation read from an import file.
+#[derive(Debug)]
+pub struct ImportedChat {
+    pub title: Option<String>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Messages worth exporting: empty replies (cancelled or never streamed) and
+/// blank messages are dropped.
+fn exported(conv: &Conversation) -> impl Iterator<Item = &ChatMessage> {
+    conv.messages.iter().filter(|m| !m.content.trim().is_empty())
+}
+
+pub fn to_markdown(convs: &[Conversation]) -> String {
+    let mut out = String::new();
+    for (i, conv) in convs.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n---\n\n");
+        }
+        out.push_str(&format!("# {}\n\n", conv.summary.title));
+        let when = chrono::DateTime::from_timestamp(conv.summary.updated_at, 0)
+            .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_default();
+        let meta: Vec<&str> = [conv.summary.model.as_str(), when.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !meta.is_empty() {
+            out.push_str(&format!("_{}_\n\n", meta.join(" · ")));
+        }
+        for m in exported(conv) {
+            let heading = match m.role.as_str() {
+                "system" => "System",
+                "user" => "User",
+                "assistant" => "Assistant",
+                "tool" => "Tool",
+                other => other,
+            };
+            out.push_str(&format!("### {heading}\n\n"));
+            if !m.reasoning.trim().is_empty() {
+                out.push_str(&format!(
+                    "<details><summary>Thinking</summary>\n\n{}\n\n</details>\n\n",
+                    m.reasoning.trim()
+                ));
+            }
+            out.push_str(m.content.trim_end());
+            out.push_str("\n\n");
+        }
+    }
+    out
+}
+
+pub fn to_sharegpt(convs: &[Conversation]) -> String {
+    let items: Vec<Value> = convs
+        .iter()
+        .filter(|c| exported(c).next().is_some())
+        .map(|conv| {
+            let turns: Vec<Value> = exported(conv)
+                .map(|m| json!({ "from": sharegpt_role(&m.role), "value": m.content }))
+                .collect();
+            json!({ "id": conv.summary.id, "title": conv.summary.title, "conversations": turns })
+        })
+        .collect();
+    serde_json::to_string_pretty(&items).unwrap_or_default()
+}
+
+pub fn to_openai_jsonl(convs: &[Conversation]) -> String {
+    convs
+        .iter()
+        .filter(|c| exported(c).next().is_some())
+        .map(|conv| {
+            let messages: Vec<Value> = exported(conv)
+                .map(|m| json!({ "role": m.role, "content": m.content }))
+                .collect();
+            format!("{}\n", json!({ "messages": messages }))
+        })
+        .collect()
+}
+
+fn sharegpt_role(role: &str) -> &str {
+    match role {
+        "user" => "human",
+        "assistant" => "gpt",
+        "tool" => "observation",
+        other => other,
+    }
+}
+
+/// ShareGPT speaker names vary between datasets; these are the common ones.
+fn role_from_sharegpt(from: &str) -> Option<&'static str> {
+    Some(match from.to_lowercase().as_str() {
+        "system" => "system",
+        "human" | "user" => "user",
+        "gpt" | "assistant" | "chatgpt" | "bard" | "model" | "function_call" => "assistant",
+        "observation" | "tool" | "function_response" => "tool",
+        _ => return None,
+    })
+}
+
+/// A ShareGPT file: an array of `{"conversations": […]}` objects, or a single
+/// one. A top-level `"system"` string becomes the first message.
+pub fn from_sharegpt(text: &str) -> Result<Vec<ImportedChat>, String> {
+    let root: Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {e}"))?;
+    let items = match root {
+        Value::Array(items) => items,
+        item @ Value::Object(_) => vec![item],
+        _ => return Err("Expected a ShareGPT array or object".into()),
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let turns = item["conversations"]
+                .as_array()
+                .ok_or_else(|| format!("Item {}: no `conversations` list", i + 1))?;
+            let mut messages = Vec::new();
+            if let Some(system) = item["system"].as_str().filter(|s| !s.trim().is_empty()) {
+                messages.push(ChatMessage::new("system", system));
+            }
+            for turn in turns {
+                let from = turn["from"].as_str().or(turn["role"].as_str()).unwrap_or_default();
+                let role = role_from_sharegpt(from)
+                    .ok_or_else(|| format!("Item {}: unknown speaker `{from}`", i + 1))?;
+                let value = turn["value"].as_str().or(turn["content"].as_str()).unwrap_or_default();
+                messages.push(ChatMessage::new(role, value));
+            }
+            Ok(ImportedChat {
+                title: item["title"].as_str().map(str::to_string),
+                messages,
+            })
+        })
+        .collect()
+}
+
+/// OpenAI chat fine-tuning JSONL: one `{"messages": […]}` object per line.
+pub fn from_openai_jsonl(text: &str) -> Result<Vec<ImportedChat>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            let line_no = n + 1;
+            let v: Value = serde_json::from_str(line).map_err(|e| format!("Line {line_no}: {e}"))?;
+            let raw = v["messages"]
+                .as_array()
+                .ok_or_else(|| format!("Line {line_no}: no `messages` list"))?;
+            let messages = raw
+                .iter()
+                .map(|m| {
+                    let role = match m["role"].as_str().unwrap_or_default() {
+                        "system" | "developer" => "system",
+                        "user" => "user",
+                        "assistant" => "assistant",
+                        "tool" | "function" => "tool",
+                        other => return Err(format!("Line {line_no}: unknown role `{other}`")),
+                    };
+                    let mut content = openai_content(&m["content"]);
+                    if let Some(calls) = m["tool_calls"].as_array().filter(|c| !c.is_empty()) {
+                        if !content.is_empty() {
+                            content.push('\n');
+                        }
+                        content.push_str(&Value::Array(calls.clone()).to_string());
+                    }
+                    Ok(ChatMessage::new(role, content))
+                })
+                .collect::<Result<_, String>>()?;
+            Ok(ImportedChat { title: None, messages })
+        })
+        .collect()
+}
+
+/// `content` is a string, `null`, or a list of parts of which we keep the text.
+fn openai_content(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::ipc::{ChatSampling, ConversationSummary};
+
+    fn conversation(messages: Vec<ChatMessage>) -> Conversation {
+        Conversation {
+            summary: ConversationSummary {
+                id: "conv-1".into(),
+                title: "Weather tool".into(),
+                created_at: 0,
+                updated_at: 1_700_000_000,
+                model: "qwen3-8b".into(),
+                archived: false,
+                message_count: messages.len() as u32,
+            },
+            sampling: ChatSampling::default(),
+            messages,
+        }
+    }
+
+    fn sample() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::new("system", "You can call get_weather."),
+            ChatMessage::new("user", "Weather in Oslo?"),
+            ChatMessage::new("assistant", r#"{"name":"get_weather","arguments":{"city":"Oslo"}}"#),
+            ChatMessage::new("tool", r#"{"temp_c":4}"#),
+            ChatMessage {
+                reasoning: "4 °C is cold.".into(),
+                ..ChatMessage::new("assistant", "It's 4 °C in Oslo.\nBring a coat.")
+            },
+            ChatMessage::new("assistant", ""),
+        ]
+    }
+
+    fn roles_and_content(messages: &[ChatMessage]) -> Vec<(String, String)> {
+        messages.iter().map(|m| (m.role.clone(), m.content.clone())).collect()
+    }
+
+    #[test]
+    fn sharegpt_and_openai_round_trip() {
+        let conv = conversation(sample());
+        let expected = roles_and_content(&sample()[..5]);
+
+        let sharegpt = to_sharegpt(std::slice::from_ref(&conv));
+        assert!(sharegpt.contains(r#""from": "observation""#));
+        let back = from_sharegpt(&sharegpt).unwrap();
+        assert_eq!(back.len(), 1);
+        assert_eq!(back[0].title.as_deref(), Some("Weather tool"));
+        assert_eq!(roles_and_content(&back[0].messages), expected);
+        assert!(back[0].messages.iter().all(|m| m.reasoning.is_empty()));
+
+        let jsonl = to_openai_jsonl(&[conv.clone(), conv]);
+        assert_eq!(jsonl.lines().count(), 2);
+        let back = from_openai_jsonl(&jsonl).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(roles_and_content(&back[1].messages), expected);
+    }
+
+    #[test]
+    fn markdown_has_a_section_per_message() {
+        let md = to_markdown(&[conversation(sample())]);
+        assert!(md.starts_with("# Weather tool\n\n_qwen3-8b · 2023-11-14 22:13 UTC_"));
+        assert_eq!(md.matches("### Assistant").count(), 2, "the empty reply is skipped");
+        assert!(md.contains("### Tool\n\n{\"temp_c\":4}"));
+        assert!(md.contains("<details><summary>Thinking</summary>\n\n4 °C is cold."));
+    }
+
+    #[test]
+    fn imports_common_dataset_variants() {
+        let sharegpt = r#"{"system": "Be terse.", "conversations": [
+            {"from": "human", "value": "Hi"}, {"from": "gpt", "value": "Hello"}]}"#;
+        let chats = from_sharegpt(sharegpt).unwrap();
+        assert_eq!(
+            roles_and_content(&chats[0].messages),
+            vec![("system".into(), "Be terse.".into()), ("user".into(), "Hi".into()), ("assistant".into(), "Hello".into())]
+        );
+        assert!(from_sharegpt(r#"[{"conversations": [{"from": "narrator", "value": "x"}]}]"#)
+            .unwrap_err()
+            .contains("narrator"));
+
+        let jsonl = concat!(
+            r#"{"messages": [{"role": "developer", "content": "Use tools."}, {"role": "user", "content": [{"type": "text", "text": "Time?"}]}, "#,
+            r#"{"role": "assistant", "content": null, "tool_calls": [{"id": "c1", "type": "function", "function": {"name": "now", "arguments": "{}"}}]}, "#,
+            r#"{"role": "tool", "tool_call_id": "c1", "content": "12:00"}]}"#,
+            "\n\n",
+            "not json\n"
+        );
+        let err = from_openai_jsonl(jsonl).unwrap_err();
+        assert!(err.starts_with("Line 3:"), "{err}");
+        let chats = from_openai_jsonl(jsonl.split("\n\n").next().unwrap()).unwrap();
+        let m = &chats[0].messages;
+        assert_eq!((m[0].role.as_str(), m[1].content.as_str()), ("system", "Time?"));
+        assert!(m[2].content.contains(r#""name":"now""#));
+        assert_eq!((m[3].role.as_str(), m[3].content.as_str()), ("tool", "12:00"));
+    }
+}
@@ -0,0 +1,5 @@
+//! Chat persistence. The streaming itself lives in `commands::chat`.
+
+pub mod formats;
+pub mod reasoning;
+pub mod store;
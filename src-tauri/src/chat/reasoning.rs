@@ -0,0 +1,92 @@
+//! Splits inline `<think>…</think>` blocks out of streamed answer text.
+//!
+//! Servers that parse reasoning themselves send it as `reasoning_content`
+//! deltas; models served without that still write the tags into `content`.
+//! Tags can be cut across deltas, so a possible tag prefix at the end of a
+//! delta is held back until the next one arrives.
+
+const OPEN: &str = "<think>";
+const CLOSE: &str = "</think>";
+
+/// One split delta: `(reasoning, content)`; either may be empty.
+pub type Split = (String, String);
+
+#[derive(Default)]
+pub struct ThinkSplitter {
+    in_think: bool,
+    /// Text held back because it may be the start of a tag.
+    pending: String,
+    /// Drop the blank lines models put between `</think>` and the answer.
+    trim_answer_start: bool,
+}
+
+impl ThinkSplitter {
+    pub fn push(&mut self, delta: &str) -> Split {
+        self.pending.push_str(delta);
+        let mut out = Split::default();
+        loop {
+            let tag = if self.in_think { CLOSE } else { OPEN };
+            if let Some(i) = self.pending.find(tag) {
+                let before: String = self.pending.drain(..i + tag.len()).take(i).collect();
+                self.emit(&mut out, &before);
+                self.in_think = !self.in_think;
+                self.trim_answer_start = !self.in_think;
+                continue;
+            }
+            // Longest suffix that could still grow into `tag`. Tags are ASCII,
+            // so the cut always lands on a char boundary.
+            let keep = (1..tag.len())
+                .rev()
+                .find(|&n| self.pending.ends_with(&tag[..n]))
+                .unwrap_or(0);
+            let ready: String = self.pending.drain(..self.pending.len() - keep).collect();
+            self.emit(&mut out, &ready);
+            return out;
+        }
+    }
+
+    /// Flush whatever was held back at the end of the stream.
+    pub fn finish(&mut self) -> Split {
+        let rest = std::mem::take(&mut self.pending);
+        let mut out = Split::default();
+        self.emit(&mut out, &rest);
+        out
+    }
+
+    fn emit(&mut self, out: &mut Split, text: &str) {
+        if self.in_think {
+            out.0.push_str(text);
+            return;
+        }
+        let text = if self.trim_answer_start { text.trim_start() } else { text };
+        if !text.is_empty() {
+            self.trim_answer_start = false;
+        }
+        out.1.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_tags_cut_across_deltas() {
+        let mut s = ThinkSplitter::default();
+        let mut reasoning = String::new();
+        let mut content = String::new();
+        for delta in ["<thi", "nk>Let me", " see.</th", "ink>\n\nThe answer", " is 4 <", "b>"] {
+            let (r, c) = s.push(delta);
+            reasoning.push_str(&r);
+            content.push_str(&c);
+        }
+        let (r, c) = s.finish();
+        reasoning.push_str(&r);
+        content.push_str(&c);
+        assert_eq!(reasoning, "Let me see.");
+        assert_eq!(content, "The answer is 4 <b>");
+
+        let mut plain = ThinkSplitter::default();
+        assert_eq!(plain.push("no tags here"), (String::new(), "no tags here".into()));
+    }
+}
@@ -0,0 +1,746 @@
+//! SQLite-backed conversation store (`chat.db` in the config dir).
+//!
+//! Replaces `chat_history.json`, which held a single flat message list.
+//! `conversations` holds one row per chat with its model and sampling settings
+//! (as JSON, so new fields need no migration); `messages` holds the messages,
+//! with the generation stats of streamed replies as JSON (added in v2), any
+//! model reasoning (v3) and the document passages a reply was grounded in
+//! (v5, JSON).
+//!
+//! Since v4 the messages form a tree: editing or regenerating a message adds
+//! a sibling instead of overwriting it. Each message points at its parent and
+//! at the child on the active branch, and `conversations.active_root` at the
+//! first message of the active branch; `get` follows those pointers, so
+//! everything built on it (the chat view, export) sees the active branch.
+//! `messages_fts` is an FTS5 mirror of message text for search, keyed by the
+//! message rowid. Opening the store imports an existing `chat_history.json`
+//! as one conversation and renames the file to `chat_history.json.bak`.
+//!
+//! Each schema version is one migration step, committed together with the
+//! `user_version` it brings the database to.
+
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use shared::ipc::{ChatMessage, ChatSampling, ChatSearchHit, Conversation, ConversationPatch, ConversationSummary};
+
+const DB_FILE: &str = "chat.db";
+const LEGACY_JSON: &str = "chat_history.json";
+const SCHEMA_VERSION: i64 = 5;
+/// Title used until the first user message arrives.
+pub const UNTITLED: &str = "New chat";
+const TITLE_CHARS: usize = 48;
+
+const SUMMARY_COLUMNS: &str = "c.id, c.title, c.created_at, c.updated_at, c.model, c.archived, \
+     (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)";
+
+pub struct ChatStore {
+    conn: Connection,
+}
+
+impl ChatStore {
+    /// Open (creating and migrating as needed) the store in `config_dir`.
+    pub fn open(config_dir: &Path) -> Result<Self, String> {
+        let conn = Connection::open(config_dir.join(DB_FILE)).map_err(|e| e.to_string())?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        let mut store = Self { conn };
+        store.migrate()?;
+        // Imports go through `save_messages`, so they need the latest schema.
+        // One that fails leaves `chat_history.json` where it is and the store
+        // usable; it is tried again on the next open.
+        if let Err(e) = store.import_legacy(config_dir) {
+            tracing::warn!("failed to import chat_history.json: {}", e);
+        }
+        Ok(store)
+    }
+
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+        if version < 1 {
+            self.migrate_v1()?;
+        }
+        if version < 2 {
+            self.migrate_v2()?;
+        }
+        if version < 3 {
+            self.migrate_v3()?;
+        }
+        if version < 4 {
+            self.migrate_v4()?;
+        }
+        if version < 5 {
+            self.migrate_v5()?;
+        }
+        Ok(())
+    }
+
+    /// Apply migration `version` and record it in `user_version` in one
+    /// transaction, so a step that fails part way leaves the schema as it
+    /// was and is tried again on the next open.
+    fn step(&mut self, version: i64, sql: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch(sql).map_err(|e| format!("chat store migration v{version}: {e}"))?;
+        tx.pragma_update(None, "user_version", version).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    fn migrate_v1(&mut self) -> Result<(), String> {
+        self.step(
+            1,
+            "CREATE TABLE IF NOT EXISTS conversations (
+                id         TEXT PRIMARY KEY,
+                title      TEXT NOT NULL,
+                title_auto INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                model      TEXT NOT NULL,
+                sampling   TEXT NOT NULL,
+                archived   INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS messages (
+                id              INTEGER PRIMARY KEY,
+                conversation_id TEXT NOT NULL,
+                position        INTEGER NOT NULL,
+                role            TEXT NOT NULL,
+                content         TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_messages_conversation ON messages(conversation_id, position);
+            CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                content,
+                conversation_id UNINDEXED,
+                role UNINDEXED
+            );",
+        )
+    }
+
+    /// Import `chat_history.json` if it is still there. The file is moved to
+    /// `chat_history.json.bak` first and moved back if the import fails, so
+    /// it is only ever imported once.
+    fn import_legacy(&mut self, config_dir: &Path) -> Result<(), String> {
+        let legacy = config_dir.join(LEGACY_JSON);
+        if !legacy.exists() {
+            return Ok(());
+        }
+        let backup = config_dir.join(format!("{LEGACY_JSON}.bak"));
+        std::fs::rename(&legacy, &backup).map_err(|e| e.to_string())?;
+        let imported = self.import_messages(&backup);
+        if imported.is_err() {
+            if let Err(e) = std::fs::rename(&backup, &legacy) {
+                tracing::warn!("failed to restore chat_history.json: {}", e);
+            }
+        }
+        imported
+    }
+
+    fn import_messages(&mut self, file: &Path) -> Result<(), String> {
+        let messages: Vec<ChatMessage> = std::fs::read_to_string(file)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let modified = std::fs::metadata(file)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or_else(|| chrono::Utc::now().timestamp(), |d| d.as_secs() as i64);
+        let id = self.create("", &ChatSampling::default(), modified)?.summary.id;
+        self.save_messages(&id, &messages, modified)?;
+        tracing::info!(count = messages.len(), "imported chat_history.json into chat store");
+        Ok(())
+    }
+
+    /// v2: per-message generation stats.
+    fn migrate_v2(&mut self) -> Result<(), String> {
+        self.step(2, "ALTER TABLE messages ADD COLUMN stats TEXT")
+    }
+
+    /// v3: reasoning kept apart from message content.
+    fn migrate_v3(&mut self) -> Result<(), String> {
+        self.step(3, "ALTER TABLE messages ADD COLUMN reasoning TEXT NOT NULL DEFAULT ''")
+    }
+
+    /// v4: the message tree. Existing chats become a single branch.
+    fn migrate_v4(&mut self) -> Result<(), String> {
+        self.step(
+            4,
+            "ALTER TABLE messages ADD COLUMN uid TEXT;
+            ALTER TABLE messages ADD COLUMN parent_uid TEXT;
+            ALTER TABLE messages ADD COLUMN active_child TEXT;
+            ALTER TABLE conversations ADD COLUMN active_root TEXT;
+            UPDATE messages SET uid = 'msg-' || id;
+            UPDATE messages SET parent_uid = (
+                SELECT p.uid FROM messages p
+                WHERE p.conversation_id = messages.conversation_id AND p.position = messages.position - 1
+            );
+            UPDATE messages SET active_child = (
+                SELECT c.uid FROM messages c
+                WHERE c.conversation_id = messages.conversation_id AND c.position = messages.position + 1
+            );
+            UPDATE conversations SET active_root = (
+                SELECT m.uid FROM messages m WHERE m.conversation_id = conversations.id AND m.position = 0
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_uid ON messages(uid);
+            CREATE INDEX IF NOT EXISTS idx_messages_parent ON messages(conversation_id, parent_uid);
+            DELETE FROM messages_fts;
+            INSERT INTO messages_fts (rowid, content, conversation_id, role)
+                SELECT id, content, conversation_id, role FROM messages;",
+        )
+    }
+
+    /// v5: sources of document-grounded replies.
+    fn migrate_v5(&mut self) -> Result<(), String> {
+        self.step(5, "ALTER TABLE messages ADD COLUMN sources TEXT")
+    }
+
+    /// Conversations, most recently updated first.
+    pub fn list(&self, include_archived: bool) -> Result<Vec<ConversationSummary>, String> {
+        let sql = format!(
+            "SELECT {SUMMARY_COLUMNS} FROM conversations c WHERE ?1 OR c.archived = 0 \
+             ORDER BY c.updated_at DESC, c.rowid DESC"
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([include_archived], row_to_summary)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    pub fn create(&self, model: &str, sampling: &ChatSampling, now: i64) -> Result<Conversation, String> {
+        let id = crate::util::new_id("conv");
+        let sampling_json = serde_json::to_string(sampling).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO conversations (id, title, title_auto, created_at, updated_at, model, sampling, archived)
+                 VALUES (?1, ?2, 1, ?3, ?3, ?4, ?5, 0)",
+                params![id, UNTITLED, now, model, sampling_json],
+            )
+            .map_err(|e| e.to_string())?;
+        self.get(&id)?.ok_or_else(|| "Conversation vanished after insert".to_string())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Conversation>, String> {
+        let sql = format!("SELECT {SUMMARY_COLUMNS}, c.sampling, c.active_root FROM conversations c WHERE c.id = ?1");
+        let row = self
+            .conn
+            .query_row(&sql, [id], |r| {
+                Ok((row_to_summary(r)?, r.get::<_, String>(7)?, r.get::<_, Option<String>>(8)?))
+            })
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some((summary, sampling, root)) = row else {
+            return Ok(None);
+        };
+        let mut messages = Vec::new();
+        let mut next = root;
+        while let Some(uid) = next {
+            let Some(node) = load_node(&self.conn, id, &uid)? else {
+                break;
+            };
+            let siblings = children(&self.conn, id, node.parent.as_deref())?;
+            messages.push(ChatMessage {
+                siblings: if siblings.len() > 1 { siblings } else { Vec::new() },
+                ..node.message
+            });
+            next = node.active_child;
+        }
+        Ok(Some(Conversation {
+            summary,
+            sampling: serde_json::from_str(&sampling).unwrap_or_default(),
+            messages,
+        }))
+    }
+
+    /// Save the active branch: messages are matched by id and updated, new
+    /// ones (with an id the UI picked, or none) are added under the previous
+    /// message, and the branch becomes the active one. Saving the same branch
+    /// twice is harmless. Also bumps `updated_at` and regenerates the title
+    /// unless the user set one.
+    pub fn save_messages(&mut self, id: &str, messages: &[ChatMessage], now: i64) -> Result<ConversationSummary, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let title_auto: bool = tx
+            .query_row("SELECT title_auto FROM conversations WHERE id = ?1", [id], |r| r.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Conversation {id} not found"))?;
+        let mut parent: Option<String> = None;
+        for (depth, m) in messages.iter().enumerate() {
+            let uid = if m.id.is_empty() { crate::util::new_id("msg") } else { m.id.clone() };
+            upsert_message(&tx, id, &uid, parent.as_deref(), depth, m)?;
+            set_active(&tx, id, parent.as_deref(), &uid)?;
+            parent = Some(uid);
+        }
+        tx.execute("UPDATE conversations SET updated_at = ?2 WHERE id = ?1", params![id, now])
+            .map_err(|e| e.to_string())?;
+        if title_auto {
+            let title = auto_title(messages).unwrap_or_else(|| UNTITLED.to_string());
+            tx.execute("UPDATE conversations SET title = ?2 WHERE id = ?1", params![id, title])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.summary(id)
+    }
+
+    /// Add an empty assistant reply next to `message_id` and make it the
+    /// active branch, ready to be streamed into.
+    pub fn regenerate(&mut self, id: &str, message_id: &str, now: i64) -> Result<Conversation, String> {
+        self.add_sibling(id, message_id, None, now)
+    }
+
+    /// Add an edited copy of `message_id` next to it and make that the active
+    /// branch; the original and everything after it stay reachable.
+    pub fn edit(&mut self, id: &str, message_id: &str, content: &str, now: i64) -> Result<Conversation, String> {
+        self.add_sibling(id, message_id, Some(content), now)
+    }
+
+    fn add_sibling(&mut self, id: &str, of: &str, content: Option<&str>, now: i64) -> Result<Conversation, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let node = load_node(&tx, id, of)?.ok_or_else(|| format!("Message {of} not found"))?;
+        let message = match content {
+            Some(content) => ChatMessage::new(node.message.role, content),
+            None if node.message.role == "assistant" => ChatMessage::new("assistant", ""),
+            None => return Err("Only assistant replies can be regenerated".into()),
+        };
+        let uid = crate::util::new_id("msg");
+        upsert_message(&tx, id, &uid, node.parent.as_deref(), node.depth, &message)?;
+        set_active(&tx, id, node.parent.as_deref(), &uid)?;
+        tx.execute("UPDATE conversations SET updated_at = ?2 WHERE id = ?1", params![id, now])
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        self.get(id)?.ok_or_else(|| format!("Conversation {id} not found"))
+    }
+
+    /// Make `message_id` the active choice among its siblings. Below it the
+    /// branch continues wherever it was active last time.
+    pub fn switch_branch(&mut self, id: &str, message_id: &str) -> Result<Conversation, String> {
+        let node = load_node(&self.conn, id, message_id)?.ok_or_else(|| format!("Message {message_id} not found"))?;
+        set_active(&self.conn, id, node.parent.as_deref(), message_id)?;
+        self.get(id)?.ok_or_else(|| format!("Conversation {id} not found"))
+    }
+
+    pub fn update(&self, id: &str, patch: &ConversationPatch, now: i64) -> Result<ConversationSummary, String> {
+        let exec = |sql: &str, value: &dyn rusqlite::ToSql| {
+            self.conn
+                .execute(sql, params![id, value])
+                .map_err(|e| e.to_string())
+        };
+        if let Some(title) = &patch.title {
+            let title = title.trim();
+            if title.is_empty() {
+                // Clearing the title hands it back to the generator.
+                let messages = self.get(id)?.map(|c| c.messages).unwrap_or_default();
+                let title = auto_title(&messages).unwrap_or_else(|| UNTITLED.to_string());
+                exec("UPDATE conversations SET title = ?2, title_auto = 1 WHERE id = ?1", &title)?;
+            } else {
+                exec("UPDATE conversations SET title = ?2, title_auto = 0 WHERE id = ?1", &title)?;
+            }
+        }
+        if let Some(model) = &patch.model {
+            exec("UPDATE conversations SET model = ?2 WHERE id = ?1", model)?;
+        }
+        if let Some(sampling) = &patch.sampling {
+            let json = serde_json::to_string(sampling).map_err(|e| e.to_string())?;
+            exec("UPDATE conversations SET sampling = ?2 WHERE id = ?1", &json)?;
+        }
+        if let Some(archived) = patch.archived {
+            exec("UPDATE conversations SET archived = ?2 WHERE id = ?1", &archived)?;
+        }
+        exec("UPDATE conversations SET updated_at = ?2 WHERE id = ?1", &now)?;
+        self.summary(id)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for (table, column) in [("messages_fts", "conversation_id"), ("messages", "conversation_id"), ("conversations", "id")] {
+            tx.execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [id])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Full-text search over every conversation, archived ones included. Each
+    /// word matches as a prefix; all words must appear in the message.
+    pub fn search(&self, query: &str, limit: u32) -> Result<Vec<ChatSearchHit>, String> {
+        let Some(fts_query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT f.conversation_id, c.title, f.role, snippet(messages_fts, 0, '[', ']', '…', 12), c.updated_at
+                 FROM messages_fts f JOIN conversations c ON c.id = f.conversation_id
+                 WHERE messages_fts MATCH ?1
+                 ORDER BY bm25(messages_fts), c.updated_at DESC
+                 LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![fts_query, limit], |r| {
+                Ok(ChatSearchHit {
+                    conversation_id: r.get(0)?,
+                    title: r.get(1)?,
+                    role: r.get(2)?,
+                    snippet: r.get(3)?,
+                    updated_at: r.get(4)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    fn summary(&self, id: &str) -> Result<ConversationSummary, String> {
+        let sql = format!("SELECT {SUMMARY_COLUMNS} FROM conversations c WHERE c.id = ?1");
+        self.conn
+            .query_row(&sql, [id], row_to_summary)
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Conversation {id} not found"))
+    }
+}
+
+/// A message row with its place in the tree.
+struct Node {
+    message: ChatMessage,
+    parent: Option<String>,
+    active_child: Option<String>,
+    depth: usize,
+}
+
+fn load_node(conn: &Connection, conversation_id: &str, uid: &str) -> Result<Option<Node>, String> {
+    conn.query_row(
+        "SELECT role, content, stats, reasoning, parent_uid, active_child, position, sources
+         FROM messages WHERE conversation_id = ?1 AND uid = ?2",
+        [conversation_id, uid],
+        |r| {
+            let stats: Option<String> = r.get(2)?;
+            let sources: Option<String> = r.get(7)?;
+            Ok(Node {
+                message: ChatMessage {
+                    id: uid.to_string(),
+                    reasoning: r.get(3)?,
+                    stats: stats.and_then(|json| serde_json::from_str(&json).ok()),
+                    sources: sources.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default(),
+                    ..ChatMessage::new(r.get::<_, String>(0)?, r.get::<_, String>(1)?)
+                },
+                parent: r.get(4)?,
+                active_child: r.get(5)?,
+                depth: r.get::<_, i64>(6)? as usize,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Ids of the messages under `parent` (the roots when `None`), oldest first.
+fn children(conn: &Connection, conversation_id: &str, parent: Option<&str>) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT uid FROM messages WHERE conversation_id = ?1 AND parent_uid IS ?2 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![conversation_id, parent], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn upsert_message(
+    conn: &Connection,
+    conversation_id: &str,
+    uid: &str,
+    parent: Option<&str>,
+    depth: usize,
+    m: &ChatMessage,
+) -> Result<(), String> {
+    let stats = m.stats.as_ref().and_then(|s| serde_json::to_string(s).ok());
+    let sources = (!m.sources.is_empty()).then(|| serde_json::to_string(&m.sources).ok()).flatten();
+    let existing: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT id, content FROM messages WHERE conversation_id = ?1 AND uid = ?2",
+            [conversation_id, uid],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match existing {
+        Some((rowid, content)) => {
+            conn.execute(
+                "UPDATE messages SET content = ?2, reasoning = ?3, stats = ?4, sources = ?5 WHERE id = ?1",
+                params![rowid, m.content, m.reasoning, stats, sources],
+            )
+            .map_err(|e| e.to_string())?;
+            if content != m.content {
+                conn.execute("UPDATE messages_fts SET content = ?2 WHERE rowid = ?1", params![rowid, m.content])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO messages (uid, parent_uid, conversation_id, position, role, content, stats, reasoning, sources)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![uid, parent, conversation_id, depth as i64, m.role, m.content, stats, m.reasoning, sources],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT INTO messages_fts (rowid, content, conversation_id, role) VALUES (?1, ?2, ?3, ?4)",
+                params![conn.last_insert_rowid(), m.content, conversation_id, m.role],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Point `parent` (or the conversation, for a root) at `uid` as its active child.
+fn set_active(conn: &Connection, conversation_id: &str, parent: Option<&str>, uid: &str) -> Result<(), String> {
+    match parent {
+        None => conn.execute(
+            "UPDATE conversations SET active_root = ?2 WHERE id = ?1",
+            [conversation_id, uid],
+        ),
+        Some(parent) => conn.execute(
+            "UPDATE messages SET active_child = ?3 WHERE conversation_id = ?1 AND uid = ?2",
+            [conversation_id, parent, uid],
+        ),
+    }
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+fn row_to_summary(r: &Row) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: r.get(0)?,
+        title: r.get(1)?,
+        created_at: r.get(2)?,
+        updated_at: r.get(3)?,
+        model: r.get(4)?,
+        archived: r.get(5)?,
+        message_count: r.get(6)?,
+    })
+}
+
+/// Title from the first real user message: its first line, whitespace
+/// collapsed, cut at a word boundary. Slash commands don't count.
+pub fn auto_title(messages: &[ChatMessage]) -> Option<String> {
+    let first = messages
+        .iter()
+        .find(|m| m.role == "user" && !m.content.trim().is_empty() && !m.content.trim_start().starts_with('/'))?;
+    let line = first.content.trim().lines().next().unwrap_or_default();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let mut title = String::new();
+    for word in &words {
+        if title.chars().count() + word.chars().count() + 1 > TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    if title.is_empty() {
+        // One very long word.
+        title = line.chars().take(TITLE_CHARS).collect();
+    }
+    if title.chars().count() < line.chars().count() {
+        title.push('…');
+    }
+    Some(title)
+}
+
+/// Quote every word so user input can't hit FTS5 syntax, and match prefixes.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn msg(role: &str, content: &str) -> ChatMessage {
+        ChatMessage::new(role, content)
+    }
+
+    #[test]
+    fn conversations_save_search_archive_and_delete() {
+        let dir = TempDir::new("chat-store");
+        let mut store = ChatStore::open(&dir.0).unwrap();
+        let sampling = ChatSampling { temperature: Some(0.2), ..Default::default() };
+        let a = store.create("qwen3-8b", &sampling, 100).unwrap().summary.id;
+        let b = store.create("gemma-3", &ChatSampling::default(), 110).unwrap().summary.id;
+        let stats = shared::ipc::GenerationStats {
+            model: "qwen3-8b".into(),
+            ttft_ms: Some(182.0),
+            completion_tokens: Some(9),
+            predicted_per_second: Some(41.5),
+            ..Default::default()
+        };
+        let source = shared::ipc::ChatSource {
+            index: 1,
+            path: "/docs/kv-cache.md".into(),
+            start_line: 4,
+            end_line: 19,
+            heading: "Quantisation".into(),
+            score: 0.82,
+        };
+        let reply = ChatMessage {
+            reasoning: "The user has a 12 GB card.".into(),
+            stats: Some(stats.clone()),
+            sources: vec![source.clone()],
+            ..msg("assistant", "Quantise the cache with q8_0.")
+        };
+
+        let summary = store
+            .save_messages(
+                &a,
+                &[
+                    msg("user", "/help"),
+                    msg("user", "How do I tune the KV cache for a long context window on a 12 GB card?"),
+                    reply,
+                ],
+                120,
+            )
+            .unwrap();
+        assert_eq!(summary.title, "How do I tune the KV cache for a long context…");
+        assert_eq!(summary.message_count, 3);
+        store.save_messages(&b, &[msg("user", "Write a haiku about caching")], 130).unwrap();
+
+        let listed: Vec<_> = store.list(false).unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(listed, vec![b.clone(), a.clone()]);
+        let conv = store.get(&a).unwrap().unwrap();
+        assert_eq!(conv.sampling, sampling);
+        assert_eq!(conv.messages[2].content, "Quantise the cache with q8_0.");
+        assert_eq!(conv.messages[2].stats.as_ref(), Some(&stats));
+        assert_eq!(conv.messages[2].reasoning, "The user has a 12 GB card.");
+        assert_eq!(conv.messages[2].sources, vec![source]);
+        assert_eq!(conv.messages[1].stats, None);
+
+        let hits = store.search("quantis", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].conversation_id.as_str(), hits[0].role.as_str()), (a.as_str(), "assistant"));
+        assert!(hits[0].snippet.contains("[Quantise]"));
+        assert_eq!(store.search("cach", 10).unwrap().len(), 3);
+        assert!(store.search("card", 10).unwrap().iter().all(|h| h.role == "user"), "reasoning isn't indexed");
+        assert!(store.search("\"unbalanced OR", 10).unwrap().is_empty());
+
+        let renamed = store
+            .update(&a, &ConversationPatch { title: Some("KV cache".into()), archived: Some(true), ..Default::default() }, 140)
+            .unwrap();
+        assert!(renamed.archived);
+        store.save_messages(&a, &[msg("user", "Something else")], 150).unwrap();
+        assert_eq!(store.get(&a).unwrap().unwrap().summary.title, "KV cache", "a set title sticks");
+        assert_eq!(store.list(false).unwrap().len(), 1);
+        assert_eq!(store.list(true).unwrap().len(), 2);
+
+        store.delete(&a).unwrap();
+        assert!(store.get(&a).unwrap().is_none());
+        assert!(store.search("something", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn edits_and_regenerations_branch_and_can_be_switched() {
+        let dir = TempDir::new("chat-store");
+        let mut store = ChatStore::open(&dir.0).unwrap();
+        let id = store.create("m", &ChatSampling::default(), 100).unwrap().summary.id;
+        let path = |store: &ChatStore| -> Vec<(String, String, usize)> {
+            let conv = store.get(&id).unwrap().unwrap();
+            conv.messages.into_iter().map(|m| (m.role, m.content, m.siblings.len())).collect()
+        };
+        let pair = |c: &str, a: &str| vec![msg("user", c), msg("assistant", a)];
+
+        store.save_messages(&id, &pair("2+2?", "5"), 110).unwrap();
+        let saved = store.get(&id).unwrap().unwrap().messages;
+        store.save_messages(&id, &saved, 111).unwrap();
+        assert_eq!(store.summary(&id).unwrap().message_count, 2, "re-saving adds nothing");
+
+        let regen = store.regenerate(&id, &saved[1].id, 120).unwrap();
+        assert_eq!(path(&store), vec![("user".into(), "2+2?".into(), 0), ("assistant".into(), String::new(), 2)]);
+        let mut branch = regen.messages;
+        branch[1].content = "4".into();
+        store.save_messages(&id, &branch, 121).unwrap();
+        assert!(store.regenerate(&id, &saved[0].id, 122).is_err(), "user messages are edited, not regenerated");
+
+        let edited = store.edit(&id, &saved[0].id, "3+3?", 130).unwrap();
+        assert_eq!(path(&store), vec![("user".into(), "3+3?".into(), 2)]);
+        let mut branch = edited.messages;
+        branch.push(msg("assistant", "6"));
+        store.save_messages(&id, &branch, 131).unwrap();
+
+        store.switch_branch(&id, &saved[0].id).unwrap();
+        assert_eq!(path(&store), vec![("user".into(), "2+2?".into(), 2), ("assistant".into(), "4".into(), 2)]);
+        store.switch_branch(&id, &saved[1].id).unwrap();
+        assert_eq!(path(&store)[1].1, "5");
+        assert_eq!(store.search("6", 10).unwrap().len(), 1, "other branches stay searchable");
+    }
+
+    #[test]
+    fn migrates_legacy_history_once() {
+        let dir = TempDir::new("chat-store");
+        let legacy = vec![msg("system", "Be brief."), msg("user", "Hello there"), msg("assistant", "Hi!")];
+        std::fs::write(dir.0.join(LEGACY_JSON), serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        let store = ChatStore::open(&dir.0).unwrap();
+        let list = store.list(false).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].title, "Hello there");
+        assert_eq!(store.get(&list[0].id).unwrap().unwrap().messages.len(), 3);
+        assert!(!dir.0.join(LEGACY_JSON).exists());
+        assert!(dir.0.join("chat_history.json.bak").exists());
+        drop(store);
+
+        let store = ChatStore::open(&dir.0).unwrap();
+        assert_eq!(store.list(true).unwrap().len(), 1);
+        drop(store);
+
+        // A history that turns up after the schema is current is imported too.
+        std::fs::write(dir.0.join(LEGACY_JSON), serde_json::to_string(&legacy[1..]).unwrap()).unwrap();
+        let store = ChatStore::open(&dir.0).unwrap();
+        assert_eq!(store.list(true).unwrap().len(), 2);
+        assert!(!dir.0.join(LEGACY_JSON).exists());
+    }
+
+    #[test]
+    fn a_failed_migration_step_is_rolled_back_and_retried() {
+        let dir = TempDir::new("chat-store");
+        // A v3 store whose v4 step fails on its fourth statement.
+        let conn = Connection::open(dir.0.join(DB_FILE)).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE conversations (id TEXT PRIMARY KEY, title TEXT NOT NULL, title_auto INTEGER NOT NULL,
+                created_at INTEGER NOT NULL, updated_at INTEGER NOT NULL, model TEXT NOT NULL,
+                sampling TEXT NOT NULL, archived INTEGER NOT NULL, active_root TEXT);
+            CREATE TABLE messages (id INTEGER PRIMARY KEY, conversation_id TEXT NOT NULL, position INTEGER NOT NULL,
+                role TEXT NOT NULL, content TEXT NOT NULL, stats TEXT, reasoning TEXT NOT NULL DEFAULT '');
+            CREATE VIRTUAL TABLE messages_fts USING fts5(content, conversation_id UNINDEXED, role UNINDEXED);
+            PRAGMA user_version = 3;",
+        )
+        .unwrap();
+        let columns = |conn: &Connection| -> Vec<String> {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('messages')").unwrap();
+            stmt.query_map([], |r| r.get(0)).unwrap().map(Result::unwrap).collect()
+        };
+
+        let err = ChatStore::open(&dir.0).err().unwrap();
+        assert!(err.contains("v4"), "{err}");
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
+        assert_eq!(version, 3);
+        assert!(!columns(&conn).contains(&"uid".to_string()), "the columns added before the failure are gone");
+
+        conn.execute_batch("ALTER TABLE conversations DROP COLUMN active_root").unwrap();
+        let mut store = ChatStore::open(&dir.0).unwrap();
+        let id = store.create("m", &ChatSampling::default(), 1).unwrap().summary.id;
+        store.save_messages(&id, &[msg("user", "after the retry")], 2).unwrap();
+        assert_eq!(store.search("retry", 10).unwrap().len(), 1);
+        assert!(columns(&conn).contains(&"sources".to_string()));
+    }
+}
@@ -1,13 +1,23 @@
 //! Agent commands. `agent_start` kicks off a run on a background task and returns
 //! its id immediately; all progress streams over `agent://event`. `agent_approve`
-//! / `agent_cancel` drive the L4 control surface from the UI.
+//! / `agent_cancel` drive the L4 control surface from the UI; approvals given
+//! "for this session" live in [`AppState`] until `agent_forget_approvals` or a
+//! restart. The task can be a
+//! prompt from the library, rendered when the run starts, and the run's budget
+//! is checked against the ceilings in settings before it does. Finished and
+//! interrupted runs are read back from the run store, and resumed from their
+//! last completed step with `agent_resume`.
 
-use shared::ipc::{AgentRequest, ApprovalDecision};
+use std::sync::Arc;
+
+use shared::ipc::{AgentRequest, AgentRun, AgentRunSummary, ApprovalDecision};
 use tauri::{AppHandle, Manager, State};
 
-use crate::agent::{self, AgentContext};
+use crate::agent::approval::Policy;
+use crate::agent::runs::RunStore;
+use crate::agent::{self, AgentContext, budget};
 use crate::state::{AgentHandle, AppState};
-use crate::{config_io, util};
+use crate::{config_io, prompts, util};
 
 #[tauri::command]
 pub async fn agent_start(
@@ -15,25 +25,31 @@ pub async fn agent_start(
     state: State<'_, AppState>,
     req: AgentRequest,
 ) -> Result<String, String> {
+    let task = match &req.prompt {
+        Some(prompt) => {
+            let text = prompts::render(&config_io::config_dir(), prompt)?;
+            if req.task.trim().is_empty() {
+                text
+            } else {
+                format!("{text}\n\n{}", req.task)
+            }
+        }
+        None => req.task,
+    };
+    let cfg = state.config.lock().unwrap().clone();
+    let limits = budget::resolve(&req.budget.unwrap_or_default(), &cfg)?;
     let agent_id = util::new_id("agent");
     let handle = AgentHandle::new(agent_id.clone());
     state.register_agent(handle.clone());
 
-    let cfg = state.config.lock().unwrap().clone();
-    let ctx = AgentContext {
-        app: app.clone(),
-        model: req.model.clone(),
-        host: req.host.clone(),
-        port: req.port,
-        searxng_url: (!cfg.searxng_url.is_empty()).then_some(cfg.searxng_url),
-        config_dir: config_io::config_dir(),
-    };
+    let ctx = AgentContext::new(Arc::new(app.clone()), &cfg, req.host.clone(), req.port, req.model.clone(), limits, &agent_id)
+        .with_policy(Policy::new(&cfg, state.approval_grants.clone(), false));
 
     tracing::info!(agent = %agent_id, model = %req.model, "agent_start");
     let id = agent_id.clone();
     // Detach the run; the UI is driven by events, not this command's return.
     tokio::spawn(async move {
-        agent::run_agent(ctx, handle, None, "task".into(), req.task, 0).await;
+        agent::run_agent(ctx, handle, None, "task".into(), task, 0, None).await;
         app.state::<AppState>().remove_agent(&id);
     });
 
@@ -45,13 +61,21 @@ pub fn agent_approve(state: State<'_, AppState>, decision: ApprovalDecision) ->
     let handle = state
         .get_agent(&decision.agent_id)
         .ok_or("unknown agent")?;
-    if handle.resolve_approval(&decision.call_id, decision.approved) {
+    if handle.resolve_approval(&decision.call_id, decision.approved, decision.remember) {
         Ok(())
     } else {
         Err("no pending approval for that call".into())
     }
 }
 
+/// Drop the tools approved "for this session"; later calls are asked about
+/// again.
+#[tauri::command]
+pub fn agent_forget_approvals(state: State<'_, AppState>) {
+    state.approval_grants.lock().unwrap().clear();
+    tracing::info!("agent_forget_approvals");
+}
+
 #[tauri::command]
 pub fn agent_cancel(state: State<'_, AppState>, agent_id: String) -> Result<(), String> {
     let handle = state.get_agent(&agent_id).ok_or("unknown agent")?;
@@ -64,3 +88,56 @@ pub fn agent_cancel(state: State<'_, AppState>, agent_id: String) -> Result<(),
 pub fn agent_status(state: State<'_, AppState>) -> Vec<String> {
     state.agents.lock().unwrap().keys().cloned().collect()
 }
+
+/// Recorded runs, newest first.
+#[tauri::command]
+pub fn agent_runs_list() -> Result<Vec<AgentRunSummary>, String> {
+    RunStore::open(&config_io::config_dir())?.list()
+}
+
+/// A recorded run with its events, to look at or replay.
+#[tauri::command]
+pub fn agent_run_get(id: String) -> Result<AgentRun, String> {
+    RunStore::open(&config_io::config_dir())?
+        .get(&id)?
+        .ok_or_else(|| format!("Run {id} not found"))
+}
+
+#[tauri::command]
+pub fn agent_run_delete(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    if state.get_agent(&id).is_some() {
+        return Err("The run is still going; cancel it first".into());
+    }
+    RunStore::open(&config_io::config_dir())?.delete(&id)
+}
+
+/// Carry on with an interrupted, failed or cancelled run from its last
+/// completed step, under the same id and with the limits it started with.
+#[tauri::command]
+pub async fn agent_resume(app: AppHandle, state: State<'_, AppState>, id: String) -> Result<String, String> {
+    if state.get_agent(&id).is_some() {
+        return Err("The run is still going".into());
+    }
+    let run = RunStore::open(&config_io::config_dir())?
+        .load(&id)?
+        .ok_or_else(|| format!("Run {id} not found"))?;
+    if !run.summary.status.resumable() {
+        return Err(format!("A {:?} run can't be resumed", run.summary.status).to_lowercase());
+    }
+    let cfg = state.config.lock().unwrap().clone();
+    let ctx = AgentContext::new(Arc::new(app.clone()), &cfg, run.host, run.port, run.summary.model, run.limits, &id)
+        .with_policy(Policy::new(&cfg, state.approval_grants.clone(), false));
+    ctx.usage.add_tokens(run.summary.tokens);
+    let handle = AgentHandle::new(id.clone());
+    state.register_agent(handle.clone());
+
+    // Without a checkpoint there is nothing to keep; start over.
+    let resume = (!run.checkpoint.messages.is_empty()).then_some(run.checkpoint);
+    tracing::info!(agent = %id, from_step = resume.as_ref().map_or(0, |c| c.steps), "agent_resume");
+    let agent_id = id.clone();
+    tokio::spawn(async move {
+        agent::run_agent(ctx, handle, None, run.role, run.summary.task, 0, resume).await;
+        app.state::<AppState>().remove_agent(&agent_id);
+    });
+    Ok(id)
+}
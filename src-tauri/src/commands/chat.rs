@@ -3,14 +3,22 @@
 //! `chat_list_models` ports the legacy `/v1/models` poll. `chat_send` is the
 //! streaming upgrade over the legacy single-shot request: it requests
 //! `"stream": true` and forwards each token to the UI as a `chat://event`
-//! [`ChatEvent::Token`], finishing with `Done` (or `Error`). Streaming is the
-//! decisive pattern the whole app reuses (see [[migration-conventions]]).
+//! [`ChatEvent::Token`], finishing with `Done` (or `Error`, or `Cancelled` after
+//! `chat_cancel`). Streaming is the decisive pattern the whole app reuses (see
+//! [[migration-conventions]]). When the conversation has documents attached,
+//! the question is grounded in them first and the passages used go out as
+//! `Sources`.
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures_util::StreamExt;
-use shared::ipc::{CHAT_EVENT, ChatEvent, ChatRequest, ModelList};
-use tauri::{AppHandle, Emitter};
+use shared::ipc::{CHAT_EVENT, ChatEvent, ChatRequest, GenerationStats, ModelList, ResponseFormat};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
+
+use crate::chat::reasoning::{Split, ThinkSplitter};
+use crate::rag;
+use crate::state::AppState;
 
 /// Query the OpenAI-compatible `/v1/models` endpoint. Returns `online: false`
 /// (rather than erroring) when the server is unreachable, so the UI can render a
@@ -68,25 +76,58 @@ pub async fn chat_list_models(host: String, port: u16) -> ModelList {
 
 /// Start a streaming chat completion. Returns immediately; tokens arrive on
 /// `chat://event`. We spawn the streaming task so the `invoke` promise resolves
-/// right away and the UI is driven entirely by events.
+/// right away and the UI is driven entirely by events. The stream's
+/// cancellation token is registered under `stream_id` for `chat_cancel`.
 #[tauri::command]
-pub async fn chat_send(app: AppHandle, req: ChatRequest) -> Result<(), String> {
+pub async fn chat_send(app: AppHandle, state: State<'_, AppState>, req: ChatRequest) -> Result<(), String> {
     tracing::debug!(stream_id = %req.stream_id, model = %req.model, "chat_send");
+    let cancel = state.register_chat_stream(&req.stream_id);
+    let cfg = state.config.lock().unwrap().clone();
     tokio::spawn(async move {
-        if let Err(e) = stream_completion(&app, &req).await {
-            tracing::warn!(%e, "chat stream failed");
-            let _ = app.emit(
-                CHAT_EVENT,
-                ChatEvent::Error {
-                    stream_id: req.stream_id.clone(),
-                    message: e,
-                },
-            );
+        let emit = |ev: ChatEvent| {
+            let _ = app.emit(CHAT_EVENT, ev);
+        };
+        // The grounded copy goes to the model; reflection sees the question
+        // as the user asked it.
+        let mut grounded = req.clone();
+        match rag::ground(&mut grounded, &cfg, &config_dir()).await {
+            Ok(sources) if !sources.is_empty() => emit(ChatEvent::Sources {
+                stream_id: req.stream_id.clone(),
+                sources,
+            }),
+            Ok(_) => {}
+            Err(e) => tracing::warn!(%e, "document retrieval failed; answering without it"),
         }
+        match stream_completion(&grounded, &cancel, emit).await {
+            Ok(Some(answer)) => spawn_reflection(&req, answer),
+            Ok(None) => tracing::info!(stream_id = %req.stream_id, "chat stream cancelled"),
+            Err(e) => {
+                tracing::warn!(%e, "chat stream failed");
+                let _ = app.emit(
+                    CHAT_EVENT,
+                    ChatEvent::Error {
+                        stream_id: req.stream_id.clone(),
+                        message: e,
+                    },
+                );
+            }
+        }
+        app.state::<AppState>().remove_chat_stream(&req.stream_id);
     });
     Ok(())
 }
 
+/// Stop an in-flight `chat_send`. The stream task drops the HTTP response,
+/// which makes llama-server stop generating, and emits `Cancelled`.
+#[tauri::command]
+pub fn chat_cancel(state: State<'_, AppState>, stream_id: String) -> Result<(), String> {
+    if !state.cancel_chat_stream(&stream_id) {
+        return Err("unknown stream".into());
+    }
+    tracing::info!(stream_id = %stream_id, "chat_cancel");
+    Ok(())
+}
+
 async fn reflect_chat(host: String, port: u16, model: String, user_msg: String, assistant_msg: String) {
     let url = format!("http://{}:{}/v1/chat/completions", host, port);
     let system_prompt = "You are an agent memory consolidation system. Review the user's message, and the assistant's response. Extract a single concise lesson, fact, rule, or user preference that should be stored in memory to help future runs of the agent. Respond with ONLY that single key takeaway (max 100 characters, no prefix like 'Lesson:', no quotes, no conversational filler).";
@@ -118,7 +159,7 @@ async fn reflect_chat(host: String, port: u16, model: String, user_msg: String,
                             let lesson = content.trim().trim_matches('"').trim().to_string();
                             if !lesson.is_empty() {
                                 let memory = crate::agent::memory::MemoryManager::new(&config_dir());
-                                memory.add_lesson(&lesson);
+                                memory.add_lesson(&lesson, "chat", chrono::Utc::now().timestamp());
                             }
                         }
                     }
@@ -131,22 +172,116 @@ async fn reflect_chat(host: String, port: u16, model: String, user_msg: String,
     }
 }
 
-async fn stream_completion(app: &AppHandle, req: &ChatRequest) -> Result<(), String> {
-    let url = format!("http://{}:{}/v1/chat/completions", req.host, req.port);
-    let payload = serde_json::json!({
+/// Distil a lesson from the finished exchange in the background.
+fn spawn_reflection(req: &ChatRequest, answer: String) {
+    if answer.trim().is_empty() {
+        return;
+    }
+    let Some(last_user) = req.messages.iter().rev().find(|m| m.role == "user") else {
+        return;
+    };
+    let user_content = last_user.content.clone();
+    let host = req.host.clone();
+    let port = req.port;
+    let model = req.model.clone();
+    tokio::spawn(async move {
+        reflect_chat(host, port, model, user_content, answer).await;
+    });
+}
+
+/// The `/v1/chat/completions` body for `req`. Only sampling fields that are set
+/// are sent, so anything left unset keeps the server's launch value.
+fn completion_payload(req: &ChatRequest) -> serde_json::Value {
+    let messages: Vec<_> = req
+        .messages
+        .iter()
+        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+        .collect();
+    let mut payload = serde_json::json!({
         "model": req.model,
-        "messages": req.messages,
-        "temperature": req.temperature,
+        "messages": messages,
         "stream": true,
+        "stream_options": { "include_usage": true },
     });
+    let Some(s) = &req.sampling else {
+        return payload;
+    };
+    let body = payload.as_object_mut().unwrap();
+    let mut set = |key: &str, value: serde_json::Value| {
+        if !value.is_null() {
+            body.insert(key.to_string(), value);
+        }
+    };
+    set("temperature", serde_json::json!(s.temperature));
+    set("top_k", serde_json::json!(s.top_k));
+    set("top_p", serde_json::json!(s.top_p));
+    set("min_p", serde_json::json!(s.min_p));
+    set("repeat_penalty", serde_json::json!(s.repeat_penalty));
+    set("presence_penalty", serde_json::json!(s.presence_penalty));
+    set("frequency_penalty", serde_json::json!(s.frequency_penalty));
+    set("seed", serde_json::json!(s.seed));
+    set("max_tokens", serde_json::json!(s.n_predict));
+    if !s.stop.is_empty() {
+        set("stop", serde_json::json!(s.stop));
+    }
+    if !s.logit_bias.is_empty() {
+        // llama-server takes `[token, bias]` pairs, where the token is an id or a
+        // piece of text to tokenize.
+        let pairs: Vec<_> = s
+            .logit_bias
+            .iter()
+            .map(|(token, bias)| match token.trim().parse::<i64>() {
+                Ok(id) => serde_json::json!([id, bias]),
+                Err(_) => serde_json::json!([token, bias]),
+            })
+            .collect();
+        set("logit_bias", serde_json::json!(pairs));
+    }
+    // The server rejects a grammar alongside a JSON response format.
+    match &s.response_format {
+        Some(ResponseFormat::JsonObject) => set("response_format", serde_json::json!({ "type": "json_object" })),
+        Some(ResponseFormat::JsonSchema { schema }) => set(
+            "response_format",
+            serde_json::json!({ "type": "json_schema", "json_schema": { "schema": schema } }),
+        ),
+        None => {
+            if let Some(grammar) = s.grammar.as_ref().filter(|g| !g.trim().is_empty()) {
+                set("grammar", serde_json::json!(grammar));
+            }
+        }
+    }
+    payload
+}
+
+/// Stream one completion, forwarding tokens through `emit` and finishing with
+/// `Done` and the generation stats. Reasoning goes out as `Reasoning` events
+/// and is not part of the answer. Returns the full answer, or `None` after
+/// emitting `Cancelled` when `cancel` fires first. Errors are left to the
+/// caller to report.
+async fn stream_completion(
+    req: &ChatRequest,
+    cancel: &CancellationToken,
+    emit: impl Fn(ChatEvent),
+) -> Result<Option<String>, String> {
+    let url = format!("http://{}:{}/v1/chat/completions", req.host, req.port);
+    let payload = completion_payload(req);
+    let cancelled = |partial: String| {
+        emit(ChatEvent::Cancelled {
+            stream_id: req.stream_id.clone(),
+            partial,
+        });
+        Ok(None)
+    };
 
+    let started = Instant::now();
+    let mut stats = GenerationStats { model: req.model.clone(), ..Default::default() };
     let client = reqwest::Client::new();
-    let res = client
-        .post(&url)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| format!("Error contacting model server: {e}"))?;
+    let res = tokio::select! {
+        _ = cancel.cancelled() => return cancelled(String::new()),
+        res = client.post(&url).json(&payload).send() => {
+            res.map_err(|e| format!("Error contacting model server: {e}"))?
+        }
+    };
 
     if !res.status().is_success() {
         return Err(format!("Model server returned status {}", res.status()));
@@ -157,77 +292,76 @@ async fn stream_completion(app: &AppHandle, req: &ChatRequest) -> Result<(), Str
     let mut stream = res.bytes_stream();
     let mut buf = String::new();
     let mut assistant_response = String::new();
-    while let Some(chunk) = stream.next().await {
+    let mut think = ThinkSplitter::default();
+    let forward = |(reasoning, content): Split, answer: &mut String| {
+        if !reasoning.is_empty() {
+            emit(ChatEvent::Reasoning {
+                stream_id: req.stream_id.clone(),
+                delta: reasoning,
+            });
+        }
+        if !content.is_empty() {
+            answer.push_str(&content);
+            emit(ChatEvent::Token {
+                stream_id: req.stream_id.clone(),
+                delta: content,
+            });
+        }
+    };
+    loop {
+        let next = tokio::select! {
+            _ = cancel.cancelled() => return cancelled(assistant_response),
+            next = stream.next() => next,
+        };
+        let Some(chunk) = next else { break };
         let chunk = chunk.map_err(|e| format!("stream error: {e}"))?;
         buf.push_str(&String::from_utf8_lossy(&chunk));
         while let Some(nl) = buf.find('\n') {
+            // One chunk can carry many tokens; stop between them too.
+            if cancel.is_cancelled() {
+                return cancelled(assistant_response);
+            }
             let line: String = buf.drain(..=nl).collect();
             match parse_sse_line(line.trim()) {
-                SseLine::Token(delta) if !delta.is_empty() => {
-                    assistant_response.push_str(&delta);
-                    let _ = app.emit(
-                        CHAT_EVENT,
-                        ChatEvent::Token {
-                            stream_id: req.stream_id.clone(),
-                            delta,
-                        },
-                    );
+                SseLine::Token(delta) | SseLine::Reasoning(delta) if delta.is_empty() => {}
+                SseLine::Token(delta) => {
+                    stats.ttft_ms.get_or_insert_with(|| started.elapsed().as_secs_f64() * 1000.0);
+                    forward(think.push(&delta), &mut assistant_response);
+                }
+                SseLine::Reasoning(delta) => {
+                    stats.ttft_ms.get_or_insert_with(|| started.elapsed().as_secs_f64() * 1000.0);
+                    forward((delta, String::new()), &mut assistant_response);
                 }
+                SseLine::Stats(reported) => merge_stats(&mut stats, reported),
                 SseLine::Done => {
-                    let _ = app.emit(
-                        CHAT_EVENT,
-                        ChatEvent::Done {
-                            stream_id: req.stream_id.clone(),
-                        },
-                    );
-                    
-                    if !assistant_response.trim().is_empty() {
-                        if let Some(last_user) = req.messages.iter().rev().find(|m| m.role == "user") {
-                            let user_content = last_user.content.clone();
-                            let assistant_content = assistant_response.clone();
-                            let host = req.host.clone();
-                            let port = req.port;
-                            let model = req.model.clone();
-                            tokio::spawn(async move {
-                                reflect_chat(host, port, model, user_content, assistant_content).await;
-                            });
-                        }
-                    }
-                    
-                    return Ok(());
+                    forward(think.finish(), &mut assistant_response);
+                    emit(ChatEvent::Done {
+                        stream_id: req.stream_id.clone(),
+                        stats: Some(stats),
+                    });
+                    return Ok(Some(assistant_response));
                 }
                 _ => {}
             }
         }
     }
     // Stream ended without an explicit [DONE].
-    let _ = app.emit(
-        CHAT_EVENT,
-        ChatEvent::Done {
-            stream_id: req.stream_id.clone(),
-        },
-    );
-    
-    if !assistant_response.trim().is_empty() {
-        if let Some(last_user) = req.messages.iter().rev().find(|m| m.role == "user") {
-            let user_content = last_user.content.clone();
-            let assistant_content = assistant_response.clone();
-            let host = req.host.clone();
-            let port = req.port;
-            let model = req.model.clone();
-            tokio::spawn(async move {
-                reflect_chat(host, port, model, user_content, assistant_content).await;
-            });
-        }
-    }
-    
-    Ok(())
+    forward(think.finish(), &mut assistant_response);
+    emit(ChatEvent::Done {
+        stream_id: req.stream_id.clone(),
+        stats: Some(stats),
+    });
+    Ok(Some(assistant_response))
 }
 
 /// Outcome of parsing a single SSE line from an OpenAI-compatible stream.
 #[derive(Debug, PartialEq)]
 enum SseLine {
     Token(String),
+    /// A `reasoning_content` delta.
+    Reasoning(String),
+    /// A chunk without text that carries `timings` and/or `usage`.
+    Stats(GenerationStats),
     Done,
     Ignore,
 }
@@ -244,35 +378,199 @@ fn parse_sse_line(line: &str) -> SseLine {
     }
     match serde_json::from_str::<serde_json::Value>(data) {
         Ok(v) => {
-            let delta = v["choices"][0]["delta"]["content"]
-                .as_str()
-                .unwrap_or("")
-                .to_string();
+            let delta = &v["choices"][0]["delta"];
+            if let Some(reasoning) = delta["reasoning_content"].as_str().filter(|r| !r.is_empty()) {
+                return SseLine::Reasoning(reasoning.to_string());
+            }
+            let delta = delta["content"].as_str().unwrap_or("").to_string();
+            if delta.is_empty() && (v["timings"].is_object() || v["usage"].is_object()) {
+                return SseLine::Stats(stats_from_chunk(&v));
+            }
             SseLine::Token(delta)
         }
         Err(_) => SseLine::Ignore,
     }
 }
 
+/// llama-server reports `timings` on the last chunk and, with
+/// `include_usage`, OpenAI-style `usage`; `timings` wins where both exist.
+fn stats_from_chunk(v: &serde_json::Value) -> GenerationStats {
+    let (t, u) = (&v["timings"], &v["usage"]);
+    let count = |x: &serde_json::Value| x.as_u64().map(|n| n as u32);
+    GenerationStats {
+        prompt_tokens: count(&t["prompt_n"]).or_else(|| count(&u["prompt_tokens"])),
+        prompt_per_second: t["prompt_per_second"].as_f64(),
+        completion_tokens: count(&t["predicted_n"]).or_else(|| count(&u["completion_tokens"])),
+        predicted_per_second: t["predicted_per_second"].as_f64(),
+        ..Default::default()
+    }
+}
+
+fn merge_stats(into: &mut GenerationStats, reported: GenerationStats) {
+    into.prompt_tokens = into.prompt_tokens.or(reported.prompt_tokens);
+    into.prompt_per_second = into.prompt_per_second.or(reported.prompt_per_second);
+    into.completion_tokens = into.completion_tokens.or(reported.completion_tokens);
+    into.predicted_per_second = into.predicted_per_second.or(reported.predicted_per_second);
+}
+
+use crate::chat::formats;
+use crate::chat::store::ChatStore;
 use crate::config_io::config_dir;
-use shared::ipc::ChatMessage;
+use crate::rag::embed::Embedder;
+use crate::rag::index::RagIndex;
+use shared::ipc::{
+    ChatAttachment, ChatExportFormat, ChatMessage, ChatSampling, ChatSearchHit, Conversation, ConversationPatch, ConversationSummary,
+};
+
+// ── Conversations ────────────────────────────────────────────────────────────
+
+/// Most search hits `chat_search` returns.
+const SEARCH_LIMIT: u32 = 50;
+
+fn open_store() -> Result<ChatStore, String> {
+    ChatStore::open(&config_dir())
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
 
 #[tauri::command]
-pub fn chat_save_history(history: Vec<ChatMessage>) -> Result<(), String> {
-    let path = config_dir().join("chat_history.json");
-    let json = serde_json::to_string_pretty(&history).map_err(|e| e.to_string())?;
-    std::fs::write(path, json).map_err(|e| e.to_string())
+pub fn chat_list_conversations(include_archived: bool) -> Result<Vec<ConversationSummary>, String> {
+    open_store()?.list(include_archived)
 }
 
 #[tauri::command]
-pub fn chat_load_history() -> Result<Vec<ChatMessage>, String> {
-    let path = config_dir().join("chat_history.json");
-    if !path.exists() {
-        return Ok(vec![]);
+pub fn chat_create_conversation(model: String, sampling: ChatSampling) -> Result<Conversation, String> {
+    open_store()?.create(&model, &sampling, now())
+}
+
+#[tauri::command]
+pub fn chat_get_conversation(id: String) -> Result<Conversation, String> {
+    open_store()?
+        .get(&id)?
+        .ok_or_else(|| format!("Conversation {id} not found"))
+}
+
+/// Save the active branch; called after every finished turn.
+#[tauri::command]
+pub fn chat_save_messages(id: String, messages: Vec<ChatMessage>) -> Result<ConversationSummary, String> {
+    open_store()?.save_messages(&id, &messages, now())
+}
+
+/// Start an alternative to an assistant reply. Returns the new active branch,
+/// ending in an empty reply for the UI to stream into.
+#[tauri::command]
+pub fn chat_regenerate(id: String, message_id: String) -> Result<Conversation, String> {
+    open_store()?.regenerate(&id, &message_id, now())
+}
+
+/// Branch off an edited copy of a message.
+#[tauri::command]
+pub fn chat_edit_message(id: String, message_id: String, content: String) -> Result<Conversation, String> {
+    open_store()?.edit(&id, &message_id, &content, now())
+}
+
+/// Write conversations (all of them when `ids` is empty) to `path`, each as
+/// its active branch. Returns how many were written.
+#[tauri::command]
+pub fn chat_export(ids: Vec<String>, format: ChatExportFormat, path: String) -> Result<u32, String> {
+    let store = open_store()?;
+    let ids = if ids.is_empty() {
+        store.list(true)?.into_iter().map(|c| c.id).collect()
+    } else {
+        ids
+    };
+    let mut convs = Vec::new();
+    for id in &ids {
+        convs.push(store.get(id)?.ok_or_else(|| format!("Conversation {id} not found"))?);
+    }
+    let text = match format {
+        ChatExportFormat::Markdown => formats::to_markdown(&convs),
+        ChatExportFormat::Sharegpt => formats::to_sharegpt(&convs),
+        ChatExportFormat::OpenaiJsonl => formats::to_openai_jsonl(&convs),
+    };
+    std::fs::write(&path, text).map_err(|e| format!("{path}: {e}"))?;
+    tracing::info!(count = convs.len(), %path, "exported conversations");
+    Ok(convs.len() as u32)
+}
+
+/// Import a ShareGPT `.json` or OpenAI `.jsonl` file as new conversations.
+#[tauri::command]
+pub fn chat_import(path: String) -> Result<Vec<ConversationSummary>, String> {
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+    let chats = if path.to_lowercase().ends_with(".jsonl") {
+        formats::from_openai_jsonl(&text)?
+    } else {
+        formats::from_sharegpt(&text)?
+    };
+    let mut store = open_store()?;
+    let now = now();
+    let mut imported = Vec::new();
+    for chat in chats.into_iter().filter(|c| !c.messages.is_empty()) {
+        let id = store.create("", &ChatSampling::default(), now)?.summary.id;
+        let mut summary = store.save_messages(&id, &chat.messages, now)?;
+        if let Some(title) = chat.title.filter(|t| !t.trim().is_empty()) {
+            let patch = ConversationPatch { title: Some(title), ..Default::default() };
+            summary = store.update(&id, &patch, now)?;
+        }
+        imported.push(summary);
     }
-    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let history: Vec<ChatMessage> = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-    Ok(history)
+    tracing::info!(count = imported.len(), %path, "imported conversations");
+    Ok(imported)
+}
+
+#[tauri::command]
+pub fn chat_switch_branch(id: String, message_id: String) -> Result<Conversation, String> {
+    open_store()?.switch_branch(&id, &message_id)
+}
+
+/// Rename, archive/unarchive, or change the model or sampling settings.
+#[tauri::command]
+pub fn chat_update_conversation(id: String, patch: ConversationPatch) -> Result<ConversationSummary, String> {
+    open_store()?.update(&id, &patch, now())
+}
+
+#[tauri::command]
+pub fn chat_delete_conversation(id: String) -> Result<(), String> {
+    open_store()?.delete(&id)?;
+    RagIndex::open(&config_dir())?.remove_conversation(&id)
+}
+
+#[tauri::command]
+pub fn chat_search(query: String) -> Result<Vec<ChatSearchHit>, String> {
+    open_store()?.search(&query, SEARCH_LIMIT)
+}
+
+// ── Attached documents ───────────────────────────────────────────────────────
+
+/// Index a file, or the documents in a folder, for a conversation and return
+/// everything now attached to it. `host`/`port` are the chat's server, used
+/// for embeddings unless another server is configured.
+#[tauri::command]
+pub async fn chat_attach(
+    state: State<'_, AppState>,
+    conversation_id: String,
+    path: String,
+    host: String,
+    port: u16,
+) -> Result<Vec<ChatAttachment>, String> {
+    let cfg = state.config.lock().unwrap().clone();
+    let embedder = Embedder::new(&rag::embedding_url(&cfg, &host, port), &cfg.rag_embedding_model);
+    rag::attach(&config_dir(), &embedder, &conversation_id, std::path::Path::new(&path), now()).await?;
+    RagIndex::open(&config_dir())?.list(&conversation_id)
+}
+
+#[tauri::command]
+pub fn chat_list_attachments(conversation_id: String) -> Result<Vec<ChatAttachment>, String> {
+    RagIndex::open(&config_dir())?.list(&conversation_id)
+}
+
+#[tauri::command]
+pub fn chat_detach(conversation_id: String, attachment_id: String) -> Result<Vec<ChatAttachment>, String> {
+    let mut index = RagIndex::open(&config_dir())?;
+    index.remove(&attachment_id)?;
+    index.list(&conversation_id)
 }
 
 #[cfg(test)]
@@ -290,10 +588,111 @@ mod tests {
         assert_eq!(parse_sse_line("data: [DONE]"), SseLine::Done);
     }
 
+    #[test]
+    fn parses_reasoning_deltas() {
+        let line = r#"data: {"choices":[{"delta":{"reasoning_content":"Hmm","content":null}}]}"#;
+        assert_eq!(parse_sse_line(line), SseLine::Reasoning("Hmm".into()));
+    }
+
     #[test]
     fn ignores_non_data_and_empty_delta() {
         assert_eq!(parse_sse_line(": keep-alive"), SseLine::Ignore);
         let role = r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#;
         assert_eq!(parse_sse_line(role), SseLine::Token(String::new()));
     }
+
+    #[test]
+    fn final_chunks_carry_generation_stats() {
+        let last = r#"data: {"choices":[{"delta":{},"finish_reason":"stop"}],"timings":{"prompt_n":12,"prompt_per_second":480.5,"predicted_n":40,"predicted_per_second":31.25}}"#;
+        let usage = r#"data: {"choices":[],"usage":{"prompt_tokens":13,"completion_tokens":41,"total_tokens":54}}"#;
+        let mut stats = GenerationStats::default();
+        for line in [last, usage] {
+            match parse_sse_line(line) {
+                SseLine::Stats(s) => merge_stats(&mut stats, s),
+                other => panic!("expected stats, got {other:?}"),
+            }
+        }
+        assert_eq!((stats.prompt_tokens, stats.completion_tokens), (Some(12), Some(40)));
+        assert_eq!(stats.prompt_per_second, Some(480.5));
+        assert_eq!(stats.predicted_per_second, Some(31.25));
+    }
+
+    #[test]
+    fn payload_sends_only_the_sampling_fields_that_are_set() {
+        use shared::ipc::ChatSampling;
+
+        let mut req = ChatRequest {
+            stream_id: "s".into(),
+            host: "127.0.0.1".into(),
+            port: 8080,
+            model: "m".into(),
+            messages: vec![],
+            sampling: None,
+            conversation_id: None,
+        };
+        let bare = completion_payload(&req);
+        assert_eq!(bare.as_object().unwrap().len(), 4, "{bare}");
+
+        req.sampling = Some(ChatSampling {
+            temperature: Some(0.25),
+            top_k: Some(20),
+            seed: Some(7),
+            n_predict: Some(128),
+            stop: vec!["</s>".into()],
+            grammar: Some("root ::= \"x\"".into()),
+            response_format: Some(ResponseFormat::JsonSchema {
+                schema: serde_json::json!({ "type": "object" }),
+            }),
+            logit_bias: vec![("15043".into(), -100.0), ("Hello".into(), 2.0)],
+            ..Default::default()
+        });
+        let p = completion_payload(&req);
+        assert_eq!(p["temperature"], 0.25);
+        assert_eq!(p["top_k"], 20);
+        assert_eq!(p["seed"], 7);
+        assert_eq!(p["max_tokens"], 128);
+        assert_eq!(p["stop"], serde_json::json!(["</s>"]));
+        assert_eq!(p["response_format"]["json_schema"]["schema"]["type"], "object");
+        assert_eq!(p["logit_bias"], serde_json::json!([[15043, -100.0], ["Hello", 2.0]]));
+        assert!(p.get("top_p").is_none());
+        assert!(p.get("grammar").is_none(), "grammar is dropped for JSON output");
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_the_stream_with_the_partial_text() {
+        use crate::test_support::{StubResponse, StubServer};
+        use std::sync::Mutex;
+
+        let token = |t: &str| format!("data: {{\"choices\":[{{\"delta\":{{\"content\":\"{t}\"}}}}]}}\n\n");
+        let body = format!("{}{}{}data: [DONE]\n\n", token("Hel"), token("lo"), token("!"));
+        let server = StubServer::start(move |_| StubResponse::text(200, &body)).await;
+        let port = server.base_url.rsplit(':').next().unwrap().parse().unwrap();
+        let req = ChatRequest {
+            stream_id: "s1".into(),
+            host: "127.0.0.1".into(),
+            port,
+            model: "m".into(),
+            messages: vec![ChatMessage::new("user", "hi")],
+            sampling: None,
+            conversation_id: None,
+        };
+
+        let events = Mutex::new(Vec::new());
+        let cancel = CancellationToken::new();
+        let answer = stream_completion(&req, &cancel, |ev| {
+            if matches!(ev, ChatEvent::Token { .. }) {
+                cancel.cancel();
+            }
+            events.lock().unwrap().push(ev);
+        })
+        .await
+        .unwrap();
+        assert_eq!(answer, None);
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 2, "{events:?}");
+        assert!(matches!(&events[1], ChatEvent::Cancelled { partial, .. } if partial == "Hel"));
+
+        let answer = stream_completion(&req, &CancellationToken::new(), |_| {}).await.unwrap();
+        assert_eq!(answer.as_deref(), Some("Hello!"));
+    }
 }
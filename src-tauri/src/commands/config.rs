@@ -3,10 +3,10 @@
 //! `config://changed` so every window stays in sync (replacing the legacy 1 s
 //! disk-polling loop and its read/write race).
 
-use shared::{ServerConfig, ipc::CONFIG_CHANGED_EVENT};
+use shared::{ServerConfig, ipc::{ConfigProfile, CONFIG_CHANGED_EVENT}};
 use tauri::{AppHandle, Emitter, State};
 
-use crate::{config_io, state::AppState};
+use crate::{config_io, profiles, state::AppState};
 
 #[tauri::command]
 pub fn get_config(state: State<'_, AppState>) -> ServerConfig {
@@ -28,3 +28,31 @@ pub fn update_config(
     }
     Ok(())
 }
+
+// ── Profiles ─────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn profile_list() -> Vec<ConfigProfile> {
+    profiles::list()
+}
+
+/// Snapshot the current config under `name` (overwriting an existing profile).
+#[tauri::command]
+pub fn profile_save(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let config = state.config.lock().unwrap().clone();
+    profiles::save(&name, &config)
+}
+
+/// Make profile `name` the current config, persisting and broadcasting it like
+/// `update_config` does.
+#[tauri::command]
+pub fn profile_apply(app: AppHandle, state: State<'_, AppState>, name: String) -> Result<ServerConfig, String> {
+    let config = profiles::load(&name)?;
+    update_config(app, state, config.clone())?;
+    Ok(config)
+}
+
+#[tauri::command]
+pub fn profile_delete(name: String) -> Result<(), String> {
+    profiles::delete(&name)
+}
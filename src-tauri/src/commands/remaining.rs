@@ -1,65 +1,88 @@
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::Arc;
 use tauri::{AppHandle, Emitter, State, Manager};
 use tokio::process::Command;
+use shared::ServerConfig;
 use shared::ipc::{
-    ScannedModel, KanbanTask, PlannerState, MonitorState, AgentStatus, AgentActivityEvent,
-    CalendarEvent, CalendarState, LlamaInstance, DownloadStatus, BenchmarkResult, BenchmarkOutput,
+    ScannedModel, LibraryQuery, LibraryQueryResult, StorageReport, DeleteTicket, KanbanTask, PlannerState, MonitorState, AgentStatus, AgentActivityEvent,
+    CalendarEvent, CalendarState, LlamaInstance, DownloadStatus, DownloadEvent, DownloadState, DownloadItem, HfRepoFile, HfRepoListing, DOWNLOAD_EVENT, BenchmarkResult, BenchmarkOutput,
     ResearchStatus, ResearchReportInfo, OptimizationSuggestion, Memory, EventStatus,
 };
-use crate::state::AppState;
+use crate::state::{AppState, PendingDelete};
+use crate::download::{self, engine::FetchRequest};
+use shared::targets::parse_targets;
 use crate::config_io::config_dir;
 use crate::library;
+use crate::library::index_db::IndexDb;
 use crate::agent::AgentContext;
+use crate::agent::memory::MemoryManager;
 
 // ── Model Library ───────────────────────────────────────────────────────────
 
+fn open_index() -> Result<IndexDb, String> {
+    IndexDb::open(&config_dir())
+}
+
+/// The configured scan dirs, or the legacy fixed layout under
+/// `/mnt/modelsext/models` when none are configured.
+fn effective_scan_dirs(cfg: &ServerConfig) -> Vec<String> {
+    if !cfg.model_scan_dirs.is_empty() {
+        return cfg.model_scan_dirs.clone();
+    }
+    let base = "/mnt/modelsext/models";
+    let subdirs = [
+        "3D", "all", "asr", "audio", "coder", "dflash", "diffusion", "embedding",
+        "embodied", "image", "infographic", "mtp", "multilingual", "multimodal",
+        "ocr", "osworld", "privacy", "research", "reshoot", "text", "timeseries",
+        "tts", "ui", "uncensored", "video", "vision", "web", "world",
+    ];
+    subdirs
+        .iter()
+        .map(|sub| format!("{}/{}", base, sub))
+        .filter(|p| std::path::Path::new(p).exists())
+        .collect()
+}
+
 #[tauri::command]
-pub fn library_get_index() -> Vec<ScannedModel> {
-    let index_path = config_dir().join("model_index.json");
-    library::load_index(&index_path.to_string_lossy())
+pub fn library_get_index() -> Result<Vec<ScannedModel>, String> {
+    open_index()?.all()
+}
+
+#[tauri::command]
+pub fn library_query(query: LibraryQuery) -> Result<LibraryQueryResult, String> {
+    open_index()?.query(&query)
 }
 
 #[tauri::command]
 pub async fn library_scan(state: State<'_, AppState>) -> Result<Vec<ScannedModel>, String> {
-    let dirs = {
+    let (dirs, scan_stores) = {
         let cfg = state.config.lock().unwrap();
-        if cfg.model_scan_dirs.is_empty() {
-            let mut auto_dirs = Vec::new();
-            let base = "/mnt/modelsext/models";
-            let subdirs = [
-                "3D", "all", "asr", "audio", "coder", "dflash", "diffusion", "embedding",
-                "embodied", "image", "infographic", "mtp", "multilingual", "multimodal",
-                "ocr", "osworld", "privacy", "research", "reshoot", "text", "timeseries",
-                "tts", "ui", "uncensored", "video", "vision", "web", "world",
-            ];
-            for sub in &subdirs {
-                let p = format!("{}/{}", base, sub);
-                if std::path::Path::new(&p).exists() {
-                    auto_dirs.push(p);
-                }
-            }
-            auto_dirs
-        } else {
-            cfg.model_scan_dirs.clone()
-        }
+        (effective_scan_dirs(&cfg), cfg.scan_model_stores)
     };
 
-    if dirs.is_empty() {
-        return Ok(library_get_index());
+    if dirs.is_empty() && !scan_stores {
+        return library_get_index();
     }
 
-    let existing = library_get_index();
+    let existing = library_get_index()?;
     let updated = tokio::task::spawn_blocking(move || {
-        let scanned = library::scan_directories(&dirs);
+        let mut scanned = library::scan_directories(&dirs);
+        if scan_stores {
+            // A scan dir may already contain a store (e.g. the HF cache); keep
+            // the store-aware entry, which carries the repo id.
+            let store_models = library::stores::scan_stores(&library::stores::default_roots());
+            scanned.retain(|m| !store_models.iter().any(|s| s.path == m.path));
+            scanned.extend(store_models);
+        }
         library::merge_indexes(scanned, existing)
     })
     .await
     .map_err(|e| e.to_string())?;
 
-    let index_path = config_dir().join("model_index.json");
-    library::save_index(&index_path.to_string_lossy(), &updated)?;
-    Ok(updated)
+    let mut db = open_index()?;
+    db.replace_all(&updated)?;
+    db.all()
 }
 
 #[tauri::command]
@@ -67,98 +90,53 @@ pub async fn library_enrich_single(
     state: State<'_, AppState>,
     path: String,
 ) -> Result<Vec<ScannedModel>, String> {
-    let mut index = library_get_index();
-    let idx = index.iter().position(|m| m.path == path)
-        .ok_or_else(|| "Model not found in index".to_string())?;
-
-    let searx_url = state.config.lock().unwrap().searxng_url.clone();
-    let port = state.config.lock().unwrap().port;
-
-    index[idx].status = "enriching".to_string();
-    let index_path = config_dir().join("model_index.json");
-    library::save_index(&index_path.to_string_lossy(), &index)?;
-
-    let mut model = index[idx].clone();
-    let result = library::enrich_model(&mut model, &searx_url, port).await;
-
-    // Reload index to apply update
-    let mut index = library_get_index();
-    if let Some(m_idx) = index.iter().position(|m| m.path == path) {
-        match result {
-            Ok(_) => {
-                index[m_idx] = model;
-            }
-            Err(e) => {
-                model.status = "failed".to_string();
-                index[m_idx] = model;
-                return Err(format!("Enrichment failed: {}", e));
-            }
-        }
-        library::save_index(&index_path.to_string_lossy(), &index)?;
+    let mut db = open_index()?;
+    let mut model = db.get(&path)?.ok_or_else(|| "Model not found in index".to_string())?;
+
+    let chain = library::enrich::EnrichmentChain::from_config(&state.config.lock().unwrap(), &config_dir());
+    db.set_status(&path, "enriching")?;
+
+    // An explicit single-model enrich bypasses the cache.
+    let result = chain.enrich(&mut model, true).await;
+    if let Err(e) = result {
+        db.set_status(&path, "failed")?;
+        return Err(format!("Enrichment failed: {}", e));
     }
-    Ok(index)
+    db.upsert(&model)?;
+    db.all()
 }
 
 #[tauri::command]
 pub async fn library_enrich_all(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
-    let index = library_get_index();
-    let pending_paths: Vec<String> = index.iter()
-        .filter(|m| m.status == "pending_enrichment")
-        .map(|m| m.path.clone())
+    let pending_paths: Vec<String> = library_get_index()?
+        .into_iter()
+        .filter(|m| m.status == "pending_enrichment" || m.status == "not_found")
+        .map(|m| m.path)
         .collect();
 
     if pending_paths.is_empty() {
         return Ok(());
     }
 
-    let searx_url = state.config.lock().unwrap().searxng_url.clone();
-    let port = state.config.lock().unwrap().port;
-    let index_path = config_dir().join("model_index.json");
+    let chain = library::enrich::EnrichmentChain::from_config(&state.config.lock().unwrap(), &config_dir());
+    let mut db = open_index()?;
 
     tokio::spawn(async move {
         for path in pending_paths {
-            // Update to enriching
-            let mut index = library::load_index(&index_path.to_string_lossy());
-            if let Some(idx) = index.iter().position(|m| m.path == path) {
-                index[idx].status = "enriching".to_string();
-                let _ = library::save_index(&index_path.to_string_lossy(), &index);
-            }
-            let _ = app.emit("library://status", format!("Enriching {}...", path));
-
-            let mut model = ScannedModel {
-                path: path.clone(),
-                filename: std::path::Path::new(&path).file_name().unwrap_or_default().to_string_lossy().to_string(),
-                size_bytes: 0,
-                clean_name: "".into(),
-                use_case: "Unknown".into(),
-                hf_link: "".into(),
-                github_link: "".into(),
-                size_info: "".into(),
-                status: "enriching".into(),
-                family: "".into(),
-                version: "".into(),
-                tags: vec![],
+            // The model may have been removed by a rescan since we started.
+            let Ok(Some(mut model)) = db.get(&path) else {
+                continue;
             };
+            let _ = db.set_status(&path, "enriching");
+            let _ = app.emit("library://status", format!("Enriching {}...", path));
 
-            // Reload model data from index
-            let index = library::load_index(&index_path.to_string_lossy());
-            if let Some(m) = index.iter().find(|m| m.path == path) {
-                model = m.clone();
-            }
-
-            let result = library::enrich_model(&mut model, &searx_url, port).await;
-
-            let mut index = library::load_index(&index_path.to_string_lossy());
-            if let Some(idx) = index.iter().position(|m| m.path == path) {
-                match result {
-                    Ok(_) => {
-                        index[idx] = model;
-                    }
-                    Err(_) => {
-                        index[idx].status = "failed".to_string();
-                    }
+            match chain.enrich(&mut model, false).await {
+                Ok(()) => {
+                    let _ = db.upsert(&model);
+                }
+                Err(_) => {
+                    let _ = db.set_status(&path, "failed");
                 }
-                let _ = library::save_index(&index_path.to_string_lossy(), &index);
             }
         }
         let _ = app.emit("library://status", "Enrichment complete.");
@@ -167,6 +145,151 @@ pub async fn library_enrich_all(app: AppHandle, state: State<'_, AppState>) -> R
     Ok(())
 }
 
+// ── Model Storage ───────────────────────────────────────────────────────────
+
+/// How long a `library_delete_prepare` ticket stays valid.
+const DELETE_TICKET_TTL_SECS: i64 = 120;
+/// Default window for "unused" in the storage report.
+const DEFAULT_UNUSED_DAYS: u32 = 30;
+
+/// Refuse to move/delete files another tool owns, or the model the running
+/// server has open.
+fn ensure_mutable(model: &ScannedModel, action: &str, state: &AppState) -> Result<(), String> {
+    match model.source.as_str() {
+        library::stores::SOURCE_HUGGINGFACE | library::stores::SOURCE_OLLAMA => {
+            return Err(format!(
+                "{} is managed by {}; {} it with that tool instead.",
+                model.filename, model.source, action
+            ));
+        }
+        library::stores::SOURCE_LMSTUDIO if action == "move" => {
+            return Err(format!("{} is managed by LM Studio; move it from LM Studio instead.", model.filename));
+        }
+        _ => {}
+    }
+    let serving = state.server.lock().unwrap().is_some()
+        && state.config.lock().unwrap().model_path == model.path;
+    if serving {
+        return Err(format!("Stop the server before you {} the model it is serving.", action));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn library_storage_report(
+    state: State<'_, AppState>,
+    unused_days: Option<u32>,
+) -> Result<StorageReport, String> {
+    let (mut roots, active_model) = {
+        let cfg = state.config.lock().unwrap();
+        let mut roots: Vec<PathBuf> = effective_scan_dirs(&cfg).into_iter().map(PathBuf::from).collect();
+        if cfg.scan_model_stores {
+            let stores = library::stores::default_roots();
+            roots.extend(stores.huggingface.into_iter().chain(stores.ollama).chain(stores.lmstudio));
+        }
+        (roots, cfg.model_path.clone())
+    };
+    roots.dedup();
+
+    tokio::task::spawn_blocking(move || {
+        let db = open_index()?;
+        let models = db.all()?;
+        let launches = db.launches()?;
+        Ok(library::storage::storage_report(&library::storage::ReportInput {
+            models: &models,
+            roots: &roots,
+            launches: &launches,
+            active_model: &active_model,
+            unused_after_days: unused_days.unwrap_or(DEFAULT_UNUSED_DAYS),
+            now: chrono::Utc::now().timestamp(),
+        }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Move a model file into another configured scan dir, re-keying its index
+/// entry and pointing the current config and saved profiles at the new path.
+#[tauri::command]
+pub async fn library_move_model(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    target_dir: String,
+) -> Result<Vec<ScannedModel>, String> {
+    let scan_dirs = effective_scan_dirs(&state.config.lock().unwrap());
+    if !scan_dirs.contains(&target_dir) {
+        return Err("Target must be one of the configured scan directories.".into());
+    }
+    let mut db = open_index()?;
+    let model = db.get(&path)?.ok_or_else(|| "Model not found in index".to_string())?;
+    ensure_mutable(&model, "move", &state)?;
+
+    let src = PathBuf::from(&path);
+    let dest = tokio::task::spawn_blocking(move || library::storage::move_file(&src, std::path::Path::new(&target_dir)))
+        .await
+        .map_err(|e| e.to_string())??;
+    let new_path = dest.to_string_lossy().to_string();
+    db.rename_path(&path, &new_path, &model.filename)?;
+
+    let profiles = crate::profiles::rewrite_model_path_in_profiles(&path, &new_path)?;
+    tracing::info!(from = %path, to = %new_path, ?profiles, "library_move_model");
+
+    let mut cfg = state.config.lock().unwrap().clone();
+    if crate::profiles::rewrite_model_path(&mut cfg, &path, &new_path) {
+        crate::commands::config::update_config(app, state, cfg)?;
+    }
+    db.all()
+}
+
+/// First step of a guarded delete: validate and hand out a short-lived ticket.
+/// Nothing is removed until `library_delete_confirm` is called with it.
+#[tauri::command]
+pub fn library_delete_prepare(state: State<'_, AppState>, path: String) -> Result<DeleteTicket, String> {
+    let model = open_index()?.get(&path)?.ok_or_else(|| "Model not found in index".to_string())?;
+    ensure_mutable(&model, "delete", &state)?;
+
+    let token = crate::util::new_id("delete");
+    let expires_at = chrono::Utc::now().timestamp() + DELETE_TICKET_TTL_SECS;
+    state.pending_deletes.lock().unwrap().insert(
+        token.clone(),
+        PendingDelete { path: path.clone(), expires_at },
+    );
+    Ok(DeleteTicket { token, path, size_bytes: model.size_bytes, expires_at })
+}
+
+#[tauri::command]
+pub fn library_delete_confirm(state: State<'_, AppState>, token: String) -> Result<Vec<ScannedModel>, String> {
+    let pending = state
+        .pending_deletes
+        .lock()
+        .unwrap()
+        .remove(&token)
+        .ok_or_else(|| "Unknown or already used delete ticket.".to_string())?;
+    if chrono::Utc::now().timestamp() > pending.expires_at {
+        return Err("Delete ticket expired; request the delete again.".into());
+    }
+
+    let mut db = open_index()?;
+    // Re-check: the server may have started on this model since the prepare.
+    if let Some(model) = db.get(&pending.path)? {
+        ensure_mutable(&model, "delete", &state)?;
+    }
+    match std::fs::remove_file(&pending.path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to delete {}: {}", pending.path, e)),
+    }
+    db.remove(&pending.path)?;
+    tracing::warn!(path = %pending.path, "library model deleted");
+    db.all()
+}
+
+#[tauri::command]
+pub fn library_delete_cancel(state: State<'_, AppState>, token: String) {
+    state.pending_deletes.lock().unwrap().remove(&token);
+}
+
 // ── Planner / Kanban ─────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -354,7 +477,15 @@ pub fn spawn_calendar_scheduler(app: AppHandle) {
                     updated = true;
                     
                     let app_clone = app.clone();
-                    let prompt = event.prompt.clone();
+                    // Rendered now so `{{date}}` and friends are the firing
+                    // time's; a missing library prompt falls back to the text.
+                    let prompt = match &event.prompt_ref {
+                        Some(r) => crate::prompts::render(&config_dir(), r).unwrap_or_else(|e| {
+                            tracing::warn!(%e, event = %event.id, "calendar prompt unavailable; using its text");
+                            crate::prompts::render_builtins(&event.prompt)
+                        }),
+                        None => crate::prompts::render_builtins(&event.prompt),
+                    };
                     let event_id = event.id.clone();
                     let event_title = event.title.clone();
                     
@@ -378,19 +509,17 @@ pub fn spawn_calendar_scheduler(app: AppHandle) {
                             (cfg.host.clone(), cfg.port, m)
                         };
 
-                        let ctx = AgentContext {
-                            app: app_clone.clone(),
-                            model,
-                            host,
-                            port,
-                            searxng_url: (!cfg.searxng_url.is_empty()).then_some(cfg.searxng_url),
-                            config_dir: config_dir(),
-                        };
+                        let limits = crate::agent::budget::resolve(&Default::default(), &cfg).unwrap_or_default();
+                        // Nobody is there to approve tool calls; the headless
+                        // policy answers for them, not what was approved in the UI.
+                        let policy = crate::agent::approval::Policy::new(&cfg, Default::default(), true);
+                        let ctx = AgentContext::new(Arc::new(app_clone.clone()), &cfg, host, port, model, limits, &agent_id)
+                            .with_policy(policy);
                         
                         let start_msg = format!("Calendar Event '{}' triggered prompt: '{}'", event_title, prompt);
                         log_monitor_event_internal(&agent_id, "Active", &format!("Running: {}", event_title), &start_msg);
                         
-                        let output = crate::agent::run_agent(ctx, handle, None, "CalendarTrigger".into(), prompt, 0).await;
+                        let output = crate::agent::run_agent(ctx, handle, None, "CalendarTrigger".into(), prompt, 0, None).await;
                         app_clone.state::<AppState>().remove_agent(&agent_id);
                         
                         let mut state = calendar_load();
@@ -428,8 +557,11 @@ pub fn download_save_targets(targets: String) -> Result<(), String> {
     std::fs::write(path, targets).map_err(|e| e.to_string())
 }
 
+/// Queue every target in the targets file and start the download worker.
+/// Hugging Face repo ids are expanded to their file list up front so each
+/// file is its own item with its own progress and cancel.
 #[tauri::command]
-pub async fn download_start(state: State<'_, AppState>, targets: String) -> Result<(), String> {
+pub async fn download_start(app: AppHandle, state: State<'_, AppState>, targets: String) -> Result<(), String> {
     download_save_targets(targets.clone())?;
 
     let cfg = state.config.lock().unwrap().clone();
@@ -438,173 +570,160 @@ pub async fn download_start(state: State<'_, AppState>, targets: String) -> Resu
     } else {
         cfg.model_dir.clone()
     };
-
-    // Create the download script (dw.sh)
-    let script_path = config_dir().join("dw.sh");
-    let mut script = String::new();
-    script.push_str("#!/bin/bash\n");
-    script.push_str("export PATH=\"$HOME/.local/bin:$PATH\"\n\n");
-    script.push_str("# ==========================================\n");
-    script.push_str("# TMUX ENFORCEMENT\n");
-    script.push_str("# ==========================================\n");
-    script.push_str("if [ -z \"$TMUX\" ]; then\n");
-    script.push_str("    echo \"Not running in tmux. Starting a background tmux session named 'hf_downloads'...\"\n");
-    script.push_str("    SCRIPT_PATH=$(realpath \"$0\")\n");
-    script.push_str("    tmux new-session -d -s hf_downloads \"bash \\\"$SCRIPT_PATH\\\"\"\n");
-    script.push_str("    echo \"Downloads have started in the background!\"\n");
-    script.push_str("    echo \"To view progress, run: tmux attach -t hf_downloads\"\n");
-    script.push_str("    exit 0\n");
-    script.push_str("fi\n\n");
-    script.push_str("# ==========================================\n");
-    script.push_str("# DOWNLOAD COMMANDS\n");
-    script.push_str("# ==========================================\n");
-    script.push_str(&format!("MODELS_ROOT=\"{}\"\n", models_root));
-    script.push_str("mkdir -p \"$MODELS_ROOT\"\n");
-    script.push_str("cd \"$MODELS_ROOT\"\n\n");
-
-    let mut current_category = "mtp".to_string();
-    let mut current_subfolder: Option<String> = None;
-
-    for line in targets.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue;
-        }
-
-        if trimmed.ends_with(':') {
-            current_category = trimmed[..trimmed.len() - 1].trim().to_string();
-            current_subfolder = None;
-            continue;
-        }
-
-        if trimmed.starts_with('-') {
-            current_subfolder = Some(trimmed[1..].trim().to_string());
-            continue;
-        }
-
-        let base_dir = match &current_subfolder {
-            Some(sub) => format!("{}/{}", current_category, sub),
-            None => current_category.clone(),
-        };
-
-        // Create target directory in the script
-        script.push_str(&format!("mkdir -p \"$MODELS_ROOT/{}\"\n", base_dir));
-
-        if trimmed.ends_with(".git") || trimmed.starts_with("https://github.com") {
-            let mut repo_name = trimmed.split('/').last().unwrap_or("").to_string();
-            if repo_name.ends_with(".git") {
-                repo_name = repo_name[..repo_name.len() - 4].to_string();
+    let targets = parse_targets(&targets).map_err(|errors| {
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+    })?;
+    let endpoint = download::hf::endpoint();
+    let token = Some(cfg.hf_token.clone()).filter(|t| !t.is_empty());
+    let client = reqwest::Client::new();
+    // Repo listings fetched for direct Hub URLs, so several files from one
+    // repo cost one request.
+    let mut listings: std::collections::HashMap<String, Vec<HfRepoFile>> = std::collections::HashMap::new();
+
+    for target in &targets {
+        match download::plan(target, std::path::Path::new(&models_root)) {
+            download::Planned::Url { url, dest } => {
+                // Hub files carry a sha256 in the repo listing; without it the
+                // download is only checked structurally afterwards.
+                let mut sha256 = None;
+                if let Some((repo, path)) = download::hf::parse_resolve_url(&endpoint, &url) {
+                    if !listings.contains_key(&repo) {
+                        let files = download::hf::list_repo_files(&client, &endpoint, &repo, token.as_deref())
+                            .await
+                            .unwrap_or_else(|e| {
+                                tracing::warn!(repo = %repo, error = %e, "no checksum for direct download");
+                                Vec::new()
+                            });
+                        listings.insert(repo.clone(), files);
+                    }
+                    sha256 = listings[&repo].iter().find(|f| f.path == path).and_then(|f| f.sha256.clone());
+                }
+                let job = download::Job::File(FetchRequest { url: url.clone(), dest: dest.clone(), sha256, token: None });
+                queue_download(&app, &state, url, &dest, Ok(job));
             }
-            script.push_str(&format!(
-                "git clone \"{}\" \"$MODELS_ROOT/{}/{}\"\n",
-                trimmed, base_dir, repo_name
-            ));
-        } else if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
-            let parts: Vec<&str> = trimmed.split('/').collect();
-            let repo = if parts.len() > 4 { parts[4] } else { "unknown" };
-            let filename = parts.last().unwrap_or(&"");
-
-            let target_name = if filename.starts_with("mmproj") {
-                let prefix = repo.to_lowercase().replace("-gguf", "");
-                format!("{}-{}", prefix, filename)
-            } else {
-                if let Some(last_dash) = filename.rfind('-') {
-                    format!(
-                        "{}{}",
-                        filename[..last_dash].to_lowercase(),
-                        &filename[last_dash..]
-                    )
-                } else {
-                    filename.to_lowercase()
+            download::Planned::Git { url, dest } => {
+                let job = download::Job::Git { url: url.clone(), dest: dest.clone() };
+                queue_download(&app, &state, url, &dest, Ok(job));
+            }
+            download::Planned::HfRepo { repo, dest_dir } => {
+                match download::hf::list_repo_files(&client, &endpoint, &repo, token.as_deref()).await {
+                    Ok(files) => {
+                        for f in files {
+                            let dest = dest_dir.join(&f.path);
+                            let job = download::Job::File(FetchRequest {
+                                url: download::hf::resolve_url(&endpoint, &repo, &f.path),
+                                dest: dest.clone(),
+                                sha256: f.sha256,
+                                token: None,
+                            });
+                            queue_download(&app, &state, format!("{}/{}", repo, f.path), &dest, Ok(job));
+                        }
+                    }
+                    Err(e) => {
+                        let e = format!("line {}: {}", target.line, e);
+                        queue_download(&app, &state, repo, &dest_dir, Err(e));
+                    }
                 }
-            };
-
-            script.push_str(&format!(
-                "if command -v aria2c &> /dev/null; then\n  aria2c -c -x 16 -s 16 -o \"{}\" -d \"$MODELS_ROOT/{}\" \"{}\"\nelse\n  wget -c -O \"$MODELS_ROOT/{}/{}\" \"{}\"\nfi\n",
-                target_name, base_dir, trimmed, base_dir, target_name, trimmed
-            ));
-        } else {
-            let repo_name = trimmed.split('/').last().unwrap_or("");
-            let target_dir = repo_name.to_lowercase();
-            script.push_str(&format!(
-                "hf download \"{}\" --local-dir \"$MODELS_ROOT/{}/{}\"\n",
-                trimmed, base_dir, target_dir
-            ));
+            }
         }
     }
 
-    script.push_str(
-        "\n# Keep tmux window open for 10 seconds after completion to see the final logs\n",
-    );
-    script.push_str("sleep 10\n");
+    download::ensure_worker(&app);
+    Ok(())
+}
 
-    std::fs::write(&script_path, script).map_err(|e| e.to_string())?;
+/// List a Hugging Face repo's GGUF options with sizes, quant names and a
+/// fit estimate against the local memory budget.
+#[tauri::command]
+pub async fn hf_list_repo(state: State<'_, AppState>, repo: String) -> Result<HfRepoListing, String> {
+    let cfg = state.config.lock().unwrap().clone();
+    let repo = repo.trim().trim_matches('/').to_string();
+    let client = reqwest::Client::new();
+    let files = download::hf::list_repo_files(&client, &download::hf::endpoint(), &repo, Some(&cfg.hf_token)).await?;
+    let budget = download::fit::memory_budget(&cfg).await;
+    let (options, extras) = download::hf::group_quants(files, budget);
+    Ok(HfRepoListing { repo, options, extras, budget })
+}
 
-    // Make script executable
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = std::fs::metadata(&script_path).map_err(|e| e.to_string())?.permissions();
-        perms.set_mode(0o755);
-        std::fs::set_permissions(&script_path, perms).map_err(|e| e.to_string())?;
+/// Queue the chosen files of `repo` under `<model_dir>/<subdir>/<repo name>`.
+#[tauri::command]
+pub async fn hf_enqueue_files(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo: String,
+    paths: Vec<String>,
+    subdir: String,
+) -> Result<Vec<DownloadItem>, String> {
+    let cfg = state.config.lock().unwrap().clone();
+    if cfg.model_dir.is_empty() {
+        return Err("Set a model directory before downloading.".into());
     }
+    let subdir = subdir.trim().trim_matches('/');
+    if subdir.split('/').any(|p| p == "..") {
+        return Err("Invalid subfolder".into());
+    }
+    let token = Some(cfg.hf_token.clone()).filter(|t| !t.is_empty());
+    let endpoint = download::hf::endpoint();
+    let client = reqwest::Client::new();
+    // Re-list so checksums come from the Hub, not the caller.
+    let files = download::hf::list_repo_files(&client, &endpoint, &repo, token.as_deref()).await?;
+
+    let repo_name = repo.rsplit('/').next().unwrap_or(&repo).to_lowercase();
+    let dest_dir = std::path::Path::new(&cfg.model_dir).join(subdir).join(repo_name);
+    let mut items = Vec::new();
+    for path in paths {
+        let file = files
+            .iter()
+            .find(|f| f.path == path)
+            .ok_or_else(|| format!("{path} is not in {repo}"))?;
+        let dest = dest_dir.join(&file.path);
+        let job = download::Job::File(FetchRequest {
+            url: download::hf::resolve_url(&endpoint, &repo, &file.path),
+            dest: dest.clone(),
+            sha256: file.sha256.clone(),
+            token: None,
+        });
+        items.extend(queue_download(&app, &state, format!("{}/{}", repo, file.path), &dest, Ok(job)));
+    }
+    download::ensure_worker(&app);
+    Ok(items)
+}
 
-    // Spawn download in tmux session 'hf_downloads'
-    let _ = Command::new("tmux")
-        .args(&["kill-session", "-t", "hf_downloads"])
-        .output()
-        .await;
-
-    let child = Command::new("tmux")
-        .args(&["new-session", "-d", "-s", "hf_downloads", "bash", &script_path.to_string_lossy()])
-        .spawn()
-        .map_err(|e| format!("Failed to spawn tmux session: {}", e))?;
-
-    // Let process run in background
-    tokio::spawn(async move {
-        let mut c = child;
-        let _ = c.wait().await;
-    });
-
-    Ok(())
+/// Returns `None` when the same destination is already queued or running.
+fn queue_download(
+    app: &AppHandle,
+    state: &AppState,
+    source: String,
+    dest: &std::path::Path,
+    job: Result<download::Job, String>,
+) -> Option<DownloadItem> {
+    let item = state.downloads.push(source, dest, job)?;
+    let _ = app.emit(DOWNLOAD_EVENT, DownloadEvent::Added { item: item.clone() });
+    Some(item)
 }
 
+/// Cancel one download, or all unfinished ones when `id` is omitted. Partial
+/// files are kept, so starting the same target again resumes it.
 #[tauri::command]
-pub async fn download_cancel() -> Result<(), String> {
-    let output = Command::new("tmux")
-        .args(&["kill-session", "-t", "hf_downloads"])
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+pub fn download_cancel(app: AppHandle, state: State<'_, AppState>, id: Option<String>) -> Result<(), String> {
+    for id in state.downloads.cancel(id.as_deref()) {
+        download::emit_state(&app, &state.downloads, &id, DownloadState::Cancelled, None);
     }
+    Ok(())
 }
 
+/// Remove finished, failed and cancelled items from the queue.
 #[tauri::command]
-pub async fn download_status() -> Result<DownloadStatus, String> {
-    let has_session_out = Command::new("tmux")
-        .args(&["has-session", "-t", "hf_downloads"])
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let is_downloading = has_session_out.status.success();
-    let mut log = String::new();
+pub fn download_clear_finished(state: State<'_, AppState>) {
+    state.downloads.clear_finished();
+}
 
-    if is_downloading {
-        let capture_out = Command::new("tmux")
-            .args(&["capture-pane", "-pt", "hf_downloads"])
-            .output()
-            .await
-            .map_err(|e| e.to_string())?;
-        log = String::from_utf8_lossy(&capture_out.stdout).to_string();
+#[tauri::command]
+pub fn download_status(state: State<'_, AppState>) -> DownloadStatus {
+    DownloadStatus {
+        is_downloading: state.downloads.is_active(),
+        items: state.downloads.items(),
     }
-
-    Ok(DownloadStatus { is_downloading, log })
 }
 
 // ── Instance Monitor ─────────────────────────────────────────────────────────
@@ -1061,15 +1180,24 @@ pub async fn compare_run_eval(
 // ── Agent Memory ─────────────────────────────────────────────────────────────
 
 #[tauri::command]
-pub fn memory_get() -> Result<Vec<String>, String> {
-    let path = config_dir().join("agent_memory.json");
-    if path.exists() {
-        let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        let lessons: Vec<String> = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-        Ok(lessons)
-    } else {
-        Ok(Vec::new())
-    }
+pub fn memory_get() -> Result<Vec<shared::ipc::Lesson>, String> {
+    Ok(MemoryManager::new(&config_dir()).list(chrono::Utc::now().timestamp()))
+}
+
+/// Approve (optionally reworded) or reject a lesson from the review queue.
+#[tauri::command]
+pub fn memory_review(id: String, approve: bool, text: Option<String>) -> Result<(), String> {
+    MemoryManager::new(&config_dir()).review(&id, approve, text, chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn memory_delete(id: String) -> Result<(), String> {
+    MemoryManager::new(&config_dir()).delete(&id, chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn memory_consolidate() -> Result<Vec<shared::ipc::Lesson>, String> {
+    MemoryManager::new(&config_dir()).consolidate(chrono::Utc::now().timestamp())
 }
 
 #[tauri::command]
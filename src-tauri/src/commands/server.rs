@@ -95,6 +95,15 @@ pub async fn server_start(app: AppHandle, state: State<'_, AppState>) -> Result<
     }
 
     *state.server.lock().unwrap() = Some(child);
+
+    // Feeds the library's "unused models" report.
+    if !cfg.model_path.is_empty() {
+        let launched = crate::library::index_db::IndexDb::open(&crate::config_io::config_dir())
+            .and_then(|db| db.record_launch(&cfg.model_path, chrono::Utc::now().timestamp()));
+        if let Err(e) = launched {
+            tracing::warn!(%e, "failed to record model launch");
+        }
+    }
     Ok(())
 }
 
@@ -186,3 +195,17 @@ pub async fn pick_path(directory: bool) -> Option<String> {
     .ok()
     .flatten()
 }
+
+/// Native save dialog, starting at `default_name`. `None` if cancelled.
+#[tauri::command]
+pub async fn pick_save_path(default_name: String) -> Option<String> {
+    tokio::task::spawn_blocking(move || {
+        rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .save_file()
+            .map(|p| p.to_string_lossy().to_string())
+    })
+    .await
+    .ok()
+    .flatten()
+}
@@ -1,6 +1,7 @@
-use shared::ipc::{TodoItem, Note, NotesStore};
+use shared::ipc::{TodoItem, Note, NotesStore, PromptDraft, PromptRef, PromptTemplate};
 
 use crate::config_io::config_dir;
+use crate::prompts;
 use std::path::PathBuf;
 
 fn get_notes_store_path(scope: &str) -> PathBuf {
@@ -76,3 +77,32 @@ pub fn todos_set(items: Vec<TodoItem>) -> Result<(), String> {
     let json = serde_json::to_string_pretty(&items).map_err(|e| e.to_string())?;
     std::fs::write(config_dir().join("agent_todos_v2.json"), json).map_err(|e| e.to_string())
 }
+
+// ── Prompt library ───────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn prompt_list() -> Vec<PromptTemplate> {
+    prompts::list(&config_dir())
+}
+
+/// Create or update a prompt; changed text is saved as a new version.
+#[tauri::command]
+pub fn prompt_save(draft: PromptDraft) -> Result<PromptTemplate, String> {
+    prompts::save(&config_dir(), draft, chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn prompt_revert(id: String, version: u32) -> Result<PromptTemplate, String> {
+    prompts::revert(&config_dir(), &id, version, chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn prompt_delete(id: String) -> Result<(), String> {
+    prompts::delete(&config_dir(), &id)
+}
+
+/// The prompt's text with its variables and the built-ins filled in.
+#[tauri::command]
+pub fn prompt_render(prompt: PromptRef) -> Result<String, String> {
+    prompts::render(&config_dir(), &prompt)
+}
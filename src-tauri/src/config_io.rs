@@ -10,7 +10,7 @@ use std::path::PathBuf;
 use shared::ServerConfig;
 
 /// Directory holding `config.json` and the app's sidecar JSON files
-/// (`agent_activities.json`, `model_index.json`, …).
+/// (`agent_activities.json`, `model_index.db`, …).
 ///
 /// Preserves the legacy behaviour of living in the repo root so the migrated app
 /// reads the *same* files as the old one. Overridable via `LLAMA_MANAGER_DIR`.
@@ -0,0 +1,546 @@
+//! Single-file HTTP fetch with range resume and parallel chunks.
+//!
+//! Data goes to `<dest>.part`, next to a `<dest>.part.json` sidecar that
+//! records how far each chunk got. An interrupted or cancelled fetch leaves
+//! both behind, and the next fetch of the same URL continues from there. The
+//! file is renamed into place only after the optional sha256 check passes.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FetchRequest {
+    pub url: String,
+    pub dest: PathBuf,
+    /// Expected lowercase hex sha256, checked before the file is moved into place.
+    pub sha256: Option<String>,
+    /// Sent as a bearer token (gated Hugging Face repos). Never persisted.
+    #[serde(skip)]
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub struct FetchOptions {
+    pub connections: usize,
+    /// Files are never split into chunks smaller than this.
+    pub min_chunk_bytes: u64,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            connections: 4,
+            min_chunk_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub bytes_per_sec: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FetchError {
+    Cancelled,
+    Failed(String),
+}
+
+impl From<String> for FetchError {
+    fn from(e: String) -> Self {
+        FetchError::Failed(e)
+    }
+}
+
+/// Resume state persisted in the sidecar.
+#[derive(Serialize, Deserialize)]
+struct PartState {
+    url: String,
+    total: u64,
+    chunks: Vec<ChunkState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct ChunkState {
+    start: u64,
+    /// Exclusive.
+    end: u64,
+    done: u64,
+}
+
+fn part_paths(dest: &Path) -> (PathBuf, PathBuf) {
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    (
+        dest.with_file_name(format!("{name}.part")),
+        dest.with_file_name(format!("{name}.part.json")),
+    )
+}
+
+fn request(client: &reqwest::Client, req: &FetchRequest) -> reqwest::RequestBuilder {
+    let builder = client.get(&req.url);
+    match &req.token {
+        Some(t) if !t.is_empty() => builder.bearer_auth(t),
+        _ => builder,
+    }
+}
+
+/// Download `req.url` to `req.dest`, reporting progress every half second.
+/// Returns the number of bytes written.
+pub async fn fetch(
+    client: &reqwest::Client,
+    req: &FetchRequest,
+    opts: FetchOptions,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(Progress),
+) -> Result<u64, FetchError> {
+    if let Some(dir) = req.dest.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let (part, meta) = part_paths(&req.dest);
+
+    // A one-byte range probe tells us both the size and whether ranges work.
+    let probe = tokio::select! {
+        _ = cancel.cancelled() => return Err(FetchError::Cancelled),
+        r = request(client, req).header("Range", "bytes=0-0").send() => r.map_err(|e| e.to_string())?,
+    };
+    let status = probe.status();
+    let total = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+        probe
+            .headers()
+            .get("content-range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok())
+    } else if status.is_success() {
+        None
+    } else {
+        return Err(FetchError::Failed(format!("HTTP {status} for {}", req.url)));
+    };
+    drop(probe);
+
+    let written = match total {
+        Some(total) => fetch_ranged(client, req, opts, cancel, &on_progress, total, &part, &meta).await?,
+        None => fetch_whole(client, req, cancel, &on_progress, &part).await?,
+    };
+
+    if let Some(expected) = &req.sha256 {
+        let actual = sha256_file(&part).await?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = tokio::fs::remove_file(&part).await;
+            let _ = tokio::fs::remove_file(&meta).await;
+            return Err(FetchError::Failed(format!(
+                "Checksum mismatch for {}: expected {expected}, got {actual}",
+                req.dest.display()
+            )));
+        }
+    }
+
+    tokio::fs::rename(&part, &req.dest)
+        .await
+        .map_err(|e| format!("Failed to move {} into place: {e}", req.dest.display()))?;
+    let _ = tokio::fs::remove_file(&meta).await;
+    Ok(written)
+}
+
+/// Server without range support (or without a length): stream from the start.
+async fn fetch_whole(
+    client: &reqwest::Client,
+    req: &FetchRequest,
+    cancel: &CancellationToken,
+    on_progress: &impl Fn(Progress),
+    part: &Path,
+) -> Result<u64, FetchError> {
+    let res = request(client, req).send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(FetchError::Failed(format!("HTTP {} for {}", res.status(), req.url)));
+    }
+    let total = res.content_length();
+    let mut file = tokio::fs::File::create(part).await.map_err(|e| e.to_string())?;
+    let mut stream = res.bytes_stream();
+    let mut meter = Meter::new(0);
+    let mut written = 0u64;
+    loop {
+        let chunk = tokio::select! {
+            _ = cancel.cancelled() => return Err(FetchError::Cancelled),
+            c = stream.next() => c,
+        };
+        let Some(chunk) = chunk else { break };
+        let bytes = chunk.map_err(|e| e.to_string())?;
+        file.write_all(&bytes).await.map_err(|e| e.to_string())?;
+        written += bytes.len() as u64;
+        if let Some(p) = meter.tick(written, total) {
+            on_progress(p);
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    on_progress(meter.finish(written, total));
+    Ok(written)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fetch_ranged(
+    client: &reqwest::Client,
+    req: &FetchRequest,
+    opts: FetchOptions,
+    cancel: &CancellationToken,
+    on_progress: &impl Fn(Progress),
+    total: u64,
+    part: &Path,
+    meta: &Path,
+) -> Result<u64, FetchError> {
+    let state = load_state(meta, &req.url, total, part)
+        .await
+        .unwrap_or_else(|| PartState {
+            url: req.url.clone(),
+            total,
+            chunks: plan_chunks(total, opts),
+        });
+
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(part)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.set_len(total).await.map_err(|e| e.to_string())?;
+    drop(file);
+
+    let done: Arc<Vec<AtomicU64>> = Arc::new(state.chunks.iter().map(|c| AtomicU64::new(c.done)).collect());
+    let downloaded = || done.iter().map(|d| d.load(Ordering::Relaxed)).sum::<u64>();
+    let snapshot = |state: &PartState| PartState {
+        url: state.url.clone(),
+        total,
+        chunks: state
+            .chunks
+            .iter()
+            .zip(done.iter())
+            .map(|(c, d)| ChunkState { done: d.load(Ordering::Relaxed), ..*c })
+            .collect(),
+    };
+
+    // Let every chunk run to completion or failure rather than stopping at the
+    // first error, so the sidecar records as much finished data as possible.
+    let work = join_all(
+        state
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.start + c.done < c.end)
+            .map(|(i, c)| fetch_chunk(client, req, part, *c, &done[i], cancel)),
+    );
+    tokio::pin!(work);
+
+    let mut meter = Meter::new(downloaded());
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            r = &mut work => break r,
+            _ = ticker.tick() => {
+                if let Some(p) = meter.tick(downloaded(), Some(total)) {
+                    on_progress(p);
+                }
+                save_state(meta, &snapshot(&state)).await;
+            }
+        }
+    };
+    // Record progress whatever happened, so an error or cancel can resume.
+    save_state(meta, &snapshot(&state)).await;
+    if let Some(err) = result.into_iter().find_map(Result::err) {
+        return Err(err);
+    }
+    on_progress(meter.finish(downloaded(), Some(total)));
+    Ok(total)
+}
+
+fn plan_chunks(total: u64, opts: FetchOptions) -> Vec<ChunkState> {
+    let by_size = total.div_ceil(opts.min_chunk_bytes.max(1)).max(1);
+    let count = by_size.min(opts.connections.max(1) as u64);
+    let size = total.div_ceil(count).max(1);
+    (0..count)
+        .map(|i| ChunkState {
+            start: i * size,
+            end: ((i + 1) * size).min(total),
+            done: 0,
+        })
+        .filter(|c| c.start < c.end)
+        .collect()
+}
+
+async fn fetch_chunk(
+    client: &reqwest::Client,
+    req: &FetchRequest,
+    part: &Path,
+    chunk: ChunkState,
+    done: &AtomicU64,
+    cancel: &CancellationToken,
+) -> Result<(), FetchError> {
+    let from = chunk.start + chunk.done;
+    let range = format!("bytes={}-{}", from, chunk.end - 1);
+    let res = tokio::select! {
+        _ = cancel.cancelled() => return Err(FetchError::Cancelled),
+        r = request(client, req).header("Range", range).send() => r.map_err(|e| e.to_string())?,
+    };
+    if res.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(FetchError::Failed(format!(
+            "HTTP {} for range {}-{} of {}",
+            res.status(),
+            from,
+            chunk.end - 1,
+            req.url
+        )));
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(part)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.seek(std::io::SeekFrom::Start(from)).await.map_err(|e| e.to_string())?;
+
+    let mut stream = res.bytes_stream();
+    let mut pos = from;
+    loop {
+        let next = tokio::select! {
+            _ = cancel.cancelled() => {
+                let _ = file.flush().await;
+                return Err(FetchError::Cancelled);
+            }
+            n = stream.next() => n,
+        };
+        let Some(bytes) = next else { break };
+        let bytes = bytes.map_err(|e| e.to_string())?;
+        // Never write past the chunk, even if the server over-delivers.
+        let take = bytes.len().min((chunk.end - pos) as usize);
+        file.write_all(&bytes[..take]).await.map_err(|e| e.to_string())?;
+        pos += take as u64;
+        done.store(pos - chunk.start, Ordering::Relaxed);
+        if pos >= chunk.end {
+            break;
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    if pos < chunk.end {
+        return Err(FetchError::Failed(format!(
+            "Connection closed at byte {pos} of range {from}-{}",
+            chunk.end - 1
+        )));
+    }
+    Ok(())
+}
+
+/// A sidecar is only trusted if it describes the same URL and size and the
+/// partial file is still there.
+async fn load_state(meta: &Path, url: &str, total: u64, part: &Path) -> Option<PartState> {
+    if !part.exists() {
+        return None;
+    }
+    let json = tokio::fs::read_to_string(meta).await.ok()?;
+    let state: PartState = serde_json::from_str(&json).ok()?;
+    (state.url == url && state.total == total && !state.chunks.is_empty()).then_some(state)
+}
+
+async fn save_state(meta: &Path, state: &PartState) {
+    if let Ok(json) = serde_json::to_string(state) {
+        if let Err(e) = tokio::fs::write(meta, json).await {
+            tracing::warn!(path = %meta.display(), error = %e, "failed to save download progress");
+        }
+    }
+}
+
+async fn sha256_file(path: &Path) -> Result<String, FetchError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .map_err(|e| FetchError::Failed(e.to_string()))?
+}
+
+/// Turns byte counts into rate-limited progress reports.
+struct Meter {
+    last_at: Instant,
+    last_bytes: u64,
+}
+
+impl Meter {
+    fn new(start_bytes: u64) -> Self {
+        Self {
+            last_at: Instant::now(),
+            last_bytes: start_bytes,
+        }
+    }
+
+    fn tick(&mut self, bytes: u64, total: Option<u64>) -> Option<Progress> {
+        let elapsed = self.last_at.elapsed();
+        if elapsed < PROGRESS_INTERVAL {
+            return None;
+        }
+        let rate = (bytes.saturating_sub(self.last_bytes) as f64 / elapsed.as_secs_f64()) as u64;
+        self.last_at = Instant::now();
+        self.last_bytes = bytes;
+        Some(Progress {
+            downloaded_bytes: bytes,
+            total_bytes: total,
+            bytes_per_sec: rate,
+        })
+    }
+
+    fn finish(&self, bytes: u64, total: Option<u64>) -> Progress {
+        Progress {
+            downloaded_bytes: bytes,
+            total_bytes: total,
+            bytes_per_sec: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubRequest, StubResponse, StubServer, TempDir};
+    use std::sync::atomic::AtomicBool;
+
+    fn payload() -> Vec<u8> {
+        (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    fn sha_hex(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// Serves `data` with range support; ranges starting at or after
+    /// `fail_from` get a 500 while `failing` is set.
+    fn ranged(data: Vec<u8>, failing: Arc<AtomicBool>, fail_from: u64) -> impl Fn(&StubRequest) -> StubResponse {
+        move |req| {
+            let Some(range) = req.header("range").and_then(|r| r.strip_prefix("bytes=")) else {
+                return StubResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: data.clone(),
+                };
+            };
+            let (a, b) = range.split_once('-').unwrap();
+            let (a, b): (u64, u64) = (a.parse().unwrap(), b.parse().unwrap());
+            if failing.load(Ordering::SeqCst) && a >= fail_from {
+                return StubResponse::text(500, "boom");
+            }
+            StubResponse {
+                status: 206,
+                headers: vec![("Content-Range".into(), format!("bytes {a}-{b}/{}", data.len()))],
+                body: data[a as usize..=b as usize].to_vec(),
+            }
+        }
+    }
+
+    fn range_starts(reqs: &[StubRequest]) -> Vec<u64> {
+        reqs.iter()
+            .filter_map(|r| r.header("range"))
+            .filter(|r| *r != "bytes=0-0")
+            .map(|r| r["bytes=".len()..].split('-').next().unwrap().parse().unwrap())
+            .collect()
+    }
+
+    const OPTS: FetchOptions = FetchOptions {
+        connections: 4,
+        min_chunk_bytes: 1024,
+    };
+
+    #[tokio::test]
+    async fn parallel_chunks_assemble_and_verify() {
+        let data = payload();
+        let server = StubServer::start(ranged(data.clone(), Arc::new(AtomicBool::new(false)), 0)).await;
+        let dir = TempDir::new("dl");
+        let dest = dir.0.join("model.gguf");
+        let req = FetchRequest {
+            url: format!("{}/model.gguf", server.base_url),
+            dest: dest.clone(),
+            sha256: Some(sha_hex(&data)),
+            token: Some("hf_secret".into()),
+        };
+
+        let n = fetch(&reqwest::Client::new(), &req, OPTS, &CancellationToken::new(), |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        let (part, meta) = part_paths(&dest);
+        assert!(!part.exists() && !meta.exists());
+        let reqs = server.requests();
+        assert_eq!(range_starts(&reqs).len(), 4);
+        assert!(reqs.iter().all(|r| r.header("authorization") == Some("Bearer hf_secret")));
+    }
+
+    #[tokio::test]
+    async fn interrupted_fetch_resumes_remaining_chunks() {
+        let data = payload();
+        let failing = Arc::new(AtomicBool::new(true));
+        let server = StubServer::start(ranged(data.clone(), failing.clone(), 50_000)).await;
+        let dir = TempDir::new("dl");
+        let dest = dir.0.join("model.gguf");
+        let req = FetchRequest {
+            url: format!("{}/model.gguf", server.base_url),
+            dest: dest.clone(),
+            sha256: Some(sha_hex(&data)),
+            token: None,
+        };
+        let client = reqwest::Client::new();
+
+        let first = fetch(&client, &req, OPTS, &CancellationToken::new(), |_| {}).await;
+        assert!(matches!(first, Err(FetchError::Failed(_))));
+        assert!(part_paths(&dest).1.exists(), "progress sidecar kept for resume");
+
+        failing.store(false, Ordering::SeqCst);
+        let before = server.requests().len();
+        fetch(&client, &req, OPTS, &CancellationToken::new(), |_| {}).await.unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        let mut resumed = range_starts(&server.requests()[before..]);
+        resumed.sort();
+        assert_eq!(resumed, vec![50_000, 75_000], "only the failed chunks are fetched again");
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_and_cancel_leave_no_file() {
+        let data = payload();
+        let server = StubServer::start(ranged(data, Arc::new(AtomicBool::new(false)), 0)).await;
+        let dir = TempDir::new("dl");
+        let dest = dir.0.join("model.gguf");
+        let mut req = FetchRequest {
+            url: format!("{}/model.gguf", server.base_url),
+            dest: dest.clone(),
+            sha256: Some("00".repeat(32)),
+            token: None,
+        };
+        let client = reqwest::Client::new();
+
+        let err = fetch(&client, &req, OPTS, &CancellationToken::new(), |_| {}).await.unwrap_err();
+        assert!(matches!(err, FetchError::Failed(ref e) if e.contains("Checksum mismatch")));
+        assert!(!dest.exists() && !part_paths(&dest).0.exists());
+
+        req.sha256 = None;
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert_eq!(fetch(&client, &req, OPTS, &cancel, |_| {}).await, Err(FetchError::Cancelled));
+        assert!(!dest.exists());
+    }
+}
@@ -0,0 +1,73 @@
+//! Local memory budget and the "will this model fit" estimate shown in the
+//! download picker.
+
+use shared::ipc::{MemoryBudget, MemoryFit};
+use shared::ServerConfig;
+
+/// Weights plus ~10% and a fixed allowance for the KV cache and compute
+/// buffers at a modest context. Deliberately rough: it only has to sort
+/// options into "GPU", "needs offload" and "won't run".
+fn required_bytes(model_bytes: u64) -> u64 {
+    model_bytes + model_bytes / 10 + (512 << 20)
+}
+
+pub fn estimate_fit(model_bytes: u64, budget: MemoryBudget) -> MemoryFit {
+    if budget.vram_bytes == 0 && budget.ram_bytes == 0 {
+        return MemoryFit::Unknown;
+    }
+    let need = required_bytes(model_bytes);
+    if budget.vram_bytes > 0 && need <= budget.vram_bytes {
+        MemoryFit::Gpu
+    } else if need <= budget.vram_bytes + budget.ram_bytes {
+        MemoryFit::Offload
+    } else {
+        MemoryFit::TooLarge
+    }
+}
+
+/// The configured budget, with each zero field filled in by detection.
+pub async fn memory_budget(cfg: &ServerConfig) -> MemoryBudget {
+    let gib = |gb: f32| (f64::from(gb) * (1u64 << 30) as f64) as u64;
+    let vram_bytes = if cfg.vram_budget_gb > 0.0 {
+        gib(cfg.vram_budget_gb)
+    } else {
+        detect_vram().await.unwrap_or(0)
+    };
+    let ram_bytes = if cfg.ram_budget_gb > 0.0 {
+        gib(cfg.ram_budget_gb)
+    } else {
+        detect_ram().unwrap_or(0)
+    };
+    MemoryBudget { vram_bytes, ram_bytes }
+}
+
+/// Total VRAM across NVIDIA GPUs. Other vendors fall back to the configured
+/// budget.
+async fn detect_vram() -> Option<u64> {
+    let out = tokio::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.total", "--format=csv,noheader,nounits"])
+        .output()
+        .await
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let mib: u64 = String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|l| l.trim().parse::<u64>().ok())
+        .sum();
+    (mib > 0).then_some(mib << 20)
+}
+
+fn detect_ram() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kib: u64 = meminfo
+        .lines()
+        .find_map(|l| l.strip_prefix("MemTotal:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib << 10)
+}
@@ -0,0 +1,221 @@
+//! Minimal Hugging Face Hub client for downloads: list a repo's files, group
+//! its GGUFs into per-quant options and build `resolve` URLs.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use shared::ipc::{HfQuantOption, HfRepoFile, MemoryBudget};
+
+use crate::library::enrich::DEFAULT_HF_ENDPOINT;
+use crate::library::quant_of_filename;
+
+/// The Hub host; `HF_ENDPOINT` overrides it (mirrors, tests).
+pub fn endpoint() -> String {
+    std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_string())
+}
+
+/// Whether `url` is on the Hub at `endpoint`: same scheme, host and port.
+/// Only such URLs get the Hub token, so a look-alike such as
+/// `https://huggingface.co.evil.example` or `https://huggingface.co@evil`
+/// never does.
+pub fn is_hub_url(url: &str, endpoint: &str) -> bool {
+    let (Ok(url), Ok(hub)) = (reqwest::Url::parse(url), reqwest::Url::parse(endpoint)) else {
+        return false;
+    };
+    url.scheme() == hub.scheme()
+        && url.host_str().is_some()
+        && url.host_str() == hub.host_str()
+        && url.port_or_known_default() == hub.port_or_known_default()
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+    lfs: Option<LfsInfo>,
+}
+
+#[derive(Deserialize)]
+struct LfsInfo {
+    oid: String,
+    size: u64,
+}
+
+/// Every file in `repo` at `main`, recursively.
+pub async fn list_repo_files(
+    client: &reqwest::Client,
+    endpoint: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> Result<Vec<HfRepoFile>, String> {
+    let url = format!("{}/api/models/{}/tree/main", endpoint.trim_end_matches('/'), repo);
+    let mut req = client.get(&url).query(&[("recursive", "true")]);
+    if let Some(t) = token.filter(|t| !t.is_empty()) {
+        req = req.bearer_auth(t);
+    }
+    let res = req.send().await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("Listing {repo} failed: HTTP {}", res.status()));
+    }
+    let entries: Vec<TreeEntry> = res.json().await.map_err(|e| format!("Listing {repo}: {e}"))?;
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.kind == "file")
+        .map(|e| HfRepoFile {
+            size_bytes: e.lfs.as_ref().map_or(e.size, |l| l.size),
+            // Only LFS files carry a sha256.
+            sha256: e.lfs.map(|l| l.oid),
+            path: e.path,
+        })
+        .collect())
+}
+
+pub fn resolve_url(endpoint: &str, repo: &str, path: &str) -> String {
+    let path: Vec<String> = path.split('/').map(|s| urlencoding::encode(s).into_owned()).collect();
+    format!("{}/{}/resolve/main/{}", endpoint.trim_end_matches('/'), repo, path.join("/"))
+}
+
+/// Split a `resolve` URL on `endpoint` back into `(repo, path)`, so a pasted
+/// Hub link can be checked against the repo listing's sha256.
+pub fn parse_resolve_url(endpoint: &str, url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix(endpoint.trim_end_matches('/'))?.strip_prefix('/')?;
+    let rest = rest.split(['?', '#']).next()?;
+    let mut parts = rest.splitn(5, '/');
+    let (owner, name) = (parts.next()?, parts.next()?);
+    if parts.next()? != "resolve" {
+        return None;
+    }
+    let _revision = parts.next()?;
+    let path = urlencoding::decode(parts.next()?).ok()?.into_owned();
+    (!owner.is_empty() && !name.is_empty() && !path.is_empty()).then(|| (format!("{owner}/{name}"), path))
+}
+
+/// `model-00001-of-00003.gguf` → `model.gguf`, so shards group together.
+fn shard_base(path: &str) -> Option<String> {
+    let stem = path.strip_suffix(".gguf")?;
+    let (head, total) = stem.rsplit_once("-of-")?;
+    let (base, index) = head.rsplit_once('-')?;
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    (digits(index) && digits(total)).then(|| format!("{base}.gguf"))
+}
+
+/// Split a repo listing into GGUF model options (shards grouped, smallest
+/// first) and everything else. Multimodal projectors go to the extras since
+/// they accompany a model rather than being one.
+pub fn group_quants(files: Vec<HfRepoFile>, budget: MemoryBudget) -> (Vec<HfQuantOption>, Vec<HfRepoFile>) {
+    let mut groups: BTreeMap<String, Vec<HfRepoFile>> = BTreeMap::new();
+    let mut extras = Vec::new();
+    for f in files {
+        let name = f.path.rsplit('/').next().unwrap_or(&f.path).to_lowercase();
+        if !name.ends_with(".gguf") || name.starts_with("mmproj") {
+            extras.push(f);
+            continue;
+        }
+        let key = shard_base(&f.path).unwrap_or_else(|| f.path.clone());
+        groups.entry(key).or_default().push(f);
+    }
+
+    let mut options: Vec<HfQuantOption> = groups
+        .into_iter()
+        .map(|(key, mut files)| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            let size_bytes = files.iter().map(|f| f.size_bytes).sum();
+            HfQuantOption {
+                quant: quant_of_filename(&key).unwrap_or_default(),
+                files,
+                size_bytes,
+                fit: super::fit::estimate_fit(size_bytes, budget),
+            }
+        })
+        .collect();
+    options.sort_by_key(|o| o.size_bytes);
+    (options, extras)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+    use shared::ipc::MemoryFit;
+
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn only_the_hub_itself_gets_the_token() {
+        let hub = "https://huggingface.co";
+        assert!(is_hub_url("https://huggingface.co/org/m/resolve/main/m.gguf", hub));
+        assert!(is_hub_url("https://huggingface.co:443/org/m/resolve/main/m.gguf", hub));
+        for url in [
+            "https://huggingface.co.evil.example/org/m/resolve/main/m.gguf",
+            "https://huggingface.co@evil.example/m.gguf",
+            "https://huggingface.co:8443/m.gguf",
+            "http://huggingface.co/m.gguf",
+            "https://cdn.example/huggingface.co/m.gguf",
+            "not a url",
+        ] {
+            assert!(!is_hub_url(url, hub), "{url}");
+        }
+        assert!(is_hub_url("http://127.0.0.1:9000/org/m/resolve/main/m.gguf", "http://127.0.0.1:9000/"));
+        assert!(!is_hub_url("http://127.0.0.1:9001/m.gguf", "http://127.0.0.1:9000"));
+    }
+
+    #[tokio::test]
+    async fn lists_and_groups_repo_files() {
+        let server = StubServer::start(|req| {
+            if req.path() != "/api/models/org/Model-GGUF/tree/main" {
+                return StubResponse::not_found();
+            }
+            let lfs = |size: u64, oid: &str| serde_json::json!({ "oid": oid, "size": size });
+            StubResponse::json(serde_json::json!([
+                { "type": "file", "path": "README.md", "size": 900 },
+                { "type": "directory", "path": "Q8_0", "size": 0 },
+                { "type": "file", "path": "Model-Q4_K_M.gguf", "size": 130, "lfs": lfs(5 * GIB, "aa") },
+                { "type": "file", "path": "Model-UD-IQ3_XXS.gguf", "size": 130, "lfs": lfs(3 * GIB, "bb") },
+                { "type": "file", "path": "Q8_0/Model-Q8_0-00002-of-00002.gguf", "size": 130, "lfs": lfs(4 * GIB, "cc") },
+                { "type": "file", "path": "Q8_0/Model-Q8_0-00001-of-00002.gguf", "size": 130, "lfs": lfs(5 * GIB, "dd") },
+                { "type": "file", "path": "mmproj-F16.gguf", "size": 130, "lfs": lfs(GIB, "ee") },
+            ]))
+        })
+        .await;
+        let client = reqwest::Client::new();
+
+        let files = list_repo_files(&client, &server.base_url, "org/Model-GGUF", Some("hf_tok"))
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 6, "directories are skipped");
+        assert_eq!(server.requests()[0].header("authorization"), Some("Bearer hf_tok"));
+        assert_eq!(server.requests()[0].target, "/api/models/org/Model-GGUF/tree/main?recursive=true");
+
+        let budget = MemoryBudget { vram_bytes: 8 * GIB, ram_bytes: 8 * GIB };
+        let (options, extras) = group_quants(files, budget);
+        let summary: Vec<_> = options
+            .iter()
+            .map(|o| (o.quant.as_str(), o.files.len(), o.size_bytes / GIB, o.fit))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("IQ3_XXS", 1, 3, MemoryFit::Gpu),
+                ("Q4_K_M", 1, 5, MemoryFit::Gpu),
+                ("Q8_0", 2, 9, MemoryFit::Offload),
+            ]
+        );
+        assert_eq!(options[2].files[0].path, "Q8_0/Model-Q8_0-00001-of-00002.gguf");
+        assert_eq!(options[0].files[0].sha256.as_deref(), Some("bb"));
+        let extras: Vec<_> = extras.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(extras, vec!["README.md", "mmproj-F16.gguf"]);
+
+        assert_eq!(
+            resolve_url(&server.base_url, "org/Model-GGUF", "Q8_0/a b.gguf"),
+            format!("{}/org/Model-GGUF/resolve/main/Q8_0/a%20b.gguf", server.base_url)
+        );
+        assert_eq!(
+            parse_resolve_url(&server.base_url, &format!("{}/org/M/resolve/main/Q8_0/a%20b.gguf?download=true", server.base_url)),
+            Some(("org/M".into(), "Q8_0/a b.gguf".into()))
+        );
+        assert_eq!(parse_resolve_url(&server.base_url, &format!("{}/org/M/blob/main/a.gguf", server.base_url)), None);
+    }
+}
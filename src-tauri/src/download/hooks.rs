@@ -0,0 +1,202 @@
+//! Post-download hooks for finished model files, driven by the
+//! `post_download_*` config fields: verify the GGUF structure, merge the file
+//! into the library index, enrich it in the background and finally create a
+//! profile or start the server with it.
+//!
+//! The sha256 check happens earlier, in [`super::engine::fetch`], for every
+//! file the Hub lists a hash for. Each step is reported as a
+//! [`DownloadEvent::PostStep`] and kept in the item's `post_log`.
+
+use std::path::{Path, PathBuf};
+
+use shared::config::PostDownloadAction;
+use shared::ipc::{DownloadEvent, DOWNLOAD_EVENT};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config_io::config_dir;
+use crate::library::{self, enrich::EnrichmentChain, gguf, index_db::IndexDb};
+use crate::state::AppState;
+
+fn step(app: &AppHandle, id: &str, message: String) {
+    tracing::info!(id = %id, "{message}");
+    app.state::<AppState>().downloads.log_step(id, message.clone());
+    let _ = app.emit(DOWNLOAD_EVENT, DownloadEvent::PostStep { id: id.to_string(), message });
+}
+
+/// `model-00002-of-00003.gguf` → every shard of the set, in order.
+fn split_set(path: &Path) -> Option<Vec<PathBuf>> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".gguf")?;
+    let (head, total) = stem.rsplit_once("-of-")?;
+    let (base, index) = head.rsplit_once('-')?;
+    let total_n: usize = total.parse().ok()?;
+    if index.len() != total.len() || index.parse::<usize>().is_err() {
+        return None;
+    }
+    let width = total.len();
+    Some(
+        (1..=total_n)
+            .map(|i| path.with_file_name(format!("{base}-{i:0width$}-of-{total}.gguf")))
+            .collect(),
+    )
+}
+
+/// The file to point the server at once `path` is on disk: the file itself,
+/// or the first shard when it completes a split set. `None` while shards are
+/// still missing, and for projectors, which only accompany a model.
+fn launchable(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if name.starts_with("mmproj") {
+        return None;
+    }
+    match split_set(path) {
+        None => Some(path.to_path_buf()),
+        Some(shards) => {
+            let part = |p: &PathBuf| p.with_file_name(format!("{}.part", p.file_name().unwrap().to_string_lossy()));
+            let complete = shards.iter().all(|s| s.is_file() && !part(s).exists());
+            complete.then(|| shards[0].clone())
+        }
+    }
+}
+
+/// Profile names allow letters, digits, `-`, `_`, ` ` and `.`.
+fn profile_name(model: &Path) -> String {
+    let stem = model.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    name.trim_start_matches('.').to_string()
+}
+
+/// Run the configured hooks on a finished download. An `Err` means the file
+/// failed verification and the item should be marked failed; later steps only
+/// log their failures.
+pub async fn run(app: &AppHandle, id: &str, dest: &Path) -> Result<(), String> {
+    let is_gguf = dest
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("gguf"));
+    if !is_gguf {
+        return Ok(());
+    }
+    let cfg = app.state::<AppState>().config.lock().unwrap().clone();
+
+    if cfg.post_download_verify {
+        let path = dest.to_path_buf();
+        let info = tokio::task::spawn_blocking(move || gguf::verify(&path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("GGUF check failed: {e}"))?;
+        let arch = info.architecture.as_deref().unwrap_or("unknown architecture");
+        step(app, id, format!("Verified GGUF v{} ({arch}, {} tensors)", info.version, info.tensor_count));
+    }
+
+    if cfg.post_download_index {
+        let size_bytes = std::fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+        let path = dest.to_string_lossy().to_string();
+        let indexed = IndexDb::open(&config_dir()).and_then(|mut db| {
+            let existing = db.get(&path)?.into_iter().collect();
+            let merged = library::merge_indexes(vec![library::scanned_model(dest, size_bytes)], existing);
+            for model in &merged {
+                db.upsert(model)?;
+            }
+            Ok(merged.into_iter().next())
+        });
+        match indexed {
+            Ok(Some(model)) => {
+                step(app, id, "Added to the library".into());
+                if cfg.post_download_enrich && model.status == "pending_enrichment" {
+                    spawn_enrich(app, id, model);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => step(app, id, format!("Indexing failed: {e}")),
+        }
+    }
+
+    if let Some(model) = launchable(dest) {
+        if let Err(e) = run_action(app, id, &cfg.post_download_action, &model).await {
+            step(app, id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Enrichment may call out to the Hub or an LLM, so it runs off the queue.
+fn spawn_enrich(app: &AppHandle, id: &str, mut model: shared::ipc::ScannedModel) {
+    let chain = EnrichmentChain::from_config(&app.state::<AppState>().config.lock().unwrap(), &config_dir());
+    let (app, id) = (app.clone(), id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let Ok(mut db) = IndexDb::open(&config_dir()) else { return };
+        let _ = db.set_status(&model.path, "enriching");
+        match chain.enrich(&mut model, false).await {
+            Ok(()) => {
+                let _ = db.upsert(&model);
+                match model.status.as_str() {
+                    "enriched" => step(&app, &id, format!("Enriched as {}", model.clean_name)),
+                    _ => step(&app, &id, "No metadata found yet; enrichment will be retried".into()),
+                }
+            }
+            Err(e) => {
+                let _ = db.set_status(&model.path, "failed");
+                step(&app, &id, format!("Enrichment failed: {e}"));
+            }
+        }
+    });
+}
+
+async fn run_action(app: &AppHandle, id: &str, action: &PostDownloadAction, model: &Path) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let model_path = model.to_string_lossy().to_string();
+    match action {
+        PostDownloadAction::None => {}
+        PostDownloadAction::CreateProfile => {
+            let mut cfg = state.config.lock().unwrap().clone();
+            cfg.model_path = model_path;
+            let name = profile_name(model);
+            crate::profiles::save(&name, &cfg).map_err(|e| format!("Creating profile failed: {e}"))?;
+            step(app, id, format!("Saved profile `{name}`"));
+        }
+        PostDownloadAction::StartServer => {
+            if state.server.lock().unwrap().is_some() {
+                step(app, id, "Server already running; not switching models".into());
+                return Ok(());
+            }
+            let mut cfg = state.config.lock().unwrap().clone();
+            cfg.model_path = model_path;
+            crate::commands::config::update_config(app.clone(), app.state(), cfg)?;
+            crate::commands::server::server_start(app.clone(), app.state())
+                .await
+                .map_err(|e| format!("Starting the server failed: {e}"))?;
+            step(app, id, "Server started with the new model".into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn split_sets_launch_from_the_first_shard_once_complete() {
+        let tmp = TempDir::new("hooks");
+        let dir = &tmp.0;
+        let first = dir.join("Model-Q8_0-00001-of-00002.gguf");
+        let second = dir.join("Model-Q8_0-00002-of-00002.gguf");
+
+        std::fs::write(&second, b"x").unwrap();
+        assert_eq!(launchable(&second), None, "first shard missing");
+        std::fs::write(&first, b"x").unwrap();
+        std::fs::write(dir.join("Model-Q8_0-00001-of-00002.gguf.part"), b"").unwrap();
+        assert_eq!(launchable(&second), None, "first shard still downloading");
+        std::fs::remove_file(dir.join("Model-Q8_0-00001-of-00002.gguf.part")).unwrap();
+        assert_eq!(launchable(&second), Some(first.clone()));
+
+        assert_eq!(launchable(&dir.join("mmproj-F16.gguf")), None);
+        assert_eq!(launchable(&dir.join("model-Q4_K_M.gguf")), Some(dir.join("model-Q4_K_M.gguf")));
+        assert_eq!(profile_name(&first), "Model-Q8_0-00001-of-00002");
+    }
+}
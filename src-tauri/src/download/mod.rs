@@ -0,0 +1,479 @@
+//! Native download queue: turns the targets file into jobs and works through
+//! them one at a time, streaming [`DownloadEvent`]s on `download://event`.
+//!
+//! Files go through [`engine::fetch`] (range resume, parallel chunks, sha256
+//! check); git URLs are still cloned with `git`. Every item has its own
+//! cancellation token, so one download can be stopped without touching the rest.
+//!
+//! The queue is saved to `download_queue.json` on every state change. On
+//! startup unfinished items are queued again and resume from their `.part`
+//! files. Finished model files go through [`hooks`] before they are marked done.
+
+pub mod engine;
+pub mod fit;
+pub mod hf;
+pub mod hooks;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use shared::ipc::{DownloadEvent, DownloadItem, DownloadState, DOWNLOAD_EVENT};
+use shared::targets::{DownloadTarget, TargetSource};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::state::AppState;
+use engine::{FetchError, FetchOptions, FetchRequest};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    File(FetchRequest),
+    Git { url: String, dest: PathBuf },
+}
+
+struct Entry {
+    item: DownloadItem,
+    /// `None` for items that failed before they could be queued.
+    job: Option<Job>,
+    cancel: CancellationToken,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    item: DownloadItem,
+    job: Option<Job>,
+}
+
+/// Lives in [`AppState`]; the single worker task drains it in order.
+#[derive(Default)]
+pub struct DownloadQueue {
+    entries: Mutex<Vec<Entry>>,
+    worker_running: AtomicBool,
+    /// Where the queue is persisted; `None` keeps it in memory only.
+    store: Option<PathBuf>,
+}
+
+impl DownloadQueue {
+    /// Load the queue saved at `path`. Items that were running when the app
+    /// stopped are queued again.
+    pub fn open(path: PathBuf) -> Self {
+        let stored: Vec<StoredEntry> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let entries = stored
+            .into_iter()
+            .map(|StoredEntry { mut item, job }| {
+                if item.state == DownloadState::Running {
+                    item.state = DownloadState::Queued;
+                }
+                Entry {
+                    item,
+                    job,
+                    cancel: CancellationToken::new(),
+                }
+            })
+            .collect();
+        Self {
+            entries: Mutex::new(entries),
+            worker_running: AtomicBool::new(false),
+            store: Some(path),
+        }
+    }
+
+    fn save(&self, entries: &[Entry]) {
+        let Some(path) = &self.store else { return };
+        let stored: Vec<StoredEntry> = entries
+            .iter()
+            .map(|e| StoredEntry {
+                item: e.item.clone(),
+                job: e.job.clone(),
+            })
+            .collect();
+        match serde_json::to_string_pretty(&stored) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(path, json) {
+                    tracing::warn!(path = %path.display(), error = %e, "failed to save download queue");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to serialise download queue"),
+        }
+    }
+
+    pub fn items(&self) -> Vec<DownloadItem> {
+        self.entries.lock().unwrap().iter().map(|e| e.item.clone()).collect()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| matches!(e.item.state, DownloadState::Queued | DownloadState::Running))
+    }
+
+    /// Add a job; an `Err` job is recorded as already failed so it still shows
+    /// up. Returns `None` if the destination is already queued or running.
+    pub fn push(&self, source: String, dest: &Path, job: Result<Job, String>) -> Option<DownloadItem> {
+        let (state, error) = match &job {
+            Ok(_) => (DownloadState::Queued, None),
+            Err(e) => (DownloadState::Failed, Some(e.clone())),
+        };
+        let item = DownloadItem {
+            id: crate::util::new_id("dl"),
+            source,
+            dest: dest.to_string_lossy().to_string(),
+            state,
+            downloaded_bytes: 0,
+            total_bytes: None,
+            error,
+            post_log: Vec::new(),
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if job.is_ok()
+            && entries.iter().any(|e| {
+                e.item.dest == item.dest && matches!(e.item.state, DownloadState::Queued | DownloadState::Running)
+            })
+        {
+            return None;
+        }
+        entries.push(Entry {
+            item: item.clone(),
+            job: job.ok(),
+            cancel: CancellationToken::new(),
+        });
+        self.save(&entries);
+        Some(item)
+    }
+
+    /// Cancel one item, or everything unfinished when `id` is `None`. Returns
+    /// the ids that were cancelled.
+    pub fn cancel(&self, id: Option<&str>) -> Vec<String> {
+        let mut cancelled = Vec::new();
+        let mut entries = self.entries.lock().unwrap();
+        for e in entries.iter_mut() {
+            if id.is_some_and(|id| id != e.item.id) {
+                continue;
+            }
+            match e.item.state {
+                DownloadState::Queued => {
+                    e.item.state = DownloadState::Cancelled;
+                    cancelled.push(e.item.id.clone());
+                }
+                // The worker notices the token and reports the state itself.
+                DownloadState::Running => e.cancel.cancel(),
+                _ => {}
+            }
+        }
+        self.save(&entries);
+        cancelled
+    }
+
+    /// Drop finished, failed and cancelled items from the list.
+    pub fn clear_finished(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| matches!(e.item.state, DownloadState::Queued | DownloadState::Running));
+        self.save(&entries);
+    }
+
+    fn next(&self) -> Option<(String, Job, CancellationToken)> {
+        let mut entries = self.entries.lock().unwrap();
+        let e = entries.iter_mut().find(|e| e.item.state == DownloadState::Queued)?;
+        e.item.state = DownloadState::Running;
+        Some((e.item.id.clone(), e.job.clone()?, e.cancel.clone()))
+    }
+
+    fn has_queued(&self) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.item.state == DownloadState::Queued)
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut DownloadItem)) {
+        if let Some(e) = self.entries.lock().unwrap().iter_mut().find(|e| e.item.id == id) {
+            f(&mut e.item);
+        }
+    }
+
+    fn log_step(&self, id: &str, message: String) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(e) = entries.iter_mut().find(|e| e.item.id == id) {
+            e.item.post_log.push(message);
+        }
+        self.save(&entries);
+    }
+
+    fn set_state(&self, id: &str, state: DownloadState, error: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(e) = entries.iter_mut().find(|e| e.item.id == id) {
+            e.item.state = state;
+            e.item.error = error;
+        }
+        self.save(&entries);
+    }
+}
+
+/// Pick up items restored by [`DownloadQueue::open`].
+pub fn resume(app: &AppHandle) {
+    if app.state::<AppState>().downloads.has_queued() {
+        tracing::info!("resuming queued downloads");
+        ensure_worker(app);
+    }
+}
+
+/// Start the worker unless it's already draining the queue.
+pub fn ensure_worker(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.downloads.worker_running.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let queue = &state.downloads;
+        let client = reqwest::Client::new();
+        loop {
+            let Some((id, job, cancel)) = queue.next() else {
+                queue.worker_running.store(false, Ordering::SeqCst);
+                // A push may have raced with the empty check; pick it up.
+                if queue.has_queued() && !queue.worker_running.swap(true, Ordering::SeqCst) {
+                    continue;
+                }
+                break;
+            };
+            emit_state(&app, queue, &id, DownloadState::Running, None);
+
+            // Finished model files go through the post-download hooks.
+            let mut finished_file = None;
+            let result = match job {
+                Job::File(mut req) => {
+                    // The token is never stored with the job; only the Hub gets it.
+                    if hf::is_hub_url(&req.url, &hf::endpoint()) {
+                        let token = state.config.lock().unwrap().hf_token.clone();
+                        req.token = Some(token).filter(|t| !t.is_empty());
+                    }
+                    let progress_app = app.clone();
+                    let progress_id = id.clone();
+                    engine::fetch(&client, &req, FetchOptions::default(), &cancel, move |p| {
+                        progress_app
+                            .state::<AppState>()
+                            .downloads
+                            .update(&progress_id, |item| {
+                                item.downloaded_bytes = p.downloaded_bytes;
+                                item.total_bytes = p.total_bytes;
+                            });
+                        let _ = progress_app.emit(
+                            DOWNLOAD_EVENT,
+                            DownloadEvent::Progress {
+                                id: progress_id.clone(),
+                                downloaded_bytes: p.downloaded_bytes,
+                                total_bytes: p.total_bytes,
+                                bytes_per_sec: p.bytes_per_sec,
+                            },
+                        );
+                    })
+                    .await
+                    .map(|_| finished_file = Some(req.dest))
+                }
+                Job::Git { url, dest } => git_clone(&url, &dest, &cancel).await,
+            };
+
+            match result {
+                Ok(()) => {
+                    tracing::info!(id = %id, "download finished");
+                    let hooks = match &finished_file {
+                        Some(dest) => hooks::run(&app, &id, dest).await,
+                        None => Ok(()),
+                    };
+                    match hooks {
+                        Ok(()) => emit_state(&app, queue, &id, DownloadState::Done, None),
+                        Err(e) => {
+                            tracing::warn!(id = %id, error = %e, "downloaded file failed verification");
+                            emit_state(&app, queue, &id, DownloadState::Failed, Some(e));
+                        }
+                    }
+                }
+                Err(FetchError::Cancelled) => emit_state(&app, queue, &id, DownloadState::Cancelled, None),
+                Err(FetchError::Failed(e)) => {
+                    tracing::warn!(id = %id, error = %e, "download failed");
+                    emit_state(&app, queue, &id, DownloadState::Failed, Some(e));
+                }
+            }
+        }
+    });
+}
+
+pub fn emit_state(app: &AppHandle, queue: &DownloadQueue, id: &str, state: DownloadState, error: Option<String>) {
+    queue.set_state(id, state, error.clone());
+    let _ = app.emit(
+        DOWNLOAD_EVENT,
+        DownloadEvent::State {
+            id: id.to_string(),
+            state,
+            error,
+        },
+    );
+}
+
+async fn git_clone(url: &str, dest: &Path, cancel: &CancellationToken) -> Result<(), FetchError> {
+    if let Some(dir) = dest.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut child = tokio::process::Command::new("git")
+        .arg("clone")
+        .arg(url)
+        .arg(dest)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    let stderr = child.stderr.take();
+    tokio::select! {
+        _ = cancel.cancelled() => {
+            let _ = child.kill().await;
+            Err(FetchError::Cancelled)
+        }
+        status = child.wait() => {
+            let status = status.map_err(|e| e.to_string())?;
+            if status.success() {
+                return Ok(());
+            }
+            let mut msg = String::new();
+            if let Some(mut s) = stderr {
+                use tokio::io::AsyncReadExt;
+                let _ = s.read_to_string(&mut msg).await;
+            }
+            Err(FetchError::Failed(format!("git clone failed: {}", msg.trim())))
+        }
+    }
+}
+
+/// What one target asks for, with its destination resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Planned {
+    Url { url: String, dest: PathBuf },
+    HfRepo { repo: String, dest_dir: PathBuf },
+    Git { url: String, dest: PathBuf },
+}
+
+/// Resolve where a parsed target lands under `models_root`. File names keep
+/// the historical `dw.sh` naming so re-downloads hit the same paths.
+pub fn plan(target: &DownloadTarget, models_root: &Path) -> Planned {
+    let base_dir = models_root.join(target.rel_dir());
+    match &target.source {
+        TargetSource::Git { url } => {
+            let repo_name = url.rsplit('/').next().unwrap_or("");
+            let repo_name = repo_name.strip_suffix(".git").unwrap_or(repo_name);
+            Planned::Git {
+                url: url.clone(),
+                dest: base_dir.join(repo_name),
+            }
+        }
+        TargetSource::Url { url } => {
+            let parts: Vec<&str> = url.split('/').collect();
+            let repo = parts.get(4).copied().unwrap_or("unknown");
+            let filename = parts.last().copied().unwrap_or("");
+            let filename = filename.split('?').next().unwrap_or(filename);
+
+            let target_name = if filename.starts_with("mmproj") {
+                let prefix = repo.to_lowercase().replace("-gguf", "");
+                format!("{}-{}", prefix, filename)
+            } else if let Some(last_dash) = filename.rfind('-') {
+                format!("{}{}", filename[..last_dash].to_lowercase(), &filename[last_dash..])
+            } else {
+                filename.to_lowercase()
+            };
+            Planned::Url {
+                url: url.clone(),
+                dest: base_dir.join(target_name),
+            }
+        }
+        TargetSource::HfRepo { repo } => {
+            let repo_name = repo.rsplit('/').next().unwrap_or("");
+            Planned::HfRepo {
+                repo: repo.clone(),
+                dest_dir: base_dir.join(repo_name.to_lowercase()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use shared::targets::parse_targets;
+
+    #[test]
+    fn plans_follow_categories_and_subfolders() {
+        let root = Path::new("/models");
+        let targets = parse_targets(
+            "# comment\ntext:\n- stable\nQwen/Qwen3-8B-GGUF\n\nvision:\n\
+             https://huggingface.co/org/Gemma-GGUF/resolve/main/mmproj-F16.gguf\n\
+             https://huggingface.co/org/Gemma-GGUF/resolve/main/Gemma-3-Q4_K_M.gguf?download=true\n\
+             https://github.com/user/tool.git\n",
+        )
+        .unwrap();
+        let plans: Vec<_> = targets.iter().map(|t| plan(t, root)).collect();
+        assert_eq!(
+            plans,
+            vec![
+                Planned::HfRepo {
+                    repo: "Qwen/Qwen3-8B-GGUF".into(),
+                    dest_dir: root.join("text/stable/qwen3-8b-gguf"),
+                },
+                Planned::Url {
+                    url: "https://huggingface.co/org/Gemma-GGUF/resolve/main/mmproj-F16.gguf".into(),
+                    dest: root.join("vision/gemma-mmproj-F16.gguf"),
+                },
+                Planned::Url {
+                    url: "https://huggingface.co/org/Gemma-GGUF/resolve/main/Gemma-3-Q4_K_M.gguf?download=true".into(),
+                    dest: root.join("vision/gemma-3-Q4_K_M.gguf"),
+                },
+                Planned::Git {
+                    url: "https://github.com/user/tool.git".into(),
+                    dest: root.join("vision/tool"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn queue_survives_restart() {
+        let tmp = TempDir::new("dlq");
+        let dir = &tmp.0;
+        let path = dir.join("download_queue.json");
+        let file_job = |name: &str| {
+            Job::File(FetchRequest {
+                url: format!("https://huggingface.co/o/r/resolve/main/{name}"),
+                dest: dir.join(name),
+                sha256: Some("ab".repeat(32)),
+                token: Some("hf_secret".into()),
+            })
+        };
+
+        let queue = DownloadQueue::open(path.clone());
+        queue.push("a".into(), &dir.join("a.gguf"), Ok(file_job("a.gguf"))).unwrap();
+        queue.push("b".into(), &dir.join("b.gguf"), Ok(file_job("b.gguf"))).unwrap();
+        assert!(
+            queue.push("a".into(), &dir.join("a.gguf"), Ok(file_job("a.gguf"))).is_none(),
+            "same destination isn't queued twice"
+        );
+        let (running, _, _) = queue.next().unwrap();
+        queue.set_state(&running, DownloadState::Running, None);
+        drop(queue);
+
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("hf_secret"));
+        let reopened = DownloadQueue::open(path);
+        let states: Vec<_> = reopened.items().into_iter().map(|i| i.state).collect();
+        assert_eq!(states, vec![DownloadState::Queued, DownloadState::Queued]);
+        let (_, job, _) = reopened.next().unwrap();
+        assert!(matches!(job, Job::File(req) if req.sha256.is_some() && req.token.is_none()));
+    }
+}
@@ -5,12 +5,19 @@
 //! commands (request/response) and events (streaming). See [[migration-conventions]].
 
 mod agent;
+mod chat;
 mod commands;
 mod config_io;
+mod download;
 mod logging;
+mod profiles;
+mod prompts;
+mod rag;
 mod state;
 mod util;
 pub mod library;
+#[cfg(test)]
+mod test_support;
 
 use state::AppState;
 
@@ -27,18 +34,42 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::config::get_config,
             commands::config::update_config,
+            commands::config::profile_list,
+            commands::config::profile_save,
+            commands::config::profile_apply,
+            commands::config::profile_delete,
             commands::chat::chat_list_models,
             commands::chat::chat_send,
-            commands::chat::chat_save_history,
-            commands::chat::chat_load_history,
+            commands::chat::chat_cancel,
+            commands::chat::chat_list_conversations,
+            commands::chat::chat_create_conversation,
+            commands::chat::chat_get_conversation,
+            commands::chat::chat_save_messages,
+            commands::chat::chat_update_conversation,
+            commands::chat::chat_delete_conversation,
+            commands::chat::chat_search,
+            commands::chat::chat_regenerate,
+            commands::chat::chat_edit_message,
+            commands::chat::chat_switch_branch,
+            commands::chat::chat_export,
+            commands::chat::chat_import,
+            commands::chat::chat_attach,
+            commands::chat::chat_list_attachments,
+            commands::chat::chat_detach,
             commands::agent::agent_start,
             commands::agent::agent_approve,
             commands::agent::agent_cancel,
             commands::agent::agent_status,
+            commands::agent::agent_forget_approvals,
+            commands::agent::agent_runs_list,
+            commands::agent::agent_run_get,
+            commands::agent::agent_run_delete,
+            commands::agent::agent_resume,
             commands::server::server_start,
             commands::server::server_stop,
             commands::server::server_status,
             commands::server::pick_path,
+            commands::server::pick_save_path,
             commands::window::win_minimize,
             commands::window::win_toggle_maximize,
             commands::window::win_close,
@@ -48,7 +79,18 @@ pub fn run() {
             commands::store::notes_store_set,
             commands::store::todos_get,
             commands::store::todos_set,
+            commands::store::prompt_list,
+            commands::store::prompt_save,
+            commands::store::prompt_revert,
+            commands::store::prompt_delete,
+            commands::store::prompt_render,
             commands::remaining::library_get_index,
+            commands::remaining::library_query,
+            commands::remaining::library_storage_report,
+            commands::remaining::library_move_model,
+            commands::remaining::library_delete_prepare,
+            commands::remaining::library_delete_confirm,
+            commands::remaining::library_delete_cancel,
             commands::remaining::library_scan,
             commands::remaining::library_enrich_single,
             commands::remaining::library_enrich_all,
@@ -65,6 +107,9 @@ pub fn run() {
             commands::remaining::download_start,
             commands::remaining::download_cancel,
             commands::remaining::download_status,
+            commands::remaining::download_clear_finished,
+            commands::remaining::hf_list_repo,
+            commands::remaining::hf_enqueue_files,
             commands::remaining::instances_detect,
             commands::remaining::mcp_get_registry,
             commands::remaining::mcp_save_registry,
@@ -76,6 +121,9 @@ pub fn run() {
             commands::remaining::compare_run_bench,
             commands::remaining::compare_run_eval,
             commands::remaining::memory_get,
+            commands::remaining::memory_review,
+            commands::remaining::memory_delete,
+            commands::remaining::memory_consolidate,
             commands::remaining::memory_clear,
             commands::remaining::server_suggest_optimizations,
             commands::remaining::obsidian_memories_get,
@@ -84,6 +132,13 @@ pub fn run() {
             commands::remaining::list_skills_and_agents,
         ])
         .setup(|app| {
+            download::resume(app.handle());
+            // Runs still marked running were cut off when the app last closed.
+            match agent::runs::RunStore::open(&config_io::config_dir()).and_then(|s| s.mark_interrupted()) {
+                Ok(0) => {}
+                Ok(n) => tracing::info!(count = n, "marked interrupted agent runs"),
+                Err(e) => tracing::warn!("failed to check for interrupted agent runs: {e}"),
+            }
             tracing::info!("llama-manager backend ready");
             commands::remaining::spawn_calendar_scheduler(app.handle().clone());
             Ok(())
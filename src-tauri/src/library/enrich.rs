@@ -0,0 +1,801 @@
+//! Model metadata enrichment.
+//!
+//! An [`EnrichmentChain`] runs a list of [`EnrichmentProvider`]s in order,
+//! each filling whatever fields the earlier ones left empty, and stops as soon
+//! as the metadata is complete. The chain is built from
+//! `ServerConfig::enrichment_providers`:
+//!
+//! - `model_card` — parses a downloaded repo's `README.md` (works offline).
+//! - `huggingface` — the Hub API (`HF_ENDPOINT` overrides the host).
+//! - `searxng_llm` — web search summarised by the local llama-server.
+//!
+//! Results are cached in `enrichment_cache.json` so re-scans and bulk
+//! enrichment don't hit the network again for models already resolved. A
+//! model nothing was found for is cached as a miss with a retry-after time:
+//! `not_found` when every provider answered, `pending_enrichment` when some
+//! couldn't be reached.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shared::ipc::ScannedModel;
+use shared::ServerConfig;
+
+use super::{clean_query_name, format_size, get_target_model_with_host, map_pipeline_tag};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a model no provider knows is left alone.
+const NOT_FOUND_RETRY_SECS: i64 = 7 * 24 * 3600;
+/// How long to wait before asking providers that were unreachable again.
+const PENDING_RETRY_SECS: i64 = 3600;
+pub(crate) const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Metadata found by a provider. `None` means "not found here".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Enrichment {
+    pub clean_name: Option<String>,
+    pub use_case: Option<String>,
+    pub hf_link: Option<String>,
+    pub github_link: Option<String>,
+    /// Parameter count label such as `8B`.
+    pub size_label: Option<String>,
+}
+
+impl Enrichment {
+    /// Fill the fields still missing here from `other`.
+    fn merge(&mut self, other: Enrichment) {
+        fn fill(slot: &mut Option<String>, value: Option<String>) {
+            if slot.is_none() {
+                *slot = value.filter(|v| !v.trim().is_empty());
+            }
+        }
+        fill(&mut self.clean_name, other.clean_name);
+        fill(&mut self.use_case, other.use_case);
+        fill(&mut self.hf_link, other.hf_link);
+        fill(&mut self.github_link, other.github_link);
+        fill(&mut self.size_label, other.size_label);
+    }
+
+    /// Name, use-case and HF link are what the library view needs; the
+    /// GitHub link and size label are nice-to-have and don't keep the chain
+    /// going. The scanner's filename-derived name counts as a name.
+    pub fn is_complete(&self, model: &ScannedModel) -> bool {
+        let has_name = self.clean_name.is_some() || !(model.clean_name.is_empty() || model.clean_name == "Unknown");
+        has_name && self.use_case.is_some() && self.hf_link.is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Enrichment::default()
+    }
+
+    /// Write the found fields onto `model`.
+    pub fn apply(&self, model: &mut ScannedModel) {
+        if let Some(name) = &self.clean_name {
+            model.clean_name = name.clone();
+        }
+        if let Some(use_case) = &self.use_case {
+            model.use_case = use_case.clone();
+        }
+        if let Some(link) = &self.hf_link {
+            model.hf_link = link.clone();
+        }
+        if let Some(link) = &self.github_link {
+            model.github_link = link.clone();
+        }
+        if let Some(size) = &self.size_label {
+            model.size_info = format!("{} ({})", format_size(model.size_bytes), size);
+        }
+    }
+}
+
+#[async_trait]
+pub trait EnrichmentProvider: Send + Sync {
+    /// Config key, also used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Look up metadata for `model`. `Err` means the provider couldn't run
+    /// (offline, server down); "nothing found" is an empty `Ok`.
+    async fn enrich(&self, model: &ScannedModel) -> Result<Enrichment, String>;
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .user_agent(concat!("llama-manager/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+// ── Model card (offline) ─────────────────────────────────────────────────────
+
+/// Reads the `README.md` model card shipped next to a downloaded model — the
+/// repo folder itself, or its parent when quants live in subfolders.
+pub struct ModelCardProvider;
+
+#[async_trait]
+impl EnrichmentProvider for ModelCardProvider {
+    fn name(&self) -> &'static str {
+        "model_card"
+    }
+
+    async fn enrich(&self, model: &ScannedModel) -> Result<Enrichment, String> {
+        let Some(readme) = find_model_card(Path::new(&model.path)) else {
+            return Ok(Enrichment::default());
+        };
+        let text = fs::read_to_string(&readme).map_err(|e| e.to_string())?;
+        let mut found = parse_model_card(&text);
+        if !model.repo_id.is_empty() && !model.repo_id.contains(':') {
+            found.hf_link = Some(format!("https://huggingface.co/{}", model.repo_id));
+        }
+        Ok(found)
+    }
+}
+
+fn find_model_card(model_path: &Path) -> Option<PathBuf> {
+    model_path
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .flat_map(|dir| ["README.md", "readme.md", "MODEL_CARD.md"].map(|f| dir.join(f)))
+        .find(|p| p.is_file())
+}
+
+/// Extract metadata from a Hugging Face style model card: YAML front matter
+/// (`pipeline_tag`, `tags`, `model_name`) plus the first heading and the first
+/// GitHub link of the body. The front matter is only ever flat keys and simple
+/// lists, so it is read line by line rather than with a YAML parser.
+pub fn parse_model_card(text: &str) -> Enrichment {
+    let mut found = Enrichment::default();
+    let (front, body) = split_front_matter(text);
+
+    let mut tags: Vec<String> = Vec::new();
+    let mut current_key = String::new();
+    for line in front.lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if current_key == "tags" {
+                tags.push(unquote(item));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        current_key = key.trim().to_string();
+        let value = unquote(value);
+        if value.is_empty() {
+            continue;
+        }
+        match current_key.as_str() {
+            "pipeline_tag" => found.use_case = Some(map_pipeline_tag(&value)),
+            "model_name" => found.clean_name = Some(value),
+            "tags" => tags.extend(
+                value
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .split(',')
+                    .map(unquote),
+            ),
+            _ => {}
+        }
+    }
+    if found.use_case.is_none() {
+        found.use_case = tags
+            .iter()
+            .find(|t| KNOWN_PIPELINE_TAGS.contains(&t.as_str()))
+            .map(|t| map_pipeline_tag(t));
+    }
+
+    for line in body.lines() {
+        let line = line.trim();
+        if found.clean_name.is_none() {
+            if let Some(heading) = line.strip_prefix("# ") {
+                let heading = heading
+                    .trim()
+                    .trim_start_matches("Model Card for ")
+                    .trim_start_matches("Model card for ")
+                    .trim();
+                if !heading.is_empty() && !heading.eq_ignore_ascii_case("model card") {
+                    found.clean_name = Some(heading.to_string());
+                }
+            }
+        }
+        if found.github_link.is_none() {
+            if let Some(start) = line.find("https://github.com/") {
+                let url: String = line[start..]
+                    .chars()
+                    .take_while(|c| !c.is_whitespace() && !matches!(c, ')' | ']' | '"' | '>' | '<'))
+                    .collect();
+                found.github_link = Some(url.trim_end_matches(['.', ',']).to_string());
+            }
+        }
+    }
+
+    found.size_label = found
+        .clean_name
+        .as_deref()
+        .and_then(param_count_label);
+    found
+}
+
+/// Pipeline tags that sometimes appear only in `tags:`.
+const KNOWN_PIPELINE_TAGS: &[&str] = &[
+    "text-generation",
+    "text-to-image",
+    "image-text-to-text",
+    "automatic-speech-recognition",
+    "text-to-speech",
+    "feature-extraction",
+    "sentence-similarity",
+];
+
+fn split_front_matter(text: &str) -> (&str, &str) {
+    let trimmed = text.trim_start_matches('\u{feff}');
+    if let Some(rest) = trimmed.strip_prefix("---") {
+        if let Some(end) = rest.find("\n---") {
+            let body = &rest[end + 4..];
+            return (&rest[..end], body);
+        }
+    }
+    ("", trimmed)
+}
+
+fn unquote(s: &str) -> String {
+    s.trim().trim_matches(|c| c == '"' || c == '\'').trim().to_string()
+}
+
+/// `Qwen3 8B Instruct` → `8B`; `bge-small-335M` → `335M`.
+fn param_count_label(name: &str) -> Option<String> {
+    name.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .find(|tok| {
+            let Some(num) = tok.strip_suffix(['B', 'b', 'M', 'm']) else {
+                return false;
+            };
+            !num.is_empty() && num.chars().all(|c| c.is_ascii_digit() || c == '.')
+                && num.chars().next().is_some_and(|c| c.is_ascii_digit())
+        })
+        .map(|tok| tok.to_uppercase())
+}
+
+// ── Hugging Face API ─────────────────────────────────────────────────────────
+
+#[derive(Deserialize, Clone)]
+struct HfModelInfo {
+    id: Option<String>,
+    pipeline_tag: Option<String>,
+}
+
+/// Queries the Hub API: a direct lookup when the repo id is known (store
+/// scans), otherwise a name search falling back to `family version`.
+pub struct HfApiProvider {
+    pub base_url: String,
+}
+
+impl HfApiProvider {
+    pub fn from_env() -> Self {
+        Self {
+            base_url: std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_string()),
+        }
+    }
+
+    fn link(&self, id: &str) -> String {
+        format!("https://huggingface.co/{}", id)
+    }
+
+    async fn search(&self, client: &reqwest::Client, query: &str) -> Result<Option<HfModelInfo>, String> {
+        let url = format!("{}/api/models", self.base_url.trim_end_matches('/'));
+        let models = client
+            .get(&url)
+            .query(&[("search", query), ("limit", "10")])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .json::<Vec<HfModelInfo>>()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(models.into_iter().find(|m| m.pipeline_tag.is_some()))
+    }
+}
+
+#[async_trait]
+impl EnrichmentProvider for HfApiProvider {
+    fn name(&self) -> &'static str {
+        "huggingface"
+    }
+
+    async fn enrich(&self, model: &ScannedModel) -> Result<Enrichment, String> {
+        let client = http_client()?;
+        let mut found = Enrichment::default();
+
+        // Ollama references (`llama3:8b`) aren't Hub repo ids.
+        if !model.repo_id.is_empty() && !model.repo_id.contains(':') {
+            let url = format!("{}/api/models/{}", self.base_url.trim_end_matches('/'), model.repo_id);
+            let res = client.get(&url).send().await.map_err(|e| e.to_string())?;
+            if res.status().is_success() {
+                let info = res.json::<HfModelInfo>().await.map_err(|e| e.to_string())?;
+                found.use_case = info.pipeline_tag.as_deref().map(map_pipeline_tag);
+                found.hf_link = Some(self.link(&model.repo_id));
+                if found.use_case.is_some() {
+                    return Ok(found);
+                }
+            }
+        }
+
+        let mut hit = self.search(&client, &clean_query_name(&model.filename)).await?;
+        if hit.is_none() && !model.family.is_empty() {
+            hit = self.search(&client, &format!("{} {}", model.family, model.version)).await?;
+        }
+        if let Some(m) = hit {
+            found.merge(Enrichment {
+                use_case: m.pipeline_tag.as_deref().map(map_pipeline_tag),
+                hf_link: m.id.as_deref().map(|id| self.link(id)),
+                ..Default::default()
+            });
+        }
+        Ok(found)
+    }
+}
+
+// ── SearXNG + LLM summariser ─────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct SearxResult {
+    title: Option<String>,
+    url: Option<String>,
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearxResponse {
+    results: Option<Vec<SearxResult>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LlmResponse {
+    name: String,
+    use_case: String,
+    size: String,
+    hf_link: String,
+    github_link: String,
+}
+
+/// Searches the web via SearXNG and asks the local llama-server to extract
+/// metadata from the top results.
+pub struct LlmSummariserProvider {
+    pub searxng_url: String,
+    pub llm_host: String,
+    pub llm_port: u16,
+}
+
+#[async_trait]
+impl EnrichmentProvider for LlmSummariserProvider {
+    fn name(&self) -> &'static str {
+        "searxng_llm"
+    }
+
+    async fn enrich(&self, model: &ScannedModel) -> Result<Enrichment, String> {
+        let client = http_client()?;
+        let query = format!("{} model Hugging Face github", clean_query_name(&model.filename));
+        let search_url = format!("{}/search", self.searxng_url.trim_end_matches('/'));
+        let searx = client
+            .get(&search_url)
+            .query(&[("q", query.as_str()), ("format", "json")])
+            .send()
+            .await
+            .map_err(|e| format!("SearXNG unreachable: {}", e))?
+            .json::<SearxResponse>()
+            .await
+            .map_err(|e| format!("Bad SearXNG response: {}", e))?;
+        let results = searx.results.unwrap_or_default();
+        if results.is_empty() {
+            return Ok(Enrichment::default());
+        }
+
+        let mut search_results_text = String::new();
+        for (i, item) in results.iter().take(5).enumerate() {
+            search_results_text.push_str(&format!(
+                "Result {}:\nTitle: {}\nURL: {}\nSnippet: {}\n\n",
+                i + 1,
+                item.title.as_deref().unwrap_or_default(),
+                item.url.as_deref().unwrap_or_default(),
+                item.content.as_deref().unwrap_or_default()
+            ));
+        }
+
+        let prompt = format!(
+            "You are a model metadata extractor. Your task is to analyze search results and extract accurate metadata about the AI model \"{}\".\n\n\
+            Search Results:\n\
+            {}\n\n\
+            Extract the following fields:\n\
+            1. Official Clean Name (e.g. \"BGE-M3\" or \"Qwen 3.5 Coder\")\n\
+            2. Primary Use-case (e.g. \"Text Embedding\", \"Text Generation\", \"ASR\", \"TTS\", \"Image Generation\", \"OCR\", \"Video Generation\", etc.)\n\
+            3. Parameter Size / File Info (e.g. \"8B\", \"300M\", \"24B\", or \"Unknown\")\n\
+            4. Hugging Face Link (the main Hugging Face repository URL, e.g. https://huggingface.co/...)\n\
+            5. GitHub Link (the source code repository URL, e.g. https://github.com/...)\n\n\
+            Respond ONLY with a JSON object in this format:\n\
+            {{\n\
+              \"name\": \"...\",\n\
+              \"use_case\": \"...\",\n\
+              \"size\": \"...\",\n\
+              \"hf_link\": \"...\",\n\
+              \"github_link\": \"...\"\n\
+            }}\n\
+            Do not include any other text, markdown formatting, or explanation. Just the raw JSON object.",
+            model.filename, search_results_text
+        );
+
+        let target_model = get_target_model_with_host(&self.llm_host, self.llm_port, &model.filename).await;
+        let llm_url = format!("http://{}:{}/v1/chat/completions", self.llm_host, self.llm_port);
+        let payload = serde_json::json!({
+            "model": target_model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "temperature": 0.1,
+            "response_format": { "type": "json_object" }
+        });
+
+        let res = client
+            .post(&llm_url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| format!("LLM unreachable: {}", e))?;
+        if !res.status().is_success() {
+            return Err(format!("LLM returned {}", res.status()));
+        }
+        let body: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        let content = body["choices"][0]["message"]["content"].as_str().unwrap_or_default();
+        let Some(parsed) = parse_json_from_llm(content) else {
+            return Ok(Enrichment::default());
+        };
+
+        // The prompt's template values ("...") leak through on weak models.
+        let keep = |s: String| Some(s).filter(|v| !v.is_empty() && v != "..." && v != "Unknown");
+        Ok(Enrichment {
+            clean_name: keep(parsed.name),
+            use_case: keep(parsed.use_case),
+            hf_link: keep(parsed.hf_link),
+            github_link: keep(parsed.github_link),
+            size_label: keep(parsed.size),
+        })
+    }
+}
+
+fn parse_json_from_llm(text: &str) -> Option<LlmResponse> {
+    let trimmed = text.trim();
+    if let Ok(res) = serde_json::from_str::<LlmResponse>(trimmed) {
+        return Some(res);
+    }
+
+    // Try fenced blocks, then the outermost braces.
+    for fence in ["```json", "```"] {
+        if let Some(start) = trimmed.find(fence) {
+            let sub = &trimmed[start + fence.len()..];
+            if let Some(end) = sub.find("```") {
+                if let Ok(res) = serde_json::from_str::<LlmResponse>(sub[..end].trim()) {
+                    return Some(res);
+                }
+            }
+        }
+    }
+    let start = trimmed.find('{')?;
+    let end = trimmed.rfind('}')?;
+    serde_json::from_str::<LlmResponse>(trimmed.get(start..=end)?).ok()
+}
+
+// ── Chain + cache ────────────────────────────────────────────────────────────
+
+pub struct EnrichmentChain {
+    providers: Vec<Box<dyn EnrichmentProvider>>,
+    cache_path: PathBuf,
+}
+
+impl EnrichmentChain {
+    pub fn new(providers: Vec<Box<dyn EnrichmentProvider>>, cache_path: PathBuf) -> Self {
+        Self { providers, cache_path }
+    }
+
+    /// Build the chain named by `cfg.enrichment_providers`; unknown names are skipped.
+    pub fn from_config(cfg: &ServerConfig, config_dir: &Path) -> Self {
+        let providers = cfg
+            .enrichment_providers
+            .iter()
+            .filter_map(|name| -> Option<Box<dyn EnrichmentProvider>> {
+                match name.as_str() {
+                    "model_card" => Some(Box::new(ModelCardProvider)),
+                    "huggingface" => Some(Box::new(HfApiProvider::from_env())),
+                    "searxng_llm" => Some(Box::new(LlmSummariserProvider {
+                        searxng_url: cfg.searxng_url.clone(),
+                        llm_host: "127.0.0.1".to_string(),
+                        llm_port: cfg.port,
+                    })),
+                    other => {
+                        tracing::warn!(provider = other, "unknown enrichment provider, skipping");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self::new(providers, config_dir.join("enrichment_cache.json"))
+    }
+
+    /// Enrich `model` in place. It is marked `enriched` only when some
+    /// provider found something; otherwise `not_found` or, when a provider
+    /// errored, `pending_enrichment`, and the chain isn't asked again before
+    /// the retry-after time. A cached result is used unless `refresh` is set.
+    /// Fails only when every provider errored.
+    pub async fn enrich(&self, model: &mut ScannedModel, refresh: bool) -> Result<(), String> {
+        let key = cache_key(model);
+        let now = chrono::Utc::now().timestamp();
+        if !refresh {
+            match self.load_cache().get(&key) {
+                Some(CacheEntry::Found(hit)) => {
+                    hit.apply(model);
+                    model.status = "enriched".to_string();
+                    return Ok(());
+                }
+                Some(CacheEntry::Miss { status, retry_after }) if *retry_after > now => {
+                    model.status = status.clone();
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        let mut found = Enrichment::default();
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            match provider.enrich(model).await {
+                Ok(e) => found.merge(e),
+                Err(e) => {
+                    tracing::warn!(provider = provider.name(), model = %model.filename, "enrichment failed: {}", e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                }
+            }
+            if found.is_complete(model) {
+                break;
+            }
+        }
+
+        if found.is_empty() && !errors.is_empty() && errors.len() == self.providers.len() {
+            return Err(errors.join("; "));
+        }
+
+        let entry = if !found.is_empty() {
+            found.apply(model);
+            model.status = "enriched".to_string();
+            CacheEntry::Found(found)
+        } else if errors.is_empty() {
+            model.status = "not_found".to_string();
+            CacheEntry::Miss { status: model.status.clone(), retry_after: now + NOT_FOUND_RETRY_SECS }
+        } else {
+            model.status = "pending_enrichment".to_string();
+            CacheEntry::Miss { status: model.status.clone(), retry_after: now + PENDING_RETRY_SECS }
+        };
+        let mut cache = self.load_cache();
+        cache.insert(key, entry);
+        self.save_cache(&cache);
+        Ok(())
+    }
+
+    fn load_cache(&self) -> HashMap<String, CacheEntry> {
+        fs::read_to_string(&self.cache_path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_cache(&self, cache: &HashMap<String, CacheEntry>) {
+        match serde_json::to_string_pretty(cache) {
+            Ok(json) => {
+                if let Err(e) = fs::write(&self.cache_path, json) {
+                    tracing::warn!("failed to write enrichment cache: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("failed to serialize enrichment cache: {}", e),
+        }
+    }
+}
+
+/// What the cache holds for a model. Entries written before misses were
+/// cached are plain [`Enrichment`]s, which still read as `Found`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CacheEntry {
+    /// Nothing found; `status` is what the model was marked, and the
+    /// providers are asked again after `retry_after` (unix seconds).
+    Miss { status: String, retry_after: i64 },
+    Found(Enrichment),
+}
+
+/// Store scans give a stable upstream id; plain files fall back to name + size
+/// so a re-downloaded file with the same name but different weights misses.
+fn cache_key(model: &ScannedModel) -> String {
+    if model.repo_id.is_empty() {
+        format!("{}:{}", model.filename, model.size_bytes)
+    } else {
+        format!("{}/{}", model.repo_id, model.filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer, TempDir};
+
+    fn model(filename: &str) -> ScannedModel {
+        crate::library::scanned_model(Path::new(&format!("/nonexistent/{filename}")), 1024)
+    }
+
+    #[test]
+    fn model_card_front_matter_and_body() {
+        let card = "---\nlicense: apache-2.0\npipeline_tag: text-generation\ntags:\n  - gguf\n  - qwen\n---\n\n\
+                    # Qwen3 8B Instruct\n\nCode at [GitHub](https://github.com/QwenLM/Qwen3).\n";
+        let e = parse_model_card(card);
+        assert_eq!(e.clean_name.as_deref(), Some("Qwen3 8B Instruct"));
+        assert_eq!(e.use_case.as_deref(), Some("Text Generation"));
+        assert_eq!(e.github_link.as_deref(), Some("https://github.com/QwenLM/Qwen3"));
+        assert_eq!(e.size_label.as_deref(), Some("8B"));
+        assert_eq!(e.hf_link, None);
+    }
+
+    #[test]
+    fn model_card_pipeline_from_tags_list() {
+        let e = parse_model_card("---\ntags: [gguf, feature-extraction]\n---\n# Model Card for bge-m3\n");
+        assert_eq!(e.use_case.as_deref(), Some("Text Embedding"));
+        assert_eq!(e.clean_name.as_deref(), Some("bge-m3"));
+    }
+
+    #[tokio::test]
+    async fn hf_provider_uses_repo_lookup_then_search() {
+        let stub = StubServer::start(|req| match req.path() {
+            "/api/models/Qwen/Qwen3-8B-GGUF" => StubResponse::json(serde_json::json!({
+                "id": "Qwen/Qwen3-8B-GGUF", "pipeline_tag": "text-generation"
+            })),
+            "/api/models" => StubResponse::json(serde_json::json!([
+                { "id": "no/tag" },
+                { "id": "BAAI/bge-m3", "pipeline_tag": "feature-extraction" }
+            ])),
+            _ => StubResponse::not_found(),
+        })
+        .await;
+        let provider = HfApiProvider { base_url: stub.base_url.clone() };
+
+        let mut m = model("Qwen3-8B-Q4_K_M.gguf");
+        m.repo_id = "Qwen/Qwen3-8B-GGUF".into();
+        let e = provider.enrich(&m).await.unwrap();
+        assert_eq!(e.use_case.as_deref(), Some("Text Generation"));
+        assert_eq!(e.hf_link.as_deref(), Some("https://huggingface.co/Qwen/Qwen3-8B-GGUF"));
+
+        let e = provider.enrich(&model("bge-m3-f16.gguf")).await.unwrap();
+        assert_eq!(e.use_case.as_deref(), Some("Text Embedding"));
+        assert_eq!(e.hf_link.as_deref(), Some("https://huggingface.co/BAAI/bge-m3"));
+        let last = stub.requests().pop().unwrap();
+        assert_eq!(last.method, "GET");
+        assert!(last.target.contains("search=bge"));
+        assert!(last.header("user-agent").unwrap().starts_with("llama-manager/"));
+    }
+
+    #[tokio::test]
+    async fn llm_summariser_parses_fenced_reply() {
+        let stub = StubServer::start(|req| match req.path() {
+            "/search" => StubResponse::json(serde_json::json!({
+                "results": [{ "title": "Phi-4", "url": "https://huggingface.co/microsoft/phi-4", "content": "14B" }]
+            })),
+            "/v1/models" => StubResponse::json(serde_json::json!({ "data": [{ "id": "tiny" }] })),
+            "/v1/chat/completions" => StubResponse::json(serde_json::json!({
+                "choices": [{ "message": { "content": "```json\n{\"name\":\"Phi-4\",\"use_case\":\"Text Generation\",\"size\":\"14B\",\"hf_link\":\"https://huggingface.co/microsoft/phi-4\",\"github_link\":\"...\"}\n```" } }]
+            })),
+            _ => StubResponse::not_found(),
+        })
+        .await;
+        let port = stub.base_url.rsplit(':').next().unwrap().parse().unwrap();
+        let provider = LlmSummariserProvider {
+            searxng_url: stub.base_url.clone(),
+            llm_host: "127.0.0.1".into(),
+            llm_port: port,
+        };
+
+        let e = provider.enrich(&model("phi-4-Q4_K_M.gguf")).await.unwrap();
+        assert_eq!(e.clean_name.as_deref(), Some("Phi-4"));
+        assert_eq!(e.size_label.as_deref(), Some("14B"));
+        assert_eq!(e.github_link, None);
+        let chat = stub.requests().into_iter().find(|r| r.path() == "/v1/chat/completions").unwrap();
+        assert!(String::from_utf8_lossy(&chat.body).contains("\"model\":\"tiny\""));
+    }
+
+    #[tokio::test]
+    async fn chain_stops_when_complete_and_caches() {
+        let stub = StubServer::start(|_| {
+            StubResponse::json(serde_json::json!([{ "id": "google/gemma-3-4b-it", "pipeline_tag": "image-text-to-text" }]))
+        })
+        .await;
+        let dir = TempDir::new("enrich");
+        let cache = dir.0.join("enrichment.json");
+        let chain = EnrichmentChain::new(
+            vec![
+                Box::new(HfApiProvider { base_url: stub.base_url.clone() }),
+                // Unreachable; never consulted because HF plus the scanner's
+                // clean name already complete the metadata.
+                Box::new(LlmSummariserProvider {
+                    searxng_url: "http://127.0.0.1:9".into(),
+                    llm_host: "127.0.0.1".into(),
+                    llm_port: 9,
+                }),
+            ],
+            cache.clone(),
+        );
+
+        let mut m = model("gemma-3-4b-it-Q4_K_M.gguf");
+        chain.enrich(&mut m, false).await.unwrap();
+        assert_eq!(m.status, "enriched");
+        assert_eq!(m.use_case, "Image-Text-to-Text");
+        assert_eq!(m.hf_link, "https://huggingface.co/google/gemma-3-4b-it");
+        let served = stub.requests().len();
+
+        let mut again = model("gemma-3-4b-it-Q4_K_M.gguf");
+        chain.enrich(&mut again, false).await.unwrap();
+        assert_eq!(again.hf_link, m.hf_link);
+        assert_eq!(stub.requests().len(), served, "second run must come from the cache");
+    }
+
+    #[tokio::test]
+    async fn chain_fails_only_when_every_provider_errors() {
+        let dir = TempDir::new("enrich");
+        let cache = dir.0.join("enrichment.json");
+        let chain = EnrichmentChain::new(
+            vec![Box::new(HfApiProvider { base_url: "http://127.0.0.1:9".into() })],
+            cache.clone(),
+        );
+        assert!(chain.enrich(&mut model("offline-7B.gguf"), false).await.is_err());
+        assert!(!cache.exists());
+
+        // The model card answered, with nothing: retried later, not enriched.
+        let chain = EnrichmentChain::new(
+            vec![
+                Box::new(HfApiProvider { base_url: "http://127.0.0.1:9".into() }),
+                Box::new(ModelCardProvider),
+            ],
+            cache.clone(),
+        );
+        let mut m = model("offline-7B.gguf");
+        chain.enrich(&mut m, false).await.unwrap();
+        assert_eq!(m.status, "pending_enrichment");
+    }
+
+    #[tokio::test]
+    async fn a_model_nobody_knows_is_not_found_until_the_retry_time() {
+        let stub = StubServer::start(|_| StubResponse::json(serde_json::json!([]))).await;
+        let dir = TempDir::new("enrich");
+        let cache = dir.0.join("enrichment.json");
+        let chain = EnrichmentChain::new(
+            vec![Box::new(HfApiProvider { base_url: stub.base_url.clone() }), Box::new(ModelCardProvider)],
+            cache.clone(),
+        );
+
+        let mut m = model("homebrew-merge-Q4_K_M.gguf");
+        chain.enrich(&mut m, false).await.unwrap();
+        assert_eq!(m.status, "not_found");
+        assert!(m.hf_link.is_empty());
+        let served = stub.requests().len();
+        assert!(served > 0);
+
+        let mut again = model("homebrew-merge-Q4_K_M.gguf");
+        chain.enrich(&mut again, false).await.unwrap();
+        assert_eq!(again.status, "not_found");
+        assert_eq!(stub.requests().len(), served, "not asked again before the retry time");
+
+        chain.enrich(&mut again, true).await.unwrap();
+        assert!(stub.requests().len() > served, "a refresh asks again");
+
+        // Once the retry time has passed, the providers are asked again.
+        let served = stub.requests().len();
+        let key = cache_key(&again);
+        let mut entries = chain.load_cache();
+        entries.insert(key, CacheEntry::Miss { status: "not_found".into(), retry_after: 0 });
+        chain.save_cache(&entries);
+        chain.enrich(&mut again, false).await.unwrap();
+        assert!(stub.requests().len() > served);
+    }
+}
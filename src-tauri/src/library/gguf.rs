@@ -0,0 +1,295 @@
+//! Structural check for GGUF files: header, metadata and tensor table, plus
+//! that every tensor's data fits inside the file. Used to catch truncated or
+//! mislabelled downloads before they reach the library.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+const DEFAULT_ALIGNMENT: u64 = 32;
+/// Longest string we accept in the header; real keys and values are far shorter.
+const MAX_STRING_BYTES: u64 = 64 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgufInfo {
+    pub version: u32,
+    pub tensor_count: u64,
+    pub kv_count: u64,
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+}
+
+enum Value {
+    Str(String),
+    Uint(u64),
+    Other,
+}
+
+struct Reader<R> {
+    inner: R,
+    version: u32,
+    /// Bytes consumed so far, i.e. the offset of the next read.
+    pos: u64,
+    file_len: u64,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0u8; N];
+        self.inner
+            .read_exact(&mut buf)
+            .map_err(|_| format!("file ends inside the header (offset {})", self.pos))?;
+        self.pos += N as u64;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.bytes::<4>().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.bytes::<8>().map(u64::from_le_bytes)
+    }
+
+    /// Counts and lengths were 32-bit in version 1.
+    fn len(&mut self) -> Result<u64, String> {
+        if self.version == 1 {
+            self.u32().map(u64::from)
+        } else {
+            self.u64()
+        }
+    }
+
+    fn skip(&mut self, n: u64) -> Result<(), String> {
+        if self.pos + n > self.file_len {
+            return Err(format!("file ends inside the header (offset {})", self.pos));
+        }
+        let copied = std::io::copy(&mut (&mut self.inner).take(n), &mut std::io::sink())
+            .map_err(|e| e.to_string())?;
+        self.pos += copied;
+        if copied < n {
+            return Err(format!("file ends inside the header (offset {})", self.pos));
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        if len > MAX_STRING_BYTES {
+            return Err(format!("implausible string length {len} at offset {}", self.pos));
+        }
+        if self.pos + len > self.file_len {
+            return Err(format!("file ends inside the header (offset {})", self.pos));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.inner
+            .read_exact(&mut buf)
+            .map_err(|_| format!("file ends inside the header (offset {})", self.pos))?;
+        self.pos += len;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    fn value(&mut self, ty: u32) -> Result<Value, String> {
+        Ok(match ty {
+            0 | 1 | 7 => Value::Uint(u64::from(self.bytes::<1>()?[0])),
+            2 | 3 => Value::Uint(u64::from(u16::from_le_bytes(self.bytes::<2>()?))),
+            4 | 5 => Value::Uint(u64::from(self.u32()?)),
+            10 | 11 => Value::Uint(self.u64()?),
+            6 => self.skip(4).map(|_| Value::Other)?,
+            12 => self.skip(8).map(|_| Value::Other)?,
+            8 => Value::Str(self.string()?),
+            9 => {
+                let elem = self.u32()?;
+                let count = self.len()?;
+                // Every element takes at least one byte, which bounds `count`.
+                if count > self.file_len - self.pos {
+                    return Err(format!("implausible array length {count} at offset {}", self.pos));
+                }
+                match fixed_size(elem) {
+                    Some(size) => self.skip(count * size)?,
+                    None => {
+                        for _ in 0..count {
+                            self.value(elem)?;
+                        }
+                    }
+                }
+                Value::Other
+            }
+            _ => return Err(format!("unknown metadata type {ty} at offset {}", self.pos)),
+        })
+    }
+}
+
+/// Size of a fixed-width metadata value; strings and arrays have none.
+fn fixed_size(ty: u32) -> Option<u64> {
+    match ty {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+/// `(block elements, block bytes)` for the ggml tensor types we know; newer
+/// types are only checked for their start offset.
+fn block_layout(ggml_type: u32) -> Option<(u64, u64)> {
+    Some(match ggml_type {
+        0 => (1, 4),     // F32
+        1 => (1, 2),     // F16
+        2 => (32, 18),   // Q4_0
+        3 => (32, 20),   // Q4_1
+        6 => (32, 22),   // Q5_0
+        7 => (32, 24),   // Q5_1
+        8 => (32, 34),   // Q8_0
+        9 => (32, 36),   // Q8_1
+        10 => (256, 84), // Q2_K
+        11 => (256, 110),
+        12 => (256, 144),
+        13 => (256, 176),
+        14 => (256, 210),
+        15 => (256, 292), // Q8_K
+        16 => (256, 66),  // IQ2_XXS
+        17 => (256, 74),
+        18 => (256, 98),
+        19 => (256, 50),
+        20 => (32, 18), // IQ4_NL
+        21 => (256, 110),
+        22 => (256, 82),
+        23 => (256, 136), // IQ4_XS
+        24 => (1, 1),     // I8
+        25 => (1, 2),
+        26 => (1, 4),
+        27 => (1, 8),
+        28 => (1, 8),    // F64
+        29 => (256, 56), // IQ1_M
+        30 => (1, 2),    // BF16
+        _ => return None,
+    })
+}
+
+/// Parse the GGUF header at `path` and check the tensor data is all there.
+pub fn verify(path: &Path) -> Result<GgufInfo, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let file_len = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut r = Reader {
+        inner: BufReader::new(file),
+        version: 0,
+        pos: 0,
+        file_len,
+    };
+
+    if &r.bytes::<4>()? != MAGIC {
+        return Err("not a GGUF file (bad magic)".into());
+    }
+    r.version = r.u32()?;
+    if !(1..=3).contains(&r.version) {
+        return Err(format!("unsupported GGUF version {}", r.version));
+    }
+    let tensor_count = r.len()?;
+    let kv_count = r.len()?;
+
+    let mut info = GgufInfo {
+        version: r.version,
+        tensor_count,
+        kv_count,
+        architecture: None,
+        name: None,
+    };
+    let mut alignment = DEFAULT_ALIGNMENT;
+    for _ in 0..kv_count {
+        let key = r.string()?;
+        let ty = r.u32()?;
+        match (key.as_str(), r.value(ty)?) {
+            ("general.architecture", Value::Str(s)) => info.architecture = Some(s),
+            ("general.name", Value::Str(s)) => info.name = Some(s),
+            ("general.alignment", Value::Uint(n)) if n > 0 => alignment = n,
+            _ => {}
+        }
+    }
+
+    // (name, offset, bytes if the type is known)
+    let mut tensors = Vec::new();
+    for _ in 0..tensor_count {
+        let name = r.string()?;
+        let n_dims = r.u32()?;
+        if n_dims > 8 {
+            return Err(format!("tensor `{name}` has {n_dims} dimensions"));
+        }
+        let mut elements: u64 = 1;
+        for _ in 0..n_dims {
+            elements = elements.saturating_mul(r.len()?);
+        }
+        let ggml_type = r.u32()?;
+        let offset = r.u64()?;
+        let bytes = block_layout(ggml_type).map(|(block, size)| elements.div_ceil(block).saturating_mul(size));
+        tensors.push((name, offset, bytes));
+    }
+
+    let data_start = r.pos.div_ceil(alignment) * alignment;
+    let data_len = file_len.saturating_sub(data_start);
+    for (name, offset, bytes) in tensors {
+        let end = offset.saturating_add(bytes.unwrap_or(0));
+        if end > data_len {
+            return Err(format!(
+                "tensor `{name}` needs {end} data bytes but the file has {data_len}; the download is incomplete"
+            ));
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// A v3 file with two metadata keys and one 4-element F32 tensor.
+    fn tiny_gguf() -> Vec<u8> {
+        let mut b = Vec::new();
+        let string = |b: &mut Vec<u8>, s: &str| {
+            b.extend((s.len() as u64).to_le_bytes());
+            b.extend(s.as_bytes());
+        };
+        b.extend(MAGIC);
+        b.extend(3u32.to_le_bytes());
+        b.extend(1u64.to_le_bytes());
+        b.extend(2u64.to_le_bytes());
+        string(&mut b, "general.architecture");
+        b.extend(8u32.to_le_bytes());
+        string(&mut b, "llama");
+        string(&mut b, "tokenizer.ggml.scores");
+        b.extend(9u32.to_le_bytes());
+        b.extend(6u32.to_le_bytes());
+        b.extend(3u64.to_le_bytes());
+        b.extend([0u8; 12]);
+        string(&mut b, "blk.0.weight");
+        b.extend(1u32.to_le_bytes());
+        b.extend(4u64.to_le_bytes());
+        b.extend(0u32.to_le_bytes());
+        b.extend(0u64.to_le_bytes());
+        b.resize(b.len().div_ceil(32) * 32, 0);
+        b.extend([1u8; 16]);
+        b
+    }
+
+    #[test]
+    fn verifies_and_rejects_truncated_files() {
+        let dir = TempDir::new("gguf");
+        let path = dir.0.join("m.gguf");
+        let bytes = tiny_gguf();
+
+        std::fs::write(&path, &bytes).unwrap();
+        let info = verify(&path).unwrap();
+        assert_eq!((info.version, info.tensor_count, info.kv_count), (3, 1, 2));
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(verify(&path).unwrap_err().contains("incomplete"));
+        std::fs::write(&path, &bytes[..40]).unwrap();
+        assert!(verify(&path).unwrap_err().contains("ends inside the header"));
+        std::fs::write(&path, b"<html>not found</html>").unwrap();
+        assert!(verify(&path).unwrap_err().contains("bad magic"));
+    }
+}
@@ -0,0 +1,559 @@
+//! SQLite-backed model index (`model_index.db` in the config dir).
+//!
+//! Replaces the flat `model_index.json`, which was loaded and rewritten whole
+//! on every operation and filtered in the UI. Models live in `models` (one row
+//! per file, keyed by path) with their tags in `model_tags`; the quantisation
+//! tag is denormalised into `models.quant` so it can be filtered and faceted
+//! directly. `model_launches` records when `server_start` last used each path.
+//! The first open imports an existing `model_index.json` and renames it to
+//! `model_index.json.bak`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use shared::ipc::{
+    FacetCount, LibraryFacets, LibraryQuery, LibraryQueryResult, LibrarySort, ScannedModel,
+};
+
+use super::{load_index, quant_of};
+
+const DB_FILE: &str = "model_index.db";
+const LEGACY_JSON: &str = "model_index.json";
+const SCHEMA_VERSION: i64 = 2;
+
+/// Models without a detected family are grouped as "Other" in the UI; facet
+/// and filter on the same expression so the sidebar and list agree.
+const FAMILY_EXPR: &str = "CASE WHEN m.family = '' THEN 'Other' ELSE m.family END";
+
+const MODEL_COLUMNS: &str = "m.path, m.filename, m.size_bytes, m.clean_name, m.use_case, m.hf_link, \
+     m.github_link, m.size_info, m.status, m.family, m.version, m.source, m.repo_id";
+
+pub struct IndexDb {
+    conn: Connection,
+}
+
+/// Which filter to leave out when counting a facet dimension.
+#[derive(Clone, Copy, PartialEq)]
+enum Skip {
+    None,
+    Family,
+    Tag,
+    Quant,
+}
+
+impl IndexDb {
+    /// Open (creating and migrating as needed) the index in `config_dir`.
+    pub fn open(config_dir: &Path) -> Result<Self, String> {
+        let conn = Connection::open(config_dir.join(DB_FILE)).map_err(|e| e.to_string())?;
+        // Bulk enrichment writes while the UI queries.
+        conn.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        let mut db = Self { conn };
+        db.migrate(config_dir)?;
+        Ok(db)
+    }
+
+    fn migrate(&mut self, config_dir: &Path) -> Result<(), String> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+        if version < 1 {
+            self.migrate_v1(config_dir)?;
+        }
+        if version < 2 {
+            // Kept apart from `models` so launch history survives rescans,
+            // which replace the whole models table.
+            self.conn
+                .execute_batch(
+                    "CREATE TABLE IF NOT EXISTS model_launches (
+                        path          TEXT PRIMARY KEY,
+                        last_launched INTEGER NOT NULL,
+                        launch_count  INTEGER NOT NULL
+                    );",
+                )
+                .map_err(|e| e.to_string())?;
+        }
+
+        self.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| e.to_string())
+    }
+
+    fn migrate_v1(&mut self, config_dir: &Path) -> Result<(), String> {
+        self.conn
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS models (
+                    path        TEXT PRIMARY KEY,
+                    filename    TEXT NOT NULL,
+                    size_bytes  INTEGER NOT NULL,
+                    clean_name  TEXT NOT NULL,
+                    use_case    TEXT NOT NULL,
+                    hf_link     TEXT NOT NULL,
+                    github_link TEXT NOT NULL,
+                    size_info   TEXT NOT NULL,
+                    status      TEXT NOT NULL,
+                    family      TEXT NOT NULL,
+                    version     TEXT NOT NULL,
+                    quant       TEXT NOT NULL,
+                    source      TEXT NOT NULL,
+                    repo_id     TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS model_tags (
+                    path     TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    tag      TEXT NOT NULL,
+                    PRIMARY KEY (path, position)
+                );
+                CREATE INDEX IF NOT EXISTS idx_models_family ON models(family);
+                CREATE INDEX IF NOT EXISTS idx_models_quant ON models(quant);
+                CREATE INDEX IF NOT EXISTS idx_models_size ON models(size_bytes);
+                CREATE INDEX IF NOT EXISTS idx_model_tags_tag ON model_tags(tag);",
+            )
+            .map_err(|e| e.to_string())?;
+
+        let legacy = config_dir.join(LEGACY_JSON);
+        if legacy.exists() {
+            let models = load_index(&legacy.to_string_lossy());
+            self.replace_all(&models)?;
+            tracing::info!(count = models.len(), "imported model_index.json into SQLite index");
+            if let Err(e) = std::fs::rename(&legacy, config_dir.join(format!("{LEGACY_JSON}.bak"))) {
+                tracing::warn!("failed to rename migrated model_index.json: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every model, sorted by name.
+    pub fn all(&self) -> Result<Vec<ScannedModel>, String> {
+        self.models(&LibraryQuery::default())
+    }
+
+    pub fn get(&self, path: &str) -> Result<Option<ScannedModel>, String> {
+        let sql = format!("SELECT {MODEL_COLUMNS} FROM models m WHERE m.path = ?1");
+        let model = self
+            .conn
+            .query_row(&sql, [path], row_to_model)
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some(mut model) = model else {
+            return Ok(None);
+        };
+        model.tags = self.tags_for(Some(path))?.remove(path).unwrap_or_default();
+        Ok(Some(model))
+    }
+
+    /// Replace the whole index (used after a scan + merge).
+    pub fn replace_all(&mut self, models: &[ScannedModel]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch("DELETE FROM model_tags; DELETE FROM models;")
+            .map_err(|e| e.to_string())?;
+        for m in models {
+            insert_model(&tx, m)?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Insert or update one model and its tags.
+    pub fn upsert(&mut self, model: &ScannedModel) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM model_tags WHERE path = ?1", [&model.path])
+            .map_err(|e| e.to_string())?;
+        insert_model(&tx, model)?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    pub fn set_status(&self, path: &str, status: &str) -> Result<(), String> {
+        self.conn
+            .execute("UPDATE models SET status = ?2 WHERE path = ?1", params![path, status])
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Remove one model (after its file was deleted). Launch history goes too.
+    pub fn remove(&mut self, path: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for table in ["model_tags", "models", "model_launches"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE path = ?1"), [path])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Re-key a model after its file moved, keeping metadata and launch history.
+    pub fn rename_path(&mut self, old: &str, new: &str, filename: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE models SET path = ?2, filename = ?3 WHERE path = ?1",
+            params![old, new, filename],
+        )
+        .map_err(|e| e.to_string())?;
+        for table in ["model_tags", "model_launches"] {
+            tx.execute(&format!("UPDATE {table} SET path = ?2 WHERE path = ?1"), params![old, new])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Note that `server_start` launched `path` at `at` (unix seconds).
+    pub fn record_launch(&self, path: &str, at: i64) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO model_launches (path, last_launched, launch_count) VALUES (?1, ?2, 1)
+                 ON CONFLICT(path) DO UPDATE SET last_launched = ?2, launch_count = launch_count + 1",
+                params![path, at],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Last launch time (unix seconds) per model path.
+    pub fn launches(&self) -> Result<HashMap<String, i64>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, last_launched FROM model_launches")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Filtered, sorted models plus sidebar facets.
+    pub fn query(&self, q: &LibraryQuery) -> Result<LibraryQueryResult, String> {
+        Ok(LibraryQueryResult {
+            models: self.models(q)?,
+            facets: self.facets(q)?,
+        })
+    }
+
+    fn models(&self, q: &LibraryQuery) -> Result<Vec<ScannedModel>, String> {
+        let (filter, args) = where_clause(q, Skip::None);
+        let order = match q.sort {
+            LibrarySort::Name => {
+                "LOWER(CASE WHEN m.clean_name IN ('', 'Unknown') THEN m.filename ELSE m.clean_name END)"
+                    .to_string()
+            }
+            LibrarySort::SizeAsc => "m.size_bytes ASC, m.filename".to_string(),
+            LibrarySort::SizeDesc => "m.size_bytes DESC, m.filename".to_string(),
+            LibrarySort::Family => format!("LOWER({FAMILY_EXPR}), LOWER(m.version), LOWER(m.filename)"),
+        };
+        let sql = format!("SELECT {MODEL_COLUMNS} FROM models m WHERE {filter} ORDER BY {order}");
+
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut models = stmt
+            .query_map(params_from_iter(args), row_to_model)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut tags = self.tags_for(None)?;
+        for m in &mut models {
+            m.tags = tags.remove(&m.path).unwrap_or_default();
+        }
+        Ok(models)
+    }
+
+    fn facets(&self, q: &LibraryQuery) -> Result<LibraryFacets, String> {
+        let (filter, args) = where_clause(q, Skip::Family);
+        let families = self.counts(
+            &format!(
+                "SELECT {FAMILY_EXPR} AS v, COUNT(*) FROM models m WHERE {filter} GROUP BY v ORDER BY 2 DESC, v"
+            ),
+            args,
+        )?;
+
+        let (filter, args) = where_clause(q, Skip::Quant);
+        let quants = self.counts(
+            &format!(
+                "SELECT m.quant, COUNT(*) FROM models m WHERE m.quant != '' AND {filter} \
+                 GROUP BY m.quant ORDER BY 2 DESC, m.quant"
+            ),
+            args,
+        )?;
+
+        // Quants have their own facet, so leave them out of the tag counts.
+        let (filter, args) = where_clause(q, Skip::Tag);
+        let tags = self.counts(
+            &format!(
+                "SELECT t.tag, COUNT(DISTINCT m.path) FROM model_tags t JOIN models m ON m.path = t.path \
+                 WHERE t.tag != m.quant AND {filter} GROUP BY t.tag ORDER BY 2 DESC, t.tag"
+            ),
+            args,
+        )?;
+
+        Ok(LibraryFacets { families, tags, quants })
+    }
+
+    fn counts(&self, sql: &str, args: Vec<Value>) -> Result<Vec<FacetCount>, String> {
+        let mut stmt = self.conn.prepare(sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params_from_iter(args), |r| {
+                Ok(FacetCount {
+                    value: r.get(0)?,
+                    count: r.get::<_, i64>(1)? as u64,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    fn tags_for(&self, path: Option<&str>) -> Result<HashMap<String, Vec<String>>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, tag FROM model_tags WHERE ?1 IS NULL OR path = ?1 ORDER BY path, position")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([path], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut out: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (path, tag) = row.map_err(|e| e.to_string())?;
+            out.entry(path).or_default().push(tag);
+        }
+        Ok(out)
+    }
+}
+
+fn insert_model(conn: &Connection, m: &ScannedModel) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO models (path, filename, size_bytes, clean_name, use_case, hf_link, github_link,
+             size_info, status, family, version, quant, source, repo_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            m.path,
+            m.filename,
+            m.size_bytes as i64,
+            m.clean_name,
+            m.use_case,
+            m.hf_link,
+            m.github_link,
+            m.size_info,
+            m.status,
+            m.family,
+            m.version,
+            quant_of(&m.tags).unwrap_or_default(),
+            m.source,
+            m.repo_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    for (i, tag) in m.tags.iter().enumerate() {
+        conn.execute(
+            "INSERT OR REPLACE INTO model_tags (path, position, tag) VALUES (?1, ?2, ?3)",
+            params![m.path, i as i64, tag],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn row_to_model(r: &rusqlite::Row) -> rusqlite::Result<ScannedModel> {
+    Ok(ScannedModel {
+        path: r.get(0)?,
+        filename: r.get(1)?,
+        size_bytes: r.get::<_, i64>(2)? as u64,
+        clean_name: r.get(3)?,
+        use_case: r.get(4)?,
+        hf_link: r.get(5)?,
+        github_link: r.get(6)?,
+        size_info: r.get(7)?,
+        status: r.get(8)?,
+        family: r.get(9)?,
+        version: r.get(10)?,
+        tags: Vec::new(),
+        source: r.get(11)?,
+        repo_id: r.get(12)?,
+    })
+}
+
+/// Build the `WHERE` body (never empty) and its positional arguments.
+fn where_clause(q: &LibraryQuery, skip: Skip) -> (String, Vec<Value>) {
+    let mut clauses = vec!["1 = 1".to_string()];
+    let mut args: Vec<Value> = Vec::new();
+    let nonempty = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+
+    if skip != Skip::Family {
+        if let Some(family) = nonempty(&q.family) {
+            clauses.push(format!("{FAMILY_EXPR} = ? COLLATE NOCASE"));
+            args.push(Value::Text(family));
+        }
+    }
+    if skip != Skip::Quant {
+        if let Some(quant) = nonempty(&q.quant) {
+            clauses.push("m.quant = ? COLLATE NOCASE".to_string());
+            args.push(Value::Text(quant));
+        }
+    }
+    if skip != Skip::Tag {
+        if let Some(tag) = nonempty(&q.tag) {
+            clauses.push(
+                "EXISTS (SELECT 1 FROM model_tags ft WHERE ft.path = m.path AND ft.tag = ? COLLATE NOCASE)"
+                    .to_string(),
+            );
+            args.push(Value::Text(tag));
+        }
+    }
+    if let Some(min) = q.min_size_bytes {
+        clauses.push("m.size_bytes >= ?".to_string());
+        args.push(Value::Integer(min as i64));
+    }
+    if let Some(max) = q.max_size_bytes {
+        clauses.push("m.size_bytes <= ?".to_string());
+        args.push(Value::Integer(max as i64));
+    }
+    if let Some(text) = nonempty(&q.text) {
+        let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let pattern = format!("%{escaped}%");
+        clauses.push(
+            "(m.filename LIKE ? ESCAPE '\\' OR m.clean_name LIKE ? ESCAPE '\\' OR m.family LIKE ? ESCAPE '\\' \
+             OR m.use_case LIKE ? ESCAPE '\\' OR EXISTS (SELECT 1 FROM model_tags tt WHERE tt.path = m.path \
+             AND tt.tag LIKE ? ESCAPE '\\'))"
+                .to_string(),
+        );
+        for _ in 0..5 {
+            args.push(Value::Text(pattern.clone()));
+        }
+    }
+
+    (clauses.join(" AND "), args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn model(filename: &str, size_gb: u64) -> ScannedModel {
+        crate::library::scanned_model(Path::new(&format!("/models/{filename}")), size_gb << 30)
+    }
+
+    fn seeded() -> (TempDir, IndexDb) {
+        let dir = TempDir::new("index-db");
+        let mut db = IndexDb::open(&dir.0).unwrap();
+        db.replace_all(&[
+            model("Qwen3-8B-Q4_K_M.gguf", 5),
+            model("Qwen3-32B-Q8_0.gguf", 34),
+            model("gemma-3-4b-it-Q4_K_M.gguf", 3),
+            model("Phi-4-mini-uncensored-Q8_0.gguf", 4),
+        ])
+        .unwrap();
+        (dir, db)
+    }
+
+    fn count(facets: &[FacetCount], value: &str) -> u64 {
+        facets.iter().find(|f| f.value == value).map_or(0, |f| f.count)
+    }
+
+    #[test]
+    fn filters_combine_and_sort() {
+        let (_dir, db) = seeded();
+        let res = db
+            .query(&LibraryQuery {
+                quant: Some("q4_k_m".into()),
+                sort: LibrarySort::SizeDesc,
+                ..Default::default()
+            })
+            .unwrap();
+        let names: Vec<_> = res.models.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(names, ["Qwen3-8B-Q4_K_M.gguf", "gemma-3-4b-it-Q4_K_M.gguf"]);
+
+        let res = db
+            .query(&LibraryQuery {
+                family: Some("Qwen".into()),
+                max_size_bytes: Some(10 << 30),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.models.len(), 1);
+        assert_eq!(res.models[0].tags, ["GGUF", "Q4_K_M"]);
+
+        let res = db
+            .query(&LibraryQuery {
+                text: Some("uncensored".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.models.len(), 1);
+        assert!(res.models[0].filename.starts_with("Phi"));
+    }
+
+    #[test]
+    fn facets_ignore_their_own_dimension() {
+        let (_dir, db) = seeded();
+        let res = db
+            .query(&LibraryQuery {
+                family: Some("Qwen".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        // Other families stay visible while Qwen is selected…
+        assert_eq!(count(&res.facets.families, "Qwen"), 2);
+        assert_eq!(count(&res.facets.families, "Gemma"), 1);
+        // …while the other dimensions narrow to Qwen.
+        assert_eq!(count(&res.facets.quants, "Q8_0"), 1);
+        assert_eq!(count(&res.facets.tags, "GGUF"), 2);
+        assert_eq!(count(&res.facets.tags, "Uncensored"), 0);
+        assert!(res.facets.tags.iter().all(|t| !t.value.starts_with('Q')));
+    }
+
+    #[test]
+    fn upsert_and_status_updates() {
+        let (_dir, mut db) = seeded();
+        let mut m = db.get("/models/Qwen3-8B-Q4_K_M.gguf").unwrap().unwrap();
+        m.use_case = "Text Generation".into();
+        m.tags.push("Thinking".into());
+        db.upsert(&m).unwrap();
+        db.set_status(&m.path, "enriched").unwrap();
+
+        let back = db.get(&m.path).unwrap().unwrap();
+        assert_eq!(back.use_case, "Text Generation");
+        assert_eq!(back.status, "enriched");
+        assert_eq!(back.tags.last().map(String::as_str), Some("Thinking"));
+        assert_eq!(db.all().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn launches_survive_rescan_and_follow_moves() {
+        let (_dir, mut db) = seeded();
+        let path = "/models/Qwen3-8B-Q4_K_M.gguf";
+        db.record_launch(path, 100).unwrap();
+        db.record_launch(path, 200).unwrap();
+
+        let models = db.all().unwrap();
+        db.replace_all(&models).unwrap();
+        assert_eq!(db.launches().unwrap().get(path), Some(&200));
+
+        db.rename_path(path, "/fast/Qwen3-8B-Q4_K_M.gguf", "Qwen3-8B-Q4_K_M.gguf").unwrap();
+        assert!(db.get(path).unwrap().is_none());
+        let moved = db.get("/fast/Qwen3-8B-Q4_K_M.gguf").unwrap().unwrap();
+        assert_eq!(moved.tags, ["GGUF", "Q4_K_M"]);
+        assert_eq!(db.launches().unwrap().get("/fast/Qwen3-8B-Q4_K_M.gguf"), Some(&200));
+
+        db.remove("/fast/Qwen3-8B-Q4_K_M.gguf").unwrap();
+        assert_eq!(db.all().unwrap().len(), 3);
+        assert!(db.launches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrates_legacy_json_once() {
+        let dir = TempDir::new("index-db");
+        let legacy = vec![model("bge-m3-f16.gguf", 1)];
+        std::fs::write(dir.0.join(LEGACY_JSON), serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        let db = IndexDb::open(&dir.0).unwrap();
+        assert_eq!(db.all().unwrap().len(), 1);
+        assert!(!dir.0.join(LEGACY_JSON).exists());
+        assert!(dir.0.join("model_index.json.bak").exists());
+        drop(db);
+
+        // A reopen must not re-import (or wipe) anything.
+        let db = IndexDb::open(&dir.0).unwrap();
+        assert_eq!(db.all().unwrap()[0].filename, "bge-m3-f16.gguf");
+    }
+}
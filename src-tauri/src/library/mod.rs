@@ -3,26 +3,11 @@ use std::fs;
 use std::path::Path;
 use shared::ipc::ScannedModel;
 
-#[derive(Deserialize)]
-struct SearxResult {
-    title: Option<String>,
-    url: Option<String>,
-    content: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct SearxResponse {
-    results: Option<Vec<SearxResult>>,
-}
-
-#[derive(serde::Serialize, Deserialize)]
-struct LlmResponse {
-    name: String,
-    use_case: String,
-    size: String,
-    hf_link: String,
-    github_link: String,
-}
+pub mod enrich;
+pub mod gguf;
+pub mod index_db;
+pub mod storage;
+pub mod stores;
 
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
@@ -84,47 +69,9 @@ pub fn clean_query_name(name: &str) -> String {
     cleaned.trim().to_string()
 }
 
-fn parse_json_from_llm(text: &str) -> Option<LlmResponse> {
-    let trimmed = text.trim();
-    if let Ok(res) = serde_json::from_str::<LlmResponse>(trimmed) {
-        return Some(res);
-    }
-
-    // Try finding JSON block
-    if let Some(start) = trimmed.find("```json") {
-        let sub = &trimmed[start + 7..];
-        if let Some(end) = sub.find("```") {
-            let block = &sub[..end];
-            if let Ok(res) = serde_json::from_str::<LlmResponse>(block.trim()) {
-                return Some(res);
-            }
-        }
-    }
-    if let Some(start) = trimmed.find("```") {
-        let sub = &trimmed[start + 3..];
-        if let Some(end) = sub.find("```") {
-            let block = &sub[..end];
-            if let Ok(res) = serde_json::from_str::<LlmResponse>(block.trim()) {
-                return Some(res);
-            }
-        }
-    }
-
-    // Fallback: look for opening { and closing }
-    if let Some(start) = trimmed.find('{') {
-        if let Some(end) = trimmed.rfind('}') {
-            let block = &trimmed[start..=end];
-            if let Ok(res) = serde_json::from_str::<LlmResponse>(block) {
-                return Some(res);
-            }
-        }
-    }
-
-    None
-}
-
 // ── Public API ───────────────────────────────────────────────────────────────
 
+/// Read the legacy JSON index; only used to migrate it into [`index_db`].
 pub fn load_index(path: &str) -> Vec<ScannedModel> {
     if let Ok(json) = fs::read_to_string(path) {
         if let Ok(index) = serde_json::from_str::<Vec<ScannedModel>>(&json) {
@@ -134,11 +81,6 @@ pub fn load_index(path: &str) -> Vec<ScannedModel> {
     Vec::new()
 }
 
-pub fn save_index(path: &str, index: &[ScannedModel]) -> Result<(), String> {
-    let json = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())
-}
-
 pub struct ModelHierarchy {
     pub family: String,
     pub version: String,
@@ -385,21 +327,7 @@ pub fn parse_model_hierarchy(filename: &str) -> ModelHierarchy {
             continue;
         }
 
-        let is_quant = if p_lower.starts_with('q') && p_lower.len() >= 2 {
-            let second = p_lower.chars().nth(1).unwrap();
-            second.is_ascii_digit()
-        } else if p_lower.starts_with("iq") && p_lower.len() >= 3 {
-            let third = p_lower.chars().nth(2).unwrap();
-            third.is_ascii_digit()
-        } else {
-            p_lower == "bf16"
-                || p_lower == "f16"
-                || p_lower == "fp16"
-                || p_lower == "f32"
-                || p_lower == "fp32"
-        };
-
-        if is_quant {
+        if is_quant_token(&p_lower) {
             let tag_upper = p.to_uppercase();
             if !tags.contains(&tag_upper) {
                 tags.push(tag_upper);
@@ -448,6 +376,32 @@ pub fn parse_model_hierarchy(filename: &str) -> ModelHierarchy {
     }
 }
 
+/// `q4_k_m`, `iq3_xxs`, `bf16`, … (lowercase input).
+fn is_quant_token(p_lower: &str) -> bool {
+    if p_lower.starts_with('q') && p_lower.len() >= 2 {
+        p_lower.chars().nth(1).unwrap().is_ascii_digit()
+    } else if p_lower.starts_with("iq") && p_lower.len() >= 3 {
+        p_lower.chars().nth(2).unwrap().is_ascii_digit()
+    } else {
+        matches!(p_lower, "bf16" | "f16" | "fp16" | "f32" | "fp32")
+    }
+}
+
+/// The quantisation tag among a model's tags, if any.
+pub fn quant_of(tags: &[String]) -> Option<&str> {
+    tags.iter()
+        .map(String::as_str)
+        .find(|t| is_quant_token(&t.to_lowercase()))
+}
+
+/// The quantisation named in a file name or repo path, uppercased
+/// (`Qwen3-8B-UD-Q4_K_XL.gguf` → `Q4_K_XL`).
+pub fn quant_of_filename(name: &str) -> Option<String> {
+    name.split(['-', '.', ' ', '(', ')', '[', ']', '/'])
+        .find(|p| is_quant_token(&p.to_lowercase()))
+        .map(str::to_uppercase)
+}
+
 fn is_supported_model_file(path: &Path) -> bool {
     if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
         matches!(
@@ -491,29 +445,36 @@ fn scan_directory_recursive(path: &Path, models: &mut Vec<ScannedModel>) {
             }
 
             let size_bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
-            let path_str = path.to_string_lossy().to_string();
-            let clean = clean_query_name(&name);
-            let size_info = format_size(size_bytes);
-            let hierarchy = parse_model_hierarchy(&name);
-
-            models.push(ScannedModel {
-                path: path_str,
-                filename: name,
-                size_bytes,
-                clean_name: clean,
-                use_case: "Unknown".to_string(),
-                hf_link: "".to_string(),
-                github_link: "".to_string(),
-                size_info,
-                status: "pending_enrichment".to_string(),
-                family: hierarchy.family,
-                version: hierarchy.version,
-                tags: hierarchy.tags,
-            });
+            models.push(scanned_model(&path, size_bytes));
         }
     }
 }
 
+/// A fresh index entry for a model file, before enrichment.
+pub fn scanned_model(path: &Path, size_bytes: u64) -> ScannedModel {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let hierarchy = parse_model_hierarchy(&name);
+    ScannedModel {
+        path: path.to_string_lossy().to_string(),
+        clean_name: clean_query_name(&name),
+        filename: name,
+        size_bytes,
+        use_case: "Unknown".to_string(),
+        hf_link: "".to_string(),
+        github_link: "".to_string(),
+        size_info: format_size(size_bytes),
+        status: "pending_enrichment".to_string(),
+        family: hierarchy.family,
+        version: hierarchy.version,
+        tags: hierarchy.tags,
+        source: String::new(),
+        repo_id: String::new(),
+    }
+}
+
 pub fn scan_directories(dirs: &[String]) -> Vec<ScannedModel> {
     let mut models = Vec::new();
     for dir_str in dirs {
@@ -532,9 +493,12 @@ pub fn merge_indexes(scanned: Vec<ScannedModel>, existing: Vec<ScannedModel>) ->
         if let Some(ext) = existing.iter().find(|e| e.path == s.path) {
             s.clean_name = ext.clean_name.clone();
             s.use_case = ext.use_case.clone();
-            s.hf_link = ext.hf_link.clone();
+            // Store scanners resolve the HF link themselves; don't blank it.
+            if !ext.hf_link.is_empty() {
+                s.hf_link = ext.hf_link.clone();
+            }
             s.github_link = ext.github_link.clone();
-            if ext.status == "enriched" || ext.status == "failed" || ext.status == "enriching" {
+            if matches!(ext.status.as_str(), "enriched" | "not_found" | "failed" | "enriching") {
                 s.status = ext.status.clone();
                 s.size_info = ext.size_info.clone();
             }
@@ -657,226 +621,3 @@ fn map_pipeline_tag(tag: &str) -> String {
         }
     }
 }
-
-pub async fn enrich_model(
-    model: &mut ScannedModel,
-    searxng_url: &str,
-    model_server_port: u16,
-) -> Result<(), String> {
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert("User-Agent", reqwest::header::HeaderValue::from_static(
-        "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"
-    ));
-    headers.insert("Accept", reqwest::header::HeaderValue::from_static(
-        "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"
-    ));
-    headers.insert(
-        "Accept-Language",
-        reqwest::header::HeaderValue::from_static("en-US,en;q=0.9"),
-    );
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .default_headers(headers)
-        .build()
-        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
-
-    let mut found_use_case = None;
-    let mut found_hf_link = None;
-
-    // 2. Query Hugging Face API
-    let hf_query = clean_query_name(&model.filename);
-    #[derive(Deserialize, Clone)]
-    struct HfModelInfo {
-        id: Option<String>,
-        pipeline_tag: Option<String>,
-    }
-
-    if let Ok(res) = client
-        .get("https://huggingface.co/api/models")
-        .query(&[("search", &hf_query), ("limit", &"10".to_string())])
-        .send()
-        .await
-    {
-        if let Ok(models) = res.json::<Vec<HfModelInfo>>().await {
-            for m in &models {
-                if let Some(ref tag) = m.pipeline_tag {
-                    found_use_case = Some(map_pipeline_tag(tag));
-                    if let Some(ref hf_id) = m.id {
-                        found_hf_link = Some(format!("https://huggingface.co/{}", hf_id));
-                    }
-                    break;
-                }
-            }
-        }
-    }
-
-    if found_use_case.is_none() {
-        let backup_query = format!("{} {}", model.family, model.version);
-        if let Ok(res) = client
-            .get("https://huggingface.co/api/models")
-            .query(&[("search", &backup_query), ("limit", &"10".to_string())])
-            .send()
-            .await
-        {
-            if let Ok(models) = res.json::<Vec<HfModelInfo>>().await {
-                for m in &models {
-                    if let Some(ref tag) = m.pipeline_tag {
-                        found_use_case = Some(map_pipeline_tag(tag));
-                        if found_hf_link.is_none() {
-                            if let Some(ref hf_id) = m.id {
-                                found_hf_link = Some(format!("https://huggingface.co/{}", hf_id));
-                            }
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    if let Some(uc) = found_use_case {
-        model.use_case = uc;
-    }
-    if let Some(hl) = found_hf_link {
-        model.hf_link = hl;
-    }
-
-    if model.use_case == "Unknown"
-        || model.hf_link.is_empty()
-        || model.clean_name == "Unknown"
-        || model.clean_name.is_empty()
-    {
-        let clean = clean_query_name(&model.filename);
-        let query = format!("{} model Hugging Face github", clean);
-
-        let search_url = format!("{}/search", searxng_url.trim_end_matches('/'));
-        if let Ok(res) = client
-            .get(&search_url)
-            .query(&[("q", &query), ("format", &"json".to_string())])
-            .send()
-            .await
-        {
-            if let Ok(response_text) = res.text().await {
-                if let Ok(searx_res) = serde_json::from_str::<SearxResponse>(&response_text) {
-                    let results = searx_res.results.unwrap_or_default();
-                    if !results.is_empty() {
-                        let mut search_results_text = String::new();
-                        for (i, item) in results.iter().take(5).enumerate() {
-                            let title = item.title.clone().unwrap_or_default();
-                            let url = item.url.clone().unwrap_or_default();
-                            let content = item.content.clone().unwrap_or_default();
-                            search_results_text.push_str(&format!(
-                                "Result {}:\nTitle: {}\nURL: {}\nSnippet: {}\n\n",
-                                i + 1,
-                                title,
-                                url,
-                                content
-                            ));
-                        }
-
-                        let prompt = format!(
-                            "You are a model metadata extractor. Your task is to analyze search results and extract accurate metadata about the AI model \"{}\".\n\n\
-                            Search Results:\n\
-                            {}\n\n\
-                            Extract the following fields:\n\
-                            1. Official Clean Name (e.g. \"BGE-M3\" or \"Qwen 3.5 Coder\")\n\
-                            2. Primary Use-case (e.g. \"Text Embedding\", \"Text Generation\", \"ASR\", \"TTS\", \"Image Generation\", \"OCR\", \"Video Generation\", etc.)\n\
-                            3. Parameter Size / File Info (e.g. \"8B\", \"300M\", \"24B\", or \"Unknown\")\n\
-                            4. Hugging Face Link (the main Hugging Face repository URL, e.g. https://huggingface.co/...)\n\
-                            5. GitHub Link (the source code repository URL, e.g. https://github.com/...)\n\n\
-                            Respond ONLY with a JSON object in this format:\n\
-                            {{\n\
-                              \"name\": \"...\",\n\
-                              \"use_case\": \"...\",\n\
-                              \"size\": \"...\",\n\
-                              \"hf_link\": \"...\",\n\
-                              \"github_link\": \"...\"\n\
-                            }}\n\
-                            Do not include any other text, markdown formatting, or explanation. Just the raw JSON object.",
-                            model.filename, search_results_text
-                        );
-
-                        let target_model =
-                            get_target_model(model_server_port, &model.filename).await;
-                        let llm_url =
-                            format!("http://127.0.0.1:{}/v1/chat/completions", model_server_port);
-                        let payload = serde_json::json!({
-                            "model": target_model,
-                            "messages": [
-                                {
-                                    "role": "user",
-                                    "content": prompt
-                                }
-                            ],
-                            "temperature": 0.1,
-                            "response_format": { "type": "json_object" }
-                        });
-
-                        if let Ok(llm_res) = client.post(&llm_url).json(&payload).send().await {
-                            if llm_res.status().is_success() {
-                                #[derive(Deserialize, Clone)]
-                                struct ChatMessage {
-                                    content: Option<String>,
-                                }
-                                #[derive(Deserialize, Clone)]
-                                struct ChatChoice {
-                                    message: Option<ChatMessage>,
-                                }
-                                #[derive(Deserialize)]
-                                struct ChatResponse {
-                                    choices: Option<Vec<ChatChoice>>,
-                                }
-
-                                if let Ok(chat_response) = llm_res.json::<ChatResponse>().await {
-                                    if let Some(content) = chat_response
-                                        .choices
-                                        .and_then(|c| c.first().cloned())
-                                        .and_then(|c| c.message)
-                                        .and_then(|m| m.content)
-                                    {
-                                        if let Some(parsed) = parse_json_from_llm(&content) {
-                                            if !parsed.name.is_empty() && parsed.name != "..." {
-                                                model.clean_name = parsed.name;
-                                            }
-                                            if model.use_case == "Unknown"
-                                                && !parsed.use_case.is_empty()
-                                                && parsed.use_case != "..."
-                                            {
-                                                model.use_case = parsed.use_case;
-                                            }
-                                            if model.hf_link.is_empty()
-                                                && !parsed.hf_link.is_empty()
-                                                && parsed.hf_link != "..."
-                                            {
-                                                model.hf_link = parsed.hf_link;
-                                            }
-                                            if !parsed.github_link.is_empty()
-                                                && parsed.github_link != "..."
-                                            {
-                                                model.github_link = parsed.github_link;
-                                            }
-                                            if !parsed.size.is_empty()
-                                                && parsed.size != "..."
-                                                && parsed.size != "Unknown"
-                                            {
-                                                model.size_info = format!(
-                                                    "{} ({})",
-                                                    format_size(model.size_bytes),
-                                                    parsed.size
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    model.status = "enriched".to_string();
-    Ok(())
-}
@@ -0,0 +1,339 @@
+//! Disk-space reporting for model storage: usage per root, duplicate files and
+//! models that haven't been launched recently, plus the file move used by
+//! `library_move_model`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use shared::ipc::{DirUsage, DuplicateGroup, ScannedModel, StorageReport, UnusedModel};
+
+/// Bytes read at the start, middle and end of each same-sized candidate.
+/// Only files that agree on size and all three samples are read in full to
+/// confirm they are identical, so unrelated models of one size cost little.
+const SAMPLE_BYTES: usize = 1 << 20;
+
+pub struct ReportInput<'a> {
+    pub models: &'a [ScannedModel],
+    /// Scan dirs and store roots; each model is counted under the longest match.
+    pub roots: &'a [PathBuf],
+    /// Last launch (unix seconds) per path.
+    pub launches: &'a HashMap<String, i64>,
+    /// Never reported as unused (the configured model).
+    pub active_model: &'a str,
+    pub unused_after_days: u32,
+    pub now: i64,
+}
+
+pub fn storage_report(input: &ReportInput) -> StorageReport {
+    let dirs = dir_usage(input.models, input.roots);
+    let duplicates = find_duplicates(input.models);
+
+    let cutoff = input.now - i64::from(input.unused_after_days) * 86_400;
+    let mut unused: Vec<UnusedModel> = input
+        .models
+        .iter()
+        .filter(|m| m.path != input.active_model)
+        .filter_map(|m| {
+            let last = input.launches.get(&m.path).copied();
+            (last.is_none_or(|t| t < cutoff)).then(|| UnusedModel {
+                path: m.path.clone(),
+                size_bytes: m.size_bytes,
+                last_launched: last,
+            })
+        })
+        .collect();
+    unused.sort_by_key(|u| std::cmp::Reverse(u.size_bytes));
+
+    // Count each file once: extra duplicate copies, then unused models that
+    // weren't already counted as an extra copy.
+    let mut reclaim: HashMap<&str, u64> = HashMap::new();
+    for group in &duplicates {
+        for path in group.paths.iter().skip(1) {
+            reclaim.insert(path, group.size_bytes);
+        }
+    }
+    for m in &unused {
+        reclaim.insert(&m.path, m.size_bytes);
+    }
+    let reclaimable_bytes = reclaim.values().sum();
+
+    StorageReport {
+        dirs,
+        duplicates,
+        unused,
+        reclaimable_bytes,
+    }
+}
+
+fn dir_usage(models: &[ScannedModel], roots: &[PathBuf]) -> Vec<DirUsage> {
+    let mut usage: Vec<DirUsage> = roots
+        .iter()
+        .filter(|r| r.is_dir())
+        .map(|root| {
+            let (total, free) = fs_space(root).unwrap_or((0, 0));
+            DirUsage {
+                dir: root.to_string_lossy().to_string(),
+                model_count: 0,
+                model_bytes: 0,
+                fs_total_bytes: total,
+                fs_free_bytes: free,
+            }
+        })
+        .collect();
+
+    for m in models {
+        let path = Path::new(&m.path);
+        let best = usage
+            .iter_mut()
+            .filter(|u| path.starts_with(&u.dir))
+            .max_by_key(|u| u.dir.len());
+        if let Some(u) = best {
+            u.model_count += 1;
+            u.model_bytes += m.size_bytes;
+        }
+    }
+    usage
+}
+
+/// `(total, available)` bytes of the filesystem holding `path`.
+#[cfg(unix)]
+fn fs_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is NUL-terminated and `st` is a valid out-pointer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    let frsize = st.f_frsize as u64;
+    Some((st.f_blocks as u64 * frsize, st.f_bavail as u64 * frsize))
+}
+
+#[cfg(not(unix))]
+fn fs_space(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+fn find_duplicates(models: &[ScannedModel]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<&ScannedModel>> = HashMap::new();
+    for m in models.iter().filter(|m| m.size_bytes > 0) {
+        by_size.entry(m.size_bytes).or_default().push(m);
+    }
+
+    let mut groups = Vec::new();
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+        // Links to one file (HF snapshot symlinks, hard links) aren't
+        // duplicates — removing one frees nothing.
+        let mut seen_files = Vec::new();
+        let mut by_sample: HashMap<u64, Vec<String>> = HashMap::new();
+        for m in candidates {
+            let Some(id) = file_identity(Path::new(&m.path)) else {
+                continue;
+            };
+            if seen_files.contains(&id) {
+                continue;
+            }
+            seen_files.push(id);
+            if let Some(sample) = sample_hash(Path::new(&m.path), size) {
+                by_sample.entry(sample).or_default().push(m.path.clone());
+            }
+        }
+        for (_, paths) in by_sample {
+            if paths.len() < 2 {
+                continue;
+            }
+            // Agreeing samples only make a match likely; the group is the
+            // files whose whole contents agree.
+            let mut by_content: HashMap<[u8; 32], Vec<String>> = HashMap::new();
+            for path in paths {
+                if let Some(hash) = content_hash(Path::new(&path)) {
+                    by_content.entry(hash).or_default().push(path);
+                }
+            }
+            for (_, mut paths) in by_content {
+                if paths.len() > 1 {
+                    paths.sort();
+                    groups.push(DuplicateGroup { size_bytes: size, paths });
+                }
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| a.paths.cmp(&b.paths)));
+    groups
+}
+
+#[cfg(unix)]
+fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::hash::DefaultHasher;
+    let canonical = fs::canonicalize(path).ok()?;
+    let mut h = DefaultHasher::new();
+    canonical.hash(&mut h);
+    Some((0, h.finish()))
+}
+
+fn sample_hash(path: &Path, size: u64) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut hasher = std::hash::DefaultHasher::new();
+    let mut buf = vec![0u8; SAMPLE_BYTES];
+    let sample = SAMPLE_BYTES as u64;
+    for offset in [0, size.saturating_sub(sample) / 2, size.saturating_sub(sample)] {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let n = read_up_to(&mut file, &mut buf).ok()?;
+        buf[..n].hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+fn content_hash(path: &Path) -> Option<[u8; 32]> {
+    let mut file = File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; SAMPLE_BYTES];
+    loop {
+        let n = read_up_to(&mut file, &mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Some(hasher.finalize().into())
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Move `src` into `dest_dir`, keeping its file name. Falls back to copy +
+/// remove when the directories are on different filesystems.
+pub fn move_file(src: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    let name = src.file_name().ok_or("Source has no file name")?;
+    let dest = dest_dir.join(name);
+    if dest.exists() {
+        return Err(format!("{} already exists", dest.display()));
+    }
+    if fs::rename(src, &dest).is_ok() {
+        return Ok(dest);
+    }
+    // `rename` fails across devices (EXDEV); copy, then drop the original only
+    // once the copy is complete.
+    if let Err(e) = fs::copy(src, &dest) {
+        let _ = fs::remove_file(&dest);
+        return Err(format!("Failed to copy to {}: {e}", dest.display()));
+    }
+    fs::remove_file(src).map_err(|e| format!("Copied, but failed to remove {}: {e}", src.display()))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn model(dir: &TempDir, rel: &str, contents: &[u8]) -> ScannedModel {
+        let path = dir.0.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        crate::library::scanned_model(&path, contents.len() as u64)
+    }
+
+    #[test]
+    fn report_finds_duplicates_unused_and_usage() {
+        let tmp = TempDir::new("storage");
+        let a = model(&tmp, "fast/a.gguf", b"same-weights");
+        let b = model(&tmp, "slow/copy-of-a.gguf", b"same-weights");
+        let c = model(&tmp, "slow/c.gguf", b"diff-weights"); // same size, different bytes
+        let d = model(&tmp, "slow/d.gguf", b"used recently, big enough");
+        // A symlink to `a` is the same file, not a duplicate.
+        let link = tmp.0.join("fast/link.gguf");
+        std::os::unix::fs::symlink(&a.path, &link).unwrap();
+        let mut l = a.clone();
+        l.path = link.to_string_lossy().to_string();
+
+        let models = vec![a.clone(), b.clone(), c.clone(), d.clone(), l];
+        let launches = HashMap::from([
+            (d.path.clone(), 1_000_000),
+            (c.path.clone(), 10),
+            (link.to_string_lossy().to_string(), 1_000_000),
+        ]);
+        let roots = vec![tmp.0.clone(), tmp.0.join("slow")];
+        let report = storage_report(&ReportInput {
+            models: &models,
+            roots: &roots,
+            launches: &launches,
+            active_model: &a.path,
+            unused_after_days: 1,
+            now: 1_000_100,
+        });
+
+        assert_eq!(report.duplicates.len(), 1);
+        let mut expected = vec![a.path.clone(), b.path.clone()];
+        expected.sort();
+        assert_eq!(report.duplicates[0].paths, expected);
+
+        let unused: Vec<_> = report.unused.iter().map(|u| u.path.as_str()).collect();
+        assert!(unused.contains(&b.path.as_str()));
+        assert!(unused.contains(&c.path.as_str()));
+        assert!(!unused.contains(&a.path.as_str()), "active model is never unused");
+        assert!(!unused.contains(&d.path.as_str()), "launched within the window");
+
+        // `slow` is the longest matching root for b, c and d.
+        let slow = report.dirs.iter().find(|u| u.dir.ends_with("slow")).unwrap();
+        assert_eq!(slow.model_count, 3);
+        assert!(slow.fs_total_bytes > 0);
+        // b (duplicate extra and unused) counted once, plus c.
+        assert_eq!(report.reclaimable_bytes, 24);
+    }
+
+    #[test]
+    fn matching_samples_are_not_duplicates_unless_the_contents_match() {
+        let tmp = TempDir::new("storage");
+        // Four sample-sized blocks: the start, middle and end samples skip
+        // the second half of the second block.
+        let weights = vec![7u8; 4 * SAMPLE_BYTES];
+        let mut tweaked = weights.clone();
+        tweaked[SAMPLE_BYTES + SAMPLE_BYTES / 4] = 8;
+        let a = model(&tmp, "a.gguf", &weights);
+        let b = model(&tmp, "b.gguf", &tweaked);
+        let size = weights.len() as u64;
+        assert_eq!(sample_hash(Path::new(&a.path), size), sample_hash(Path::new(&b.path), size));
+
+        assert!(find_duplicates(&[a.clone(), b]).is_empty());
+        let c = model(&tmp, "c.gguf", &weights);
+        assert_eq!(find_duplicates(&[a.clone(), c.clone()])[0].paths, vec![a.path, c.path]);
+    }
+
+    #[test]
+    fn move_file_refuses_to_overwrite() {
+        let tmp = TempDir::new("storage");
+        let m = model(&tmp, "src/m.gguf", b"x");
+        fs::create_dir_all(tmp.0.join("dst")).unwrap();
+        let moved = move_file(Path::new(&m.path), &tmp.0.join("dst")).unwrap();
+        assert!(moved.exists());
+        assert!(!Path::new(&m.path).exists());
+
+        let again = model(&tmp, "src/m.gguf", b"y");
+        assert!(move_file(Path::new(&again.path), &tmp.0.join("dst")).is_err());
+        assert!(Path::new(&again.path).exists());
+    }
+}
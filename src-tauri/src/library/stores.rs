@@ -0,0 +1,426 @@
+//! Store-aware scanners for model caches that other tools manage.
+//!
+//! A plain recursive scan (`scan_directories`) either misses these stores or
+//! lists them under unreadable blob names, so each store gets its own scanner
+//! that understands the on-disk layout and resolves it into [`ScannedModel`]s
+//! whose `path` can be handed straight to `llama-server -m`:
+//!
+//! | Store        | Layout                                                                 |
+//! |--------------|------------------------------------------------------------------------|
+//! | Hugging Face | `hub/models--{org}--{name}/snapshots/{rev}/…` (symlinks into `blobs/`) |
+//! | Ollama       | `manifests/{registry}/{namespace}/{model}/{tag}` + `blobs/sha256-{hex}` |
+//! | LM Studio    | `models/{publisher}/{repo}/{file}.gguf`                                |
+//!
+//! Every scanner takes its root explicitly so it can be tested against a fake
+//! layout; [`default_roots`] resolves the usual locations (honouring
+//! `HF_HUB_CACHE` / `HF_HOME` / `OLLAMA_MODELS`).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use shared::ipc::ScannedModel;
+
+use super::{format_size, is_supported_model_file, parse_model_hierarchy, clean_query_name};
+
+pub const SOURCE_HUGGINGFACE: &str = "huggingface";
+pub const SOURCE_OLLAMA: &str = "ollama";
+pub const SOURCE_LMSTUDIO: &str = "lmstudio";
+
+/// Ollama layer media type carrying the GGUF weights.
+const OLLAMA_MODEL_LAYER: &str = "application/vnd.ollama.image.model";
+/// Ollama's default registry host; references from it are shown without it.
+const OLLAMA_DEFAULT_REGISTRY: &str = "registry.ollama.ai";
+
+/// Store roots to scan. Missing directories are simply skipped.
+#[derive(Debug, Clone, Default)]
+pub struct StoreRoots {
+    pub huggingface: Vec<PathBuf>,
+    pub ollama: Vec<PathBuf>,
+    pub lmstudio: Vec<PathBuf>,
+}
+
+/// The conventional store locations for the current user. Without a home
+/// directory only the stores named by environment variables are scanned.
+pub fn default_roots() -> StoreRoots {
+    let home = dirs::home_dir();
+
+    let hf = std::env::var("HF_HUB_CACHE")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HF_HOME").map(|h| PathBuf::from(h).join("hub")))
+        .ok()
+        .or_else(|| home.as_ref().map(|h| h.join(".cache/huggingface/hub")));
+
+    let ollama = std::env::var("OLLAMA_MODELS")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| home.as_ref().map(|h| h.join(".ollama/models")));
+
+    let mut lmstudio = Vec::new();
+    if let Some(home) = &home {
+        lmstudio = vec![home.join(".lmstudio/models"), home.join(".cache/lm-studio/models")];
+        // LM Studio lets users relocate the models folder; honour its setting.
+        if let Some(custom) = lmstudio_downloads_folder(&home.join(".lmstudio/settings.json")) {
+            if !lmstudio.contains(&custom) {
+                lmstudio.insert(0, custom);
+            }
+        }
+    }
+
+    StoreRoots {
+        huggingface: hf.into_iter().collect(),
+        ollama: ollama.into_iter().collect(),
+        lmstudio,
+    }
+}
+
+fn lmstudio_downloads_folder(settings: &Path) -> Option<PathBuf> {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(settings).ok()?).ok()?;
+    json["downloadsFolder"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Scan every configured store, skipping roots that don't exist.
+pub fn scan_stores(roots: &StoreRoots) -> Vec<ScannedModel> {
+    let mut models = Vec::new();
+    for root in &roots.huggingface {
+        models.extend(scan_hf_cache(root));
+    }
+    for root in &roots.ollama {
+        models.extend(scan_ollama(root));
+    }
+    for root in &roots.lmstudio {
+        models.extend(scan_lmstudio(root));
+    }
+    models
+}
+
+// ── Hugging Face hub cache ───────────────────────────────────────────────────
+
+/// Scan a hub cache (`~/.cache/huggingface/hub`). Only the revision `refs/main`
+/// points at is listed when it exists, so stale snapshots don't show up twice.
+pub fn scan_hf_cache(hub: &Path) -> Vec<ScannedModel> {
+    let mut models = Vec::new();
+    let Ok(entries) = fs::read_dir(hub) else {
+        return models;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(repo_id) = hf_repo_id(&name) else {
+            continue;
+        };
+        let repo_dir = entry.path();
+        let snapshots = repo_dir.join("snapshots");
+
+        let revisions: Vec<PathBuf> = match fs::read_to_string(repo_dir.join("refs/main")) {
+            Ok(rev) if snapshots.join(rev.trim()).is_dir() => vec![snapshots.join(rev.trim())],
+            _ => fs::read_dir(&snapshots)
+                .map(|rd| rd.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+                .unwrap_or_default(),
+        };
+
+        for rev in revisions {
+            let mut files = Vec::new();
+            collect_model_files(&rev, &mut files);
+            for file in files {
+                // Snapshot entries are symlinks into `blobs/`; a dangling link
+                // means the download never finished.
+                let Ok(meta) = fs::metadata(&file) else {
+                    continue;
+                };
+                let filename = file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut model = store_model(&file, &filename, meta.len(), SOURCE_HUGGINGFACE, &repo_id);
+                model.hf_link = format!("https://huggingface.co/{repo_id}");
+                models.push(model);
+            }
+        }
+    }
+    models
+}
+
+/// `models--org--name` → `org/name`, and `models--gpt2` → `gpt2` for the
+/// older repos without an owner. Datasets and spaces share the cache but
+/// use a different prefix and are ignored.
+fn hf_repo_id(dir_name: &str) -> Option<String> {
+    let rest = dir_name.strip_prefix("models--")?;
+    match rest.split_once("--") {
+        Some((org, name)) if !org.is_empty() && !name.is_empty() => Some(format!("{org}/{name}")),
+        Some(_) => None,
+        None if !rest.is_empty() => Some(rest.to_string()),
+        None => None,
+    }
+}
+
+// ── Ollama ───────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct OllamaManifest {
+    #[serde(default)]
+    layers: Vec<OllamaLayer>,
+}
+
+#[derive(Deserialize)]
+struct OllamaLayer {
+    #[serde(rename = "mediaType")]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    size: u64,
+}
+
+/// Scan an Ollama models dir (`~/.ollama/models`). Each manifest names the blob
+/// holding the GGUF weights; the entry's `path` is that blob, displayed under
+/// the `model:tag` reference users know it by.
+pub fn scan_ollama(root: &Path) -> Vec<ScannedModel> {
+    let mut models = Vec::new();
+    let manifests = root.join("manifests");
+    let mut files = Vec::new();
+    collect_files(&manifests, &mut files, 0);
+
+    for manifest_path in files {
+        let Ok(rel) = manifest_path.strip_prefix(&manifests) else {
+            continue;
+        };
+        let parts: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        // {registry}/{namespace}/{model}/{tag}
+        if parts.len() != 4 {
+            continue;
+        }
+        let Some(manifest) = fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<OllamaManifest>(&s).ok())
+        else {
+            continue;
+        };
+        let Some(layer) = manifest.layers.iter().find(|l| l.media_type == OLLAMA_MODEL_LAYER) else {
+            continue;
+        };
+        let blob = root.join("blobs").join(layer.digest.replacen(':', "-", 1));
+        let Ok(meta) = fs::metadata(&blob) else {
+            continue;
+        };
+
+        let reference = ollama_reference(&parts[0], &parts[1], &parts[2], &parts[3]);
+        let size = if meta.len() > 0 { meta.len() } else { layer.size };
+        let mut model = store_model(&blob, &reference, size, SOURCE_OLLAMA, &reference);
+        // The blob has no extension, so the hierarchy parser can't infer the format.
+        if !model.tags.iter().any(|t| t == "GGUF") {
+            model.tags.insert(0, "GGUF".to_string());
+        }
+        models.push(model);
+    }
+    models
+}
+
+/// Render a manifest location the way `ollama list` does: `llama3:8b` for the
+/// default library, `user/model:tag` for other namespaces, and the full
+/// `host/namespace/model:tag` for third-party registries.
+fn ollama_reference(registry: &str, namespace: &str, model: &str, tag: &str) -> String {
+    if registry != OLLAMA_DEFAULT_REGISTRY {
+        format!("{registry}/{namespace}/{model}:{tag}")
+    } else if namespace == "library" {
+        format!("{model}:{tag}")
+    } else {
+        format!("{namespace}/{model}:{tag}")
+    }
+}
+
+// ── LM Studio ────────────────────────────────────────────────────────────────
+
+/// Scan an LM Studio models dir, laid out as `{publisher}/{repo}/{file}`. The
+/// publisher/repo pair mirrors the Hugging Face repo the file came from.
+pub fn scan_lmstudio(root: &Path) -> Vec<ScannedModel> {
+    let mut models = Vec::new();
+    let mut files = Vec::new();
+    collect_model_files(root, &mut files);
+
+    for file in files {
+        let Ok(rel) = file.strip_prefix(root) else {
+            continue;
+        };
+        let parts: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let Ok(meta) = fs::metadata(&file) else {
+            continue;
+        };
+        let filename = parts.last().cloned().unwrap_or_default();
+        let repo_id = if parts.len() >= 3 {
+            format!("{}/{}", parts[0], parts[1])
+        } else {
+            String::new()
+        };
+        let mut model = store_model(&file, &filename, meta.len(), SOURCE_LMSTUDIO, &repo_id);
+        if !repo_id.is_empty() {
+            model.hf_link = format!("https://huggingface.co/{repo_id}");
+        }
+        models.push(model);
+    }
+    models
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+fn store_model(path: &Path, display_name: &str, size_bytes: u64, source: &str, repo_id: &str) -> ScannedModel {
+    let hierarchy = parse_model_hierarchy(display_name);
+    ScannedModel {
+        path: path.to_string_lossy().to_string(),
+        filename: display_name.to_string(),
+        size_bytes,
+        clean_name: clean_query_name(display_name),
+        use_case: "Unknown".to_string(),
+        hf_link: String::new(),
+        github_link: String::new(),
+        size_info: format_size(size_bytes),
+        status: "pending_enrichment".to_string(),
+        family: hierarchy.family,
+        version: hierarchy.version,
+        tags: hierarchy.tags,
+        source: source.to_string(),
+        repo_id: repo_id.to_string(),
+    }
+}
+
+/// Recursively collect supported model files (following snapshot symlinks).
+fn collect_model_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files, 0);
+    out.extend(files.into_iter().filter(|p| is_supported_model_file(p)));
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>, depth: usize) {
+    // Store layouts are shallow; the cap guards against symlink loops.
+    if depth > 6 {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, out, depth + 1);
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn write(store: &TempDir, rel: &str, contents: &[u8]) -> PathBuf {
+        let path = store.0.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn hf_cache_resolves_repo_and_main_snapshot() {
+        let store = TempDir::new("hf");
+        let repo = "models--Qwen--Qwen3-8B-GGUF";
+        write(&store, &format!("{repo}/blobs/abc123"), b"GGUF-weights");
+        write(&store, &format!("{repo}/refs/main"), b"rev2\n");
+        write(&store, &format!("{repo}/snapshots/rev1/old-Q8_0.gguf"), b"stale");
+        let live = store.0.join(format!("{repo}/snapshots/rev2/Qwen3-8B-Q4_K_M.gguf"));
+        fs::create_dir_all(live.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(store.0.join(format!("{repo}/blobs/abc123")), &live).unwrap();
+        // Dangling link = unfinished download; must be skipped.
+        std::os::unix::fs::symlink(store.0.join("missing"), live.with_file_name("partial.gguf")).unwrap();
+        write(&store, "datasets--foo--bar/snapshots/x/data.gguf", b"not a model");
+
+        let models = scan_hf_cache(&store.0);
+        assert_eq!(models.len(), 1);
+        let m = &models[0];
+        assert_eq!(m.repo_id, "Qwen/Qwen3-8B-GGUF");
+        assert_eq!(m.source, SOURCE_HUGGINGFACE);
+        assert_eq!(m.filename, "Qwen3-8B-Q4_K_M.gguf");
+        assert_eq!(m.size_bytes, 12);
+        assert_eq!(m.hf_link, "https://huggingface.co/Qwen/Qwen3-8B-GGUF");
+        assert!(m.tags.contains(&"Q4_K_M".to_string()));
+    }
+
+    #[test]
+    fn ollama_manifest_resolves_model_blob() {
+        let store = TempDir::new("ollama");
+        let manifest = r#"{
+            "schemaVersion": 2,
+            "layers": [
+                {"mediaType": "application/vnd.ollama.image.model", "digest": "sha256:aaaa", "size": 7},
+                {"mediaType": "application/vnd.ollama.image.template", "digest": "sha256:bbbb", "size": 3}
+            ]
+        }"#;
+        write(&store, "manifests/registry.ollama.ai/library/llama3/8b", manifest.as_bytes());
+        write(&store, "manifests/registry.ollama.ai/alice/tiny/latest", manifest.as_bytes());
+        write(&store, "blobs/sha256-aaaa", b"weights");
+        write(&store, "blobs/sha256-bbbb", b"tpl");
+
+        let mut models = scan_ollama(&store.0);
+        models.sort_by(|a, b| a.filename.cmp(&b.filename));
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].filename, "alice/tiny:latest");
+        assert_eq!(models[1].filename, "llama3:8b");
+        assert_eq!(models[1].repo_id, "llama3:8b");
+        assert_eq!(models[1].source, SOURCE_OLLAMA);
+        assert!(models[1].path.ends_with("blobs/sha256-aaaa"));
+        assert_eq!(models[1].size_bytes, 7);
+        assert!(models[1].tags.contains(&"GGUF".to_string()));
+    }
+
+    #[test]
+    fn ollama_skips_manifests_without_blobs() {
+        let store = TempDir::new("ollama-missing");
+        write(
+            &store,
+            "manifests/registry.ollama.ai/library/gone/latest",
+            br#"{"layers":[{"mediaType":"application/vnd.ollama.image.model","digest":"sha256:ffff"}]}"#,
+        );
+        assert!(scan_ollama(&store.0).is_empty());
+    }
+
+    #[test]
+    fn ollama_reference_formats() {
+        assert_eq!(ollama_reference("registry.ollama.ai", "library", "phi3", "mini"), "phi3:mini");
+        assert_eq!(ollama_reference("hf.co", "bartowski", "x-GGUF", "Q4_K_M"), "hf.co/bartowski/x-GGUF:Q4_K_M");
+    }
+
+    #[test]
+    fn lmstudio_uses_publisher_repo() {
+        let store = TempDir::new("lms");
+        write(&store, "lmstudio-community/gemma-3-4b-it-GGUF/gemma-3-4b-it-Q4_K_M.gguf", b"1234");
+        write(&store, "lmstudio-community/gemma-3-4b-it-GGUF/README.md", b"docs");
+
+        let models = scan_lmstudio(&store.0);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].repo_id, "lmstudio-community/gemma-3-4b-it-GGUF");
+        assert_eq!(models[0].source, SOURCE_LMSTUDIO);
+        assert_eq!(models[0].family, "Gemma");
+    }
+
+    #[test]
+    fn hf_repo_id_parsing() {
+        assert_eq!(hf_repo_id("models--org--name").as_deref(), Some("org/name"));
+        assert_eq!(hf_repo_id("models--org--name--with--dashes").as_deref(), Some("org/name--with--dashes"));
+        assert_eq!(hf_repo_id("datasets--org--name"), None);
+        assert_eq!(hf_repo_id("models--gpt2").as_deref(), Some("gpt2"));
+        assert_eq!(hf_repo_id("models--bert-base-uncased").as_deref(), Some("bert-base-uncased"));
+        assert_eq!(hf_repo_id("models--"), None);
+        assert_eq!(hf_repo_id("models----name"), None);
+    }
+}
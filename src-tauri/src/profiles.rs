@@ -0,0 +1,132 @@
+//! Named config profiles: full [`ServerConfig`] snapshots stored as
+//! `profiles/<name>.json` in the config dir, so a model + launch-flag
+//! combination can be saved once and re-applied later.
+
+use std::fs;
+use std::path::PathBuf;
+
+use shared::ipc::ConfigProfile;
+use shared::ServerConfig;
+
+use crate::config_io::config_dir;
+
+fn profiles_dir() -> PathBuf {
+    config_dir().join("profiles")
+}
+
+/// Profile names become file names; allow only characters that are safe there.
+fn profile_path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.'))
+        || name.starts_with('.')
+    {
+        return Err(format!("Invalid profile name `{name}`"));
+    }
+    Ok(profiles_dir().join(format!("{name}.json")))
+}
+
+pub fn list() -> Vec<ConfigProfile> {
+    let mut profiles: Vec<ConfigProfile> = fs::read_dir(profiles_dir())
+        .map(|rd| {
+            rd.flatten()
+                .filter_map(|e| {
+                    let path = e.path();
+                    if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                        return None;
+                    }
+                    let name = path.file_stem()?.to_string_lossy().to_string();
+                    match fs::read_to_string(&path).map(|s| serde_json::from_str::<ServerConfig>(&s)) {
+                        Ok(Ok(config)) => Some(ConfigProfile { name, config }),
+                        _ => {
+                            tracing::warn!(path = %path.display(), "skipping unreadable profile");
+                            None
+                        }
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    profiles
+}
+
+pub fn load(name: &str) -> Result<ServerConfig, String> {
+    let json = fs::read_to_string(profile_path(name)?).map_err(|e| format!("Profile `{name}`: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Profile `{name}` is invalid: {e}"))
+}
+
+pub fn save(name: &str, config: &ServerConfig) -> Result<(), String> {
+    let path = profile_path(name)?;
+    fs::create_dir_all(profiles_dir()).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    fs::remove_file(profile_path(name)?).map_err(|e| format!("Profile `{name}`: {e}"))
+}
+
+/// Point every model reference in `cfg` (main model, draft model, LoRA
+/// adapters) at `new` where it was `old`. Returns whether anything changed.
+pub fn rewrite_model_path(cfg: &mut ServerConfig, old: &str, new: &str) -> bool {
+    let mut changed = false;
+    for slot in [&mut cfg.model_path, &mut cfg.draft_model]
+        .into_iter()
+        .chain(cfg.lora_adapters.iter_mut().map(|l| &mut l.path))
+    {
+        if slot == old {
+            *slot = new.to_string();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Apply [`rewrite_model_path`] to every saved profile; returns the names of
+/// the profiles that were updated.
+pub fn rewrite_model_path_in_profiles(old: &str, new: &str) -> Result<Vec<String>, String> {
+    let mut updated = Vec::new();
+    for mut profile in list() {
+        if rewrite_model_path(&mut profile.config, old, new) {
+            save(&profile.name, &profile.config)?;
+            updated.push(profile.name);
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::config::LoraAdapter;
+
+    #[test]
+    fn rewrites_every_model_reference() {
+        let mut cfg = ServerConfig {
+            model_path: "/a/m.gguf".into(),
+            draft_model: "/a/m.gguf".into(),
+            lora_adapters: vec![
+                LoraAdapter { path: "/a/m.gguf".into(), scale: 1.0 },
+                LoraAdapter { path: "/a/other.gguf".into(), scale: 0.5 },
+            ],
+            ..Default::default()
+        };
+        assert!(rewrite_model_path(&mut cfg, "/a/m.gguf", "/b/m.gguf"));
+        assert_eq!(cfg.model_path, "/b/m.gguf");
+        assert_eq!(cfg.draft_model, "/b/m.gguf");
+        assert_eq!(cfg.lora_adapters[0].path, "/b/m.gguf");
+        assert_eq!(cfg.lora_adapters[1].path, "/a/other.gguf");
+        assert!(!rewrite_model_path(&mut cfg, "/a/m.gguf", "/c/m.gguf"));
+    }
+
+    #[test]
+    fn rejects_path_like_names() {
+        assert!(profile_path("../escape").is_err());
+        assert!(profile_path(".hidden").is_err());
+        assert!(profile_path("").is_err());
+        assert!(profile_path("Qwen 8B fast-ctx").is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+//! The prompt library: [`PromptTemplate`]s kept in `prompts.json` in the
+//! config dir. Saving a changed text adds a version, so earlier wordings can
+//! be looked at and restored. Rendering fills `{{variables}}` from the caller
+//! and the built-ins (date and time, clipboard), which are read at the moment
+//! of rendering — for a calendar event, when it fires.
+
+use std::path::Path;
+use std::process::Command;
+
+use shared::ipc::{PromptDraft, PromptRef, PromptTemplate, PromptVersion};
+use shared::prompts;
+
+const FILE: &str = "prompts.json";
+
+/// Every prompt, by name.
+pub fn list(dir: &Path) -> Vec<PromptTemplate> {
+    let mut all: Vec<PromptTemplate> = std::fs::read_to_string(dir.join(FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    all.sort_by_key(|p| p.name.to_lowercase());
+    all
+}
+
+fn write(dir: &Path, all: &[PromptTemplate]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(all).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(FILE), json).map_err(|e| format!("{FILE}: {e}"))
+}
+
+/// Create a prompt, or update one. Name, description and tags change in
+/// place; a different text becomes a new version.
+pub fn save(dir: &Path, draft: PromptDraft, now: i64) -> Result<PromptTemplate, String> {
+    let name = draft.name.trim().to_string();
+    if name.is_empty() {
+        return Err("A prompt needs a name".into());
+    }
+    if draft.body.trim().is_empty() {
+        return Err("A prompt needs some text".into());
+    }
+    let mut tags: Vec<String> = Vec::new();
+    for tag in draft.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    let mut all = list(dir);
+    let prompt = match &draft.id {
+        Some(id) => {
+            let p = all
+                .iter_mut()
+                .find(|p| p.id == *id)
+                .ok_or_else(|| format!("Prompt {id} not found"))?;
+            if p.body() != draft.body {
+                add_version(p, draft.body, now);
+            }
+            p
+        }
+        None => {
+            all.push(PromptTemplate {
+                id: crate::util::new_id("prompt"),
+                name: String::new(),
+                description: String::new(),
+                tags: Vec::new(),
+                versions: vec![PromptVersion { version: 1, body: draft.body, saved_at: now }],
+                created_at: now,
+                updated_at: now,
+            });
+            all.last_mut().unwrap()
+        }
+    };
+    prompt.name = name;
+    prompt.description = draft.description.trim().to_string();
+    prompt.tags = tags;
+    prompt.updated_at = now;
+    let saved = prompt.clone();
+    write(dir, &all)?;
+    Ok(saved)
+}
+
+fn add_version(p: &mut PromptTemplate, body: String, now: i64) {
+    let version = p.versions.last().map_or(1, |v| v.version + 1);
+    p.versions.push(PromptVersion { version, body, saved_at: now });
+}
+
+/// Make an earlier version current again, as a new version on top.
+pub fn revert(dir: &Path, id: &str, version: u32, now: i64) -> Result<PromptTemplate, String> {
+    let mut all = list(dir);
+    let p = all
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Prompt {id} not found"))?;
+    let body = p
+        .versions
+        .iter()
+        .find(|v| v.version == version)
+        .ok_or_else(|| format!("Prompt `{}` has no version {version}", p.name))?
+        .body
+        .clone();
+    if p.body() != body {
+        add_version(p, body, now);
+        p.updated_at = now;
+    }
+    let saved = p.clone();
+    write(dir, &all)?;
+    Ok(saved)
+}
+
+pub fn delete(dir: &Path, id: &str) -> Result<(), String> {
+    let mut all = list(dir);
+    let before = all.len();
+    all.retain(|p| p.id != id);
+    if all.len() == before {
+        return Err(format!("Prompt {id} not found"));
+    }
+    write(dir, &all)
+}
+
+/// The text of a library prompt with its variables filled in. Variables
+/// without a value are left as written.
+pub fn render(dir: &Path, r: &PromptRef) -> Result<String, String> {
+    let all = list(dir);
+    let p = all
+        .iter()
+        .find(|p| p.id == r.id)
+        .ok_or_else(|| format!("Prompt {} not found", r.id))?;
+    let body = match r.version {
+        Some(n) => p
+            .versions
+            .iter()
+            .find(|v| v.version == n)
+            .map(|v| v.body.as_str())
+            .ok_or_else(|| format!("Prompt `{}` has no version {n}", p.name))?,
+        None => p.body(),
+    };
+    Ok(prompts::render(body, |name| r.vars.get(name).cloned().or_else(|| builtin(name))))
+}
+
+/// Fill only the built-in variables; for free-form prompts like a calendar
+/// event's own text.
+pub fn render_builtins(text: &str) -> String {
+    prompts::render(text, builtin)
+}
+
+fn builtin(name: &str) -> Option<String> {
+    let now = chrono::Local::now();
+    Some(match name {
+        "date" => now.format("%Y-%m-%d").to_string(),
+        "time" => now.format("%H:%M").to_string(),
+        "datetime" => now.format("%Y-%m-%d %H:%M").to_string(),
+        "weekday" => now.format("%A").to_string(),
+        "clipboard" => clipboard_text().unwrap_or_default(),
+        _ => return None,
+    })
+}
+
+/// Clipboard text through whichever platform tool is available.
+fn clipboard_text() -> Option<String> {
+    let tools: &[(&str, &[&str])] = &[
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-selection", "clipboard", "-o"]),
+        ("xsel", &["--clipboard", "--output"]),
+        ("pbpaste", &[]),
+        ("powershell", &["-NoProfile", "-Command", "Get-Clipboard"]),
+    ];
+    tools.iter().find_map(|(tool, args)| {
+        let out = Command::new(tool).args(*args).output().ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim_end_matches(['\r', '\n']).to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn edits_add_versions_and_render_fills_variables() {
+        let dir = TempDir::new("prompts");
+        let draft = PromptDraft {
+            name: " Standup ".into(),
+            tags: vec!["work".into(), " Work".into(), "".into()],
+            body: "Summarise {{project}} for {{date}}.".into(),
+            ..Default::default()
+        };
+        let p = save(&dir.0, draft.clone(), 1).unwrap();
+        assert_eq!((p.name.as_str(), p.tags.clone()), ("Standup", vec!["work".to_string()]));
+
+        // Renaming alone keeps the version; new text adds one.
+        let id = Some(p.id.clone());
+        let p = save(&dir.0, PromptDraft { id: id.clone(), name: "Daily standup".into(), ..draft.clone() }, 2).unwrap();
+        assert_eq!(p.versions.len(), 1);
+        let body = "List blockers in {{project}}.".to_string();
+        let p = save(&dir.0, PromptDraft { id, body, ..draft }, 3).unwrap();
+        assert_eq!(p.versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![1, 2]);
+
+        let mut r = PromptRef { id: p.id.clone(), ..Default::default() };
+        r.vars.insert("project".into(), "llama-manager".into());
+        assert_eq!(render(&dir.0, &r).unwrap(), "List blockers in llama-manager.");
+        r.version = Some(1);
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(render(&dir.0, &r).unwrap(), format!("Summarise llama-manager for {today}."));
+
+        let p = revert(&dir.0, &p.id, 1, 4).unwrap();
+        assert_eq!((p.versions.len(), p.body()), (3, "Summarise {{project}} for {{date}}."));
+        assert_eq!(list(&dir.0), vec![p.clone()]);
+        delete(&dir.0, &p.id).unwrap();
+        assert!(list(&dir.0).is_empty());
+    }
+}
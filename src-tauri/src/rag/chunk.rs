@@ -0,0 +1,243 @@
+//! Splits a document into passages small enough to embed and quote.
+//!
+//! The text is first cut into blocks along the structure of the format:
+//! Markdown at headings and paragraphs (never inside a fenced code block),
+//! code at blank lines before an unindented line (top-level items), plain
+//! text at paragraphs. Blocks are then packed into chunks of about
+//! [`TARGET_CHARS`]; a Markdown chunk never spans two sections. A block over
+//! [`MAX_CHARS`] is cut first.
+
+use std::path::Path;
+
+/// Chunks are packed up to this size when the blocks allow it.
+pub const TARGET_CHARS: usize = 1200;
+/// Hard limit for one chunk.
+pub const MAX_CHARS: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocKind {
+    Markdown,
+    Code,
+    Text,
+}
+
+impl DocKind {
+    /// The kind for a file name, or `None` for files we don't index (binaries,
+    /// images, archives and anything else unknown).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        Some(match ext.as_str() {
+            "md" | "markdown" | "mdx" => Self::Markdown,
+            "txt" | "text" | "rst" | "org" | "adoc" | "log" | "csv" | "tsv" => Self::Text,
+            "rs" | "py" | "js" | "mjs" | "ts" | "tsx" | "jsx" | "go" | "c" | "h" | "cc" | "cpp" | "hpp" | "java"
+            | "kt" | "swift" | "rb" | "php" | "cs" | "scala" | "lua" | "zig" | "sh" | "bash" | "zsh" | "ps1"
+            | "sql" | "html" | "css" | "scss" | "vue" | "svelte" | "toml" | "yaml" | "yml" | "json" | "xml"
+            | "ini" | "cfg" | "proto" | "graphql" => Self::Code,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    /// 1-based, inclusive.
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Enclosing Markdown headings, outermost first, joined with ` › `.
+    pub heading: String,
+    pub text: String,
+}
+
+/// A run of lines that belongs together.
+struct Block {
+    start: usize,
+    lines: Vec<String>,
+    heading: String,
+}
+
+impl Block {
+    fn len(&self) -> usize {
+        self.lines.iter().map(|l| l.len() + 1).sum()
+    }
+}
+
+pub fn chunk(text: &str, kind: DocKind) -> Vec<Chunk> {
+    let blocks = match kind {
+        DocKind::Markdown => prose_blocks(text, true),
+        DocKind::Text => prose_blocks(text, false),
+        DocKind::Code => code_blocks(text),
+    };
+    pack(blocks)
+}
+
+/// Paragraph blocks; with `markdown`, headings also start a block and set the
+/// heading path, and fenced code stays in one piece.
+fn prose_blocks(text: &str, markdown: bool) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if markdown && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            in_fence = !in_fence;
+        }
+        if !in_fence {
+            if markdown {
+                if let Some((level, title)) = heading(trimmed) {
+                    blocks.extend(current.take());
+                    headings.retain(|(l, _)| *l < level);
+                    headings.push((level, title.to_string()));
+                }
+            }
+            if line.trim().is_empty() {
+                blocks.extend(current.take());
+                continue;
+            }
+        }
+        let path = || headings.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>().join(" › ");
+        current
+            .get_or_insert_with(|| Block { start: i, lines: Vec::new(), heading: path() })
+            .lines
+            .push(line.to_string());
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// `## Title` → `(2, "Title")`.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')))
+        .then(|| (level, rest.trim().trim_end_matches('#').trim()))
+}
+
+/// A new block starts at an unindented line after a blank one, which is where
+/// top-level items begin in most languages. Comments directly above an item
+/// stay with it.
+fn code_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut after_blank = false;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            after_blank = true;
+            if let Some(block) = current.as_mut() {
+                block.lines.push(String::new());
+            }
+            continue;
+        }
+        let top_level = !line.starts_with([' ', '\t']);
+        if after_blank && top_level {
+            blocks.extend(current.take());
+        }
+        after_blank = false;
+        current
+            .get_or_insert_with(|| Block { start: i, lines: Vec::new(), heading: String::new() })
+            .lines
+            .push(line.to_string());
+    }
+    blocks.extend(current);
+    for block in &mut blocks {
+        while block.lines.last().is_some_and(|l| l.is_empty()) {
+            block.lines.pop();
+        }
+    }
+    blocks
+}
+
+fn pack(blocks: Vec<Block>) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut current: Option<Block> = None;
+    for block in blocks.into_iter().flat_map(split_long) {
+        if let Some(open) = current.as_mut() {
+            let next_line = open.start + open.lines.len();
+            if block.start >= next_line && open.heading == block.heading && open.len() + block.len() <= TARGET_CHARS {
+                // Keep the gap between the blocks so line numbers stay true.
+                let gap = block.start - next_line;
+                open.lines.extend(std::iter::repeat_n(String::new(), gap));
+                open.lines.extend(block.lines);
+                continue;
+            }
+        }
+        chunks.extend(current.replace(block).map(to_chunk));
+    }
+    chunks.extend(current.map(to_chunk));
+    chunks
+}
+
+/// Cut a block over [`MAX_CHARS`] at line ends. A single overlong line (say,
+/// minified code) is cut into pieces of its own that all point at that line.
+fn split_long(block: Block) -> Vec<Block> {
+    if block.len() <= MAX_CHARS {
+        return vec![block];
+    }
+    let heading = block.heading;
+    let empty = |start| Block { start, lines: Vec::new(), heading: heading.clone() };
+    let mut parts = Vec::new();
+    let mut part = empty(block.start);
+    for (offset, line) in block.lines.into_iter().enumerate() {
+        let line_no = block.start + offset;
+        if !part.lines.is_empty() && part.len() + line.len() + 1 > MAX_CHARS {
+            parts.push(std::mem::replace(&mut part, empty(line_no)));
+        }
+        if line.len() + 1 > MAX_CHARS {
+            // Counted in chars so cuts land on char boundaries; a quarter of
+            // the limit stays under it even for 4-byte characters.
+            let chars: Vec<char> = line.chars().collect();
+            parts.extend(chars.chunks(MAX_CHARS / 4).map(|piece| Block {
+                lines: vec![piece.iter().collect()],
+                ..empty(line_no)
+            }));
+            part = empty(line_no + 1);
+            continue;
+        }
+        part.lines.push(line);
+    }
+    if !part.lines.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+fn to_chunk(block: Block) -> Chunk {
+    Chunk {
+        start_line: block.start as u32 + 1,
+        end_line: (block.start + block.lines.len()) as u32,
+        heading: block.heading,
+        text: block.lines.join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_chunks_follow_sections_and_keep_fences_whole() {
+        let doc = "# Guide\n\nIntro.\n\n## Install\n\nRun this:\n\n```sh\ncargo build\n\ncargo run\n```\n\n## Use\n\nOpen it.\n";
+        let chunks = chunk(doc, DocKind::Markdown);
+        let summary: Vec<_> = chunks.iter().map(|c| (c.heading.as_str(), c.start_line, c.end_line)).collect();
+        assert_eq!(summary, vec![("Guide", 1, 3), ("Guide › Install", 5, 13), ("Guide › Use", 15, 17)]);
+        assert!(chunks[1].text.contains("cargo build\n\ncargo run"));
+        let lines: Vec<&str> = doc.lines().collect();
+        assert_eq!(chunks[1].text, lines[4..13].join("\n"), "line numbers match the text");
+    }
+
+    #[test]
+    fn code_splits_between_items_and_long_text_is_capped() {
+        let item = |name: &str| format!("/// {name}\nfn {name}() {{\n    let x = 1;\n\n    x\n}}\n");
+        let source = [item("a"), item("b")].join("\n");
+        let blocks = code_blocks(&source);
+        assert_eq!(blocks.len(), 2, "the blank line inside `a` doesn't split it");
+        assert_eq!((blocks[1].start, blocks[1].lines[0].as_str()), (7, "/// b"));
+
+        let long = "word ".repeat(1000);
+        let chunks = chunk(&format!("{long}\n\n{long}"), DocKind::Text);
+        // Ten pieces per line; the last of line 1 packs with the first of line 3.
+        assert_eq!(chunks.len(), 19);
+        assert!(chunks.iter().all(|c| c.text.len() <= MAX_CHARS));
+        assert_eq!(chunks.last().unwrap().start_line, 3);
+    }
+}
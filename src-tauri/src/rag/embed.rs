@@ -0,0 +1,123 @@
+//! Client for an OpenAI-compatible `/v1/embeddings` endpoint: llama-server
+//! started with `--embedding`, or any other server that speaks the API.
+
+use std::time::Duration;
+
+use serde_json::json;
+
+/// Inputs per request; llama-server handles a batch in one pass as long as it
+/// fits its batch size.
+const BATCH: usize = 16;
+
+pub struct Embedder {
+    url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl Embedder {
+    /// `base_url` is the server root, e.g. `http://127.0.0.1:8080`.
+    pub fn new(base_url: &str, model: &str) -> Self {
+        Self {
+            url: format!("{}/v1/embeddings", base_url.trim_end_matches('/')),
+            model: model.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// One L2-normalised vector per input, in input order.
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut vectors = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(BATCH) {
+            vectors.extend(self.embed_batch(batch).await?);
+        }
+        Ok(vectors)
+    }
+
+    async fn embed_batch(&self, batch: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut body = json!({ "input": batch, "encoding_format": "float" });
+        if !self.model.is_empty() {
+            body["model"] = json!(self.model);
+        }
+        let res = self
+            .client
+            .post(&self.url)
+            .timeout(Duration::from_secs(120))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Error contacting embedding server: {e}"))?;
+        let status = res.status();
+        let v: serde_json::Value = res.json().await.unwrap_or_default();
+        if !status.is_success() {
+            let detail = v["error"]["message"].as_str().unwrap_or_default();
+            // llama-server answers 501 when started without `--embedding`.
+            let hint = if status.as_u16() == 501 || detail.contains("--embedding") {
+                " Turn on Embedding in the server settings or set an embedding server under Document Chat."
+            } else {
+                ""
+            };
+            return Err(format!("Embedding server returned {status}: {detail}{hint}"));
+        }
+        let mut data: Vec<(u64, Vec<f32>)> = v["data"]
+            .as_array()
+            .ok_or("Embedding response has no `data` list")?
+            .iter()
+            .map(|d| {
+                let vector = d["embedding"]
+                    .as_array()
+                    .ok_or("Embedding response item has no `embedding` list")?
+                    .iter()
+                    .map(|x| x.as_f64().map(|x| x as f32))
+                    .collect::<Option<Vec<f32>>>()
+                    .ok_or("Embedding is not a flat list of numbers (is pooling set to none?)")?;
+                Ok((d["index"].as_u64().unwrap_or_default(), vector))
+            })
+            .collect::<Result<_, &str>>()?;
+        if data.len() != batch.len() {
+            return Err(format!("Embedding server returned {} vectors for {} inputs", data.len(), batch.len()));
+        }
+        data.sort_by_key(|(i, _)| *i);
+        Ok(data.into_iter().map(|(_, v)| normalize(v)).collect())
+    }
+}
+
+fn normalize(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn embeds_in_input_order_and_explains_a_disabled_endpoint() {
+        let server = StubServer::start(|req| {
+            let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+            let n = body["input"].as_array().unwrap().len();
+            // Out of order, like some servers answer.
+            let data: Vec<_> = (0..n).rev().map(|i| json!({ "index": i, "embedding": [i as f32 + 1.0, 0.0] })).collect();
+            StubResponse::json(json!({ "data": data }))
+        })
+        .await;
+        let inputs: Vec<String> = (0..20).map(|i| format!("chunk {i}")).collect();
+        let vectors = Embedder::new(&server.base_url, "").embed(&inputs).await.unwrap();
+        assert_eq!(vectors.len(), 20);
+        assert_eq!(vectors[0], vec![1.0, 0.0], "normalised");
+        assert_eq!(server.requests().len(), 2, "sent in batches");
+
+        let off = StubServer::start(|_| {
+            let mut res = StubResponse::json(json!({ "error": { "code": 501, "message": "This server does not support embeddings. Start it with `--embeddings`" } }));
+            res.status = 501;
+            res
+        })
+        .await;
+        let err = Embedder::new(&off.base_url, "").embed(&inputs[..1]).await.unwrap_err();
+        assert!(err.contains("501") && err.contains("Turn on Embedding"), "{err}");
+    }
+}
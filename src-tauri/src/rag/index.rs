@@ -0,0 +1,245 @@
+//! SQLite-backed vector index of attached documents (`rag.db` in the config
+//! dir).
+//!
+//! `documents` has one row per attached file and conversation; `chunks` holds
+//! its passages with their embeddings as little-endian `f32` blobs. Vectors
+//! are normalised, so similarity is a dot product, and search is a scan over
+//! one conversation's chunks — a few thousand per chat is well within what a
+//! linear scan handles per turn.
+
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection};
+use shared::ipc::ChatAttachment;
+
+use super::chunk::Chunk;
+
+const DB_FILE: &str = "rag.db";
+const SCHEMA_VERSION: i64 = 1;
+
+/// A chunk found by [`RagIndex::search`].
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub path: String,
+    pub chunk: Chunk,
+    pub score: f32,
+}
+
+pub struct RagIndex {
+    conn: Connection,
+}
+
+impl RagIndex {
+    /// Open (creating and migrating as needed) the index in `config_dir`.
+    pub fn open(config_dir: &Path) -> Result<Self, String> {
+        let conn = Connection::open(config_dir.join(DB_FILE)).map_err(|e| e.to_string())?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+        let index = Self { conn };
+        index.migrate()?;
+        Ok(index)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+        self.conn
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS documents (
+                    id              TEXT PRIMARY KEY,
+                    conversation_id TEXT NOT NULL,
+                    path            TEXT NOT NULL,
+                    added_at        INTEGER NOT NULL,
+                    UNIQUE (conversation_id, path)
+                );
+                CREATE TABLE IF NOT EXISTS chunks (
+                    id              INTEGER PRIMARY KEY,
+                    document_id     TEXT NOT NULL,
+                    conversation_id TEXT NOT NULL,
+                    start_line      INTEGER NOT NULL,
+                    end_line        INTEGER NOT NULL,
+                    heading         TEXT NOT NULL,
+                    text            TEXT NOT NULL,
+                    vector          BLOB NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_chunks_conversation ON chunks(conversation_id);
+                CREATE INDEX IF NOT EXISTS idx_chunks_document ON chunks(document_id);",
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Store a file's chunks and their vectors, replacing an earlier copy of
+    /// the same path in this conversation.
+    pub fn add_document(
+        &mut self,
+        conversation_id: &str,
+        path: &str,
+        chunks: &[(Chunk, Vec<f32>)],
+        now: i64,
+    ) -> Result<ChatAttachment, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let old: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT id FROM documents WHERE conversation_id = ?1 AND path = ?2")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([conversation_id, path], |r| r.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        for id in old {
+            remove_document(&tx, &id)?;
+        }
+        let id = crate::util::new_id("doc");
+        tx.execute(
+            "INSERT INTO documents (id, conversation_id, path, added_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, conversation_id, path, now],
+        )
+        .map_err(|e| e.to_string())?;
+        for (chunk, vector) in chunks {
+            let blob: Vec<u8> = vector.iter().flat_map(|x| x.to_le_bytes()).collect();
+            tx.execute(
+                "INSERT INTO chunks (document_id, conversation_id, start_line, end_line, heading, text, vector)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![id, conversation_id, chunk.start_line, chunk.end_line, chunk.heading, chunk.text, blob],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ChatAttachment {
+            id,
+            path: path.to_string(),
+            chunks: chunks.len() as u32,
+            added_at: now,
+        })
+    }
+
+    /// A conversation's attachments, in the order they were added.
+    pub fn list(&self, conversation_id: &str) -> Result<Vec<ChatAttachment>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.id, d.path, (SELECT COUNT(*) FROM chunks c WHERE c.document_id = d.id), d.added_at
+                 FROM documents d WHERE d.conversation_id = ?1 ORDER BY d.added_at, d.rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([conversation_id], |r| {
+                Ok(ChatAttachment {
+                    id: r.get(0)?,
+                    path: r.get(1)?,
+                    chunks: r.get(2)?,
+                    added_at: r.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    pub fn remove(&mut self, document_id: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        remove_document(&tx, document_id)?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Drop everything attached to a conversation; called when it is deleted.
+    pub fn remove_conversation(&mut self, conversation_id: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        for table in ["chunks", "documents"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE conversation_id = ?1"), [conversation_id])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// The `k` chunks closest to `query` (a normalised vector), best first.
+    /// Chunks embedded with a model of another dimension are skipped.
+    pub fn search(&self, conversation_id: &str, query: &[f32], k: usize) -> Result<Vec<Hit>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.path, c.start_line, c.end_line, c.heading, c.text, c.vector
+                 FROM chunks c JOIN documents d ON d.id = c.document_id
+                 WHERE c.conversation_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([conversation_id]).map_err(|e| e.to_string())?;
+        let mut hits = Vec::new();
+        while let Some(r) = rows.next().map_err(|e| e.to_string())? {
+            let blob: Vec<u8> = r.get(5).map_err(|e| e.to_string())?;
+            if blob.len() != query.len() * 4 {
+                continue;
+            }
+            let score = blob
+                .chunks_exact(4)
+                .zip(query)
+                .map(|(b, q)| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) * q)
+                .sum();
+            let get = |i| r.get::<_, String>(i).map_err(|e| e.to_string());
+            hits.push(Hit {
+                path: get(0)?,
+                chunk: Chunk {
+                    start_line: r.get(1).map_err(|e| e.to_string())?,
+                    end_line: r.get(2).map_err(|e| e.to_string())?,
+                    heading: get(3)?,
+                    text: get(4)?,
+                },
+                score,
+            });
+        }
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        Ok(hits)
+    }
+}
+
+fn remove_document(conn: &Connection, document_id: &str) -> Result<(), String> {
+    for (table, column) in [("chunks", "document_id"), ("documents", "id")] {
+        conn.execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [document_id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn chunk(line: u32, text: &str) -> Chunk {
+        Chunk { start_line: line, end_line: line, heading: String::new(), text: text.into() }
+    }
+
+    #[test]
+    fn searches_by_similarity_within_a_conversation() {
+        let dir = TempDir::new("rag-index");
+        let mut index = RagIndex::open(&dir.0).unwrap();
+        let a = vec![(chunk(1, "cats"), vec![1.0, 0.0]), (chunk(2, "dogs"), vec![0.0, 1.0])];
+        index.add_document("conv-1", "/notes/pets.md", &a, 10).unwrap();
+        index.add_document("conv-2", "/notes/other.md", &[(chunk(1, "fish"), vec![1.0, 0.0])], 10).unwrap();
+
+        let hits = index.search("conv-1", &[0.6, 0.8], 1).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].path.as_str(), hits[0].chunk.text.as_str()), ("/notes/pets.md", "dogs"));
+        assert!(index.search("conv-1", &[1.0, 0.0, 0.0], 4).unwrap().is_empty(), "other dimension");
+
+        // Re-attaching a path replaces it.
+        let doc = index.add_document("conv-1", "/notes/pets.md", &a[..1], 20).unwrap();
+        let listed = index.list("conv-1").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].id.as_str(), listed[0].chunks), (doc.id.as_str(), 1));
+
+        index.remove(&doc.id).unwrap();
+        assert!(index.search("conv-1", &[1.0, 0.0], 4).unwrap().is_empty());
+        index.remove_conversation("conv-2").unwrap();
+        assert!(index.list("conv-2").unwrap().is_empty());
+    }
+}
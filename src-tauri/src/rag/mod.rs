@@ -0,0 +1,238 @@
+//! Document-grounded chat. Files attached to a conversation are split into
+//! chunks ([`chunk`]), embedded through `/v1/embeddings` ([`embed`]) and kept
+//! in a local vector index ([`index`]). Before each reply, [`ground`] looks up
+//! the passages closest to the question and puts them, numbered, in front of
+//! it so the model can cite them.
+
+pub mod chunk;
+pub mod embed;
+pub mod index;
+
+use std::path::{Path, PathBuf};
+
+use shared::ipc::{ChatAttachment, ChatRequest, ChatSource};
+use shared::ServerConfig;
+
+use chunk::DocKind;
+use embed::Embedder;
+use index::{Hit, RagIndex};
+
+/// Most files one attach walks into.
+const MAX_FILES: usize = 500;
+/// Larger files are skipped; they are rarely prose or hand-written code.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+/// Directories never worth indexing.
+const SKIP_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "venv", "__pycache__"];
+
+/// Root URL of the embedding server: the configured one, else the server the
+/// chat talks to.
+pub fn embedding_url(cfg: &ServerConfig, host: &str, port: u16) -> String {
+    if cfg.rag_embedding_url.trim().is_empty() {
+        format!("http://{host}:{port}")
+    } else {
+        cfg.rag_embedding_url.trim().to_string()
+    }
+}
+
+/// The files to index for `path`: the file itself, or the supported files
+/// below a folder, skipping hidden entries and build/dependency folders.
+pub fn collect_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(format!("{} not found", path.display()));
+    }
+    let mut files = Vec::new();
+    walk(path, &mut files);
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("No supported documents in {}", path.display()));
+    }
+    Ok(files)
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if files.len() >= MAX_FILES {
+            tracing::warn!(dir = %dir.display(), "attach stopped at {MAX_FILES} files");
+            return;
+        }
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !SKIP_DIRS.contains(&name.as_str()) {
+                walk(&path, files);
+            }
+        } else if DocKind::from_path(&path).is_some()
+            && entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES)
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Chunk, embed and store `path` (a file or a folder) for a conversation.
+/// Files that aren't UTF-8 text or hold nothing are skipped. Each file is
+/// stored as soon as it is embedded, so a failure keeps the ones before it.
+pub async fn attach(
+    config_dir: &Path,
+    embedder: &Embedder,
+    conversation_id: &str,
+    path: &Path,
+    now: i64,
+) -> Result<Vec<ChatAttachment>, String> {
+    let mut added = Vec::new();
+    for file in collect_files(path)? {
+        let Ok(text) = std::fs::read_to_string(&file) else {
+            tracing::debug!(file = %file.display(), "skipping non-text file");
+            continue;
+        };
+        // A file picked on its own is read as plain text whatever its type.
+        let kind = DocKind::from_path(&file).unwrap_or(DocKind::Text);
+        let chunks = chunk::chunk(&text, kind);
+        if chunks.is_empty() {
+            continue;
+        }
+        let display = file.to_string_lossy().to_string();
+        let inputs: Vec<String> = chunks.iter().map(|c| embed_input(&display, c)).collect();
+        let vectors = embedder.embed(&inputs).await?;
+        let pairs: Vec<_> = chunks.into_iter().zip(vectors).collect();
+        added.push(RagIndex::open(config_dir)?.add_document(conversation_id, &display, &pairs, now)?);
+    }
+    if added.is_empty() {
+        return Err(format!("Nothing to index in {}", path.display()));
+    }
+    tracing::info!(count = added.len(), path = %path.display(), "attached documents");
+    Ok(added)
+}
+
+/// What gets embedded for a chunk: its text under the file name and headings,
+/// which often carry the words a question uses.
+fn embed_input(path: &str, c: &chunk::Chunk) -> String {
+    let name = Path::new(path).file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    if c.heading.is_empty() {
+        format!("{name}\n\n{}", c.text)
+    } else {
+        format!("{name} › {}\n\n{}", c.heading, c.text)
+    }
+}
+
+/// Put the passages closest to the last user message in front of it, from
+/// the documents attached to `req.conversation_id`. Returns the sources used;
+/// empty when nothing is attached or `rag_top_k` is 0.
+pub async fn ground(req: &mut ChatRequest, cfg: &ServerConfig, config_dir: &Path) -> Result<Vec<ChatSource>, String> {
+    let Some(conversation_id) = req.conversation_id.clone() else {
+        return Ok(Vec::new());
+    };
+    if cfg.rag_top_k == 0 || RagIndex::open(config_dir)?.list(&conversation_id)?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let embedder = Embedder::new(&embedding_url(cfg, &req.host, req.port), &cfg.rag_embedding_model);
+    let Some(question) = req.messages.iter_mut().rev().find(|m| m.role == "user") else {
+        return Ok(Vec::new());
+    };
+    let query = embedder.embed(std::slice::from_ref(&question.content)).await?;
+    let hits = RagIndex::open(config_dir)?.search(&conversation_id, &query[0], cfg.rag_top_k as usize)?;
+    if hits.is_empty() {
+        return Ok(Vec::new());
+    }
+    question.content = grounded_prompt(&hits, &question.content);
+    Ok(hits
+        .into_iter()
+        .enumerate()
+        .map(|(i, hit)| ChatSource {
+            index: i as u32 + 1,
+            path: hit.path,
+            start_line: hit.chunk.start_line,
+            end_line: hit.chunk.end_line,
+            heading: hit.chunk.heading,
+            score: hit.score,
+        })
+        .collect())
+}
+
+fn grounded_prompt(hits: &[Hit], question: &str) -> String {
+    let mut out = String::from(
+        "Answer using the numbered sources below where they are relevant, and cite them like [1]. \
+         If they don't cover the question, say so and answer from what you know.\n\n",
+    );
+    for (i, hit) in hits.iter().enumerate() {
+        let c = &hit.chunk;
+        out.push_str(&format!("[{}] {}:{}-{}", i + 1, hit.path, c.start_line, c.end_line));
+        if !c.heading.is_empty() {
+            out.push_str(&format!(" ({})", c.heading));
+        }
+        out.push_str(&format!("\n\"\"\"\n{}\n\"\"\"\n\n", c.text));
+    }
+    out.push_str(&format!("Question: {question}"));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer, TempDir};
+    use serde_json::json;
+    use shared::ipc::ChatMessage;
+
+    #[tokio::test]
+    async fn attached_folders_ground_the_question_with_citations() {
+        // A toy embedding: does the text mention llamas or not.
+        let server = StubServer::start(|req| {
+            let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+            let data: Vec<_> = body["input"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let llama = text.as_str().unwrap().to_lowercase().contains("llama");
+                    json!({ "index": i, "embedding": if llama { [1.0, 0.1] } else { [0.1, 1.0] } })
+                })
+                .collect();
+            StubResponse::json(json!({ "data": data }))
+        })
+        .await;
+
+        let config = TempDir::new("rag");
+        let docs = TempDir::new("rag");
+        std::fs::create_dir_all(docs.0.join("node_modules")).unwrap();
+        std::fs::create_dir_all(docs.0.join("notes")).unwrap();
+        std::fs::write(docs.0.join("node_modules/skip.md"), "llama").unwrap();
+        std::fs::write(docs.0.join("image.png"), [0u8, 159, 146, 150]).unwrap();
+        std::fs::write(docs.0.join("notes/animals.md"), "# Animals\n\n## Llamas\n\nLlamas hum.\n\n## Cats\n\nCats purr.\n").unwrap();
+        std::fs::write(docs.0.join("notes/todo.txt"), "Buy milk.\n").unwrap();
+
+        let embedder = Embedder::new(&server.base_url, "");
+        let added = attach(&config.0, &embedder, "conv-1", &docs.0, 1).await.unwrap();
+        assert_eq!(added.iter().map(|a| a.chunks).collect::<Vec<_>>(), vec![3, 1], "{added:?}");
+
+        let port = server.base_url.rsplit(':').next().unwrap().parse().unwrap();
+        let mut req = ChatRequest {
+            stream_id: "s".into(),
+            host: "127.0.0.1".into(),
+            port,
+            model: "m".into(),
+            messages: vec![ChatMessage::new("user", "What sound do llamas make?")],
+            sampling: None,
+            conversation_id: Some("conv-1".into()),
+        };
+        let cfg = ServerConfig { rag_top_k: 1, ..Default::default() };
+        let sources = ground(&mut req, &cfg, &config.0).await.unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!((sources[0].heading.as_str(), sources[0].start_line), ("Animals › Llamas", 3));
+        let prompt = &req.messages[0].content;
+        assert!(prompt.contains("animals.md:3-5 (Animals › Llamas)\n\"\"\"\n## Llamas\n\nLlamas hum."), "{prompt}");
+        assert!(prompt.ends_with("Question: What sound do llamas make?"));
+
+        req.conversation_id = Some("conv-2".into());
+        assert!(ground(&mut req, &cfg, &config.0).await.unwrap().is_empty(), "nothing attached");
+    }
+}
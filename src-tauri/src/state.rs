@@ -5,16 +5,20 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use shared::ServerConfig;
+use shared::ipc::ApprovalScope;
 use tokio::process::Child;
 use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
 
+use crate::agent::approval::SessionGrants;
+use crate::download::DownloadQueue;
+
 /// Per-agent control block: cancellation + the set of approval gates currently
 /// awaiting a human decision (keyed by tool-call id).
 pub struct AgentHandle {
     pub id: String,
     pub cancel: CancellationToken,
-    pending_approvals: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+    pending_approvals: Mutex<HashMap<String, oneshot::Sender<(bool, ApprovalScope)>>>,
 }
 
 impl AgentHandle {
@@ -27,7 +31,7 @@ impl AgentHandle {
     }
 
     /// Register an approval gate; returns the receiver the agent awaits.
-    pub fn register_approval(&self, call_id: &str) -> oneshot::Receiver<bool> {
+    pub fn register_approval(&self, call_id: &str) -> oneshot::Receiver<(bool, ApprovalScope)> {
         let (tx, rx) = oneshot::channel();
         self.pending_approvals
             .lock()
@@ -36,11 +40,12 @@ impl AgentHandle {
         rx
     }
 
-    /// Resolve a pending approval (from the `agent_approve` command). Returns
-    /// false if no such gate exists (e.g. it already timed out).
-    pub fn resolve_approval(&self, call_id: &str, approved: bool) -> bool {
+    /// Resolve a pending approval (from the `agent_approve` command), perhaps
+    /// for later calls too. Returns false if no such gate exists (e.g. it
+    /// already timed out).
+    pub fn resolve_approval(&self, call_id: &str, approved: bool, remember: ApprovalScope) -> bool {
         if let Some(tx) = self.pending_approvals.lock().unwrap().remove(call_id) {
-            let _ = tx.send(approved);
+            let _ = tx.send((approved, remember));
             true
         } else {
             false
@@ -48,6 +53,13 @@ impl AgentHandle {
     }
 }
 
+/// A model delete awaiting confirmation (see `library_delete_prepare`).
+pub struct PendingDelete {
+    pub path: String,
+    /// Unix seconds after which the ticket is void.
+    pub expires_at: i64,
+}
+
 #[derive(Default)]
 pub struct AppState {
     /// Canonical config; the only in-memory source of truth. Persisted to disk by
@@ -64,6 +76,14 @@ pub struct AppState {
     pub benchmark_child: Mutex<Option<Child>>,
     /// Live agents (top-level and sub-agents) by id, for approval routing + cancel.
     pub agents: Mutex<HashMap<String, Arc<AgentHandle>>>,
+    /// Cancellation tokens of in-flight `chat_send` streams, by stream id.
+    pub chat_streams: Mutex<HashMap<String, CancellationToken>>,
+    /// Guarded model deletes awaiting `library_delete_confirm`, by ticket token.
+    pub pending_deletes: Mutex<HashMap<String, PendingDelete>>,
+    /// Tools the user approved "for this session" from an approval prompt.
+    pub approval_grants: SessionGrants,
+    /// Download queue drained by the worker in [`crate::download`].
+    pub downloads: DownloadQueue,
 }
 
 impl AppState {
@@ -74,6 +94,10 @@ impl AppState {
             deep_research_child: Mutex::new(None),
             benchmark_child: Mutex::new(None),
             agents: Mutex::new(HashMap::new()),
+            chat_streams: Mutex::new(HashMap::new()),
+            pending_deletes: Mutex::new(HashMap::new()),
+            approval_grants: SessionGrants::default(),
+            downloads: DownloadQueue::open(crate::config_io::config_dir().join("download_queue.json")),
         }
     }
 
@@ -91,4 +115,28 @@ impl AppState {
     pub fn remove_agent(&self, id: &str) {
         self.agents.lock().unwrap().remove(id);
     }
+
+    pub fn register_chat_stream(&self, stream_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.chat_streams
+            .lock()
+            .unwrap()
+            .insert(stream_id.to_string(), token.clone());
+        token
+    }
+
+    /// Returns false if the stream already finished (or never existed).
+    pub fn cancel_chat_stream(&self, stream_id: &str) -> bool {
+        match self.chat_streams.lock().unwrap().get(stream_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_chat_stream(&self, stream_id: &str) {
+        self.chat_streams.lock().unwrap().remove(stream_id);
+    }
 }
@@ -0,0 +1,347 @@
+//! Test-only helpers shared across modules.
+//!
+//! [`StubServer`] is a tiny HTTP/1.1 server on an ephemeral localhost port so
+//! code that talks to Hugging Face, SearXNG or llama-server can be exercised
+//! without the network. Each connection serves one request and closes, which
+//! is all reqwest needs.
+//!
+//! [`MockLlm`] is a scripted OpenAI-compatible model on top of it, and
+//! [`RecordingSink`] an [`EventSink`] that keeps what an agent run reports, so
+//! whole runs can be driven and asserted on.
+//!
+//! [`TempDir`] is a scratch directory for tests that touch the disk.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Value, json};
+use shared::ipc::{AgentEvent, ApprovalScope};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::agent::sink::EventSink;
+use crate::state::AgentHandle;
+
+/// A fresh directory under the system temp dir, named after `prefix` and
+/// removed again on drop.
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        let dir = std::env::temp_dir().join(crate::util::new_id(prefix));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A request as seen by the stub.
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: String,
+    /// Path including the query string, e.g. `/api/models?search=qwen`.
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StubRequest {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A canned response.
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StubResponse {
+    pub fn json(value: serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "text/plain".into())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "not found")
+    }
+}
+
+type Handler = dyn Fn(&StubRequest) -> StubResponse + Send + Sync;
+
+pub struct StubServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl StubServer {
+    /// Start serving `handler` on `127.0.0.1:<ephemeral>`.
+    pub async fn start(handler: impl Fn(&StubRequest) -> StubResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind stub server");
+        let addr = listener.local_addr().unwrap();
+        let handler: Arc<Handler> = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        let task = tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let Some(req) = read_request(&mut sock).await else {
+                        return;
+                    };
+                    seen.lock().unwrap().push(req.clone());
+                    let res = handler(&req);
+                    let mut head = format!("HTTP/1.1 {} Stub\r\n", res.status);
+                    for (k, v) in &res.headers {
+                        head.push_str(&format!("{k}: {v}\r\n"));
+                    }
+                    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", res.body.len()));
+                    let _ = sock.write_all(head.as_bytes()).await;
+                    let _ = sock.write_all(&res.body).await;
+                    let _ = sock.shutdown().await;
+                });
+            }
+        });
+
+        Self {
+            base_url: format!("http://{addr}"),
+            requests,
+            task,
+        }
+    }
+
+    /// Every request served so far, in arrival order.
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn read_request(sock: &mut tokio::net::TcpStream) -> Option<StubRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let n = sock.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut first = lines.next()?.split_whitespace();
+    let method = first.next()?.to_string();
+    let target = first.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    let len = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buf[head_end + 4..].to_vec();
+    while body.len() < len {
+        let n = sock.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Some(StubRequest {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+/// One scripted model reply.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// An answer with no tool calls.
+    Text(String),
+    /// Tool calls as `(name, arguments)`, after an optional preface.
+    Tools(String, Vec<(String, Value)>),
+    /// An error status with a plain-text body.
+    Status(u16),
+}
+
+impl Reply {
+    pub fn text(text: &str) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn tool(name: &str, args: Value) -> Self {
+        Self::Tools(String::new(), vec![(name.into(), args)])
+    }
+}
+
+/// An OpenAI-compatible `/v1/chat/completions` that serves its script one
+/// reply per request, in order, as SSE when the request asks for a stream.
+/// Past the end of the script every request gets a 500.
+pub struct MockLlm {
+    server: StubServer,
+    pub port: u16,
+}
+
+impl MockLlm {
+    pub async fn start(script: Vec<Reply>) -> Self {
+        let next = AtomicUsize::new(0);
+        let server = StubServer::start(move |req| {
+            let n = next.fetch_add(1, Ordering::SeqCst);
+            let body: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+            match script.get(n) {
+                Some(reply) => completion(reply, n, body["stream"] == true),
+                None => StubResponse::text(500, "script exhausted"),
+            }
+        })
+        .await;
+        let port = server.base_url.rsplit(':').next().unwrap().parse().unwrap();
+        Self { server, port }
+    }
+
+    /// The JSON body of every request so far, in order.
+    pub fn requests(&self) -> Vec<Value> {
+        self.server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_slice(&r.body).unwrap_or_default())
+            .collect()
+    }
+}
+
+fn completion(reply: &Reply, n: usize, stream: bool) -> StubResponse {
+    let (content, calls) = match reply {
+        Reply::Status(status) => return StubResponse::text(*status, "scripted failure"),
+        Reply::Text(text) => (text.as_str(), Vec::new()),
+        Reply::Tools(preface, calls) => (preface.as_str(), calls.clone()),
+    };
+    let tool_calls: Vec<Value> = calls
+        .iter()
+        .enumerate()
+        .map(|(i, (name, args))| {
+            json!({
+                "index": i,
+                "id": format!("call_{n}_{i}"),
+                "type": "function",
+                "function": { "name": name, "arguments": args.to_string() },
+            })
+        })
+        .collect();
+    let usage = json!({ "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 });
+    if !stream {
+        let mut message = json!({ "role": "assistant", "content": content });
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = json!(tool_calls);
+        }
+        return StubResponse::json(json!({ "choices": [{ "message": message }], "usage": usage }));
+    }
+    // Content in word-sized deltas, then each call whole.
+    let mut chunks: Vec<Value> = content
+        .split_inclusive(' ')
+        .map(|word| json!({ "choices": [{ "delta": { "content": word } }] }))
+        .collect();
+    if !tool_calls.is_empty() {
+        chunks.push(json!({ "choices": [{ "delta": { "tool_calls": tool_calls } }] }));
+    }
+    chunks.push(json!({ "choices": [], "usage": usage }));
+    let mut body: String = chunks.iter().map(|c| format!("data: {c}\n\n")).collect();
+    body.push_str("data: [DONE]\n\n");
+    StubResponse {
+        status: 200,
+        headers: vec![("Content-Type".into(), "text/event-stream".into())],
+        body: body.into_bytes(),
+    }
+}
+
+/// Keeps every event, and answers approval requests from a queue of
+/// decisions; when the queue is empty requests are left to time out.
+#[derive(Default)]
+pub struct RecordingSink {
+    events: Mutex<Vec<AgentEvent>>,
+    agents: Mutex<HashMap<String, Arc<AgentHandle>>>,
+    decisions: Mutex<VecDeque<bool>>,
+}
+
+impl RecordingSink {
+    pub fn approving(decisions: &[bool]) -> Self {
+        Self { decisions: Mutex::new(decisions.iter().copied().collect()), ..Default::default() }
+    }
+
+    pub fn events(&self) -> Vec<AgentEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Ids of the agents registered and not yet removed.
+    pub fn live_agents(&self) -> Vec<String> {
+        self.agents.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn emit(&self, event: &AgentEvent) {
+        self.events.lock().unwrap().push(event.clone());
+        if let AgentEvent::ApprovalRequest { agent_id, request } = event {
+            let decision = self.decisions.lock().unwrap().pop_front();
+            let handle = self.agents.lock().unwrap().get(agent_id).cloned();
+            if let (Some(approved), Some(handle)) = (decision, handle) {
+                handle.resolve_approval(&request.call_id, approved, ApprovalScope::Once);
+            }
+        }
+    }
+
+    fn activity(&self, _agent_id: &str, _status: &str, _action: &str, _message: &str) {}
+
+    fn register_agent(&self, handle: Arc<AgentHandle>) {
+        self.agents.lock().unwrap().insert(handle.id.clone(), handle);
+    }
+
+    fn remove_agent(&self, id: &str) {
+        self.agents.lock().unwrap().remove(id);
+    }
+}
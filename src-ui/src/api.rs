@@ -8,9 +8,12 @@ use serde_json::json;
 use shared::ServerConfig;
 use shared::ipc::{
     AgentRequest, ApprovalDecision, ChatRequest, ModelList, TodoItem, NotesStore,
-    ScannedModel, KanbanTask, PlannerState, MonitorState, CalendarEvent, CalendarState,
-    LlamaInstance, DownloadStatus, BenchmarkOutput, ResearchStatus, ResearchReportInfo,
-    OptimizationSuggestion, Memory, ChatMessage, SkillOrAgentFile,
+    ScannedModel, LibraryQuery, LibraryQueryResult, StorageReport, DeleteTicket, ConfigProfile,
+    KanbanTask, PlannerState, MonitorState, CalendarEvent, CalendarState,
+    LlamaInstance, DownloadStatus, DownloadItem, HfRepoListing, BenchmarkOutput, ResearchStatus, ResearchReportInfo,
+    OptimizationSuggestion, Memory, ChatMessage, SkillOrAgentFile, ChatSampling, ChatSearchHit, Conversation,
+    ConversationPatch, ConversationSummary, ChatExportFormat, ChatAttachment,
+    PromptDraft, PromptRef, PromptTemplate, Lesson, AgentRun, AgentRunSummary,
 };
 
 use crate::ipc;
@@ -25,6 +28,22 @@ pub async fn update_config(config: ServerConfig) -> Result<(), String> {
     Ok(())
 }
 
+// ── Config profiles ─────────────────────────────────────────────────────────
+pub async fn profile_list() -> Result<Vec<ConfigProfile>, String> {
+    ipc::invoke("profile_list", &ipc::no_args()).await
+}
+pub async fn profile_save(name: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("profile_save", &json!({ "name": name })).await?;
+    Ok(())
+}
+pub async fn profile_apply(name: String) -> Result<ServerConfig, String> {
+    ipc::invoke("profile_apply", &json!({ "name": name })).await
+}
+pub async fn profile_delete(name: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("profile_delete", &json!({ "name": name })).await?;
+    Ok(())
+}
+
 // ── Chat ──────────────────────────────────────────────────────────────────
 pub async fn chat_list_models(host: String, port: u16) -> Result<ModelList, String> {
     ipc::invoke("chat_list_models", &json!({ "host": host, "port": port })).await
@@ -35,6 +54,11 @@ pub async fn chat_send(req: ChatRequest) -> Result<(), String> {
     Ok(())
 }
 
+pub async fn chat_cancel(stream_id: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("chat_cancel", &json!({ "streamId": stream_id })).await?;
+    Ok(())
+}
+
 // ── Agent ──────────────────────────────────────────────────────────────────
 pub async fn agent_start(req: AgentRequest) -> Result<String, String> {
     ipc::invoke("agent_start", &json!({ "req": req })).await
@@ -46,12 +70,36 @@ pub async fn agent_approve(decision: ApprovalDecision) -> Result<(), String> {
     Ok(())
 }
 
+/// Forget the tools approved "for this session".
+pub async fn agent_forget_approvals() -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("agent_forget_approvals", &json!({})).await?;
+    Ok(())
+}
+
 pub async fn agent_cancel(agent_id: String) -> Result<(), String> {
     let _: serde_json::Value =
         ipc::invoke("agent_cancel", &json!({ "agentId": agent_id })).await?;
     Ok(())
 }
 
+pub async fn agent_runs_list() -> Result<Vec<AgentRunSummary>, String> {
+    ipc::invoke("agent_runs_list", &ipc::no_args()).await
+}
+
+pub async fn agent_run_get(id: String) -> Result<AgentRun, String> {
+    ipc::invoke("agent_run_get", &json!({ "id": id })).await
+}
+
+pub async fn agent_run_delete(id: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("agent_run_delete", &json!({ "id": id })).await?;
+    Ok(())
+}
+
+/// Resume a recorded run; returns its id, which stays the same.
+pub async fn agent_resume(id: String) -> Result<String, String> {
+    ipc::invoke("agent_resume", &json!({ "id": id })).await
+}
+
 // ── Server lifecycle ─────────────────────────────────────────────────────────
 pub async fn server_start() -> Result<(), String> {
     let _: serde_json::Value = ipc::invoke("server_start", &ipc::no_args()).await?;
@@ -71,6 +119,10 @@ pub async fn pick_path(directory: bool) -> Result<Option<String>, String> {
     ipc::invoke("pick_path", &json!({ "directory": directory })).await
 }
 
+pub async fn pick_save_path(default_name: String) -> Result<Option<String>, String> {
+    ipc::invoke("pick_save_path", &json!({ "defaultName": default_name })).await
+}
+
 // ── Window controls ──────────────────────────────────────────────────────────
 pub async fn win_minimize() {
     let _: Result<serde_json::Value, _> = ipc::invoke("win_minimize", &ipc::no_args()).await;
@@ -106,10 +158,51 @@ pub async fn todos_set(items: Vec<TodoItem>) -> Result<(), String> {
     Ok(())
 }
 
+// ── Prompt library ──────────────────────────────────────────────────────────
+pub async fn prompt_list() -> Result<Vec<PromptTemplate>, String> {
+    ipc::invoke("prompt_list", &ipc::no_args()).await
+}
+
+pub async fn prompt_save(draft: PromptDraft) -> Result<PromptTemplate, String> {
+    ipc::invoke("prompt_save", &json!({ "draft": draft })).await
+}
+
+pub async fn prompt_revert(id: String, version: u32) -> Result<PromptTemplate, String> {
+    ipc::invoke("prompt_revert", &json!({ "id": id, "version": version })).await
+}
+
+pub async fn prompt_delete(id: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("prompt_delete", &json!({ "id": id })).await?;
+    Ok(())
+}
+
+pub async fn prompt_render(prompt: PromptRef) -> Result<String, String> {
+    ipc::invoke("prompt_render", &json!({ "prompt": prompt })).await
+}
+
 // ── Model Library ───────────────────────────────────────────────────────────
 pub async fn library_get_index() -> Result<Vec<ScannedModel>, String> {
     ipc::invoke("library_get_index", &ipc::no_args()).await
 }
+pub async fn library_query(query: LibraryQuery) -> Result<LibraryQueryResult, String> {
+    ipc::invoke("library_query", &json!({ "query": query })).await
+}
+pub async fn library_storage_report(unused_days: Option<u32>) -> Result<StorageReport, String> {
+    ipc::invoke("library_storage_report", &json!({ "unusedDays": unused_days })).await
+}
+pub async fn library_move_model(path: String, target_dir: String) -> Result<Vec<ScannedModel>, String> {
+    ipc::invoke("library_move_model", &json!({ "path": path, "targetDir": target_dir })).await
+}
+pub async fn library_delete_prepare(path: String) -> Result<DeleteTicket, String> {
+    ipc::invoke("library_delete_prepare", &json!({ "path": path })).await
+}
+pub async fn library_delete_confirm(token: String) -> Result<Vec<ScannedModel>, String> {
+    ipc::invoke("library_delete_confirm", &json!({ "token": token })).await
+}
+pub async fn library_delete_cancel(token: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("library_delete_cancel", &json!({ "token": token })).await?;
+    Ok(())
+}
 pub async fn library_scan() -> Result<Vec<ScannedModel>, String> {
     ipc::invoke("library_scan", &ipc::no_args()).await
 }
@@ -165,13 +258,24 @@ pub async fn download_start(targets: String) -> Result<(), String> {
     let _: serde_json::Value = ipc::invoke("download_start", &json!({ "targets": targets })).await?;
     Ok(())
 }
-pub async fn download_cancel() -> Result<(), String> {
-    let _: serde_json::Value = ipc::invoke("download_cancel", &ipc::no_args()).await?;
+/// Cancel one download, or every unfinished one when `id` is `None`.
+pub async fn download_cancel(id: Option<String>) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("download_cancel", &json!({ "id": id })).await?;
+    Ok(())
+}
+pub async fn download_clear_finished() -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("download_clear_finished", &ipc::no_args()).await?;
     Ok(())
 }
 pub async fn download_status() -> Result<DownloadStatus, String> {
     ipc::invoke("download_status", &ipc::no_args()).await
 }
+pub async fn hf_list_repo(repo: String) -> Result<HfRepoListing, String> {
+    ipc::invoke("hf_list_repo", &json!({ "repo": repo })).await
+}
+pub async fn hf_enqueue_files(repo: String, paths: Vec<String>, subdir: String) -> Result<Vec<DownloadItem>, String> {
+    ipc::invoke("hf_enqueue_files", &json!({ "repo": repo, "paths": paths, "subdir": subdir })).await
+}
 
 // ── Instance Monitor ─────────────────────────────────────────────────────────
 pub async fn instances_detect() -> Result<Vec<LlamaInstance>, String> {
@@ -236,9 +340,20 @@ pub async fn compare_run_eval(url: String, model: String, prompt: String) -> Res
 }
 
 // ── Agent Memory ─────────────────────────────────────────────────────────────
-pub async fn memory_get() -> Result<Vec<String>, String> {
+pub async fn memory_get() -> Result<Vec<Lesson>, String> {
     ipc::invoke("memory_get", &ipc::no_args()).await
 }
+pub async fn memory_review(id: String, approve: bool, text: Option<String>) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("memory_review", &json!({ "id": id, "approve": approve, "text": text })).await?;
+    Ok(())
+}
+pub async fn memory_delete(id: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("memory_delete", &json!({ "id": id })).await?;
+    Ok(())
+}
+pub async fn memory_consolidate() -> Result<Vec<Lesson>, String> {
+    ipc::invoke("memory_consolidate", &ipc::no_args()).await
+}
 pub async fn memory_clear() -> Result<(), String> {
     let _: serde_json::Value = ipc::invoke("memory_clear", &ipc::no_args()).await?;
     Ok(())
@@ -262,13 +377,69 @@ pub async fn obsidian_memory_delete(id: String, scope: String) -> Result<(), Str
     Ok(())
 }
 
-pub async fn chat_save_history(history: Vec<ChatMessage>) -> Result<(), String> {
-    let _: serde_json::Value = ipc::invoke("chat_save_history", &json!({ "history": history })).await?;
+pub async fn chat_list_conversations(include_archived: bool) -> Result<Vec<ConversationSummary>, String> {
+    ipc::invoke("chat_list_conversations", &json!({ "includeArchived": include_archived })).await
+}
+
+pub async fn chat_create_conversation(model: String, sampling: ChatSampling) -> Result<Conversation, String> {
+    ipc::invoke("chat_create_conversation", &json!({ "model": model, "sampling": sampling })).await
+}
+
+pub async fn chat_get_conversation(id: String) -> Result<Conversation, String> {
+    ipc::invoke("chat_get_conversation", &json!({ "id": id })).await
+}
+
+pub async fn chat_save_messages(id: String, messages: Vec<ChatMessage>) -> Result<ConversationSummary, String> {
+    ipc::invoke("chat_save_messages", &json!({ "id": id, "messages": messages })).await
+}
+
+pub async fn chat_regenerate(id: String, message_id: String) -> Result<Conversation, String> {
+    ipc::invoke("chat_regenerate", &json!({ "id": id, "messageId": message_id })).await
+}
+
+pub async fn chat_edit_message(id: String, message_id: String, content: String) -> Result<Conversation, String> {
+    ipc::invoke("chat_edit_message", &json!({ "id": id, "messageId": message_id, "content": content })).await
+}
+
+pub async fn chat_switch_branch(id: String, message_id: String) -> Result<Conversation, String> {
+    ipc::invoke("chat_switch_branch", &json!({ "id": id, "messageId": message_id })).await
+}
+
+pub async fn chat_export(ids: Vec<String>, format: ChatExportFormat, path: String) -> Result<u32, String> {
+    ipc::invoke("chat_export", &json!({ "ids": ids, "format": format, "path": path })).await
+}
+
+pub async fn chat_import(path: String) -> Result<Vec<ConversationSummary>, String> {
+    ipc::invoke("chat_import", &json!({ "path": path })).await
+}
+
+pub async fn chat_attach(conversation_id: String, path: String, host: String, port: u16) -> Result<Vec<ChatAttachment>, String> {
+    ipc::invoke(
+        "chat_attach",
+        &json!({ "conversationId": conversation_id, "path": path, "host": host, "port": port }),
+    )
+    .await
+}
+
+pub async fn chat_list_attachments(conversation_id: String) -> Result<Vec<ChatAttachment>, String> {
+    ipc::invoke("chat_list_attachments", &json!({ "conversationId": conversation_id })).await
+}
+
+pub async fn chat_detach(conversation_id: String, attachment_id: String) -> Result<Vec<ChatAttachment>, String> {
+    ipc::invoke("chat_detach", &json!({ "conversationId": conversation_id, "attachmentId": attachment_id })).await
+}
+
+pub async fn chat_update_conversation(id: String, patch: ConversationPatch) -> Result<ConversationSummary, String> {
+    ipc::invoke("chat_update_conversation", &json!({ "id": id, "patch": patch })).await
+}
+
+pub async fn chat_delete_conversation(id: String) -> Result<(), String> {
+    let _: serde_json::Value = ipc::invoke("chat_delete_conversation", &json!({ "id": id })).await?;
     Ok(())
 }
 
-pub async fn chat_load_history() -> Result<Vec<ChatMessage>, String> {
-    ipc::invoke("chat_load_history", &ipc::no_args()).await
+pub async fn chat_search(query: String) -> Result<Vec<ChatSearchHit>, String> {
+    ipc::invoke("chat_search", &json!({ "query": query })).await
 }
 
 pub async fn list_skills_and_agents() -> Result<Vec<SkillOrAgentFile>, String> {
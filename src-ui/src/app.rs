@@ -5,8 +5,8 @@ use gloo_timers::callback::Interval;
 use leptos::prelude::*;
 use shared::ServerConfig;
 use shared::ipc::{
-    AGENT_EVENT, CHAT_EVENT, CONFIG_CHANGED_EVENT,
-    AgentEvent, ChatEvent,
+    AGENT_EVENT, CHAT_EVENT, CONFIG_CHANGED_EVENT, DOWNLOAD_EVENT,
+    AgentEvent, ChatEvent, DownloadEvent,
 };
 use wasm_bindgen_futures::spawn_local;
 
@@ -21,7 +21,9 @@ pub fn App() -> impl IntoView {
         active_tab: RwSignal::new(Tab::Chat),
         routed_instance: RwSignal::new(None),
         routed_model: RwSignal::new(None),
-        chat_history: RwSignal::new(vec![]),
+        chat_conversation: RwSignal::new(None),
+        chat_sampling: RwSignal::new(Default::default()),
+        chat_conversations: RwSignal::new(vec![]),
         immersive: RwSignal::new(false),
         search: RwSignal::new(String::new()),
         server_running: RwSignal::new(false),
@@ -36,9 +38,17 @@ pub fn App() -> impl IntoView {
         chat_plan: RwSignal::new(vec![]),
         chat_trace: RwSignal::new(vec![]),
         chat_approvals: RwSignal::new(vec![]),
+        chat_agent_budget: RwSignal::new(Default::default()),
         chat_show_context: RwSignal::new(false),
+        chat_show_conversations: RwSignal::new(false),
+        chat_show_sampling: RwSignal::new(false),
+        chat_show_documents: RwSignal::new(false),
+        chat_show_prompts: RwSignal::new(false),
         // Observability
         obs_events: RwSignal::new(vec![]),
+        // Downloads
+        downloads: RwSignal::new(vec![]),
+        download_rates: RwSignal::new(Default::default()),
     };
     provide_context(ctx);
 
@@ -51,16 +61,16 @@ pub fn App() -> impl IntoView {
         }
     });
 
-    // Hydrate chat history into both signals
-    let chat_hist = ctx.chat_history;
-    let chat_msgs = ctx.chat_messages;
+    // Hydrate the conversation list and reopen the most recent chat.
     spawn_local(async move {
-        match api::chat_load_history().await {
-            Ok(hist) => {
-                chat_hist.set(hist.clone());
-                chat_msgs.set(hist);
+        match api::chat_list_conversations(false).await {
+            Ok(list) => {
+                if let Some(latest) = list.first() {
+                    ctx.open_conversation(latest.id.clone());
+                }
+                ctx.chat_conversations.set(list);
             }
-            Err(e) => tracing::error!("chat_load_history failed: {e}"),
+            Err(e) => tracing::error!("chat_list_conversations failed: {e}"),
         }
     });
     ipc::listen::<ServerConfig, _>(CONFIG_CHANGED_EVENT, move |cfg| config.set(cfg));
@@ -73,7 +83,6 @@ pub fn App() -> impl IntoView {
         let chat_gen   = ctx.chat_generating;
         let chat_err   = ctx.chat_error;
         let chat_stream = ctx.chat_current_stream;
-        let chat_hist  = ctx.chat_history;
         let obs        = ctx.obs_events;
 
         ipc::listen::<ChatEvent, _>(CHAT_EVENT, move |ev| {
@@ -94,16 +103,33 @@ pub fn App() -> impl IntoView {
                         });
                     }
                 }
-                ChatEvent::Done { stream_id } => {
+                ChatEvent::Reasoning { stream_id, delta } => {
                     if stream_id == chat_stream.get_untracked() {
-                        chat_gen.set(false);
-                        let msgs = chat_msgs.get_untracked();
-                        chat_hist.set(msgs.clone());
-                        spawn_local(async move {
-                            if let Err(e) = api::chat_save_history(msgs).await {
-                                tracing::error!("chat_save_history: {e}");
+                        chat_msgs.update(|m| {
+                            if let Some(last) = m.last_mut().filter(|l| l.role == "assistant") {
+                                last.reasoning.push_str(&delta);
+                            }
+                        });
+                    }
+                }
+                ChatEvent::Sources { stream_id, sources } => {
+                    if stream_id == chat_stream.get_untracked() {
+                        chat_msgs.update(|m| {
+                            if let Some(last) = m.last_mut().filter(|l| l.role == "assistant") {
+                                last.sources = sources;
+                            }
+                        });
+                    }
+                }
+                ChatEvent::Done { stream_id, stats } => {
+                    if stream_id == chat_stream.get_untracked() {
+                        chat_msgs.update(|m| {
+                            if let Some(last) = m.last_mut().filter(|l| l.role == "assistant") {
+                                last.stats = stats;
                             }
                         });
+                        chat_gen.set(false);
+                        ctx.persist_chat();
                         obs.update(|o| o.push(ObsEvent { ts: now, kind: "chat:done".into(), id: stream_id, content: String::new() }));
                     }
                 }
@@ -111,14 +137,22 @@ pub fn App() -> impl IntoView {
                     if stream_id == chat_stream.get_untracked() {
                         chat_err.set(Some(message.clone()));
                         chat_gen.set(false);
-                        let msgs = chat_msgs.get_untracked();
-                        chat_hist.set(msgs.clone());
-                        spawn_local(async move {
-                            if let Err(e) = api::chat_save_history(msgs).await {
-                                tracing::error!("chat_save_history: {e}");
+                        ctx.persist_chat();
+                        obs.update(|o| o.push(ObsEvent { ts: now, kind: "chat:error".into(), id: stream_id, content: message }));
+                    }
+                }
+                ChatEvent::Cancelled { stream_id, partial } => {
+                    if stream_id == chat_stream.get_untracked() {
+                        chat_msgs.update(|m| {
+                            if let Some(last) = m.last_mut() {
+                                if last.role == "assistant" {
+                                    last.content = partial;
+                                }
                             }
                         });
-                        obs.update(|o| o.push(ObsEvent { ts: now, kind: "chat:error".into(), id: stream_id, content: message }));
+                        chat_gen.set(false);
+                        ctx.persist_chat();
+                        obs.update(|o| o.push(ObsEvent { ts: now, kind: "chat:cancelled".into(), id: stream_id, content: String::new() }));
                     }
                 }
             }
@@ -133,18 +167,29 @@ pub fn App() -> impl IntoView {
         let chat_plan    = ctx.chat_plan;
         let chat_trace   = ctx.chat_trace;
         let chat_approvals = ctx.chat_approvals;
-        let chat_hist    = ctx.chat_history;
         let obs          = ctx.obs_events;
 
         ipc::listen::<AgentEvent, _>(AGENT_EVENT, move |ev| {
             let now = js_sys::Date::now();
             let is_root = |id: &str| chat_agent.get_untracked().as_deref() == Some(id);
             match ev {
-                AgentEvent::Started { agent_id, parent, role, task } => {
-                    if parent.is_none() && chat_agent.get_untracked().is_none() {
+                AgentEvent::Started { agent_id, parent, role, task, limits } => {
+                    let root = parent.is_none();
+                    if root && chat_agent.get_untracked().is_none() {
                         chat_agent.set(Some(agent_id.clone()));
                     }
                     chat_trace.update(|t| t.push(format!("▶ {role} started: {task}")));
+                    if root {
+                        let mut budget = format!("  ⏱ up to {} steps, depth {}", limits.max_steps, limits.max_depth);
+                        if limits.wall_clock_secs > 0 {
+                            budget.push_str(&format!(", {}s", limits.wall_clock_secs));
+                        }
+                        if limits.max_tokens > 0 {
+                            budget.push_str(&format!(", {} tokens", limits.max_tokens));
+                        }
+                        budget.push_str(&format!(", temperature {}", limits.temperature));
+                        chat_trace.update(|t| t.push(budget));
+                    }
                     obs.update(|o| o.push(ObsEvent { ts: now, kind: "agent:started".into(), id: agent_id, content: format!("{role}: {task}") }));
                 }
                 AgentEvent::Plan { steps, .. } => {
@@ -171,13 +216,37 @@ pub fn App() -> impl IntoView {
                             }
                         });
                     } else {
-                        chat_trace.update(|t| t.push(format!("  ↳ {delta}")));
+                        // Tokens stream in; keep a sub-agent's run of them on one line.
+                        chat_trace.update(|t| match t.last_mut() {
+                            Some(last) if last.starts_with("  ↳ ") => last.push_str(&delta),
+                            _ => t.push(format!("  ↳ {delta}")),
+                        });
                     }
                     obs.update(|o| {
                         if o.len() > 4000 { o.drain(0..200); }
-                        o.push(ObsEvent { ts: now, kind: "agent:token".into(), id: agent_id, content: delta });
+                        match o.last_mut() {
+                            Some(last) if last.kind == "agent:token" && last.id == agent_id => last.content.push_str(&delta),
+                            _ => o.push(ObsEvent { ts: now, kind: "agent:token".into(), id: agent_id, content: delta }),
+                        }
                     });
                 }
+                AgentEvent::Retract { agent_id, text } => {
+                    if is_root(&agent_id) {
+                        chat_msgs.update(|m| {
+                            if let Some(last) = m.last_mut() {
+                                if last.role == "assistant" && last.content.ends_with(&text) {
+                                    last.content.truncate(last.content.len() - text.len());
+                                }
+                            }
+                        });
+                    } else {
+                        chat_trace.update(|t| {
+                            if t.last().is_some_and(|l| l.starts_with("  ↳ ") && l.ends_with(&text)) {
+                                t.pop();
+                            }
+                        });
+                    }
+                }
                 AgentEvent::ToolCall { agent_id: _, call } => {
                     chat_trace.update(|t| t.push(format!("🔧 {}({})", call.tool, call.args)));
                     obs.update(|o| o.push(ObsEvent { ts: now, kind: "agent:tool_call".into(), id: call.tool.clone(), content: call.args.to_string() }));
@@ -191,6 +260,12 @@ pub fn App() -> impl IntoView {
                 AgentEvent::ApprovalRequest { agent_id, request } => {
                     chat_approvals.update(|a| a.push((agent_id, request)));
                 }
+                AgentEvent::ApprovalResolved { call_id, approved, decided_by, .. } => {
+                    // Also clears requests that timed out before anyone answered.
+                    chat_approvals.update(|a| a.retain(|(_, r)| r.call_id != call_id));
+                    let by = if decided_by.is_empty() { String::new() } else { format!(" by {decided_by}") };
+                    chat_trace.update(|t| t.push(format!("  {}{by}", if approved { "✓ approved" } else { "✕ denied" })));
+                }
                 AgentEvent::SubAgentSpawned { role, task, .. } => {
                     chat_trace.update(|t| t.push(format!("🌱 spawned {role}: {task}")));
                     obs.update(|o| o.push(ObsEvent { ts: now, kind: "agent:spawned".into(), id: String::new(), content: format!("{role}: {task}") }));
@@ -211,13 +286,7 @@ pub fn App() -> impl IntoView {
                             });
                         }
                         chat_gen.set(false);
-                        let msgs = chat_msgs.get_untracked();
-                        chat_hist.set(msgs.clone());
-                        spawn_local(async move {
-                            if let Err(e) = api::chat_save_history(msgs).await {
-                                tracing::error!("chat_save_history: {e}");
-                            }
-                        });
+                        ctx.persist_chat();
                     } else {
                         chat_trace.update(|t| t.push("✔ subagent done".to_string()));
                     }
@@ -227,13 +296,7 @@ pub fn App() -> impl IntoView {
                     if is_root(&agent_id) {
                         chat_err.set(Some(message.clone()));
                         chat_gen.set(false);
-                        let msgs = chat_msgs.get_untracked();
-                        chat_hist.set(msgs.clone());
-                        spawn_local(async move {
-                            if let Err(e) = api::chat_save_history(msgs).await {
-                                tracing::error!("chat_save_history: {e}");
-                            }
-                        });
+                        ctx.persist_chat();
                     } else {
                         chat_trace.update(|t| t.push(format!("subagent error: {message}")));
                     }
@@ -243,6 +306,45 @@ pub fn App() -> impl IntoView {
         });
     }
 
+    // ── Download queue listener ──────────────────────────────────────────────
+    // Hydrate once, then apply events; the queue keeps running across tab
+    // switches, so this lives here rather than in DownloadTab.
+    {
+        let downloads = ctx.downloads;
+        let rates = ctx.download_rates;
+        spawn_local(async move {
+            if let Ok(status) = api::download_status().await {
+                downloads.set(status.items);
+            }
+        });
+        ipc::listen::<DownloadEvent, _>(DOWNLOAD_EVENT, move |ev| match ev {
+            DownloadEvent::Added { item } => downloads.update(|d| d.push(item)),
+            DownloadEvent::Progress { id, downloaded_bytes, total_bytes, bytes_per_sec } => {
+                downloads.update(|d| {
+                    if let Some(item) = d.iter_mut().find(|i| i.id == id) {
+                        item.downloaded_bytes = downloaded_bytes;
+                        item.total_bytes = total_bytes;
+                    }
+                });
+                rates.update(|r| { r.insert(id, bytes_per_sec); });
+            }
+            DownloadEvent::State { id, state, error } => {
+                downloads.update(|d| {
+                    if let Some(item) = d.iter_mut().find(|i| i.id == id) {
+                        item.state = state;
+                        item.error = error;
+                    }
+                });
+                rates.update(|r| { r.remove(&id); });
+            }
+            DownloadEvent::PostStep { id, message } => downloads.update(|d| {
+                if let Some(item) = d.iter_mut().find(|i| i.id == id) {
+                    item.post_log.push(message);
+                }
+            }),
+        });
+    }
+
     // Poll server process status for the topbar pill.
     let running = ctx.server_running;
     let poll = move || {
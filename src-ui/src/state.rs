@@ -1,7 +1,13 @@
 //! Cross-tab reactive state + the `Tab` enum that drives navigation.
 
 use leptos::prelude::*;
-use shared::ipc::{ApprovalRequest, ChatMessage, PlanStep};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use shared::ipc::{
+    AgentBudget, ApprovalRequest, ChatMessage, ChatSampling, Conversation, ConversationPatch, ConversationSummary, DownloadItem,
+    PlanStep,
+};
 use shared::ServerConfig;
 use wasm_bindgen_futures::spawn_local;
 
@@ -214,8 +220,13 @@ pub struct AppCtx {
     pub routed_instance: RwSignal<Option<(String, u16)>>,
     /// Selected model for the active instance.
     pub routed_model: RwSignal<Option<String>>,
-    /// Chat history that persists across tab changes and app restarts.
-    pub chat_history: RwSignal<Vec<shared::ipc::ChatMessage>>,
+    /// The conversation `chat_messages` belongs to; `None` until the first
+    /// save of a new chat creates it.
+    pub chat_conversation: RwSignal<Option<String>>,
+    /// Sampling settings of the open conversation.
+    pub chat_sampling: RwSignal<ChatSampling>,
+    /// Conversation list for the chat sidebar, most recent first.
+    pub chat_conversations: RwSignal<Vec<ConversationSummary>>,
     pub immersive: RwSignal<bool>,
     /// Global search box text (sidebar filter / quick nav).
     pub search: RwSignal<String>,
@@ -236,11 +247,33 @@ pub struct AppCtx {
     pub chat_plan: RwSignal<Vec<PlanStep>>,
     pub chat_trace: RwSignal<Vec<String>>,
     pub chat_approvals: RwSignal<Vec<(String, ApprovalRequest)>>,
+    /// Budget for agent runs started from the chat; empty fields use the
+    /// defaults.
+    pub chat_agent_budget: RwSignal<AgentBudget>,
     /// Toggle for the context-overview panel inside ChatTab.
     pub chat_show_context: RwSignal<bool>,
+    /// Toggle for the conversations panel inside ChatTab.
+    pub chat_show_conversations: RwSignal<bool>,
+    /// Toggle for the sampling panel inside ChatTab.
+    pub chat_show_sampling: RwSignal<bool>,
+    /// Toggle for the attached-documents panel inside ChatTab.
+    pub chat_show_documents: RwSignal<bool>,
+    /// Toggle for the prompt-library panel inside ChatTab.
+    pub chat_show_prompts: RwSignal<bool>,
 
     // ── Observability event log ──────────────────────────────────────────────
     pub obs_events: RwSignal<Vec<ObsEvent>>,
+
+    // ── Download queue (kept live by the download://event listener) ─────────
+    pub downloads: RwSignal<Vec<DownloadItem>>,
+    /// Latest transfer rate per download id, in bytes/s.
+    pub download_rates: RwSignal<HashMap<String, u64>>,
+}
+
+fn new_message_id() -> String {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let n = SEQ.fetch_add(1, Ordering::Relaxed);
+    format!("msg-{:x}-{:x}-{n}", js_sys::Date::now() as u64, (js_sys::Math::random() * f64::from(u32::MAX)) as u32)
 }
 
 impl AppCtx {
@@ -309,4 +342,103 @@ impl AppCtx {
         self.config.update(f);
         self.save();
     }
+
+    /// Save the live chat to its conversation (creating it on the first save)
+    /// and move it to the top of the conversation list.
+    pub fn persist_chat(self) {
+        // Ids are picked here rather than by the store so overlapping saves of
+        // the same branch update the same messages instead of adding copies.
+        if self.chat_messages.with_untracked(|msgs| msgs.iter().any(|m| m.id.is_empty())) {
+            self.chat_messages.update(|msgs| {
+                for m in msgs.iter_mut().filter(|m| m.id.is_empty()) {
+                    m.id = new_message_id();
+                }
+            });
+        }
+        let msgs = self.chat_messages.get_untracked();
+        if msgs.is_empty() && self.chat_conversation.get_untracked().is_none() {
+            return;
+        }
+        spawn_local(async move {
+            let id = match self.ensure_conversation().await {
+                Ok(id) => id,
+                Err(e) => return tracing::error!("chat_create_conversation: {e}"),
+            };
+            match api::chat_save_messages(id, msgs).await {
+                Ok(summary) => self.chat_conversations.update(|list| {
+                    list.retain(|c| c.id != summary.id);
+                    list.insert(0, summary);
+                }),
+                Err(e) => tracing::error!("chat_save_messages: {e}"),
+            }
+        });
+    }
+
+    /// The open conversation's id, creating the conversation if the chat
+    /// hasn't been saved yet.
+    pub async fn ensure_conversation(self) -> Result<String, String> {
+        if let Some(id) = self.chat_conversation.get_untracked() {
+            return Ok(id);
+        }
+        let model = self.routed_model.get_untracked().unwrap_or_default();
+        let conv = api::chat_create_conversation(model, self.chat_sampling.get_untracked()).await?;
+        self.chat_conversation.set(Some(conv.summary.id.clone()));
+        Ok(conv.summary.id)
+    }
+
+    /// Change the open conversation's sampling settings and save them with it.
+    /// A chat that isn't saved yet picks them up when it's created.
+    pub fn update_chat_sampling(self, f: impl FnOnce(&mut ChatSampling)) {
+        self.chat_sampling.update(f);
+        let Some(id) = self.chat_conversation.get_untracked() else {
+            return;
+        };
+        let patch = ConversationPatch {
+            sampling: Some(self.chat_sampling.get_untracked()),
+            ..Default::default()
+        };
+        spawn_local(async move {
+            if let Err(e) = api::chat_update_conversation(id, patch).await {
+                tracing::error!("chat_update_conversation: {e}");
+            }
+        });
+    }
+
+    /// Load a conversation into the chat view, switching to its model.
+    pub fn open_conversation(self, id: String) {
+        spawn_local(async move {
+            match api::chat_get_conversation(id).await {
+                Ok(conv) => {
+                    if !conv.summary.model.is_empty() {
+                        self.routed_model.set(Some(conv.summary.model.clone()));
+                    }
+                    self.chat_sampling.set(conv.sampling);
+                    self.chat_messages.set(conv.messages);
+                    self.chat_conversation.set(Some(conv.summary.id));
+                    self.chat_error.set(None);
+                }
+                Err(e) => self.chat_error.set(Some(e)),
+            }
+        });
+    }
+
+    /// Show a conversation returned by a branch command.
+    pub fn show_branch(self, conv: Conversation) {
+        self.chat_messages.set(conv.messages);
+        self.chat_conversations.update(|list| {
+            list.retain(|c| c.id != conv.summary.id);
+            list.insert(0, conv.summary);
+        });
+    }
+
+    /// Start an empty chat; it becomes a conversation on its first save.
+    pub fn new_conversation(self) {
+        self.chat_conversation.set(None);
+        self.chat_sampling.set(ChatSampling::default());
+        self.chat_messages.set(vec![]);
+        self.chat_error.set(None);
+        self.chat_plan.set(vec![]);
+        self.chat_trace.set(vec![]);
+        self.chat_approvals.set(vec![]);
+    }
 }
@@ -10,9 +10,11 @@ use gloo_timers::callback::Interval;
 use leptos::ev::KeyboardEvent;
 use leptos::prelude::*;
 use shared::ipc::{
-    AgentRequest, ApprovalDecision, ChatMessage, ChatRequest,
-    PlanStatus,
+    AgentBudget, AgentLimits, AgentRequest, ApprovalDecision, ApprovalScope, ChatAttachment, ChatExportFormat, ChatMessage, ChatRequest, ChatSampling,
+    ChatSearchHit, ChatSource, ConversationPatch, GenerationStats, PlanStatus, PromptDraft, PromptRef,
+    PromptTemplate, ResponseFormat,
 };
+use shared::prompts::{user_variables, BUILTIN_VARIABLES};
 use wasm_bindgen_futures::spawn_local;
 
 use crate::components::Spinner;
@@ -25,6 +27,37 @@ fn next_stream_id() -> String {
     format!("s{}", STREAM_SEQ.fetch_add(1, Ordering::Relaxed))
 }
 
+/// `qwen3-8b · TTFT 180 ms · prompt 12 tok @ 480 t/s · 40 tok @ 31.2 t/s`
+fn stats_line(st: &GenerationStats) -> String {
+    let mut parts = vec![];
+    if !st.model.is_empty() {
+        parts.push(st.model.clone());
+    }
+    if let Some(ms) = st.ttft_ms {
+        parts.push(format!("TTFT {ms:.0} ms"));
+    }
+    let rate = |n: Option<u32>, tps: Option<f64>| match (n, tps) {
+        (Some(n), Some(tps)) => Some(format!("{n} tok @ {tps:.1} t/s")),
+        (Some(n), None) => Some(format!("{n} tok")),
+        _ => None,
+    };
+    if let Some(p) = rate(st.prompt_tokens, st.prompt_per_second) {
+        parts.push(format!("prompt {p}"));
+    }
+    parts.extend(rate(st.completion_tokens, st.predicted_per_second));
+    parts.join(" · ")
+}
+
+/// `[2] guide.md:14-30 · Install › Linux`
+fn source_line(src: &ChatSource) -> String {
+    let name = src.path.rsplit(['/', '\\']).next().unwrap_or(&src.path);
+    let mut line = format!("[{}] {name}:{}-{}", src.index, src.start_line, src.end_line);
+    if !src.heading.is_empty() {
+        line.push_str(&format!(" · {}", src.heading));
+    }
+    line
+}
+
 #[component]
 pub fn ChatTab() -> impl IntoView {
     let ctx = expect_context::<AppCtx>();
@@ -64,7 +97,7 @@ pub fn ChatTab() -> impl IntoView {
     }
 
     let built_ins = vec![
-        CmdOption { cmd: "/clear".into(),    desc: "Clear chat history".into() },
+        CmdOption { cmd: "/clear".into(),    desc: "Start a new conversation".into() },
         CmdOption { cmd: "/help".into(),     desc: "Show this help message".into() },
         CmdOption { cmd: "/agent".into(),    desc: "Start agent mode for the given task".into() },
         CmdOption { cmd: "/research".into(), desc: "Start Deep Research and switch to the Deep Research tab".into() },
@@ -157,6 +190,14 @@ pub fn ChatTab() -> impl IntoView {
         move |val: String| {
             selected_model.set(val.clone());
             ctx.routed_model.set(Some(val.clone()));
+            if let Some(id) = ctx.chat_conversation.get_untracked() {
+                let patch = ConversationPatch { model: Some(val.clone()), ..Default::default() };
+                spawn_local(async move {
+                    if let Err(e) = api::chat_update_conversation(id, patch).await {
+                        tracing::error!("chat_update_conversation: {e}");
+                    }
+                });
+            }
             let val_c = val.clone();
             spawn_local(async move {
                 if let Ok(scanned) = api::library_get_index().await {
@@ -225,13 +266,27 @@ pub fn ChatTab() -> impl IntoView {
     Interval::new(2000, poll_once).forget();
 
     // ── Helpers ───────────────────────────────────────────────────────────────
-    let save_history = move || {
-        let msgs = messages.get_untracked();
-        ctx.chat_history.set(msgs.clone());
+    let save_history = move || ctx.persist_chat();
+
+    // Message being edited, and its draft text.
+    let editing = RwSignal::new(None::<String>);
+    let edit_draft = RwSignal::new(String::new());
+
+    // Stream a reply into the empty assistant message at the end of the chat.
+    let stream_reply = move || {
+        let (host, port) = target();
+        let sid = next_stream_id();
+        current_stream.set(sid.clone());
+        generating.set(true);
+        let history: Vec<ChatMessage> = messages.get_untracked().into_iter()
+            .filter(|m| !(m.role == "assistant" && m.content.is_empty()))
+            .collect();
+        let sampling = Some(ctx.chat_sampling.get_untracked());
+        let model = selected_model.get_untracked();
+        let conversation_id = ctx.chat_conversation.get_untracked();
+        let req = ChatRequest { stream_id: sid, host, port, model, messages: history, sampling, conversation_id };
         spawn_local(async move {
-            if let Err(e) = api::chat_save_history(msgs).await {
-                tracing::error!("chat_save_history: {e}");
-            }
+            if let Err(e) = api::chat_send(req).await { error.set(Some(e)); generating.set(false); }
         });
     };
 
@@ -247,23 +302,17 @@ pub fn ChatTab() -> impl IntoView {
         let arg = parts.get(1).map(|s| s.trim()).unwrap_or("");
 
         if cmd == "/clear" {
-            messages.set(vec![]);
-            ctx.chat_history.set(vec![]);
-            spawn_local(async move { let _ = api::chat_save_history(vec![]).await; });
-            error.set(None);
-            plan.set(vec![]);
-            trace.set(vec![]);
-            approvals.set(vec![]);
+            ctx.new_conversation();
             input.set(String::new());
             return;
         }
 
         if cmd == "/help" {
             messages.update(|m| {
-                m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                m.push(ChatMessage { role: "assistant".into(), content: "\
+                m.push(ChatMessage::new("user", text.clone()));
+                m.push(ChatMessage::new("assistant", "\
                     Here are the available slash commands:\n\n\
-                    - `/clear` — Clear chat history\n\
+                    - `/clear` — Start a new conversation\n\
                     - `/help` — Show this help message\n\
                     - `/agent <task>` — Start agent mode for the given task\n\
                     - `/research <query>` — Start Deep Research on the given query\n\
@@ -271,7 +320,7 @@ pub fn ChatTab() -> impl IntoView {
                     - `/skills <name>` — Load a specific skill into active context\n\
                     - `/mcp` — List connected MCP servers and their tools\n\
                     - `/todo <task>` — Add a new task to your todo list\n\
-                    - `/planner <task>` — Create a new planner/Kanban task".into() });
+                    - `/planner <task>` — Create a new planner/Kanban task"));
             });
             save_history();
             input.set(String::new());
@@ -281,16 +330,16 @@ pub fn ChatTab() -> impl IntoView {
         if cmd == "/research" {
             if arg.is_empty() {
                 messages.update(|m| {
-                    m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                    m.push(ChatMessage { role: "assistant".into(), content: "Usage: `/research <query>`".into() });
+                    m.push(ChatMessage::new("user", text.clone()));
+                    m.push(ChatMessage::new("assistant", "Usage: `/research <query>`"));
                 });
                 save_history();
                 input.set(String::new());
                 return;
             }
             messages.update(|m| {
-                m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                m.push(ChatMessage { role: "assistant".into(), content: format!("Deep research started for: \"{}\". Switching to the Deep Research panel…", arg) });
+                m.push(ChatMessage::new("user", text.clone()));
+                m.push(ChatMessage::new("assistant", format!("Deep research started for: \"{}\". Switching to the Deep Research panel…", arg)));
             });
             save_history();
             input.set(String::new());
@@ -304,16 +353,16 @@ pub fn ChatTab() -> impl IntoView {
         if cmd == "/planner" {
             if arg.is_empty() {
                 messages.update(|m| {
-                    m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                    m.push(ChatMessage { role: "assistant".into(), content: "Usage: `/planner <task>`".into() });
+                    m.push(ChatMessage::new("user", text.clone()));
+                    m.push(ChatMessage::new("assistant", "Usage: `/planner <task>`"));
                 });
                 save_history();
                 input.set(String::new());
                 return;
             }
             messages.update(|m| {
-                m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                m.push(ChatMessage { role: "assistant".into(), content: format!("Planner task created: \"{}\". Switching to the Task Planner tab…", arg) });
+                m.push(ChatMessage::new("user", text.clone()));
+                m.push(ChatMessage::new("assistant", format!("Planner task created: \"{}\". Switching to the Task Planner tab…", arg)));
             });
             save_history();
             input.set(String::new());
@@ -338,14 +387,14 @@ pub fn ChatTab() -> impl IntoView {
         if cmd == "/todo" {
             if arg.is_empty() {
                 messages.update(|m| {
-                    m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                    m.push(ChatMessage { role: "assistant".into(), content: "Usage: `/todo <task>`".into() });
+                    m.push(ChatMessage::new("user", text.clone()));
+                    m.push(ChatMessage::new("assistant", "Usage: `/todo <task>`"));
                 });
                 save_history();
                 input.set(String::new());
                 return;
             }
-            messages.update(|m| m.push(ChatMessage { role: "user".into(), content: text.clone() }));
+            messages.update(|m| m.push(ChatMessage::new("user", text.clone())));
             input.set(String::new());
             generating.set(true);
             let t = arg.to_string();
@@ -354,13 +403,13 @@ pub fn ChatTab() -> impl IntoView {
                 if let Ok(mut list) = api::todos_get().await {
                     list.push(shared::ipc::TodoItem { id, text: t.clone(), done: false, priority: String::new(), due_date: None, tags: vec![] });
                     if let Ok(_) = api::todos_set(list).await {
-                        messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Todo added: \"{}\"", t) }));
+                        messages.update(|m| m.push(ChatMessage::new("assistant", format!("Todo added: \"{}\"", t))));
                         save_history();
                     } else {
-                        messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: "Failed to save todo item.".into() }));
+                        messages.update(|m| m.push(ChatMessage::new("assistant", "Failed to save todo item.")));
                     }
                 } else {
-                    messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: "Failed to retrieve todo list.".into() }));
+                    messages.update(|m| m.push(ChatMessage::new("assistant", "Failed to retrieve todo list.")));
                 }
                 generating.set(false);
             });
@@ -368,7 +417,7 @@ pub fn ChatTab() -> impl IntoView {
         }
 
         if cmd == "/mcp" {
-            messages.update(|m| m.push(ChatMessage { role: "user".into(), content: text.clone() }));
+            messages.update(|m| m.push(ChatMessage::new("user", text.clone())));
             input.set(String::new());
             generating.set(true);
             spawn_local(async move {
@@ -394,9 +443,9 @@ pub fn ChatTab() -> impl IntoView {
                             }
                         }
                         if !found { output.push_str("No MCP servers configured."); }
-                        messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: output }));
+                        messages.update(|m| m.push(ChatMessage::new("assistant", output)));
                     }
-                    Err(e) => messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Failed to retrieve MCP registry: {}", e) })),
+                    Err(e) => messages.update(|m| m.push(ChatMessage::new("assistant", format!("Failed to retrieve MCP registry: {}", e)))),
                 }
                 save_history();
                 generating.set(false);
@@ -405,7 +454,7 @@ pub fn ChatTab() -> impl IntoView {
         }
 
         if cmd == "/skills" {
-            messages.update(|m| m.push(ChatMessage { role: "user".into(), content: text.clone() }));
+            messages.update(|m| m.push(ChatMessage::new("user", text.clone())));
             input.set(String::new());
             generating.set(true);
             let name_filter = arg.to_string();
@@ -423,21 +472,21 @@ pub fn ChatTab() -> impl IntoView {
                                 }
                                 output.push_str("\nTo load a specific skill into the active chat context, use: `/skills <name>`");
                             }
-                            messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: output }));
+                            messages.update(|m| m.push(ChatMessage::new("assistant", output)));
                         } else {
                             let match_lower = name_filter.to_lowercase();
                             let matching = list.into_iter().find(|f| f.name.to_lowercase().contains(&match_lower) || f.path.to_lowercase().contains(&match_lower));
                             if let Some(f) = matching {
                                 messages.update(|m| {
-                                    m.push(ChatMessage { role: "system".into(), content: format!("Injected Context from Skill '{}' ({}):\n\n{}", f.name, f.path, f.content) });
-                                    m.push(ChatMessage { role: "assistant".into(), content: format!("Loaded skill/agent file **{}** (`{}`) into active context.", f.name, f.path) });
+                                    m.push(ChatMessage::new("system", format!("Injected Context from Skill '{}' ({}):\n\n{}", f.name, f.path, f.content)));
+                                    m.push(ChatMessage::new("assistant", format!("Loaded skill/agent file **{}** (`{}`) into active context.", f.name, f.path)));
                                 });
                             } else {
-                                messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Could not find any skill or agent file matching \"{}\". Use `/skills` to list all.", name_filter) }));
+                                messages.update(|m| m.push(ChatMessage::new("assistant", format!("Could not find any skill or agent file matching \"{}\". Use `/skills` to list all.", name_filter))));
                             }
                         }
                     }
-                    Err(e) => messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Failed to retrieve skills and agent files: {}", e) })),
+                    Err(e) => messages.update(|m| m.push(ChatMessage::new("assistant", format!("Failed to retrieve skills and agent files: {}", e)))),
                 }
                 save_history();
                 generating.set(false);
@@ -446,7 +495,7 @@ pub fn ChatTab() -> impl IntoView {
         }
 
         if cmd == "/agents" {
-            messages.update(|m| m.push(ChatMessage { role: "user".into(), content: text.clone() }));
+            messages.update(|m| m.push(ChatMessage::new("user", text.clone())));
             input.set(String::new());
             generating.set(true);
             spawn_local(async move {
@@ -461,9 +510,9 @@ pub fn ChatTab() -> impl IntoView {
                             }
                         }
                         if !found { output.push_str("No custom agent files loaded."); }
-                        messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: output }));
+                        messages.update(|m| m.push(ChatMessage::new("assistant", output)));
                     }
-                    Err(e) => messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Failed to retrieve agents: {}", e) })),
+                    Err(e) => messages.update(|m| m.push(ChatMessage::new("assistant", format!("Failed to retrieve agents: {}", e)))),
                 }
                 save_history();
                 generating.set(false);
@@ -473,7 +522,7 @@ pub fn ChatTab() -> impl IntoView {
 
         if cmd.starts_with("/agent-skills:") {
             let target_slug = cmd.strip_prefix("/agent-skills:").unwrap_or("").to_lowercase();
-            messages.update(|m| m.push(ChatMessage { role: "user".into(), content: text.clone() }));
+            messages.update(|m| m.push(ChatMessage::new("user", text.clone())));
             input.set(String::new());
             generating.set(true);
             spawn_local(async move {
@@ -500,14 +549,14 @@ pub fn ChatTab() -> impl IntoView {
                         });
                         if let Some(f) = matching {
                             messages.update(|m| {
-                                m.push(ChatMessage { role: "system".into(), content: format!("Injected Context from Skill '{}' ({}):\n\n{}", f.name, f.path, f.content) });
-                                m.push(ChatMessage { role: "assistant".into(), content: format!("Loaded skill/agent file **{}** (`{}`) into active context.", f.name, f.path) });
+                                m.push(ChatMessage::new("system", format!("Injected Context from Skill '{}' ({}):\n\n{}", f.name, f.path, f.content)));
+                                m.push(ChatMessage::new("assistant", format!("Loaded skill/agent file **{}** (`{}`) into active context.", f.name, f.path)));
                             });
                         } else {
-                            messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Could not find any skill matching \"{}\".", target_slug) }));
+                            messages.update(|m| m.push(ChatMessage::new("assistant", format!("Could not find any skill matching \"{}\".", target_slug))));
                         }
                     }
-                    Err(e) => messages.update(|m| m.push(ChatMessage { role: "assistant".into(), content: format!("Failed to retrieve skills: {}", e) })),
+                    Err(e) => messages.update(|m| m.push(ChatMessage::new("assistant", format!("Failed to retrieve skills: {}", e)))),
                 }
                 save_history();
                 generating.set(false);
@@ -521,8 +570,8 @@ pub fn ChatTab() -> impl IntoView {
         if cmd == "/agent" {
             if arg.is_empty() {
                 messages.update(|m| {
-                    m.push(ChatMessage { role: "user".into(), content: text.clone() });
-                    m.push(ChatMessage { role: "assistant".into(), content: "Usage: `/agent <task>`".into() });
+                    m.push(ChatMessage::new("user", text.clone()));
+                    m.push(ChatMessage::new("assistant", "Usage: `/agent <task>`"));
                 });
                 save_history();
                 input.set(String::new());
@@ -534,8 +583,8 @@ pub fn ChatTab() -> impl IntoView {
         }
 
         messages.update(|m| {
-            m.push(ChatMessage { role: "user".into(), content: text.clone() });
-            m.push(ChatMessage { role: "assistant".into(), content: String::new() });
+            m.push(ChatMessage::new("user", text.clone()));
+            m.push(ChatMessage::new("assistant", String::new()));
         });
         save_history();
         input.set(String::new());
@@ -549,7 +598,8 @@ pub fn ChatTab() -> impl IntoView {
             trace.set(vec![]);
             approvals.set(vec![]);
             current_agent.set(None);
-            let req = AgentRequest { host, port, model, task: task_text };
+            let budget = Some(ctx.chat_agent_budget.get_untracked()).filter(|b| *b != AgentBudget::default());
+            let req = AgentRequest { host, port, model, task: task_text, prompt: None, budget };
             spawn_local(async move {
                 match api::agent_start(req).await {
                     Ok(id) => {
@@ -559,16 +609,65 @@ pub fn ChatTab() -> impl IntoView {
                 }
             });
         } else {
-            let sid = next_stream_id();
-            current_stream.set(sid.clone());
-            let history: Vec<ChatMessage> = messages.get_untracked().into_iter()
-                .filter(|m| !(m.role == "assistant" && m.content.is_empty()))
-                .collect();
-            let req = ChatRequest { stream_id: sid, host, port, model, messages: history, temperature: 0.7 };
-            spawn_local(async move {
-                if let Err(e) = api::chat_send(req).await { error.set(Some(e)); generating.set(false); }
-            });
+            stream_reply();
+        }
+    };
+
+    // ── Branching ─────────────────────────────────────────────────────────────
+    // Each command returns the new active branch; regenerating and editing a
+    // user message then stream a fresh reply at its end.
+    let busy = move || {
+        if generating.get_untracked() {
+            error.set(Some("Wait for the reply to finish first.".into()));
         }
+        generating.get_untracked()
+    };
+
+    let regenerate = move |message_id: String| {
+        let Some(id) = ctx.chat_conversation.get_untracked() else { return };
+        if busy() { return; }
+        spawn_local(async move {
+            match api::chat_regenerate(id, message_id).await {
+                Ok(conv) => {
+                    ctx.show_branch(conv);
+                    stream_reply();
+                }
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+
+    let submit_edit = move || {
+        let Some(message_id) = editing.get_untracked() else { return };
+        let content = edit_draft.get_untracked();
+        let Some(id) = ctx.chat_conversation.get_untracked() else { return };
+        if busy() { return; }
+        editing.set(None);
+        spawn_local(async move {
+            match api::chat_edit_message(id, message_id, content).await {
+                Ok(conv) => {
+                    let ask = conv.messages.last().is_some_and(|m| m.role == "user");
+                    ctx.show_branch(conv);
+                    if ask {
+                        messages.update(|m| m.push(ChatMessage::new("assistant", String::new())));
+                        save_history();
+                        stream_reply();
+                    }
+                }
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+
+    let switch_to = move |message_id: String| {
+        let Some(id) = ctx.chat_conversation.get_untracked() else { return };
+        if busy() { return; }
+        spawn_local(async move {
+            match api::chat_switch_branch(id, message_id).await {
+                Ok(conv) => ctx.show_branch(conv),
+                Err(e) => error.set(Some(e)),
+            }
+        });
     };
 
     let run_suggestion = move |prompt_text: String| {
@@ -576,15 +675,7 @@ pub fn ChatTab() -> impl IntoView {
         send();
     };
 
-    let clear = move |_| {
-        messages.set(vec![]);
-        ctx.chat_history.set(vec![]);
-        spawn_local(async move { let _ = api::chat_save_history(vec![]).await; });
-        error.set(None);
-        plan.set(vec![]);
-        trace.set(vec![]);
-        approvals.set(vec![]);
-    };
+    let clear = move |_| ctx.new_conversation();
 
     let cancel = move || {
         generating.set(false);
@@ -592,15 +683,19 @@ pub fn ChatTab() -> impl IntoView {
             let id_c = id.clone();
             spawn_local(async move { let _ = api::agent_cancel(id_c).await; });
         }
-        let msgs = messages.get_untracked();
-        ctx.chat_history.set(msgs.clone());
-        spawn_local(async move { let _ = api::chat_save_history(msgs).await; });
+        // A plain chat stream answers with `Cancelled`, which saves the partial
+        // reply; this errors harmlessly when the stream already ended.
+        let sid = current_stream.get_untracked();
+        if !sid.is_empty() {
+            spawn_local(async move { let _ = api::chat_cancel(sid).await; });
+        }
+        ctx.persist_chat();
     };
 
-    let respond = move |agent_id: String, call_id: String, approved: bool| {
+    let respond = move |agent_id: String, call_id: String, approved: bool, remember: ApprovalScope| {
         approvals.update(|a| a.retain(|(_, r)| r.call_id != call_id));
         spawn_local(async move {
-            let _ = api::agent_approve(ApprovalDecision { agent_id, call_id, approved }).await;
+            let _ = api::agent_approve(ApprovalDecision { agent_id, call_id, approved, remember }).await;
         });
     };
 
@@ -666,6 +761,54 @@ pub fn ChatTab() -> impl IntoView {
                     >
                         "✨ Focus"
                     </button>
+                    // Conversations toggle
+                    <button
+                        style=move || if ctx.chat_show_conversations.get() {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid transparent; font-size: 12px; font-weight: 600; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: var(--primary); color: var(--on-primary);"
+                        } else {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid var(--hairline); font-size: 12px; font-weight: 500; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: transparent; color: var(--body);"
+                        }
+                        on:click=move |_| ctx.chat_show_conversations.set(!ctx.chat_show_conversations.get_untracked())
+                        title="Show/hide conversations"
+                    >
+                        "💬 Chats"
+                    </button>
+                    // Sampling toggle
+                    <button
+                        style=move || if ctx.chat_show_sampling.get() {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid transparent; font-size: 12px; font-weight: 600; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: var(--primary); color: var(--on-primary);"
+                        } else {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid var(--hairline); font-size: 12px; font-weight: 500; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: transparent; color: var(--body);"
+                        }
+                        on:click=move |_| ctx.chat_show_sampling.set(!ctx.chat_show_sampling.get_untracked())
+                        title="Sampling settings for this conversation"
+                    >
+                        "🎛 Sampling"
+                    </button>
+                    // Attached documents toggle
+                    <button
+                        style=move || if ctx.chat_show_documents.get() {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid transparent; font-size: 12px; font-weight: 600; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: var(--primary); color: var(--on-primary);"
+                        } else {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid var(--hairline); font-size: 12px; font-weight: 500; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: transparent; color: var(--body);"
+                        }
+                        on:click=move |_| ctx.chat_show_documents.set(!ctx.chat_show_documents.get_untracked())
+                        title="Files this conversation answers from"
+                    >
+                        "📎 Docs"
+                    </button>
+                    // Prompt library toggle
+                    <button
+                        style=move || if ctx.chat_show_prompts.get() {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid transparent; font-size: 12px; font-weight: 600; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: var(--primary); color: var(--on-primary);"
+                        } else {
+                            "height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid var(--hairline); font-size: 12px; font-weight: 500; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: transparent; color: var(--body);"
+                        }
+                        on:click=move |_| ctx.chat_show_prompts.set(!ctx.chat_show_prompts.get_untracked())
+                        title="Saved prompts"
+                    >
+                        "📝 Prompts"
+                    </button>
                     // Context overview toggle
                     <button
                         style=move || if ctx.chat_show_context.get() {
@@ -684,13 +827,19 @@ pub fn ChatTab() -> impl IntoView {
                     <button
                         style="height: 28px; padding: 0 10px; border-radius: 14px; border: var(--border-width) solid var(--hairline); font-size: 12px; font-weight: 500; cursor: pointer; display: inline-flex; align-items: center; gap: 4px; background: transparent; color: var(--muted);"
                         on:click=clear
-                        title="Clear chat"
+                        title="Start a new conversation"
                     >
-                        "✕ Clear"
+                        "+ New"
                     </button>
                 </div>
             </div>
 
+            {move || ctx.chat_show_conversations.get().then(|| view! { <ConversationsPanel/> })}
+            {move || ctx.chat_show_sampling.get().then(|| view! { <SamplingPanel/> })}
+            {move || use_agent.get().then(|| view! { <AgentBudgetPanel/> })}
+            {move || ctx.chat_show_documents.get().then(|| view! { <DocumentsPanel/> })}
+            {move || ctx.chat_show_prompts.get().then(|| view! { <PromptsPanel/> })}
+
             // Context overview panel (collapsible)
             {move || ctx.chat_show_context.get().then(|| {
                 let msgs = ctx.chat_messages.get();
@@ -778,11 +927,77 @@ pub fn ChatTab() -> impl IntoView {
                             </div>
                         }.into_any()
                     } else {
+                        let saved = ctx.chat_conversation.get().is_some();
                         msgs.into_iter().map(|m| {
                             let cls = if m.role == "user" { "msg user" } else if m.role == "system" { "msg system" } else { "msg assistant" };
+                            // Branching needs the message saved; system messages
+                            // (injected skills) stay as they are.
+                            let branchable = saved && !m.id.is_empty() && m.role != "system";
+                            let position = m.siblings.iter().position(|s| *s == m.id);
+                            let sibling = |offset: isize| {
+                                position
+                                    .and_then(|i| i.checked_add_signed(offset))
+                                    .and_then(|i| m.siblings.get(i).cloned())
+                            };
+                            let (prev, next) = (sibling(-1), sibling(1));
+                            let (id_edit, id_regen, is_user) = (m.id.clone(), m.id.clone(), m.role == "user");
+                            let content = m.content.clone();
+                            let is_editing = editing.with(|e| e.as_deref() == Some(m.id.as_str()));
                             view! {
                                 <div class=cls>
-                                    <div class="bubble">{m.content}</div>
+                                    {(!m.reasoning.is_empty()).then(|| view! {
+                                        <details style="font-size: 12px; color: var(--muted); margin-bottom: 4px;">
+                                            <summary style="cursor: pointer;">"💭 Thinking"</summary>
+                                            <div style="white-space: pre-wrap; padding: 4px 0 0 12px;">{m.reasoning.clone()}</div>
+                                        </details>
+                                    })}
+                                    {if is_editing {
+                                        view! {
+                                            <div class="bubble" style="display: flex; flex-direction: column; gap: 6px; min-width: 320px;">
+                                                <textarea class="input" style="min-height: 64px; font-size: 13px;"
+                                                    prop:value=move || edit_draft.get()
+                                                    on:input=move |e| edit_draft.set(event_target_value(&e))
+                                                ></textarea>
+                                                <div style="display: flex; gap: 6px; justify-content: flex-end;">
+                                                    <button class="btn sm" on:click=move |_| editing.set(None)>"Cancel"</button>
+                                                    <button class="btn sm primary" on:click=move |_| submit_edit()>"Save & branch"</button>
+                                                </div>
+                                            </div>
+                                        }.into_any()
+                                    } else {
+                                        view! { <div class="bubble">{m.content}</div> }.into_any()
+                                    }}
+                                    {(!m.sources.is_empty()).then(|| view! {
+                                        <div style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted); margin-top: 4px;">
+                                            {m.sources.iter().map(|src| view! {
+                                                <span title=format!("similarity {:.2}", src.score)>{source_line(src)}</span>
+                                            }).collect_view()}
+                                        </div>
+                                    })}
+                                    <div class="msg-meta">
+                                        {(branchable && m.siblings.len() > 1).then(|| {
+                                            let (prev_disabled, next_disabled) = (prev.is_none(), next.is_none());
+                                            view! {
+                                                <button disabled=prev_disabled title="Previous version"
+                                                    on:click=move |_| if let Some(id) = prev.clone() { switch_to(id) }>"‹"</button>
+                                                <span>{format!("{}/{}", position.map_or(0, |i| i + 1), m.siblings.len())}</span>
+                                                <button disabled=next_disabled title="Next version"
+                                                    on:click=move |_| if let Some(id) = next.clone() { switch_to(id) }>"›"</button>
+                                            }
+                                        })}
+                                        {(branchable && is_user && !is_editing).then(|| view! {
+                                            <button title="Edit into a new branch"
+                                                on:click=move |_| {
+                                                    edit_draft.set(content.clone());
+                                                    editing.set(Some(id_edit.clone()));
+                                                }>"✎ Edit"</button>
+                                        })}
+                                        {(branchable && !is_user).then(|| view! {
+                                            <button title="Generate another reply"
+                                                on:click=move |_| regenerate(id_regen.clone())>"↻ Regenerate"</button>
+                                        })}
+                                        {m.stats.as_ref().map(|st| view! { <span>{stats_line(st)}</span> })}
+                                    </div>
                                 </div>
                             }
                         }).collect_view().into_any()
@@ -816,17 +1031,24 @@ pub fn ChatTab() -> impl IntoView {
                 <div class="panel approval glass" style="margin-bottom: var(--s-sm);">
                     <div class="panel-title">"⚠️ Approval required"</div>
                     {move || approvals.get().into_iter().map(|(agent_id, req)| {
-                        let (a1, a2) = (agent_id.clone(), agent_id);
-                        let (c1, c2) = (req.call_id.clone(), req.call_id.clone());
+                        let answer = move |approved: bool, remember: ApprovalScope| {
+                            let (agent_id, call_id) = (agent_id.clone(), req.call_id.clone());
+                            move |_| respond(agent_id.clone(), call_id.clone(), approved, remember)
+                        };
                         view! {
                             <div class="approval-item">
                                 <div class="approval-tool">
                                     <strong>{req.tool.clone()}</strong>
                                     <code>{req.args.to_string()}</code>
+                                    <div class="approval-reason">{req.reason.clone()}</div>
                                 </div>
                                 <div class="approval-actions">
-                                    <button class="btn allow" on:click=move |_| respond(a1.clone(), c1.clone(), true)>"Allow"</button>
-                                    <button class="btn deny"  on:click=move |_| respond(a2.clone(), c2.clone(), false)>"Deny"</button>
+                                    <button class="btn allow" on:click=answer(true, ApprovalScope::Once)>"Allow"</button>
+                                    <button class="btn secondary sm" title=format!("Allow {} without asking until this run ends", req.tool)
+                                        on:click=answer(true, ApprovalScope::Run)>"For this run"</button>
+                                    <button class="btn secondary sm" title=format!("Allow {} without asking until the app restarts", req.tool)
+                                        on:click=answer(true, ApprovalScope::Session)>"For this session"</button>
+                                    <button class="btn deny" on:click=answer(false, ApprovalScope::Once)>"Deny"</button>
                                 </div>
                             </div>
                         }
@@ -972,3 +1194,875 @@ pub fn ChatTab() -> impl IntoView {
         </div>
     }
 }
+
+/// Conversation list with search, rename, archive and delete. Opening a
+/// conversation is blocked while a reply is streaming into the current one.
+#[component]
+fn ConversationsPanel() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let query = RwSignal::new(String::new());
+    let hits = RwSignal::new(Vec::<ChatSearchHit>::new());
+    let show_archived = RwSignal::new(false);
+    let editing = RwSignal::new(None::<(String, String)>);
+
+    let reload = move || {
+        spawn_local(async move {
+            match api::chat_list_conversations(show_archived.get_untracked()).await {
+                Ok(list) => ctx.chat_conversations.set(list),
+                Err(e) => tracing::error!("chat_list_conversations: {e}"),
+            }
+        });
+    };
+    Effect::new(move |_| {
+        let _ = show_archived.get();
+        reload();
+    });
+
+    let search = move |q: String| {
+        query.set(q.clone());
+        if q.trim().is_empty() {
+            hits.set(vec![]);
+            return;
+        }
+        spawn_local(async move {
+            match api::chat_search(q).await {
+                Ok(found) => hits.set(found),
+                Err(e) => tracing::error!("chat_search: {e}"),
+            }
+        });
+    };
+
+    let open = move |id: String| {
+        if ctx.chat_generating.get_untracked() {
+            ctx.chat_error.set(Some("Wait for the reply to finish before switching conversations.".into()));
+            return;
+        }
+        ctx.open_conversation(id);
+    };
+
+    let patch = move |id: String, patch: ConversationPatch| {
+        spawn_local(async move {
+            match api::chat_update_conversation(id, patch).await {
+                Ok(_) => reload(),
+                Err(e) => ctx.chat_error.set(Some(e)),
+            }
+        });
+    };
+
+    let delete = move |id: String| {
+        let confirmed = window()
+            .confirm_with_message("Delete this conversation permanently?")
+            .unwrap_or(false);
+        if !confirmed {
+            return;
+        }
+        spawn_local(async move {
+            match api::chat_delete_conversation(id.clone()).await {
+                Ok(()) => {
+                    if ctx.chat_conversation.get_untracked().as_deref() == Some(id.as_str()) {
+                        ctx.new_conversation();
+                    }
+                    reload();
+                }
+                Err(e) => ctx.chat_error.set(Some(e)),
+            }
+        });
+    };
+
+    // Export the open conversation (or every one) in the chosen format.
+    let export_format = RwSignal::new(ChatExportFormat::Markdown);
+    let notice = RwSignal::new(None::<String>);
+    let export = move |all: bool| {
+        let ids: Vec<String> = if all {
+            vec![]
+        } else {
+            match ctx.chat_conversation.get_untracked() {
+                Some(id) => vec![id],
+                None => return notice.set(Some("Nothing to export yet.".into())),
+            }
+        };
+        let format = export_format.get_untracked();
+        let name = format!("{}.{}", if all { "conversations" } else { "conversation" }, format.extension());
+        spawn_local(async move {
+            let Ok(Some(path)) = api::pick_save_path(name).await else { return };
+            match api::chat_export(ids, format, path.clone()).await {
+                Ok(n) => notice.set(Some(format!("Exported {n} conversation(s) to {path}"))),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+    let import = move || {
+        spawn_local(async move {
+            let Ok(Some(path)) = api::pick_path(false).await else { return };
+            match api::chat_import(path).await {
+                Ok(list) => {
+                    notice.set(Some(format!("Imported {} conversation(s)", list.len())));
+                    reload();
+                }
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+
+    let btn = "height: 22px; padding: 0 6px; border-radius: var(--r-sm); border: var(--border-width) solid var(--hairline); font-size: 11px; cursor: pointer; background: transparent; color: var(--muted);";
+
+    view! {
+        <div style="padding: 10px 14px; border-bottom: 1px solid var(--hairline); background: var(--canvas); max-height: 280px; overflow-y: auto; flex-shrink: 0; display: flex; flex-direction: column; gap: 6px;">
+            <div style="display: flex; align-items: center; gap: 6px; font-size: 11px; color: var(--muted);">
+                <select class="input" style="height: 24px; font-size: 11px;"
+                    on:change=move |e| export_format.set(match event_target_value(&e).as_str() {
+                        "sharegpt" => ChatExportFormat::Sharegpt,
+                        "openai_jsonl" => ChatExportFormat::OpenaiJsonl,
+                        _ => ChatExportFormat::Markdown,
+                    })
+                >
+                    <option value="markdown">"Markdown"</option>
+                    <option value="sharegpt">"ShareGPT JSON"</option>
+                    <option value="openai_jsonl">"OpenAI JSONL"</option>
+                </select>
+                <button style=btn on:click=move |_| export(false)>"Export chat"</button>
+                <button style=btn on:click=move |_| export(true)>"Export all"</button>
+                <button style=btn title="ShareGPT .json or OpenAI .jsonl" on:click=move |_| import()>"Import…"</button>
+                <span style="flex: 1; overflow: hidden; text-overflow: ellipsis; white-space: nowrap;">{move || notice.get()}</span>
+            </div>
+            <div style="display: flex; align-items: center; gap: 8px;">
+                <input
+                    class="input"
+                    style="flex: 1; height: 28px; font-size: 12px;"
+                    placeholder="Search all conversations…"
+                    prop:value=move || query.get()
+                    on:input=move |e| search(event_target_value(&e))
+                />
+                <label style="font-size: 11px; color: var(--muted); display: inline-flex; align-items: center; gap: 4px; white-space: nowrap;">
+                    <input type="checkbox" prop:checked=move || show_archived.get() on:change=move |e| show_archived.set(event_target_checked(&e))/>
+                    "Archived"
+                </label>
+            </div>
+
+            {move || if !query.get().trim().is_empty() {
+                let found = hits.get();
+                if found.is_empty() {
+                    return view! { <div style="font-size: 11px; color: var(--muted);">"No matches."</div> }.into_any();
+                }
+                found.into_iter().map(|h| {
+                    let id = h.conversation_id.clone();
+                    view! {
+                        <div style="cursor: pointer; padding: 4px 6px; border-radius: var(--r-sm); font-size: 11.5px;" on:click=move |_| open(id.clone())>
+                            <div style="font-weight: 600; color: var(--ink);">{h.title}</div>
+                            <div style="color: var(--body);">{format!("{}: {}", h.role, h.snippet)}</div>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            } else {
+                let list = ctx.chat_conversations.get();
+                if list.is_empty() {
+                    return view! { <div style="font-size: 11px; color: var(--muted);">"No saved conversations yet."</div> }.into_any();
+                }
+                let active = ctx.chat_conversation.get();
+                list.into_iter()
+                    .filter(|c| show_archived.get() || !c.archived)
+                    .map(|c| {
+                    let is_active = active.as_deref() == Some(c.id.as_str());
+                    let when = chrono::DateTime::from_timestamp(c.updated_at, 0)
+                        .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    let meta = format!("{} · {} messages · {}", if c.model.is_empty() { "no model" } else { &c.model }, c.message_count, when);
+                    let (id_open, id_edit, id_arch, id_del) = (c.id.clone(), c.id.clone(), c.id.clone(), c.id.clone());
+                    let (title, archived) = (c.title.clone(), c.archived);
+                    let row_id = c.id.clone();
+                    view! {
+                        <div style=format!(
+                            "display: flex; align-items: center; gap: 6px; padding: 4px 6px; border-radius: var(--r-sm); background: {};",
+                            if is_active { "var(--surface-soft)" } else { "transparent" }
+                        )>
+                            <div style="flex: 1; min-width: 0; cursor: pointer;" on:click=move |_| open(id_open.clone())>
+                                {move || match editing.get() {
+                                    Some((id, draft)) if id == row_id => view! {
+                                        <input
+                                            class="input"
+                                            style="height: 24px; font-size: 12px; width: 100%;"
+                                            prop:value=draft
+                                            on:click=|e| e.stop_propagation()
+                                            on:input=move |e| editing.update(|ed| if let Some((_, d)) = ed { *d = event_target_value(&e) })
+                                            on:keydown=move |e: KeyboardEvent| match e.key().as_str() {
+                                                "Enter" => {
+                                                    if let Some((id, title)) = editing.get_untracked() {
+                                                        editing.set(None);
+                                                        patch(id, ConversationPatch { title: Some(title), ..Default::default() });
+                                                    }
+                                                }
+                                                "Escape" => editing.set(None),
+                                                _ => {}
+                                            }
+                                        />
+                                    }.into_any(),
+                                    _ => view! {
+                                        <div style=format!("font-size: 12px; font-weight: {}; color: var(--ink); overflow: hidden; text-overflow: ellipsis; white-space: nowrap;", if is_active { 700 } else { 500 })>
+                                            {if archived { format!("🗄 {}", title) } else { title.clone() }}
+                                        </div>
+                                    }.into_any(),
+                                }}
+                                <div style="font-size: 10.5px; color: var(--muted); overflow: hidden; text-overflow: ellipsis; white-space: nowrap;">{meta}</div>
+                            </div>
+                            <button style=btn title="Rename (empty = automatic title)" on:click={
+                                let title = c.title.clone();
+                                move |_| editing.set(Some((id_edit.clone(), title.clone())))
+                            }>"✎"</button>
+                            <button style=btn title=if archived { "Unarchive" } else { "Archive" }
+                                on:click=move |_| patch(id_arch.clone(), ConversationPatch { archived: Some(!archived), ..Default::default() })
+                            >{if archived { "Unarchive" } else { "Archive" }}</button>
+                            <button style=btn title="Delete permanently" on:click=move |_| delete(id_del.clone())>"🗑"</button>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            }}
+        </div>
+    }
+}
+
+/// One optional numeric sampling field; empty means "use the server's value",
+/// which is shown as the placeholder.
+fn sampling_input<T>(
+    label: &'static str,
+    server: impl Fn(&shared::ServerConfig) -> String + Send + Sync + 'static,
+    get: fn(&ChatSampling) -> Option<T>,
+    set: fn(&mut ChatSampling, Option<T>),
+) -> impl IntoView
+where
+    T: std::str::FromStr + ToString + 'static,
+{
+    let ctx = expect_context::<AppCtx>();
+    view! {
+        <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+            {label}
+            <input
+                class="input"
+                style="height: 26px; font-size: 12px; width: 96px;"
+                placeholder=move || server(&ctx.config.get())
+                prop:value=move || get(&ctx.chat_sampling.get()).map(|v| v.to_string()).unwrap_or_default()
+                on:change=move |e| {
+                    let raw = event_target_value(&e);
+                    let value = raw.trim().parse::<T>().ok();
+                    ctx.update_chat_sampling(|s| set(s, value));
+                }
+            />
+        </label>
+    }
+}
+
+/// One field of the agent budget; blank means the default, shown as the
+/// placeholder.
+fn budget_input<T>(
+    label: &'static str,
+    default: impl Fn(&shared::ServerConfig) -> String + Send + Sync + 'static,
+    get: fn(&AgentBudget) -> Option<T>,
+    set: fn(&mut AgentBudget, Option<T>),
+) -> impl IntoView
+where
+    T: std::str::FromStr + ToString + 'static,
+{
+    let ctx = expect_context::<AppCtx>();
+    view! {
+        <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+            {label}
+            <input
+                class="input"
+                style="height: 26px; font-size: 12px; width: 96px;"
+                placeholder=move || default(&ctx.config.get())
+                prop:value=move || get(&ctx.chat_agent_budget.get()).map(|v| v.to_string()).unwrap_or_default()
+                on:change=move |e| {
+                    let value = event_target_value(&e).trim().parse::<T>().ok();
+                    ctx.chat_agent_budget.update(|b| set(b, value));
+                }
+            />
+        </label>
+    }
+}
+
+/// The default for a budget field under its ceiling in settings (0 = none).
+fn capped(default: u64, ceiling: u64) -> String {
+    match (default, ceiling) {
+        (0, 0) => "no limit".into(),
+        (d, 0) => d.to_string(),
+        (0, c) => c.to_string(),
+        (d, c) => d.min(c).to_string(),
+    }
+}
+
+/// Limits for agent runs started from the chat, checked against the ceilings
+/// in settings when a run starts.
+#[component]
+fn AgentBudgetPanel() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let d = AgentLimits::default();
+    let tool_timeouts = move || {
+        ctx.chat_agent_budget.get().tool_timeouts.iter().map(|(t, s)| format!("{t}: {s}")).collect::<Vec<_>>().join(", ")
+    };
+    let set_tool_timeouts = move |text: String| {
+        let timeouts = text
+            .split([',', '\n'])
+            .filter_map(|entry| {
+                let (tool, secs) = entry.split_once(':')?;
+                Some((tool.trim().to_string(), secs.trim().parse::<u64>().ok()?))
+            })
+            .filter(|(t, _)| !t.is_empty())
+            .collect();
+        ctx.chat_agent_budget.update(|b| b.tool_timeouts = timeouts);
+    };
+    let btn = "height: 24px; padding: 0 8px; border-radius: var(--r-sm); border: var(--border-width) solid var(--hairline); font-size: 11px; cursor: pointer; background: transparent; color: var(--muted);";
+
+    view! {
+        <div style="padding: 10px 14px; border-bottom: 1px solid var(--hairline); background: var(--canvas); flex-shrink: 0; display: flex; flex-direction: column; gap: 8px;">
+            <div style="display: flex; align-items: center; gap: 8px;">
+                <span style="font-size: 12px; font-weight: 700; color: var(--ink); flex: 1;">"Agent budget — empty fields use the defaults, within the ceilings in Settings"</span>
+                <button style=btn on:click=move |_| ctx.chat_agent_budget.set(AgentBudget::default())>"Reset"</button>
+            </div>
+            <div style="display: flex; flex-wrap: wrap; gap: 8px; align-items: flex-end;">
+                {budget_input("Steps", move |c| capped(d.max_steps.into(), c.agent_max_steps.into()), |b| b.max_steps, |b, v| b.max_steps = v)}
+                {budget_input("Sub-agent depth", move |c| capped(d.max_depth.into(), c.agent_max_depth.into()), |b| b.max_depth, |b, v| b.max_depth = v)}
+                {budget_input("Time limit (s)", |c| capped(0, c.agent_max_wall_clock_secs), |b| b.wall_clock_secs, |b, v| b.wall_clock_secs = v)}
+                {budget_input("Tokens", |c| capped(0, c.agent_max_tokens), |b| b.max_tokens, |b, v| b.max_tokens = v)}
+                {budget_input("Tool timeout (s)", move |c| capped(d.tool_timeout_secs, c.agent_max_timeout_secs), |b| b.tool_timeout_secs, |b, v| b.tool_timeout_secs = v)}
+                {budget_input("Model timeout (s)", move |c| capped(d.llm_timeout_secs, c.agent_max_timeout_secs), |b| b.llm_timeout_secs, |b, v| b.llm_timeout_secs = v)}
+                {budget_input("Approval timeout (s)", move |c| capped(d.approval_timeout_secs, c.agent_max_timeout_secs), |b| b.approval_timeout_secs, |b, v| b.approval_timeout_secs = v)}
+                {budget_input("Temperature", move |_| d.temperature.to_string(), |b| b.temperature, |b, v| b.temperature = v)}
+                <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted); flex: 1; min-width: 200px;">
+                    "Per-tool timeouts (s)"
+                    <input
+                        class="input"
+                        style="height: 26px; font-size: 12px;"
+                        placeholder="run_command: 300, web_scrape: 60"
+                        prop:value=tool_timeouts
+                        on:change=move |e| set_tool_timeouts(event_target_value(&e))
+                    />
+                </label>
+            </div>
+        </div>
+    }
+}
+
+/// Per-conversation sampling: overrides for the server's launch flags plus
+/// request-only options (stop sequences, JSON output, logit bias).
+#[component]
+fn SamplingPanel() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let schema_error = RwSignal::new(None::<String>);
+
+    let format_kind = move || match ctx.chat_sampling.get().response_format {
+        None => "text",
+        Some(ResponseFormat::JsonObject) => "json_object",
+        Some(ResponseFormat::JsonSchema { .. }) => "json_schema",
+    };
+    let set_format = move |kind: String| {
+        schema_error.set(None);
+        ctx.update_chat_sampling(|s| {
+            s.response_format = match kind.as_str() {
+                "json_object" => Some(ResponseFormat::JsonObject),
+                "json_schema" => Some(ResponseFormat::JsonSchema { schema: serde_json::json!({ "type": "object" }) }),
+                _ => None,
+            }
+        });
+    };
+    let set_schema = move |text: String| match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(schema) => {
+            schema_error.set(None);
+            ctx.update_chat_sampling(|s| s.response_format = Some(ResponseFormat::JsonSchema { schema }));
+        }
+        Err(e) => schema_error.set(Some(format!("Invalid JSON: {e}"))),
+    };
+
+    let stop_text = move || ctx.chat_sampling.get().stop.join("\n");
+    let bias_text = move || {
+        ctx.chat_sampling.get().logit_bias.iter().map(|(t, b)| format!("{t}: {b}")).collect::<Vec<_>>().join("\n")
+    };
+    let set_bias = move |text: String| {
+        let pairs = text
+            .lines()
+            .filter_map(|l| {
+                let (token, bias) = l.rsplit_once(':')?;
+                Some((token.trim().to_string(), bias.trim().parse::<f32>().ok()?))
+            })
+            .filter(|(t, _)| !t.is_empty())
+            .collect();
+        ctx.update_chat_sampling(|s| s.logit_bias = pairs);
+    };
+
+    let btn = "height: 24px; padding: 0 8px; border-radius: var(--r-sm); border: var(--border-width) solid var(--hairline); font-size: 11px; cursor: pointer; background: transparent; color: var(--muted);";
+    let area = "font-size: 12px; font-family: var(--font-mono, monospace); min-height: 48px; width: 100%;";
+
+    view! {
+        <div style="padding: 10px 14px; border-bottom: 1px solid var(--hairline); background: var(--canvas); max-height: 320px; overflow-y: auto; flex-shrink: 0; display: flex; flex-direction: column; gap: 8px;">
+            <div style="display: flex; align-items: center; gap: 8px;">
+                <span style="font-size: 12px; font-weight: 700; color: var(--ink); flex: 1;">"Sampling — saved with this conversation; empty fields use the server settings"</span>
+                <button style=btn on:click=move |_| {
+                    let preset = ChatSampling::from_config(&ctx.config.get_untracked());
+                    ctx.update_chat_sampling(|s| *s = preset);
+                }>"Copy server settings"</button>
+                <button style=btn on:click=move |_| ctx.update_chat_sampling(|s| *s = ChatSampling::default())>"Reset"</button>
+            </div>
+
+            <div style="display: flex; flex-wrap: wrap; gap: 8px;">
+                {sampling_input("Temperature", |c| c.temp.to_string(), |s| s.temperature, |s, v| s.temperature = v)}
+                {sampling_input("Top K", |c| c.top_k.to_string(), |s| s.top_k, |s, v| s.top_k = v)}
+                {sampling_input("Top P", |c| c.top_p.to_string(), |s| s.top_p, |s, v| s.top_p = v)}
+                {sampling_input("Min P", |c| c.min_p.to_string(), |s| s.min_p, |s, v| s.min_p = v)}
+                {sampling_input("Repeat penalty", |c| c.repeat_penalty.to_string(), |s| s.repeat_penalty, |s, v| s.repeat_penalty = v)}
+                {sampling_input("Presence penalty", |c| c.presence_penalty.to_string(), |s| s.presence_penalty, |s, v| s.presence_penalty = v)}
+                {sampling_input("Frequency penalty", |c| c.frequency_penalty.to_string(), |s| s.frequency_penalty, |s, v| s.frequency_penalty = v)}
+                {sampling_input("Seed", |c| if c.seed == -1 { "random".into() } else { c.seed.to_string() }, |s| s.seed, |s, v| s.seed = v)}
+                {sampling_input("Max tokens", |c| if c.predict < 0 { "unlimited".into() } else { c.predict.to_string() }, |s| s.n_predict, |s, v| s.n_predict = v)}
+            </div>
+
+            <div style="display: grid; grid-template-columns: 1fr 1fr; gap: 8px;">
+                <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+                    "Stop sequences (one per line)"
+                    <textarea class="input" style=area
+                        prop:value=stop_text
+                        on:change=move |e| {
+                            let stop = event_target_value(&e).lines().filter(|l| !l.is_empty()).map(str::to_string).collect();
+                            ctx.update_chat_sampling(|s| s.stop = stop);
+                        }
+                    ></textarea>
+                </label>
+                <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+                    "Logit bias (token id or text: bias, one per line)"
+                    <textarea class="input" style=area placeholder="15043: -100"
+                        prop:value=bias_text
+                        on:change=move |e| set_bias(event_target_value(&e))
+                    ></textarea>
+                </label>
+            </div>
+
+            <div style="display: flex; align-items: center; gap: 8px; font-size: 11px; color: var(--muted);">
+                "Output"
+                <select class="input" style="height: 26px; font-size: 12px;"
+                    prop:value=format_kind
+                    on:change=move |e| set_format(event_target_value(&e))
+                >
+                    <option value="text">"Free text"</option>
+                    <option value="json_object">"JSON object"</option>
+                    <option value="json_schema">"JSON schema"</option>
+                </select>
+            </div>
+            {move || match ctx.chat_sampling.get().response_format {
+                Some(ResponseFormat::JsonSchema { schema }) => view! {
+                    <textarea class="input" style=area
+                        prop:value=serde_json::to_string_pretty(&schema).unwrap_or_default()
+                        on:change=move |e| set_schema(event_target_value(&e))
+                    ></textarea>
+                }.into_any(),
+                Some(ResponseFormat::JsonObject) => ().into_any(),
+                None => view! {
+                    <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+                        "Grammar (GBNF)"
+                        <textarea class="input" style=area
+                            placeholder=move || if ctx.config.get().grammar.is_empty() { "none".to_string() } else { "server grammar".to_string() }
+                            prop:value=move || ctx.chat_sampling.get().grammar.unwrap_or_default()
+                            on:change=move |e| {
+                                let grammar = Some(event_target_value(&e)).filter(|g| !g.trim().is_empty());
+                                ctx.update_chat_sampling(|s| s.grammar = grammar);
+                            }
+                        ></textarea>
+                    </label>
+                }.into_any(),
+            }}
+            {move || schema_error.get().map(|e| view! { <div style="font-size: 11px; color: #ef4444;">{e}</div> })}
+        </div>
+    }
+}
+
+/// Files and folders the open conversation answers from. Attaching to a chat
+/// that isn't saved yet saves it first.
+#[component]
+fn DocumentsPanel() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let attachments = RwSignal::new(Vec::<ChatAttachment>::new());
+    let busy = RwSignal::new(false);
+    let notice = RwSignal::new(None::<String>);
+
+    Effect::new(move |_| match ctx.chat_conversation.get() {
+        Some(id) => spawn_local(async move {
+            match api::chat_list_attachments(id).await {
+                Ok(list) => attachments.set(list),
+                Err(e) => notice.set(Some(e)),
+            }
+        }),
+        None => attachments.set(vec![]),
+    });
+
+    let attach = move |directory: bool| {
+        spawn_local(async move {
+            let Ok(Some(path)) = api::pick_path(directory).await else { return };
+            let id = match ctx.ensure_conversation().await {
+                Ok(id) => id,
+                Err(e) => return notice.set(Some(e)),
+            };
+            // Embeddings come from the chat's server unless one is configured.
+            let (host, port) = match ctx.routed_instance.get_untracked() {
+                Some(target) => target,
+                None => ("127.0.0.1".to_string(), ctx.config.get_untracked().port),
+            };
+            busy.set(true);
+            notice.set(Some(format!("Indexing {path}…")));
+            match api::chat_attach(id, path, host, port).await {
+                Ok(list) => {
+                    attachments.set(list);
+                    notice.set(None);
+                }
+                Err(e) => notice.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+    let detach = move |attachment_id: String| {
+        let Some(id) = ctx.chat_conversation.get_untracked() else { return };
+        spawn_local(async move {
+            match api::chat_detach(id, attachment_id).await {
+                Ok(list) => attachments.set(list),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+
+    let btn = "height: 24px; padding: 0 8px; border-radius: var(--r-sm); border: var(--border-width) solid var(--hairline); font-size: 11px; cursor: pointer; background: transparent; color: var(--muted);";
+
+    view! {
+        <div style="padding: 10px 14px; border-bottom: 1px solid var(--hairline); background: var(--canvas); max-height: 240px; overflow-y: auto; flex-shrink: 0; display: flex; flex-direction: column; gap: 6px;">
+            <div style="display: flex; align-items: center; gap: 8px;">
+                <span style="font-size: 12px; font-weight: 700; color: var(--ink); flex: 1;">
+                    {move || if ctx.config.get().rag_top_k == 0 {
+                        "Documents — retrieval is off (Settings › Document Chat)".to_string()
+                    } else {
+                        format!("Documents — the {} closest passages go with each question", ctx.config.get().rag_top_k)
+                    }}
+                </span>
+                <button style=btn disabled=move || busy.get() on:click=move |_| attach(false)>"Attach file…"</button>
+                <button style=btn disabled=move || busy.get() on:click=move |_| attach(true)>"Attach folder…"</button>
+            </div>
+            {move || notice.get().map(|n| view! { <div style="font-size: 11px; color: var(--muted);">{n}</div> })}
+            {move || {
+                let list = attachments.get();
+                if list.is_empty() {
+                    return view! {
+                        <div style="font-size: 12px; color: var(--muted);">"No documents attached."</div>
+                    }.into_any();
+                }
+                list.into_iter().map(|a| {
+                    let id = a.id.clone();
+                    view! {
+                        <div style="display: flex; align-items: center; gap: 8px; font-size: 12px;">
+                            <span style="flex: 1; overflow: hidden; text-overflow: ellipsis; white-space: nowrap;" title=a.path.clone()>{a.path.clone()}</span>
+                            <span style="color: var(--muted);">{format!("{} chunks", a.chunks)}</span>
+                            <button style=btn title="Remove from this conversation" on:click=move |_| detach(id.clone())>"✕"</button>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            }}
+        </div>
+    }
+}
+
+/// The prompt library: pick a prompt, fill in its variables, then insert it
+/// into the input, make it the system prompt, or send it as an agent task.
+/// Saving changed text adds a version; older ones can be viewed and restored.
+#[component]
+fn PromptsPanel() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let prompts = RwSignal::new(Vec::<PromptTemplate>::new());
+    let filter = RwSignal::new(String::new());
+    let notice = RwSignal::new(None::<String>);
+    // Editor: the selected prompt (`None` for a new one) and its fields.
+    let selected = RwSignal::new(None::<String>);
+    let name = RwSignal::new(String::new());
+    let tags = RwSignal::new(String::new());
+    let description = RwSignal::new(String::new());
+    let body = RwSignal::new(String::new());
+    // An older version being looked at, and the variable values.
+    let viewing = RwSignal::new(None::<u32>);
+    let vars = RwSignal::new(std::collections::BTreeMap::<String, String>::new());
+
+    let reload = move || {
+        spawn_local(async move {
+            match api::prompt_list().await {
+                Ok(list) => prompts.set(list),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+    reload();
+
+    let load = move |p: Option<PromptTemplate>| {
+        viewing.set(None);
+        notice.set(None);
+        match p {
+            Some(p) => {
+                selected.set(Some(p.id.clone()));
+                name.set(p.name.clone());
+                tags.set(p.tags.join(", "));
+                description.set(p.description.clone());
+                body.set(p.body().to_string());
+            }
+            None => {
+                selected.set(None);
+                for field in [name, tags, description, body] {
+                    field.set(String::new());
+                }
+            }
+        }
+    };
+    let saved = move || {
+        let id = selected.get()?;
+        prompts.with(|list| list.iter().find(|p| p.id == id).cloned())
+    };
+    let dirty = move || {
+        saved().is_none_or(|p| {
+            p.body() != body.get()
+                || p.name != name.get()
+                || p.description != description.get()
+                || p.tags.join(", ") != tags.get()
+        })
+    };
+
+    let draft = move || PromptDraft {
+        id: selected.get_untracked(),
+        name: name.get_untracked(),
+        description: description.get_untracked(),
+        tags: tags.get_untracked().split(',').map(|t| t.trim().to_string()).collect(),
+        body: body.get_untracked(),
+    };
+    let store = move |p: PromptTemplate| {
+        prompts.update(|list| {
+            list.retain(|x| x.id != p.id);
+            list.push(p.clone());
+            list.sort_by_key(|x| x.name.to_lowercase());
+        });
+        load(Some(p));
+    };
+    let save = move || {
+        spawn_local(async move {
+            match api::prompt_save(draft()).await {
+                Ok(p) => store(p),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+    let restore = move |version: u32| {
+        let Some(id) = selected.get_untracked() else { return };
+        spawn_local(async move {
+            match api::prompt_revert(id, version).await {
+                Ok(p) => store(p),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+    let delete = move || {
+        let Some(id) = selected.get_untracked() else { return };
+        let confirmed = window().confirm_with_message("Delete this prompt and all its versions?").unwrap_or(false);
+        if !confirmed {
+            return;
+        }
+        spawn_local(async move {
+            match api::prompt_delete(id).await {
+                Ok(()) => {
+                    load(None);
+                    reload();
+                }
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+
+    // Render (saving unsaved edits first, since rendering reads the library)
+    // and hand the text to `apply`.
+    let use_prompt = move |apply: fn(AppCtx, String)| {
+        let needs_save = viewing.get_untracked().is_none() && dirty();
+        spawn_local(async move {
+            let id = if needs_save {
+                match api::prompt_save(draft()).await {
+                    Ok(p) => {
+                        let id = p.id.clone();
+                        store(p);
+                        id
+                    }
+                    Err(e) => return notice.set(Some(e)),
+                }
+            } else {
+                match selected.get_untracked() {
+                    Some(id) => id,
+                    None => return,
+                }
+            };
+            let r = PromptRef { id, version: viewing.get_untracked(), vars: vars.get_untracked() };
+            match api::prompt_render(r).await {
+                Ok(text) => apply(ctx, text),
+                Err(e) => notice.set(Some(e)),
+            }
+        });
+    };
+    fn insert(ctx: AppCtx, text: String) {
+        ctx.chat_draft.set(text);
+    }
+    fn as_agent_task(ctx: AppCtx, text: String) {
+        ctx.chat_draft.set(format!("/agent {text}"));
+    }
+    // A system prompt has to come first, so a chat that already started
+    // without one continues as a new chat.
+    fn as_system_prompt(ctx: AppCtx, text: String) {
+        let msgs = ctx.chat_messages.get_untracked();
+        match msgs.first() {
+            Some(first) if first.role == "system" => ctx.chat_messages.update(|m| m[0].content = text),
+            Some(_) => {
+                ctx.new_conversation();
+                ctx.chat_messages.set(vec![ChatMessage::new("system", text)]);
+            }
+            None => ctx.chat_messages.set(vec![ChatMessage::new("system", text)]),
+        }
+        ctx.persist_chat();
+    }
+
+    let shown = move || {
+        let f = filter.get().to_lowercase();
+        prompts.get().into_iter().filter(|p| {
+            f.is_empty()
+                || p.name.to_lowercase().contains(&f)
+                || p.description.to_lowercase().contains(&f)
+                || p.tags.iter().any(|t| t.to_lowercase() == f)
+        }).collect::<Vec<_>>()
+    };
+    let shown_body = move || match (viewing.get(), saved()) {
+        (Some(n), Some(p)) => p.versions.iter().find(|v| v.version == n).map(|v| v.body.clone()).unwrap_or_default(),
+        _ => body.get(),
+    };
+
+    let btn = "height: 24px; padding: 0 8px; border-radius: var(--r-sm); border: var(--border-width) solid var(--hairline); font-size: 11px; cursor: pointer; background: transparent; color: var(--muted);";
+    let field = "height: 26px; font-size: 12px;";
+
+    view! {
+        <div style="padding: 10px 14px; border-bottom: 1px solid var(--hairline); background: var(--canvas); max-height: 380px; overflow-y: auto; flex-shrink: 0; display: grid; grid-template-columns: 200px 1fr; gap: 12px;">
+            <div style="display: flex; flex-direction: column; gap: 4px; min-width: 0;">
+                <div style="display: flex; gap: 4px;">
+                    <input class="input" style=field placeholder="Filter or tag…"
+                        prop:value=move || filter.get()
+                        on:input=move |e| filter.set(event_target_value(&e))
+                    />
+                    <button style=btn title="New prompt" on:click=move |_| load(None)>"+"</button>
+                </div>
+                {move || shown().into_iter().map(|p| {
+                    let id = p.id.clone();
+                    let active = move || selected.get().as_deref() == Some(id.as_str());
+                    let pick = p.clone();
+                    view! {
+                        <div
+                            style=move || format!(
+                                "padding: 4px 6px; border-radius: var(--r-sm); cursor: pointer; font-size: 12px; {}",
+                                if active() { "background: var(--hairline);" } else { "" }
+                            )
+                            on:click=move |_| load(Some(pick.clone()))
+                        >
+                            <div style="font-weight: 600; color: var(--ink); overflow: hidden; text-overflow: ellipsis; white-space: nowrap;">{p.name.clone()}</div>
+                            <div style="display: flex; flex-wrap: wrap; gap: 3px;">
+                                {p.tags.iter().map(|t| {
+                                    let tag = t.clone();
+                                    view! {
+                                        <span style="font-size: 10px; color: var(--muted); cursor: pointer;"
+                                            on:click=move |e| { e.stop_propagation(); filter.set(tag.clone()); }
+                                        >{format!("#{t}")}</span>
+                                    }
+                                }).collect_view()}
+                            </div>
+                        </div>
+                    }
+                }).collect_view()}
+            </div>
+
+            <div style="display: flex; flex-direction: column; gap: 6px; min-width: 0;">
+                <div style="display: flex; gap: 6px;">
+                    <input class="input" style=format!("{field} flex: 1;") placeholder="Name"
+                        prop:value=move || name.get()
+                        on:input=move |e| name.set(event_target_value(&e))
+                    />
+                    <input class="input" style=format!("{field} flex: 1;") placeholder="Tags, comma separated"
+                        prop:value=move || tags.get()
+                        on:input=move |e| tags.set(event_target_value(&e))
+                    />
+                </div>
+                <input class="input" style=field placeholder="Description"
+                    prop:value=move || description.get()
+                    on:input=move |e| description.set(event_target_value(&e))
+                />
+                <textarea class="input" style="font-size: 12px; font-family: var(--font-mono, monospace); min-height: 90px;"
+                    placeholder="Prompt text; {{name}} marks a variable"
+                    prop:value=shown_body
+                    prop:readonly=move || viewing.get().is_some()
+                    on:input=move |e| body.set(event_target_value(&e))
+                ></textarea>
+
+                {move || saved().filter(|p| p.versions.len() > 1).map(|p| {
+                    let current = p.versions.last().map_or(1, |v| v.version);
+                    view! {
+                        <div style="display: flex; align-items: center; gap: 6px; font-size: 11px; color: var(--muted);">
+                            "Version"
+                            <select class="input" style="height: 24px; font-size: 11px;"
+                                on:change=move |e| {
+                                    let n = event_target_value(&e).parse::<u32>().ok();
+                                    viewing.set(n.filter(|n| *n != current));
+                                }
+                            >
+                                {p.versions.into_iter().rev().map(|v| {
+                                    let n = v.version;
+                                    let when = chrono::DateTime::from_timestamp(v.saved_at, 0)
+                                        .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                                        .unwrap_or_default();
+                                    let label = if n == current { format!("v{n} (current) · {when}") } else { format!("v{n} · {when}") };
+                                    let is_shown = move || viewing.get().unwrap_or(current) == n;
+                                    view! { <option value=n.to_string() selected=is_shown>{label}</option> }
+                                }).collect_view()}
+                            </select>
+                            {move || viewing.get().map(|n| view! {
+                                <button style=btn on:click=move |_| restore(n)>"Restore this version"</button>
+                            })}
+                        </div>
+                    }
+                })}
+
+                {move || {
+                    let names = user_variables(&shown_body());
+                    (!names.is_empty()).then(|| view! {
+                        <div style="display: flex; flex-wrap: wrap; gap: 6px;">
+                            {names.into_iter().map(|var| {
+                                let key = var.clone();
+                                let key_in = var.clone();
+                                view! {
+                                    <label style="display: flex; flex-direction: column; gap: 2px; font-size: 11px; color: var(--muted);">
+                                        {format!("{{{{{var}}}}}")}
+                                        <input class="input" style=format!("{field} width: 140px;")
+                                            prop:value=move || vars.with(|v| v.get(&key).cloned().unwrap_or_default())
+                                            on:input=move |e| {
+                                                let value = event_target_value(&e);
+                                                vars.update(|v| { v.insert(key_in.clone(), value); });
+                                            }
+                                        />
+                                    </label>
+                                }
+                            }).collect_view()}
+                        </div>
+                    })
+                }}
+                <div style="font-size: 11px; color: var(--muted);">
+                    {format!("Filled in automatically: {}", BUILTIN_VARIABLES.iter().map(|b| format!("{{{{{b}}}}}")).collect::<Vec<_>>().join(" "))}
+                </div>
+                {move || notice.get().map(|n| view! { <div style="font-size: 11px; color: var(--muted);">{n}</div> })}
+
+                <div style="display: flex; flex-wrap: wrap; gap: 6px; justify-content: flex-end;">
+                    {move || selected.get().is_some().then(|| view! {
+                        <button style=btn on:click=move |_| delete()>"Delete"</button>
+                    })}
+                    <button style=btn disabled=move || viewing.get().is_some() || !dirty() on:click=move |_| save()>"Save"</button>
+                    <button style=btn on:click=move |_| use_prompt(insert)>"Insert"</button>
+                    <button style=btn on:click=move |_| use_prompt(as_system_prompt)>"Use as system prompt"</button>
+                    <button style=btn on:click=move |_| use_prompt(as_agent_task)>"Agent task"</button>
+                </div>
+            </div>
+        </div>
+    }
+}
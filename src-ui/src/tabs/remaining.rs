@@ -5,12 +5,15 @@
 use leptos::prelude::*;
 use leptos::ev::KeyboardEvent;
 use shared::ipc::{
-    AgentRequest, ScannedModel, KanbanTask, MonitorState, CalendarEvent, CalendarState,
+    AgentRequest, ScannedModel, LibraryQuery, LibrarySort, LibraryFacets, FacetCount, StorageReport,
+    DeleteTicket, KanbanTask, MonitorState, CalendarEvent, CalendarState, PromptRef, PromptTemplate,
     LlamaInstance, BenchmarkOutput, ResearchStatus, ResearchReportInfo,
-    TaskStatus, EventStatus, Memory, AgentEvent, AGENT_EVENT,
+    TaskStatus, EventStatus, Memory, Lesson, LessonStatus, AgentEvent, AGENT_EVENT, DownloadState, HfRepoListing, MemoryFit,
+    AgentRun, AgentRunStatus, AgentRunSummary,
 };
 use wasm_bindgen_futures::spawn_local;
 use gloo_timers::callback::Interval;
+use shared::targets::parse_targets;
 
 use crate::{api, ipc};
 use crate::components::{Card, PageHeader};
@@ -22,28 +25,49 @@ use crate::state::{AppCtx, Tab};
 pub fn LibraryTab() -> impl IntoView {
     let ctx = expect_context::<AppCtx>();
     let index = RwSignal::new(Vec::<ScannedModel>::new());
+    let facets = RwSignal::new(LibraryFacets::default());
     let search = RwSignal::new(String::new());
+    let family_filter = RwSignal::new(None::<String>);
+    let tag_filter = RwSignal::new(None::<String>);
+    let quant_filter = RwSignal::new(None::<String>);
+    let sort = RwSignal::new(LibrarySort::Name);
     let status_text = RwSignal::new(String::new());
     let collapsed_families = RwSignal::new(std::collections::HashSet::<String>::new());
     let collapsed_versions = RwSignal::new(std::collections::HashSet::<String>::new());
 
     let load_data = move || {
+        let query = LibraryQuery {
+            family: family_filter.get_untracked(),
+            tag: tag_filter.get_untracked(),
+            quant: quant_filter.get_untracked(),
+            text: Some(search.get_untracked()),
+            sort: sort.get_untracked(),
+            ..Default::default()
+        };
         spawn_local(async move {
-            if let Ok(idx) = api::library_get_index().await {
-                index.set(idx);
+            if let Ok(res) = api::library_query(query).await {
+                index.set(res.models);
+                facets.set(res.facets);
             }
         });
     };
 
-    // Load data on mount
-    load_data();
+    // Query on mount and whenever a filter changes; filtering happens in the backend index.
+    Effect::new(move |_| {
+        search.track();
+        family_filter.track();
+        tag_filter.track();
+        quant_filter.track();
+        sort.track();
+        load_data();
+    });
 
     let scan = move |_| {
         status_text.set("Scanning directories...".to_string());
         spawn_local(async move {
             match api::library_scan().await {
-                Ok(idx) => {
-                    index.set(idx);
+                Ok(_) => {
+                    load_data();
                     status_text.set("Scan complete.".to_string());
                 }
                 Err(e) => {
@@ -58,8 +82,8 @@ pub fn LibraryTab() -> impl IntoView {
         let path_clone = path.clone();
         spawn_local(async move {
             match api::library_enrich_single(path_clone).await {
-                Ok(idx) => {
-                    index.set(idx);
+                Ok(_) => {
+                    load_data();
                     status_text.set("Enrichment complete.".to_string());
                 }
                 Err(e) => {
@@ -118,26 +142,45 @@ pub fn LibraryTab() -> impl IntoView {
                 }}
             </Card>
 
+            <StoragePanel on_changed=Callback::new(move |_| load_data())/>
+
             <Card title="Scanned Models">
-                <input
-                    class="input"
-                    type="text"
-                    placeholder="Search by filename, family, tag, or use-case..."
-                    style="margin-bottom: 16px;"
-                    prop:value=move || search.get()
-                    on:input=move |e| search.set(event_target_value(&e))
-                />
+                <div style="display: flex; gap: 8px; margin-bottom: 16px;">
+                    <input
+                        class="input"
+                        type="text"
+                        placeholder="Search by filename, family, tag, or use-case..."
+                        style="flex: 1;"
+                        prop:value=move || search.get()
+                        on:input=move |e| search.set(event_target_value(&e))
+                    />
+                    <select
+                        class="input"
+                        style="width: 160px;"
+                        on:change=move |e| sort.set(match event_target_value(&e).as_str() {
+                            "size_desc" => LibrarySort::SizeDesc,
+                            "size_asc" => LibrarySort::SizeAsc,
+                            "family" => LibrarySort::Family,
+                            _ => LibrarySort::Name,
+                        })
+                    >
+                        <option value="name">"Sort: Name"</option>
+                        <option value="size_desc">"Sort: Largest"</option>
+                        <option value="size_asc">"Sort: Smallest"</option>
+                        <option value="family">"Sort: Family"</option>
+                    </select>
+                </div>
+
+                <div style="display: flex; gap: 16px; align-items: flex-start;">
+                <div style="width: 180px; flex-shrink: 0; display: flex; flex-direction: column; gap: 12px;">
+                    <FacetList title="Family" facets=Signal::derive(move || facets.get().families) selected=family_filter/>
+                    <FacetList title="Quant" facets=Signal::derive(move || facets.get().quants) selected=quant_filter/>
+                    <FacetList title="Tags" facets=Signal::derive(move || facets.get().tags) selected=tag_filter/>
+                </div>
 
-                <div style="display: flex; flex-direction: column; gap: 8px;">
+                <div style="flex: 1; min-width: 0; display: flex; flex-direction: column; gap: 8px;">
                     {move || {
-                        let query = search.get().to_lowercase();
-                        let filtered: Vec<ScannedModel> = index.get().into_iter().filter(|m| {
-                            m.filename.to_lowercase().contains(&query) ||
-                            m.clean_name.to_lowercase().contains(&query) ||
-                            m.family.to_lowercase().contains(&query) ||
-                            m.use_case.to_lowercase().contains(&query) ||
-                            m.tags.iter().any(|t| t.to_lowercase().contains(&query))
-                        }).collect();
+                        let filtered: Vec<ScannedModel> = index.get();
 
                         let mut unique_families = Vec::new();
                         for m in &filtered {
@@ -398,213 +441,456 @@ pub fn LibraryTab() -> impl IntoView {
                         }).collect_view().into_any()
                     }}
                 </div>
+                </div>
             </Card>
         </div>
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct TargetItem {
-    name: String,
-    line: String,
+/// One facet dimension in the Library sidebar; clicking a value toggles it as a filter.
+#[component]
+fn FacetList(
+    title: &'static str,
+    facets: Signal<Vec<FacetCount>>,
+    selected: RwSignal<Option<String>>,
+) -> impl IntoView {
+    view! {
+        <div style="display: flex; flex-direction: column; gap: 2px;">
+            <div class="field-hint" style="font-weight: 600; text-transform: uppercase; font-size: 10px; margin-bottom: 4px;">
+                {title}
+            </div>
+            {move || facets.get().into_iter().map(|f| {
+                let value = f.value.clone();
+                let is_selected = {
+                    let value = value.clone();
+                    move || selected.get().as_deref() == Some(value.as_str())
+                };
+                view! {
+                    <div
+                        class="facet-item"
+                        class:active=is_selected
+                        on:click=move |_| selected.update(|s| {
+                            *s = if s.as_deref() == Some(value.as_str()) { None } else { Some(value.clone()) };
+                        })
+                    >
+                        <span style="overflow: hidden; text-overflow: ellipsis; white-space: nowrap;">{f.value.clone()}</span>
+                        <span style="color: var(--muted);">{f.count}</span>
+                    </div>
+                }
+            }).collect_view()}
+        </div>
+    }
 }
 
-fn parse_input_targets(input: &str) -> Vec<TargetItem> {
-    let mut targets = Vec::new();
-    let mut _current_category = "mtp".to_string();
-    let mut _current_subfolder: Option<String> = None;
-
-    for line in input.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue;
-        }
-        if trimmed.ends_with(':') {
-            _current_category = trimmed[..trimmed.len() - 1].trim().to_string();
-            _current_subfolder = None;
-            continue;
-        }
-        if trimmed.starts_with('-') {
-            _current_subfolder = Some(trimmed[1..].trim().to_string());
-            continue;
-        }
+fn fmt_gb(bytes: u64) -> String {
+    format!("{:.2} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
 
-        let display_name = if trimmed.starts_with("http") {
-            trimmed.split('/').last().unwrap_or(trimmed).to_string()
-        } else if trimmed.ends_with(".git") || trimmed.starts_with("https://github") {
-            let repo = trimmed.split('/').last().unwrap_or(trimmed);
-            format!("git: {}", repo.trim_end_matches(".git"))
-        } else {
-            let repo_name = trimmed.split('/').last().unwrap_or("");
-            format!("HF: {}", repo_name)
-        };
+/// Disk usage, duplicates and unused models, with move and guarded delete.
+/// Deletes are two-step: the backend issues a short-lived ticket and the file
+/// is only removed when the user confirms it here.
+#[component]
+fn StoragePanel(on_changed: Callback<()>) -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let report = RwSignal::new(None::<StorageReport>);
+    let unused_days = RwSignal::new(30u32);
+    let pending = RwSignal::new(None::<DeleteTicket>);
+    let move_target = RwSignal::new(String::new());
+    let status = RwSignal::new(String::new());
+
+    let analyze = move || {
+        status.set("Analyzing storage...".to_string());
+        spawn_local(async move {
+            match api::library_storage_report(Some(unused_days.get_untracked())).await {
+                Ok(r) => {
+                    report.set(Some(r));
+                    status.set(String::new());
+                }
+                Err(e) => status.set(format!("Storage analysis failed: {}", e)),
+            }
+        });
+    };
 
-        targets.push(TargetItem { name: display_name, line: trimmed.to_string() });
-    }
-    targets
-}
+    let request_delete = move |path: String| {
+        spawn_local(async move {
+            match api::library_delete_prepare(path).await {
+                Ok(ticket) => pending.set(Some(ticket)),
+                Err(e) => status.set(e),
+            }
+        });
+    };
 
-fn parse_tmux_progress(log: &str, targets: &[TargetItem]) -> (Option<usize>, f32, String, String) {
-    let lines: Vec<&str> = log.lines().collect();
-    let mut active_idx: Option<usize> = None;
-    let mut percent = 0.0f32;
-    let mut speed = "Pending\u{2026}".to_string();
-    let mut eta = "Unknown".to_string();
-
-    // Find active target by matching recent log lines to target names
-    'outer: for line in lines.iter().rev() {
-        let lower = line.to_lowercase();
-        for (idx, target) in targets.iter().enumerate().rev() {
-            let kw = target.line.to_lowercase();
-            let search_terms = vec![
-                kw.clone(),
-                target.name.to_lowercase(),
-                target.line.rsplit('/').next().unwrap_or("").to_lowercase(),
-            ];
-            if search_terms.iter().any(|t| !t.is_empty() && lower.contains(t.as_str())) {
-                active_idx = Some(idx);
-                break 'outer;
+    let confirm_delete = move |_| {
+        let Some(ticket) = pending.get_untracked() else { return };
+        spawn_local(async move {
+            match api::library_delete_confirm(ticket.token).await {
+                Ok(_) => {
+                    pending.set(None);
+                    status.set(format!("Deleted {}", ticket.path));
+                    on_changed.run(());
+                    analyze();
+                }
+                Err(e) => {
+                    pending.set(None);
+                    status.set(e);
+                }
             }
+        });
+    };
+
+    let cancel_delete = move |_| {
+        if let Some(ticket) = pending.get_untracked() {
+            pending.set(None);
+            spawn_local(async move {
+                let _ = api::library_delete_cancel(ticket.token).await;
+            });
         }
-    }
+    };
 
-    // Extract percent/speed/ETA from recent lines
-    for line in lines.iter().rev() {
-        if let Some(pct) = extract_dl_percentage(line) {
-            percent = pct;
-            if let Some(s) = extract_dl_speed(line) { speed = s; }
-            if let Some(e) = extract_dl_eta(line) { eta = e; }
-            break;
+    let move_model = move |path: String| {
+        let target = move_target.get_untracked();
+        if target.is_empty() {
+            status.set("Pick a target directory first.".to_string());
+            return;
         }
-        if line.contains("Receiving objects:") || line.contains("Resolving deltas:") {
-            if let Some(pct) = extract_dl_percentage(line) { percent = pct; }
-            if let Some(s) = extract_dl_speed(line) { speed = s; }
-            eta = "Cloning\u{2026}".to_string();
-            break;
+        spawn_local(async move {
+            match api::library_move_model(path, target.clone()).await {
+                Ok(_) => {
+                    status.set(format!("Moved to {}", target));
+                    on_changed.run(());
+                    analyze();
+                }
+                Err(e) => status.set(e),
+            }
+        });
+    };
+
+    let model_row = move |path: String, size_bytes: u64, note: String| {
+        let del_path = path.clone();
+        let mv_path = path.clone();
+        view! {
+            <div style="display: flex; justify-content: space-between; align-items: center; gap: 8px; font-size: 12px; padding: 4px 0;">
+                <span style="font-family: var(--font-mono); word-break: break-all;">{path}</span>
+                <span style="display: flex; gap: 6px; align-items: center; white-space: nowrap;">
+                    <span style="color: var(--muted);">{fmt_gb(size_bytes)} {note}</span>
+                    <button class="btn ghost sm" on:click=move |_| move_model(mv_path.clone())>"Move"</button>
+                    <button class="btn secondary sm" on:click=move |_| request_delete(del_path.clone())>"Delete"</button>
+                </span>
+            </div>
         }
-    }
+    };
 
-    (active_idx, percent, speed, eta)
-}
+    view! {
+        <Card title="Storage">
+            <div class="row-actions" style="align-items: center;">
+                <button class="btn secondary" on:click=move |_| analyze()>"Analyze Storage"</button>
+                <label class="field-hint">"Unused after (days)"</label>
+                <input
+                    class="input"
+                    type="number"
+                    min="1"
+                    style="width: 80px;"
+                    prop:value=move || unused_days.get().to_string()
+                    on:change=move |e| {
+                        if let Ok(v) = event_target_value(&e).parse::<u32>() {
+                            unused_days.set(v.max(1));
+                        }
+                    }
+                />
+                <label class="field-hint">"Move to"</label>
+                <select class="input" style="width: 240px;" on:change=move |e| move_target.set(event_target_value(&e))>
+                    <option value="">"—"</option>
+                    {move || ctx.config.get().model_scan_dirs.into_iter().map(|d| view! {
+                        <option value=d.clone()>{d.clone()}</option>
+                    }).collect_view()}
+                </select>
+            </div>
+
+            {move || pending.get().map(|t| view! {
+                <div class="field-hint" style="margin-top: 10px; display: flex; gap: 8px; align-items: center; color: var(--accent);">
+                    <span>{format!("Permanently delete {} ({})?", t.path, fmt_gb(t.size_bytes))}</span>
+                    <button class="btn primary sm" on:click=confirm_delete>"Confirm Delete"</button>
+                    <button class="btn ghost sm" on:click=cancel_delete>"Cancel"</button>
+                </div>
+            })}
+
+            {move || {
+                let text = status.get();
+                (!text.is_empty()).then(|| view! {
+                    <div class="field-hint" style="margin-top: 10px; font-weight: 500;">{text}</div>
+                })
+            }}
 
-fn extract_dl_percentage(line: &str) -> Option<f32> {
-    if let Some(pct_pos) = line.find('%') {
-        let before = &line[..pct_pos];
-        let num_start = before.rfind(|c: char| !c.is_ascii_digit() && c != '.').map_or(0, |i| i + 1);
-        return before[num_start..].parse::<f32>().ok();
+            {move || report.get().map(|r| view! {
+                <div style="display: flex; flex-direction: column; gap: 12px; margin-top: 12px;">
+                    <div class="field-hint" style="font-weight: 600;">
+                        {format!("Reclaimable: {}", fmt_gb(r.reclaimable_bytes))}
+                    </div>
+                    <table class="model-table">
+                        <thead>
+                            <tr><th>"Directory"</th><th>"Models"</th><th>"Model size"</th><th>"Free / Total"</th></tr>
+                        </thead>
+                        <tbody>
+                            {r.dirs.iter().map(|d| view! {
+                                <tr>
+                                    <td style="font-family: var(--font-mono); word-break: break-all;">{d.dir.clone()}</td>
+                                    <td>{d.model_count}</td>
+                                    <td>{fmt_gb(d.model_bytes)}</td>
+                                    <td>{format!("{} / {}", fmt_gb(d.fs_free_bytes), fmt_gb(d.fs_total_bytes))}</td>
+                                </tr>
+                            }).collect_view()}
+                        </tbody>
+                    </table>
+
+                    {(!r.duplicates.is_empty()).then(|| view! {
+                        <div>
+                            <div class="field-hint" style="font-weight: 600; text-transform: uppercase; font-size: 10px;">"Duplicates"</div>
+                            {r.duplicates.iter().map(|g| view! {
+                                <div style="border-bottom: 1px solid var(--hairline); padding: 4px 0;">
+                                    {g.paths.iter().map(|p| model_row(p.clone(), g.size_bytes, String::new())).collect_view()}
+                                </div>
+                            }).collect_view()}
+                        </div>
+                    })}
+
+                    {(!r.unused.is_empty()).then(|| view! {
+                        <div>
+                            <div class="field-hint" style="font-weight: 600; text-transform: uppercase; font-size: 10px;">"Unused"</div>
+                            {r.unused.iter().map(|u| {
+                                let note = match u.last_launched {
+                                    Some(ts) => chrono::DateTime::from_timestamp(ts, 0)
+                                        .map(|d| format!("· last used {}", d.format("%Y-%m-%d")))
+                                        .unwrap_or_default(),
+                                    None => "· never launched".to_string(),
+                                };
+                                model_row(u.path.clone(), u.size_bytes, note)
+                            }).collect_view()}
+                        </div>
+                    })}
+                </div>
+            })}
+        </Card>
     }
-    None
 }
 
-fn extract_dl_speed(line: &str) -> Option<String> {
-    let lower = line.to_lowercase();
-    if let Some(dl_idx) = lower.find("dl:") {
-        let sub = line[dl_idx + 3..].trim();
-        let end = sub.find(']').or_else(|| sub.find(' ')).unwrap_or(sub.len());
-        let sp = sub[..end].trim();
-        if !sp.is_empty() { return Some(sp.to_string()); }
-    }
-    for token in line.split_whitespace().rev() {
-        let t = token.trim_end_matches(|c: char| c == ',' || c == ']' || c == ')');
-        if t.ends_with("/s") || t.ends_with("b/s") || t.ends_with("B/s") {
-            return Some(t.to_string());
-        }
+fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut v = bytes as f64;
+    let mut unit = 0;
+    while v >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
     }
-    None
+    if unit == 0 { format!("{bytes} B") } else { format!("{v:.1} {}", UNITS[unit]) }
 }
 
-fn extract_dl_eta(line: &str) -> Option<String> {
-    let lower = line.to_lowercase();
-    if let Some(idx) = lower.find("eta") {
-        let after = line[idx + 3..].trim_start_matches(&[':', ' '][..]).trim();
-        if !after.is_empty() {
-            return Some(after.split_whitespace().next().unwrap_or("Unknown").to_string());
-        }
-    }
-    if let Some(start) = lower.find("remaining") {
-        let after = line[start..].split(':').nth(1).unwrap_or("").trim();
-        if !after.is_empty() {
-            return Some(after.split_whitespace().next().unwrap_or("Unknown").to_string());
-        }
+fn fmt_eta(remaining: u64, rate: u64) -> String {
+    if rate == 0 {
+        return "Unknown".to_string();
     }
-    if let Some(open_bracket) = line.find('[') {
-        if let Some(close_bracket) = line.find(']') {
-            let sub = &line[open_bracket + 1 .. close_bracket];
-            if let Some(less_than) = sub.find('<') {
-                if let Some(comma) = sub.find(',') {
-                    if less_than < comma {
-                        let eta_val = sub[less_than + 1 .. comma].trim();
-                        if !eta_val.is_empty() {
-                            return Some(eta_val.to_string());
-                        }
-                    }
-                }
-            }
-        }
+    let secs = remaining / rate;
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
     }
-    None
 }
 
-
+/// Browse a Hugging Face repo and queue only the chosen quant(s) instead of
+/// the whole repo.
 #[component]
-pub fn DownloadTab() -> impl IntoView {
-    let targets_text = RwSignal::new(String::new());
-    let is_downloading = RwSignal::new(false);
-    let log_data = RwSignal::new(String::new());
-    let show_raw_logs = RwSignal::new(false);
-    let error_msg = RwSignal::new(String::new());
-
-    let load_targets = move || {
+fn HfRepoPicker(on_error: RwSignal<String>) -> impl IntoView {
+    let repo = RwSignal::new(String::new());
+    let subdir = RwSignal::new(String::new());
+    let listing = RwSignal::new(None::<HfRepoListing>);
+    let selected = RwSignal::new(std::collections::HashSet::<String>::new());
+    let loading = RwSignal::new(false);
+
+    let browse = move || {
+        let r = repo.get_untracked();
+        if r.trim().is_empty() {
+            return;
+        }
+        loading.set(true);
+        on_error.set(String::new());
         spawn_local(async move {
-            if let Ok(t) = api::download_get_targets().await {
-                targets_text.set(t);
+            match api::hf_list_repo(r).await {
+                Ok(l) => {
+                    selected.set(Default::default());
+                    listing.set(Some(l));
+                }
+                Err(e) => on_error.set(format!("Failed to list repo: {e}")),
             }
+            loading.set(false);
         });
     };
 
-    let check_status = move || {
+    let toggle = move |paths: Vec<String>| {
+        selected.update(|s| {
+            if paths.iter().all(|p| s.contains(p)) {
+                for p in &paths { s.remove(p); }
+            } else {
+                s.extend(paths);
+            }
+        });
+    };
+
+    let enqueue = move |_| {
+        let Some(l) = listing.get_untracked() else { return };
+        let paths: Vec<String> = selected.get_untracked().into_iter().collect();
+        if paths.is_empty() {
+            return;
+        }
+        let sub = subdir.get_untracked();
         spawn_local(async move {
-            if let Ok(status) = api::download_status().await {
-                is_downloading.set(status.is_downloading);
-                if !status.log.is_empty() {
-                    log_data.set(status.log);
-                }
+            match api::hf_enqueue_files(l.repo, paths, sub).await {
+                Ok(_) => selected.set(Default::default()),
+                Err(e) => on_error.set(format!("Failed to queue: {e}")),
             }
         });
     };
 
-    load_targets();
-    check_status();
+    let fit_badge = |fit: MemoryFit| {
+        let (label, color) = match fit {
+            MemoryFit::Gpu => ("Fits GPU", "#10b981"),
+            MemoryFit::Offload => ("CPU offload", "#f59e0b"),
+            MemoryFit::TooLarge => ("Too large", "#ef4444"),
+            MemoryFit::Unknown => ("?", "var(--muted)"),
+        };
+        view! {
+            <span style=format!("font-size:10.5px;font-weight:600;color:{color};white-space:nowrap;")>{label}</span>
+        }
+    };
 
-    let check_status_cb = check_status;
-    let poller = Interval::new(1000, move || {
-        check_status_cb();
+    view! {
+        <Card title="Hugging Face Repo">
+            <div style="display:flex;gap:8px;">
+                <input class="input" style="flex:1;" placeholder="org/Model-GGUF"
+                    prop:value=move || repo.get()
+                    on:input=move |e| repo.set(event_target_value(&e))
+                    on:keydown=move |e: KeyboardEvent| if e.key() == "Enter" { browse() }
+                />
+                <button class="btn secondary" disabled=move || loading.get() on:click=move |_| browse()>
+                    {move || if loading.get() { "Loading\u{2026}" } else { "Browse" }}
+                </button>
+            </div>
+
+            {move || listing.get().map(|l| {
+                let budget = format!(
+                    "Budget: {} VRAM · {} RAM",
+                    fmt_bytes(l.budget.vram_bytes),
+                    fmt_bytes(l.budget.ram_bytes)
+                );
+                view! {
+                    <div style="font-size:11px;color:var(--muted);margin:10px 0 6px;">{budget}</div>
+                    <div style="display:flex;flex-direction:column;gap:4px;max-height:260px;overflow-y:auto;">
+                        {l.options.into_iter().map(|o| {
+                            let paths: Vec<String> = o.files.iter().map(|f| f.path.clone()).collect();
+                            let check_paths = paths.clone();
+                            let label = if o.quant.is_empty() { paths[0].clone() } else { o.quant.clone() };
+                            let shards = (o.files.len() > 1).then(|| format!(" · {} parts", o.files.len()));
+                            let title = paths.join("\n");
+                            view! {
+                                <label style="display:flex;align-items:center;gap:8px;font-size:12px;cursor:pointer;" title=title>
+                                    <input type="checkbox"
+                                        prop:checked=move || { let s = selected.get(); check_paths.iter().all(|p| s.contains(p)) }
+                                        on:change=move |_| toggle(paths.clone())
+                                    />
+                                    <span style="font-family:var(--font-mono);flex:1;">{label}{shards}</span>
+                                    <span style="color:var(--muted);">{fmt_bytes(o.size_bytes)}</span>
+                                    {fit_badge(o.fit)}
+                                </label>
+                            }
+                        }).collect_view()}
+                        {(!l.extras.is_empty()).then(|| view! {
+                            <div class="field-hint" style="font-weight:600;text-transform:uppercase;font-size:10px;margin-top:6px;">"Other files"</div>
+                        })}
+                        {l.extras.into_iter().map(|f| {
+                            let path = f.path.clone();
+                            let check = f.path.clone();
+                            view! {
+                                <label style="display:flex;align-items:center;gap:8px;font-size:12px;cursor:pointer;">
+                                    <input type="checkbox"
+                                        prop:checked=move || selected.get().contains(&check)
+                                        on:change=move |_| toggle(vec![path.clone()])
+                                    />
+                                    <span style="font-family:var(--font-mono);flex:1;word-break:break-all;">{f.path.clone()}</span>
+                                    <span style="color:var(--muted);">{fmt_bytes(f.size_bytes)}</span>
+                                </label>
+                            }
+                        }).collect_view()}
+                    </div>
+                    <div style="display:flex;gap:8px;margin-top:10px;">
+                        <input class="input" style="flex:1;" placeholder="Subfolder under the model dir (optional)"
+                            prop:value=move || subdir.get()
+                            on:input=move |e| subdir.set(event_target_value(&e))
+                        />
+                        <button class="btn primary"
+                            disabled=move || selected.get().is_empty()
+                            on:click=enqueue
+                        >{move || format!("\u{2b07} Download {} file(s)", selected.get().len())}</button>
+                    </div>
+                }
+            })}
+        </Card>
+    }
+}
+
+#[component]
+pub fn DownloadTab() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let targets_text = RwSignal::new(String::new());
+    let error_msg = RwSignal::new(String::new());
+    let starting = RwSignal::new(false);
+
+    spawn_local(async move {
+        if let Ok(t) = api::download_get_targets().await {
+            targets_text.set(t);
+        }
+    });
+
+    let is_downloading = Memo::new(move |_| {
+        ctx.downloads
+            .get()
+            .iter()
+            .any(|i| matches!(i.state, DownloadState::Queued | DownloadState::Running))
     });
-    poller.forget();
 
     let start = move |_| {
         error_msg.set(String::new());
+        starting.set(true);
         let t = targets_text.get_untracked();
         spawn_local(async move {
-            match api::download_start(t).await {
-                Ok(_) => is_downloading.set(true),
-                Err(e) => error_msg.set(format!("Failed to start: {e}")),
+            if let Err(e) = api::download_start(t).await {
+                error_msg.set(format!("Failed to start: {e}"));
             }
+            starting.set(false);
         });
     };
 
-    let cancel = move |_| {
+    let cancel = move |id: Option<String>| {
         spawn_local(async move {
-            match api::download_cancel().await {
-                Ok(_) => is_downloading.set(false),
-                Err(e) => error_msg.set(format!("Failed to cancel: {e}")),
+            if let Err(e) = api::download_cancel(id).await {
+                error_msg.set(format!("Failed to cancel: {e}"));
+            }
+        });
+    };
+
+    let clear_finished = move |_| {
+        spawn_local(async move {
+            if api::download_clear_finished().await.is_ok() {
+                ctx.downloads.update(|d| {
+                    d.retain(|i| matches!(i.state, DownloadState::Queued | DownloadState::Running))
+                });
             }
         });
     };
 
     view! {
         <div class="page">
-            <PageHeader title="Download Manager" desc="Model and repository downloader. Edit the targets file, generate dw.sh, and monitor live progress via tmux session 'hf_downloads'."/>
+            <PageHeader title="Download Manager" desc="Model and repository downloader. Downloads resume where they stopped, run several connections per file, and verify checksums when the Hub provides them."/>
 
             {move || {
                 let err = error_msg.get();
@@ -626,27 +912,34 @@ pub fn DownloadTab() -> impl IntoView {
                             placeholder="# Example\ntext:\n- stable\nfacebook/opt-125m\n\nmultimodal:\nhttps://example.com/model.gguf\n\nrepos:\nhttps://github.com/user/repo.git"
                             prop:value=move || targets_text.get()
                             on:input=move |e| targets_text.set(event_target_value(&e))
-                            disabled=move || is_downloading.get()
                         ></textarea>
+                        {move || match parse_targets(&targets_text.get()) {
+                            Ok(t) => view! {
+                                <div style="margin-top:6px;font-size:11.5px;color:var(--muted);">
+                                    {format!("{} target(s)", t.len())}
+                                </div>
+                            }.into_any(),
+                            Err(errors) => view! {
+                                <div style="margin-top:6px;font-size:11.5px;color:#ef4444;display:flex;flex-direction:column;gap:2px;">
+                                    {errors.into_iter().map(|e| view! { <span>{e.to_string()}</span> }).collect_view()}
+                                </div>
+                            }.into_any(),
+                        }}
                         <div style="display:flex;gap:10px;margin-top:12px;flex-wrap:wrap;">
                             <button class="btn primary" style="flex:1;"
-                                disabled=move || is_downloading.get()
+                                disabled=move || starting.get() || parse_targets(&targets_text.get()).is_err()
                                 on:click=start
                             >
-                                {move || if is_downloading.get() { "\u{23f3} Generating & Downloading\u{2026}" } else { "\u{2b07} Generate dw.sh & Start" }}
+                                {move || if starting.get() { "\u{23f3} Queueing\u{2026}" } else { "\u{2b07} Queue Downloads" }}
                             </button>
                             <button class="btn danger"
                                 disabled=move || !is_downloading.get()
-                                on:click=cancel
-                            >"\u{23f9} Cancel"</button>
-                        </div>
-                        <div style="margin-top:8px;font-size:11.5px;color:var(--muted);">
-                            "Tip: attach to live session with "
-                            <code style="background:var(--surface-soft);padding:2px 6px;border-radius:4px;">
-                                "tmux attach -t hf_downloads"
-                            </code>
+                                on:click=move |_| cancel(None)
+                            >"\u{23f9} Cancel All"</button>
                         </div>
                     </Card>
+
+                    <HfRepoPicker on_error=error_msg/>
                 </div>
 
                 // ── Right: Live monitor ────────────────────────────────────────
@@ -657,87 +950,81 @@ pub fn DownloadTab() -> impl IntoView {
                             <div style=move || format!("width:9px;height:9px;border-radius:50%;flex-shrink:0;background:{};",
                                 if is_downloading.get() { "#10b981" } else { "var(--muted)" }
                             )></div>
-                            <span style="font-weight:600;font-size:13px;">
-                                {move || if is_downloading.get() { "Downloading in 'hf_downloads' tmux session" } else { "Idle / Completed" }}
+                            <span style="font-weight:600;font-size:13px;flex:1;">
+                                {move || if is_downloading.get() { "Downloading" } else { "Idle / Completed" }}
                             </span>
+                            <button class="btn ghost sm" on:click=clear_finished>"Clear finished"</button>
                         </div>
 
-                        // Per-target progress list
-                        <div style="display:flex;flex-direction:column;gap:10px;max-height:360px;overflow-y:auto;padding-right:2px;">
+                        // Per-item progress list
+                        <div style="display:flex;flex-direction:column;gap:10px;max-height:520px;overflow-y:auto;padding-right:2px;">
                             {move || {
-                                let targets = parse_input_targets(&targets_text.get());
-                                let log = log_data.get();
-                                let (active_idx, percent, speed, eta) = parse_tmux_progress(&log, &targets);
-
-                                if targets.is_empty() {
+                                let items = ctx.downloads.get();
+                                if items.is_empty() {
                                     return view! {
                                         <div style="text-align:center;padding:20px;color:var(--muted);font-style:italic;font-size:13px;">
-                                            "No targets defined. Edit the file on the left."
+                                            "Nothing queued. Edit the targets on the left and queue them."
                                         </div>
                                     }.into_any();
                                 }
-
-                                targets.into_iter().enumerate().map(|(idx, target)| {
-                                    let is_active = is_downloading.get() && active_idx == Some(idx);
-                                    let is_done = if is_downloading.get() {
-                                        active_idx.map_or(false, |a| idx < a)
-                                    } else {
-                                        !log.is_empty()
-                                    };
-
-                                    let (bar_width, bar_color, label) = if is_active {
-                                        (format!("{}%", percent as u32), "var(--primary)", format!("{}%", percent as u32))
-                                    } else if is_done {
-                                        ("100%".to_string(), "#10b981", "\u{2705} Done".to_string())
-                                    } else {
-                                        ("0%".to_string(), "var(--muted)", "\u{23f3} Pending".to_string())
+                                let rates = ctx.download_rates.get();
+
+                                items.into_iter().map(|item| {
+                                    let name = item.dest.rsplit('/').next().unwrap_or(&item.dest).to_string();
+                                    let percent = item.total_bytes
+                                        .filter(|t| *t > 0)
+                                        .map(|t| item.downloaded_bytes as f64 * 100.0 / t as f64);
+                                    let (bar_width, bar_color, label) = match item.state {
+                                        DownloadState::Queued => ("0%".to_string(), "var(--muted)", "\u{23f3} Pending".to_string()),
+                                        DownloadState::Running => {
+                                            let p = percent.unwrap_or(0.0);
+                                            (format!("{p:.0}%"), "var(--primary)", percent.map_or("\u{2026}".to_string(), |p| format!("{p:.0}%")))
+                                        }
+                                        DownloadState::Done => ("100%".to_string(), "#10b981", "\u{2705} Done".to_string()),
+                                        DownloadState::Failed => ("100%".to_string(), "#ef4444", "\u{26a0} Failed".to_string()),
+                                        DownloadState::Cancelled => (format!("{:.0}%", percent.unwrap_or(0.0)), "var(--muted)", "Cancelled".to_string()),
                                     };
+                                    let is_active = item.state == DownloadState::Running;
+                                    let can_cancel = matches!(item.state, DownloadState::Queued | DownloadState::Running);
+                                    let rate = rates.get(&item.id).copied().unwrap_or(0);
+                                    let detail = item.total_bytes.map_or_else(
+                                        || fmt_bytes(item.downloaded_bytes),
+                                        |t| format!("{} / {}", fmt_bytes(item.downloaded_bytes), fmt_bytes(t)),
+                                    );
+                                    let eta = item.total_bytes.map_or("Unknown".to_string(), |t| fmt_eta(t.saturating_sub(item.downloaded_bytes), rate));
+                                    let id = item.id.clone();
 
                                     view! {
                                         <div style="background:var(--surface-soft);border:1px solid var(--hairline);border-radius:var(--r-md);padding:10px 12px;display:flex;flex-direction:column;gap:7px;">
-                                            <div style="display:flex;justify-content:space-between;align-items:center;">
-                                                <span style="font-weight:600;font-size:12.5px;color:var(--ink);word-break:break-all;flex:1;margin-right:8px;">{target.name.clone()}</span>
-                                                <span style=format!("font-size:11px;font-weight:600;white-space:nowrap;color:{};",
-                                                    if is_active { "var(--primary)" } else if is_done { "#10b981" } else { "var(--muted)" }
-                                                )>{label}</span>
+                                            <div style="display:flex;justify-content:space-between;align-items:center;gap:8px;">
+                                                <span style="font-weight:600;font-size:12.5px;color:var(--ink);word-break:break-all;flex:1;" title=item.source.clone()>{name}</span>
+                                                <span style=format!("font-size:11px;font-weight:600;white-space:nowrap;color:{};", bar_color)>{label}</span>
+                                                {can_cancel.then(|| view! {
+                                                    <button class="btn ghost sm" on:click=move |_| cancel(Some(id.clone()))>"\u{2715}"</button>
+                                                })}
                                             </div>
                                             // Progress bar
                                             <div style="width:100%;height:5px;background:var(--surface-strong);border-radius:9999px;overflow:hidden;">
                                                 <div style=format!("height:100%;width:{};background:{};border-radius:9999px;transition:width 0.4s ease;", bar_width, bar_color)></div>
                                             </div>
-                                            {if is_active {
-                                                view! {
-                                                    <div style="display:flex;justify-content:space-between;font-size:11px;color:var(--muted);">
-                                                        <span>{format!("Speed: {speed}")}</span>
-                                                        <span>{format!("ETA: {eta}")}</span>
-                                                    </div>
-                                                }.into_any()
-                                            } else { view!{<span></span>}.into_any() }}
+                                            {is_active.then(|| view! {
+                                                <div style="display:flex;justify-content:space-between;font-size:11px;color:var(--muted);">
+                                                    <span>{detail}</span>
+                                                    <span>{format!("{}/s", fmt_bytes(rate))}</span>
+                                                    <span>{format!("ETA: {eta}")}</span>
+                                                </div>
+                                            })}
+                                            {item.post_log.into_iter().map(|line| view! {
+                                                <div style="font-size:11px;color:var(--muted);">{line}</div>
+                                            }).collect_view()}
+                                            {item.error.map(|e| view! {
+                                                <div style="font-size:11px;color:#ef4444;word-break:break-all;">{e}</div>
+                                            })}
                                         </div>
                                     }
                                 }).collect_view().into_any()
                             }}
                         </div>
-
-                        // Collapsible raw log
-                        {move || if is_downloading.get() || !log_data.get().is_empty() {
-                            view! {
-                                <div style="margin-top:12px;">
-                                    <button class="btn secondary sm" style="width:100%;font-size:11.5px;"
-                                        on:click=move |_| show_raw_logs.update(|v| *v = !*v)
-                                    >
-                                        {move || if show_raw_logs.get() { "Hide Raw Terminal Output" } else { "Show Raw Terminal Output" }}
-                                    </button>
-                                    {move || if show_raw_logs.get() {
-                                        view! {
-                                            <div class="log-console" style="height:180px;margin-top:8px;font-size:11px;background:var(--surface-soft);">
-                                                <pre class="log-line" style="margin:0;">{log_data.get()}</pre>
-                                            </div>
-                                        }.into_any()
-                                    } else { view!{<span></span>}.into_any() }}
-                                </div>
-                            }.into_any()
-                        } else { view!{<span></span>}.into_any() }}
                     </Card>
                 </div>
             </div>
@@ -1235,9 +1522,9 @@ fn GamMemorySimulation() -> impl IntoView {
     }
 }
 
-// Which sub-section to show: "memory" | "skills"
+// Which sub-section to show: "memory" | "lessons" | "runs" | "skills"
 #[derive(Clone, Copy, PartialEq)]
-enum AgentView { Memory, Skills }
+enum AgentView { Memory, Lessons, Runs, Skills }
 
 #[derive(Clone, Debug)]
 pub struct TreeItem {
@@ -1445,6 +1732,300 @@ fn TreeViewNode(
     }
 }
 
+/// Lessons the agent and chat have learned: the review queue first, then
+/// approved lessons by rank, then rejected ones.
+#[component]
+fn LessonsPanel(lessons: RwSignal<Vec<Lesson>>, reload: Callback<()>) -> impl IntoView {
+    let error = RwSignal::new(String::new());
+    let done = move |res: Result<(), String>| match res {
+        Ok(()) => {
+            error.set(String::new());
+            reload.run(());
+        }
+        Err(e) => error.set(e),
+    };
+
+    view! {
+        <div style="display:flex;flex-direction:column;gap:8px;flex:1;min-height:0;overflow-y:auto;">
+            {move || {
+                let err = error.get();
+                (!err.is_empty()).then(|| view! {
+                    <div style="padding:10px 14px;background:#ef444420;border:1px solid #ef4444;border-radius:var(--r-md);color:#ef4444;font-size:13px;flex-shrink:0;">{err}</div>
+                })
+            }}
+            {move || if lessons.get().is_empty() {
+                view! {
+                    <div style="text-align:center;padding:40px;color:var(--muted);">
+                        <div style="font-size:36px;margin-bottom:12px;">"💡"</div>
+                        <div style="font-weight:600;margin-bottom:6px;">"No lessons yet"</div>
+                        <div style="font-size:12px;">"The agent proposes a lesson after each run and chat reply. Turn on the review queue in Settings → Agent Memory to approve them before they are used."</div>
+                    </div>
+                }.into_any()
+            } else {
+                lessons.get().into_iter().map(|l| {
+                    let draft = RwSignal::new(l.text.clone());
+                    let (badge, color) = match l.status {
+                        LessonStatus::Pending => ("PENDING", "#f59e0b"),
+                        LessonStatus::Approved => ("APPROVED", "#10b981"),
+                        LessonStatus::Rejected => ("REJECTED", "#ef4444"),
+                    };
+                    let pending = l.status == LessonStatus::Pending;
+                    let seen = chrono::DateTime::from_timestamp(l.last_seen_at, 0)
+                        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    let meta = format!("{} · proposed {}× · importance {:.1} · last seen {}", l.source, l.hits, l.importance, seen);
+                    let (id_ok, id_no, id_del) = (l.id.clone(), l.id.clone(), l.id.clone());
+                    let approved = l.status == LessonStatus::Approved;
+                    let rejected = l.status == LessonStatus::Rejected;
+                    view! {
+                        <div style=format!("display:flex;gap:10px;align-items:flex-start;padding:10px 14px;background:var(--surface-card);border:1px solid var(--hairline);border-left:3px solid {color};border-radius:var(--r-md);")>
+                            <span style=format!("font-size:9px;padding:1px 6px;border-radius:9999px;font-weight:600;margin-top:3px;background:{color}20;color:{color};")>{badge}</span>
+                            <div style="flex:1;min-width:0;display:flex;flex-direction:column;gap:4px;">
+                                {if pending {
+                                    view! {
+                                        <input class="input" type="text"
+                                            prop:value=move || draft.get()
+                                            on:input=move |e| draft.set(event_target_value(&e))
+                                        />
+                                    }.into_any()
+                                } else {
+                                    view! { <div style="font-size:13px;color:var(--ink);">{l.text.clone()}</div> }.into_any()
+                                }}
+                                <div style="font-size:11px;color:var(--muted);">{meta}</div>
+                            </div>
+                            <div style="display:flex;gap:4px;flex-shrink:0;">
+                                {(!approved).then(|| view! {
+                                    <button class="btn secondary sm" on:click=move |_| {
+                                        let text = pending.then(|| draft.get_untracked());
+                                        let id = id_ok.clone();
+                                        spawn_local(async move { done(api::memory_review(id, true, text).await) });
+                                    }>"✓ Approve"</button>
+                                })}
+                                {(!rejected).then(|| view! {
+                                    <button class="btn secondary sm" on:click=move |_| {
+                                        let id = id_no.clone();
+                                        spawn_local(async move { done(api::memory_review(id, false, None).await) });
+                                    }>"✕ Reject"</button>
+                                })}
+                                <button class="btn danger sm" title="Delete" on:click=move |_| {
+                                    let id = id_del.clone();
+                                    spawn_local(async move { done(api::memory_delete(id).await) });
+                                }>"🗑"</button>
+                            </div>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            }}
+        </div>
+    }
+}
+
+/// One line of a recorded run's transcript, in the style of the chat's agent
+/// trace.
+fn run_event_line(event: &AgentEvent) -> String {
+    let clip = |s: &str, max: usize| if s.chars().count() > max { format!("{}…", s.chars().take(max).collect::<String>()) } else { s.to_string() };
+    match event {
+        AgentEvent::Started { parent: None, role, task, limits, .. } => {
+            format!("▶ {role} started: {task} (up to {} steps, temperature {})", limits.max_steps, limits.temperature)
+        }
+        AgentEvent::Started { role, task, .. } => format!("  ▶ {role} started: {task}"),
+        AgentEvent::Plan { steps, .. } => {
+            let list: Vec<String> = steps.iter().enumerate().map(|(i, s)| format!("{}. {}", i + 1, s.description)).collect();
+            format!("📋 plan: {}", list.join(" · "))
+        }
+        AgentEvent::PlanUpdate { status, .. } => format!("  plan step {status:?}").to_lowercase(),
+        AgentEvent::Step { index, thought, .. } => format!("💭 step {}: {}", index + 1, clip(thought, 300)),
+        AgentEvent::Token { delta, .. } => delta.clone(),
+        AgentEvent::Retract { .. } => String::new(),
+        AgentEvent::ToolCall { call, .. } => format!("🔧 {}({})", call.tool, clip(&call.args.to_string(), 200)),
+        AgentEvent::ToolResult { result, .. } => format!("{} {}", if result.ok { "✅" } else { "⚠️" }, clip(&result.output, 300)),
+        AgentEvent::ApprovalRequest { request, .. } => format!("✋ approval asked for {}: {}", request.tool, request.reason),
+        AgentEvent::ApprovalResolved { approved, decided_by, .. } => {
+            let by = if decided_by.is_empty() { String::new() } else { format!(" by {decided_by}") };
+            format!("  {}{by}", if *approved { "✓ approved" } else { "✕ denied" })
+        }
+        AgentEvent::SubAgentSpawned { role, task, .. } => format!("🌱 spawned {role}: {task}"),
+        AgentEvent::Log { message, .. } => message.clone(),
+        AgentEvent::Done { final_text, .. } => format!("✔ done: {}", clip(final_text, 500)),
+        AgentEvent::Error { message, .. } => format!("✖ {message}"),
+    }
+}
+
+fn run_status_badge(status: AgentRunStatus) -> (&'static str, &'static str) {
+    match status {
+        AgentRunStatus::Running => ("RUNNING", "#3b82f6"),
+        AgentRunStatus::Done => ("DONE", "#10b981"),
+        AgentRunStatus::Failed => ("FAILED", "#ef4444"),
+        AgentRunStatus::Cancelled => ("CANCELLED", "#6b7280"),
+        AgentRunStatus::Interrupted => ("INTERRUPTED", "#f59e0b"),
+    }
+}
+
+fn format_ms(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// Recorded agent runs: open one to read its transcript or replay it at the
+/// pace it ran, and resume runs that were cut off.
+#[component]
+fn RunsPanel(runs: RwSignal<Vec<AgentRunSummary>>, reload: Callback<()>) -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let selected = RwSignal::new(None::<AgentRun>);
+    // Events shown; all of them, or those replayed so far.
+    let shown = RwSignal::new(0usize);
+    // Bumped to stop a replay in progress.
+    let replay_gen = RwSignal::new(0u32);
+    let error = RwSignal::new(String::new());
+
+    let open = move |id: String| {
+        replay_gen.update(|g| *g += 1);
+        spawn_local(async move {
+            match api::agent_run_get(id).await {
+                Ok(run) => {
+                    shown.set(run.events.len());
+                    selected.set(Some(run));
+                    error.set(String::new());
+                }
+                Err(e) => error.set(e),
+            }
+        });
+    };
+    let replay = move |_| {
+        let Some(run) = selected.get_untracked() else { return };
+        replay_gen.update(|g| *g += 1);
+        let generation = replay_gen.get_untracked();
+        shown.set(0);
+        spawn_local(async move {
+            let mut last = run.events.first().map_or(0, |e| e.at);
+            for (i, e) in run.events.iter().enumerate() {
+                // The real gaps, but no pause longer than a second.
+                let gap = (e.at - last).clamp(0, 1000) as u32;
+                last = e.at;
+                gloo_timers::future::TimeoutFuture::new(gap).await;
+                if replay_gen.get_untracked() != generation {
+                    return;
+                }
+                shown.set(i + 1);
+            }
+        });
+    };
+    let resume = move |id: String| {
+        spawn_local(async move {
+            match api::agent_resume(id.clone()).await {
+                Ok(_) => {
+                    // Follow it in the chat's agent trace.
+                    ctx.chat_current_agent.set(Some(id));
+                    ctx.chat_plan.set(vec![]);
+                    ctx.chat_trace.set(vec![]);
+                    error.set(String::new());
+                    reload.run(());
+                }
+                Err(e) => error.set(e),
+            }
+        });
+    };
+    let delete = move |id: String| {
+        spawn_local(async move {
+            match api::agent_run_delete(id.clone()).await {
+                Ok(()) => {
+                    if selected.get_untracked().is_some_and(|r| r.summary.id == id) {
+                        selected.set(None);
+                    }
+                    reload.run(());
+                }
+                Err(e) => error.set(e),
+            }
+        });
+    };
+
+    view! {
+        <div style="display:flex;gap:12px;flex:1;min-height:0;">
+            <div style="width:340px;flex-shrink:0;display:flex;flex-direction:column;gap:6px;overflow-y:auto;">
+                {move || {
+                    let err = error.get();
+                    (!err.is_empty()).then(|| view! {
+                        <div style="padding:10px 14px;background:#ef444420;border:1px solid #ef4444;border-radius:var(--r-md);color:#ef4444;font-size:13px;flex-shrink:0;">{err}</div>
+                    })
+                }}
+                {move || if runs.get().is_empty() {
+                    view! {
+                        <div style="text-align:center;padding:40px;color:var(--muted);">
+                            <div style="font-size:36px;margin-bottom:12px;">"📜"</div>
+                            <div style="font-weight:600;margin-bottom:6px;">"No runs yet"</div>
+                            <div style="font-size:12px;">"Every agent run is recorded here as it goes, from the chat, the planner and the calendar."</div>
+                        </div>
+                    }.into_any()
+                } else {
+                    runs.get().into_iter().map(|r| {
+                        let (badge, color) = run_status_badge(r.status);
+                        let id = r.id.clone();
+                        let is_selected = move || selected.get().is_some_and(|s| s.summary.id == id);
+                        let id_open = r.id.clone();
+                        let meta = format!("{} · {} steps · {} tokens · {}", format_ms(r.started_at), r.steps, r.tokens, r.model);
+                        view! {
+                            <div
+                                style=move || format!("padding:8px 12px;background:var(--surface-card);border:1px solid {};border-left:3px solid {color};border-radius:var(--r-md);cursor:pointer;", if is_selected() { "var(--primary)" } else { "var(--hairline)" })
+                                on:click=move |_| open(id_open.clone())
+                            >
+                                <div style="display:flex;gap:6px;align-items:center;">
+                                    <span style=format!("font-size:9px;padding:1px 6px;border-radius:9999px;font-weight:600;background:{color}20;color:{color};")>{badge}</span>
+                                    <span style="font-size:13px;color:var(--ink);overflow:hidden;text-overflow:ellipsis;white-space:nowrap;">{r.task.clone()}</span>
+                                </div>
+                                <div style="font-size:11px;color:var(--muted);margin-top:3px;">{meta}</div>
+                            </div>
+                        }
+                    }).collect_view().into_any()
+                }}
+            </div>
+            <div style="flex:1;min-width:0;display:flex;flex-direction:column;gap:8px;overflow-y:auto;">
+                {move || match selected.get() {
+                    None => view! {
+                        <div style="padding:40px;text-align:center;color:var(--muted);font-size:13px;">"Select a run to see what it did."</div>
+                    }.into_any(),
+                    Some(run) => {
+                        let s = run.summary.clone();
+                        let (badge, color) = run_status_badge(s.status);
+                        let took = s.finished_at.map(|f| format!(" · took {}s", (f - s.started_at) / 1000)).unwrap_or_default();
+                        let id_resume = s.id.clone();
+                        let id_delete = s.id.clone();
+                        let resumable = s.status.resumable();
+                        let events = run.events.clone();
+                        view! {
+                            <div style="display:flex;gap:8px;align-items:center;flex-wrap:wrap;">
+                                <span style=format!("font-size:10px;padding:2px 8px;border-radius:9999px;font-weight:600;background:{color}20;color:{color};")>{badge}</span>
+                                <span style="font-size:12px;color:var(--muted);flex:1;">{format!("Started {}{took} · up to {} steps · {} used", format_ms(s.started_at), run.limits.max_steps, s.steps)}</span>
+                                <button class="btn secondary sm" on:click=replay>"▶ Replay"</button>
+                                {resumable.then(|| view! {
+                                    <button class="btn secondary sm" title="Carry on from the last completed step"
+                                        on:click=move |_| resume(id_resume.clone())
+                                    >"⟳ Resume"</button>
+                                })}
+                                <button class="btn danger sm" title="Delete" on:click=move |_| delete(id_delete.clone())>"🗑"</button>
+                            </div>
+                            <div style="font-size:14px;font-weight:600;color:var(--ink);">{s.task.clone()}</div>
+                            <div style="font-family:var(--font-mono, monospace);font-size:12px;background:var(--surface-card);border:1px solid var(--hairline);border-radius:var(--r-md);padding:10px 12px;display:flex;flex-direction:column;gap:3px;">
+                                {move || events.iter().take(shown.get()).map(|e| {
+                                    let time = chrono::DateTime::from_timestamp_millis(e.at)
+                                        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                                        .unwrap_or_default();
+                                    view! {
+                                        <div style="display:flex;gap:8px;">
+                                            <span style="color:var(--muted);flex-shrink:0;">{time}</span>
+                                            <span style="white-space:pre-wrap;word-break:break-word;color:var(--body);">{run_event_line(&e.event)}</span>
+                                        </div>
+                                    }
+                                }).collect_view()}
+                            </div>
+                        }.into_any()
+                    }
+                }}
+            </div>
+        </div>
+    }
+}
+
 #[component]
 pub fn AgentsTab() -> impl IntoView {
     let agent_view = RwSignal::new(AgentView::Memory);
@@ -1472,6 +2053,23 @@ pub fn AgentsTab() -> impl IntoView {
     };
     load_memories();
 
+    let lessons = RwSignal::new(Vec::<Lesson>::new());
+    let load_lessons = move || {
+        spawn_local(async move {
+            if let Ok(list) = api::memory_get().await {
+                lessons.set(list);
+            }
+        });
+    };
+
+    let runs = RwSignal::new(Vec::<AgentRunSummary>::new());
+    let load_runs = move || {
+        spawn_local(async move {
+            if let Ok(list) = api::agent_runs_list().await {
+                runs.set(list);
+            }
+        });
+    };
     let save_memory = move || {
         let id = edit_id.get_untracked();
         let title = edit_title.get_untracked().trim().to_string();
@@ -1585,7 +2183,7 @@ pub fn AgentsTab() -> impl IntoView {
         agent_output.set("⏳ Dispatching agent copilot…".to_string());
         let (host, port, model) = ctx.resolve_target("memory");
         spawn_local(async move {
-            let req = AgentRequest { host, port, model, task };
+            let req = AgentRequest { host, port, model, task, prompt: None, budget: None };
             match api::agent_start(req).await {
                 Ok(id) => agent_output.set(format!("Agent started: {id}. Watch the Monitor tab, or wait for output here. Once finished, use the controls below to insert/append.")),
                 Err(e) => agent_output.set(format!("Error starting agent: {e}")),
@@ -1645,6 +2243,16 @@ pub fn AgentsTab() -> impl IntoView {
                     <div style="font-size:12px;color:var(--muted);margin-top:2px;">
                         {move || match agent_view.get() {
                             AgentView::Memory => format!("{} memory nodes across all scopes", memories.get().len()),
+                            AgentView::Lessons => {
+                                let list = lessons.get();
+                                let pending = list.iter().filter(|l| l.status == LessonStatus::Pending).count();
+                                format!("{} lessons, {} waiting for review", list.len(), pending)
+                            }
+                            AgentView::Runs => {
+                                let list = runs.get();
+                                let resumable = list.iter().filter(|r| r.status.resumable()).count();
+                                format!("{} recorded runs, {} resumable", list.len(), resumable)
+                            }
                             AgentView::Skills => format!("{} skills and agent files loaded", skill_files.get().len()),
                         }}
                     </div>
@@ -1661,6 +2269,30 @@ pub fn AgentsTab() -> impl IntoView {
                                 })
                             on:click=move |_| agent_view.set(AgentView::Memory)
                         >"🧠 Memory"</button>
+                        <button
+                            style=move || format!("padding:4px 12px;border-radius:4px;border:none;cursor:pointer;font-size:12.5px;font-weight:600;{}",
+                                if agent_view.get() == AgentView::Lessons {
+                                    "background:var(--primary);color:var(--on-primary);"
+                                } else {
+                                    "background:transparent;color:var(--body);"
+                                })
+                            on:click=move |_| {
+                                agent_view.set(AgentView::Lessons);
+                                load_lessons();
+                            }
+                        >"💡 Lessons"</button>
+                        <button
+                            style=move || format!("padding:4px 12px;border-radius:4px;border:none;cursor:pointer;font-size:12.5px;font-weight:600;{}",
+                                if agent_view.get() == AgentView::Runs {
+                                    "background:var(--primary);color:var(--on-primary);"
+                                } else {
+                                    "background:transparent;color:var(--body);"
+                                })
+                            on:click=move |_| {
+                                agent_view.set(AgentView::Runs);
+                                load_runs();
+                            }
+                        >"📜 Runs"</button>
                         <button
                             style=move || format!("padding:4px 12px;border-radius:4px;border:none;cursor:pointer;font-size:12.5px;font-weight:600;{}",
                                 if agent_view.get() == AgentView::Skills {
@@ -1702,6 +2334,29 @@ pub fn AgentsTab() -> impl IntoView {
                                 on:click=clear_all_memories
                             >"🗑 Clear All"</button>
                         }.into_any()
+                    } else if agent_view.get() == AgentView::Lessons {
+                        view! {
+                            <button class="btn secondary sm"
+                                title="Merge near-duplicate lessons and trim to the configured limit"
+                                on:click=move |_| spawn_local(async move {
+                                    if let Ok(list) = api::memory_consolidate().await {
+                                        lessons.set(list);
+                                    }
+                                })
+                            >"🧹 Consolidate"</button>
+                            <button class="btn danger sm"
+                                prop:disabled=move || lessons.get().is_empty()
+                                on:click=move |_| spawn_local(async move {
+                                    if api::memory_clear().await.is_ok() {
+                                        lessons.set(Vec::new());
+                                    }
+                                })
+                            >"🗑 Clear All"</button>
+                        }.into_any()
+                    } else if agent_view.get() == AgentView::Runs {
+                        view! {
+                            <button class="btn secondary sm" on:click=move |_| load_runs()>"↻ Refresh"</button>
+                        }.into_any()
                     } else {
                         view! {
                             <button class="btn secondary sm"
@@ -1848,6 +2503,10 @@ pub fn AgentsTab() -> impl IntoView {
                         </div>
                     </div>
                 }.into_any()
+            } else if agent_view.get() == AgentView::Lessons {
+                view! { <LessonsPanel lessons=lessons reload=Callback::new(move |_| load_lessons())/> }.into_any()
+            } else if agent_view.get() == AgentView::Runs {
+                view! { <RunsPanel runs=runs reload=Callback::new(move |_| load_runs())/> }.into_any()
             } else if show_gam_simulation.get() {
                 view! { <GamMemorySimulation/> }.into_any()
             } else {
@@ -2530,7 +3189,7 @@ pub fn MonitorTab() -> impl IntoView {
         let (host, port, model) = ctx.resolve_target("planner");
         dispatch_running.set(true);
         spawn_local(async move {
-            let req = AgentRequest { host, port, model, task };
+            let req = AgentRequest { host, port, model, task, prompt: None, budget: None };
             let _ = api::agent_start(req).await;
             dispatch_running.set(false);
         });
@@ -2715,6 +3374,13 @@ pub fn PlannerTab() -> impl IntoView {
                         agent_output.update(|val| val.push_str(&delta));
                     }
                 }
+                AgentEvent::Retract { agent_id, text } if Some(&agent_id) == active_id.as_ref() => {
+                    agent_output.update(|val| {
+                        if val.ends_with(&text) {
+                            val.truncate(val.len() - text.len());
+                        }
+                    });
+                }
                 AgentEvent::Step { thought, .. } => {
                     agent_output.update(|val| val.push_str(&format!("\n💭 {}\n", thought)));
                 }
@@ -2807,7 +3473,7 @@ pub fn PlannerTab() -> impl IntoView {
         agent_output.set("⏳ Dispatching agent…\n".to_string());
         let (host, port, model) = ctx.resolve_target("planner");
         spawn_local(async move {
-            let req = AgentRequest { host, port, model, task: task_prompt };
+            let req = AgentRequest { host, port, model, task: task_prompt, prompt: None, budget: None };
             match api::agent_start(req).await {
                 Ok(id) => {
                     running_agent_id.set(Some(id.clone()));
@@ -3085,6 +3751,14 @@ pub fn CalendarTab() -> impl IntoView {
     let add_title = RwSignal::new(String::new());
     let add_time = RwSignal::new(String::new());
     let add_prompt = RwSignal::new(String::new());
+    // A library prompt to fire instead of the typed one, with its variables.
+    let library = RwSignal::new(Vec::<PromptTemplate>::new());
+    let add_prompt_ref = RwSignal::new(None::<PromptRef>);
+    spawn_local(async move {
+        if let Ok(list) = api::prompt_list().await {
+            library.set(list);
+        }
+    });
     let add_note = RwSignal::new(String::new());
     let add_color = RwSignal::new("#6366f1".to_string());
     let add_recurrence = RwSignal::new(String::new());
@@ -3135,6 +3809,7 @@ pub fn CalendarTab() -> impl IntoView {
             result: None,
             tags,
             recurrence: if recurrence_str.is_empty() { None } else { Some(recurrence_str) },
+            prompt_ref: add_prompt_ref.get_untracked(),
         };
 
         spawn_local(async move {
@@ -3147,6 +3822,7 @@ pub fn CalendarTab() -> impl IntoView {
         add_title.set(String::new());
         add_time.set(String::new());
         add_prompt.set(String::new());
+        add_prompt_ref.set(None);
         add_note.set(String::new());
         add_recurrence.set(String::new());
         add_tags.set(Vec::new());
@@ -3564,10 +4240,48 @@ pub fn CalendarTab() -> impl IntoView {
 
                             <div class="field">
                                 <label class="field-label">"Prompt Trigger Command"</label>
-                                <input class="input" type="text" placeholder="Prompt instruction to fire..."
+                                <select class="input" style="height: 32px; font-size: 13px; margin-bottom: 4px;"
+                                    on:change=move |e| {
+                                        let id = event_target_value(&e);
+                                        match library.get_untracked().into_iter().find(|p| p.id == id) {
+                                            Some(p) => {
+                                                add_prompt.set(p.body().to_string());
+                                                add_prompt_ref.set(Some(PromptRef { id, ..Default::default() }));
+                                            }
+                                            None => add_prompt_ref.set(None),
+                                        }
+                                    }
+                                >
+                                    <option value="" selected=move || add_prompt_ref.get().is_none()>"— Type a prompt"</option>
+                                    {move || library.get().into_iter().map(|p| view! {
+                                        <option value=p.id.clone()>{format!("📝 {}", p.name)}</option>
+                                    }).collect_view()}
+                                </select>
+                                <input class="input" type="text" placeholder="Prompt instruction to fire… ({{date}}, {{clipboard}} are filled in when it fires)"
                                     prop:value=move || add_prompt.get()
+                                    prop:readonly=move || add_prompt_ref.get().is_some()
                                     on:input=move |e| add_prompt.set(event_target_value(&e))
                                 />
+                                {move || {
+                                    let names = shared::prompts::user_variables(&add_prompt.get());
+                                    (add_prompt_ref.get().is_some() && !names.is_empty()).then(|| view! {
+                                        <div style="display: flex; flex-wrap: wrap; gap: 6px; margin-top: 4px;">
+                                            {names.into_iter().map(|var| {
+                                                let key = var.clone();
+                                                view! {
+                                                    <input class="input" type="text" style="flex: 1 1 120px;" placeholder=var
+                                                        on:input=move |e| {
+                                                            let value = event_target_value(&e);
+                                                            add_prompt_ref.update(|r| if let Some(r) = r {
+                                                                r.vars.insert(key.clone(), value);
+                                                            });
+                                                        }
+                                                    />
+                                                }
+                                            }).collect_view()}
+                                        </div>
+                                    })
+                                }}
                             </div>
 
                             <div class="field">
@@ -3,13 +3,14 @@
 //! the active theme to a "Custom" palette derived from the `ui_*` fields.
 
 use leptos::prelude::*;
+use shared::config::{ApprovalRule, ArgMatcher, PostDownloadAction};
 use wasm_bindgen_futures::spawn_local;
 
 use crate::api;
-use crate::components::{Card, PageHeader};
+use crate::components::{Card, PageHeader, SelectField};
 use crate::state::{AppCtx, Tab};
 use crate::theme::{self, PRESETS};
-use crate::{field_text, field_num};
+use crate::{field_bool, field_text, field_num};
 
 #[component]
 pub fn SettingsTab() -> impl IntoView {
@@ -564,7 +565,193 @@ pub fn SettingsTab() -> impl IntoView {
                 >
                     "Add Directory"
                 </button>
+
+                <div style="margin-top: 16px;">
+                    {field_bool!(ctx, scan_model_stores, "Scan model stores", "Also index the Hugging Face cache, Ollama and LM Studio")}
+                    {field_num!(ctx, vram_budget_gb, f32, "VRAM budget (GB)", "Download fit estimate · 0 = detect")}
+                    {field_num!(ctx, ram_budget_gb, f32, "RAM budget (GB)", "Download fit estimate · 0 = detect")}
+                </div>
+            </Card>
+
+            <Card title="After Downloads">
+                <div class="fields-grid">
+                    {field_bool!(ctx, post_download_verify, "Verify GGUF", "Check finished files parse and are complete")}
+                    {field_bool!(ctx, post_download_index, "Add to library", "Index new models without a rescan")}
+                    {field_bool!(ctx, post_download_enrich, "Enrich", "Look up details for newly indexed models")}
+                    <SelectField
+                        label="Then"
+                        id="form-post_download_action"
+                        value=Signal::derive(move || ctx.config.get().post_download_action.as_str().to_string())
+                        options=vec![
+                            ("none".into(), "Do nothing".into()),
+                            ("create_profile".into(), "Create a profile".into()),
+                            ("start_server".into(), "Start the server".into()),
+                        ]
+                        on_select=Callback::new(move |v: String| {
+                            ctx.update_cfg(|c| c.post_download_action = PostDownloadAction::from_str(&v))
+                        })
+                    />
+                </div>
+            </Card>
+
+            <Card title="Document Chat">
+                <div class="fields-grid">
+                    {field_text!(ctx, rag_embedding_url, "Embedding server", "e.g. http://127.0.0.1:8081 · empty = the chat's server")}
+                    {field_text!(ctx, rag_embedding_model, "Embedding model", "Sent as `model`; llama-server ignores it")}
+                    {field_num!(ctx, rag_top_k, u32, "Passages per question", "Top-k from attached files · 0 = off")}
+                </div>
             </Card>
+
+            <Card title="Agent Memory">
+                <div class="fields-grid">
+                    {field_bool!(ctx, memory_review_queue, "Review new lessons", "Hold proposed lessons until approved under Memory → Lessons")}
+                    {field_num!(ctx, memory_max_lessons, u32, "Lessons kept", "Lowest-ranked go first · 0 = no limit")}
+                    {field_text!(ctx, memory_embedding_url, "Context embedding server", "Ranks records for the task · empty = Document Chat's · none = keywords only")}
+                    {field_num!(ctx, memory_context_tokens, u32, "Context budget (tokens)", "Most relevant records first · 0 = all")}
+                </div>
+            </Card>
+
+            <Card title="Agent Budgets">
+                <div class="fields-grid">
+                    {field_num!(ctx, agent_max_steps, u32, "Max steps", "Most a run may ask for · default 8")}
+                    {field_num!(ctx, agent_max_depth, u8, "Max sub-agent depth", "Most a run may ask for · default 2")}
+                    {field_num!(ctx, agent_max_wall_clock_secs, u64, "Max run time (s)", "Also the limit when a run sets none · 0 = no limit")}
+                    {field_num!(ctx, agent_max_tokens, u64, "Max tokens per run", "Also the limit when a run sets none · 0 = no limit")}
+                    {field_num!(ctx, agent_max_timeout_secs, u64, "Max timeout (s)", "For tool, model and approval timeouts")}
+                </div>
+            </Card>
+
+            <Card title="Agent Approvals">
+                <ApprovalRules/>
+            </Card>
+
+            <Card title="Command Sandbox">
+                <div class="fields-grid">
+                    {field_bool!(ctx, agent_sandbox, "Sandbox run_command", "Linux only · refuses to run a command it can't confine")}
+                    {field_text!(ctx, agent_sandbox_workspace, "Workspace", "The only writable directory · empty = agent-workspace in the config dir")}
+                    {field_bool!(ctx, agent_sandbox_network, "Allow network", "Off = no network inside the sandbox")}
+                    {field_num!(ctx, agent_sandbox_cpu_secs, u64, "CPU limit (s)", "Per command · 0 = no limit")}
+                    {field_num!(ctx, agent_sandbox_memory_mb, u64, "Memory limit (MiB)", "Address space per command · 0 = no limit")}
+                </div>
+            </Card>
+        </div>
+    }
+}
+
+/// The agent approval policy: rules tried top to bottom, the headless
+/// policy for scheduled runs, and forgetting "for this session" approvals.
+#[component]
+fn ApprovalRules() -> impl IntoView {
+    let ctx = expect_context::<AppCtx>();
+    let forgotten = RwSignal::new(false);
+    // Edits land on change rather than input: the rows are rebuilt from the
+    // config, which would take the focus from a field being typed in.
+    let edit = move |i: usize, f: Box<dyn FnOnce(&mut ApprovalRule)>| {
+        ctx.config.update(|c| {
+            if let Some(rule) = c.agent_approval_rules.get_mut(i) {
+                f(rule);
+            }
+        });
+    };
+    let input_style = "height: 30px; font-size: 12.5px; padding: 0 8px;";
+
+    view! {
+        <div style="font-size: 12px; color: var(--muted); margin-bottom: 10px;">
+            "Checked before every tool call; the first matching rule decides. Calls no rule matches are asked about when the tool has side effects. Patterns are regexes (e.g. "
+            <code>"git (status|log)"</code>"; an allow rule must match the whole argument and never allows chained commands) or absolute directories for path prefixes."
+        </div>
+        <div style="display: flex; flex-direction: column; gap: 6px; margin-bottom: 10px;">
+            {move || {
+                let rules = ctx.config.get().agent_approval_rules;
+                rules.into_iter().enumerate().map(|(i, rule)| {
+                    let needs_arg = rule.matcher != ArgMatcher::Any;
+                    view! {
+                        <div style="display: flex; gap: 6px; align-items: center; flex-wrap: wrap;">
+                            <span style="font-size: 11px; color: var(--muted); width: 18px;">{i + 1}</span>
+                            <select class="input" style=format!("{input_style} width: 90px;")
+                                prop:value=rule.action.as_str().to_string()
+                                on:change=move |e| {
+                                    let v = event_target_value(&e);
+                                    edit(i, Box::new(move |r| r.action = v.parse().unwrap_or_default()));
+                                    ctx.save();
+                                }
+                            >
+                                <option value="allow">"Allow"</option>
+                                <option value="deny">"Deny"</option>
+                                <option value="ask">"Ask"</option>
+                            </select>
+                            <input class="input" style=format!("{input_style} width: 150px;") placeholder="tool, or * for any"
+                                prop:value=rule.tool.clone()
+                                on:change=move |e| {
+                                    let v = event_target_value(&e);
+                                    edit(i, Box::new(move |r| r.tool = v.trim().to_string()));
+                                    ctx.save();
+                                }
+                            />
+                            <select class="input" style=format!("{input_style} width: 130px;")
+                                prop:value=rule.matcher.as_str().to_string()
+                                on:change=move |e| {
+                                    let v = event_target_value(&e);
+                                    edit(i, Box::new(move |r| r.matcher = v.parse().unwrap_or_default()));
+                                    ctx.save();
+                                }
+                            >
+                                <option value="any">"every call"</option>
+                                <option value="regex">"when matching"</option>
+                                <option value="path_prefix">"when under"</option>
+                            </select>
+                            {needs_arg.then(|| view! {
+                                <input class="input" style=format!("{input_style} width: 100px;") placeholder="argument"
+                                    prop:value=rule.arg.clone()
+                                    on:change=move |e| {
+                                        let v = event_target_value(&e);
+                                        edit(i, Box::new(move |r| r.arg = v.trim().to_string()));
+                                        ctx.save();
+                                    }
+                                />
+                                <input class="input" style=format!("{input_style} flex: 1; min-width: 140px;")
+                                    placeholder=if rule.matcher == ArgMatcher::Regex { "regex" } else { "/path/to/dir" }
+                                    prop:value=rule.pattern.clone()
+                                    on:change=move |e| {
+                                        let v = event_target_value(&e);
+                                        edit(i, Box::new(move |r| r.pattern = v));
+                                        ctx.save();
+                                    }
+                                />
+                            })}
+                            <button class="btn secondary sm" title="Try this rule earlier" prop:disabled=i == 0
+                                on:click=move |_| ctx.update_cfg(|c| c.agent_approval_rules.swap(i - 1, i))
+                            >"↑"</button>
+                            <button class="btn danger sm" title="Remove" on:click=move |_| ctx.update_cfg(|c| { c.agent_approval_rules.remove(i); })>"✕"</button>
+                        </div>
+                    }
+                }).collect_view()
+            }}
+        </div>
+        <div style="display: flex; gap: 8px; align-items: flex-end; flex-wrap: wrap;">
+            <button class="btn secondary sm"
+                on:click=move |_| ctx.update_cfg(|c| c.agent_approval_rules.push(ApprovalRule { tool: "run_command".into(), ..Default::default() }))
+            >"＋ Add rule"</button>
+            <button class="btn secondary sm" title="Ask again about tools approved for this session"
+                on:click=move |_| spawn_local(async move {
+                    if api::agent_forget_approvals().await.is_ok() {
+                        forgotten.set(true);
+                    }
+                })
+            >{move || if forgotten.get() { "✓ Forgotten" } else { "Forget session approvals" }}</button>
+            <div style="flex: 1;"></div>
+            <SelectField
+                label="Scheduled runs, instead of asking"
+                id="form-agent_headless_policy"
+                value=Signal::derive(move || ctx.config.get().agent_headless_policy.as_str().to_string())
+                options=vec![
+                    ("deny".into(), "Deny".into()),
+                    ("allow".into(), "Allow".into()),
+                ]
+                on_select=Callback::new(move |v: String| {
+                    ctx.update_cfg(|c| c.agent_headless_policy = v.parse().unwrap_or_default())
+                })
+            />
         </div>
     }
 }